@@ -1,14 +1,100 @@
+use anyhow::Context;
 use clap::Parser;
 use codex_common::CliConfigOverrides;
+use codex_core::cache::config::CacheableTool;
 use codex_core::cache::manager::CacheManager;
 use codex_core::config::Config;
 use codex_core::config::ConfigOverrides;
+use codex_core::telemetry::CacheLatencySnapshot;
+use codex_core::telemetry::CacheToolTelemetrySnapshot;
+use serde::Serialize;
+use std::fs::File;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::path::PathBuf;
 
 #[derive(Debug, Parser)]
 pub(crate) struct CacheStatusArgs {
     /// Show cache telemetry grouped by tool.
     #[clap(long)]
     pub(crate) by_tool: bool,
+
+    /// Print cache telemetry in the Prometheus text exposition format.
+    #[clap(long, conflicts_with = "by_tool")]
+    pub(crate) prometheus: bool,
+
+    /// Reset telemetry counters after printing them.
+    #[clap(long)]
+    pub(crate) reset: bool,
+
+    /// Print cache status as JSON.
+    #[clap(long, conflicts_with_all = ["by_tool", "prometheus"])]
+    pub(crate) json: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct CacheStatusJson {
+    enabled: bool,
+    dir: PathBuf,
+    entries: usize,
+    total_bytes: u64,
+    max_bytes: u64,
+    hit_rate: Option<f64>,
+    by_tool: Vec<ToolStatusJson>,
+}
+
+#[derive(Debug, Serialize)]
+struct ToolStatusJson {
+    tool: CacheableTool,
+    hits: u64,
+    misses: u64,
+    stores: u64,
+    evictions: u64,
+    hit_rate: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+struct CacheEntryJson {
+    key: String,
+    size_bytes: u64,
+    ttl_secs: u64,
+    inserted_epoch: u64,
+    expires_epoch: u64,
+    last_access_epoch: u64,
+    is_expired: bool,
+    tool: Option<&'static str>,
+    preview_hex: String,
+}
+
+impl From<&CacheToolTelemetrySnapshot> for ToolStatusJson {
+    fn from(snapshot: &CacheToolTelemetrySnapshot) -> Self {
+        Self {
+            tool: snapshot.tool,
+            hits: snapshot.hits,
+            misses: snapshot.misses,
+            stores: snapshot.stores,
+            evictions: snapshot.evictions,
+            hit_rate: snapshot.hit_rate,
+        }
+    }
+}
+
+#[derive(Debug, Parser)]
+pub(crate) struct CacheSnapshotArgs {
+    /// File to write the JSONL snapshot to.
+    #[arg(value_name = "OUTPUT")]
+    pub(crate) output: PathBuf,
+}
+
+#[derive(Debug, Parser)]
+pub(crate) struct CacheWarmArgs {
+    /// JSONL snapshot produced by `codex cache snapshot`.
+    #[arg(value_name = "INPUT")]
+    pub(crate) input: PathBuf,
+
+    /// Replace entries that are already cached.
+    #[arg(long)]
+    pub(crate) overwrite: bool,
 }
 
 #[derive(Debug, Parser)]
@@ -26,6 +112,38 @@ pub(crate) enum CacheSubcommand {
     Status(CacheStatusArgs),
     /// Clear all cached entries.
     Clear,
+    /// Remove expired entries, keeping valid ones.
+    Prune,
+    /// Write all live entries to a JSONL snapshot file.
+    Snapshot(CacheSnapshotArgs),
+    /// Load entries from a JSONL snapshot file.
+    Warm(CacheWarmArgs),
+    /// Back up all live entries to a `.tar.zst` archive.
+    Export {
+        /// Archive file to write.
+        #[arg(value_name = "OUTPUT")]
+        output: PathBuf,
+    },
+    /// Restore entries from an archive written by `codex cache export`.
+    Import {
+        /// Archive file to read.
+        #[arg(value_name = "INPUT")]
+        input: PathBuf,
+    },
+    /// Show metadata for a single cache entry.
+    Inspect {
+        /// Cache key to inspect.
+        #[arg(value_name = "KEY")]
+        key: String,
+
+        /// Print the stored value instead of its metadata.
+        #[arg(long)]
+        raw: bool,
+
+        /// Print the metadata as JSON.
+        #[arg(long, conflicts_with = "raw")]
+        json: bool,
+    },
 }
 
 pub(crate) async fn run_cache_command(cmd: CacheCommand) -> anyhow::Result<()> {
@@ -38,12 +156,40 @@ pub(crate) async fn run_cache_command(cmd: CacheCommand) -> anyhow::Result<()> {
         ConfigOverrides::default(),
     )
     .await?;
-    let cache_manager = CacheManager::new(config.cache)?;
+    // Namespaced like the session cache, so `inspect` takes the plain key.
+    let cache_manager = CacheManager::with_git_branch_namespace(config.cache, &config.cwd)?;
 
     match cmd.subcommand {
         CacheSubcommand::Status(args) => {
             let status = cache_manager.status()?;
+            if args.reset {
+                cache_manager.reset_telemetry();
+            }
+            if args.prometheus {
+                print!("{}", status.telemetry.to_prometheus());
+                return Ok(());
+            }
+            if args.json {
+                let output = CacheStatusJson {
+                    enabled: status.enabled,
+                    dir: status.dir.into_path_buf(),
+                    entries: status.stats.entries,
+                    total_bytes: status.stats.total_bytes,
+                    max_bytes: status.max_bytes,
+                    hit_rate: status.telemetry.hit_rate,
+                    by_tool: status
+                        .telemetry
+                        .by_tool
+                        .iter()
+                        .map(ToolStatusJson::from)
+                        .collect(),
+                };
+                let payload = serde_json::to_string_pretty(&output)?;
+                println!("{payload}");
+                return Ok(());
+            }
             println!("Cache enabled: {}", status.enabled);
+            println!("Read only: {}", status.read_only);
             println!("Cache dir: {}", status.dir.display());
             println!("Entries: {}", status.stats.entries);
             println!("Size bytes: {}", status.stats.total_bytes);
@@ -52,6 +198,14 @@ pub(crate) async fn run_cache_command(cmd: CacheCommand) -> anyhow::Result<()> {
                 Some(rate) => println!("Hit rate: {:.1}%", rate * 100.0),
                 None => println!("Hit rate: n/a"),
             }
+            println!(
+                "Hit latency: {}",
+                format_latency(status.telemetry.hit_latency)
+            );
+            println!(
+                "Store latency: {}",
+                format_latency(status.telemetry.store_latency)
+            );
             if args.by_tool {
                 println!("By tool:");
                 for snapshot in &status.telemetry.by_tool {
@@ -73,9 +227,122 @@ pub(crate) async fn run_cache_command(cmd: CacheCommand) -> anyhow::Result<()> {
         }
         CacheSubcommand::Clear => {
             cache_manager.clear()?;
-            println!("Cache cleared");
+            if cache_manager.read_only() {
+                println!("Cache is read-only; nothing cleared");
+            } else {
+                println!("Cache cleared");
+            }
+        }
+        CacheSubcommand::Prune => {
+            let bytes_before = cache_manager.status()?.stats.total_bytes;
+            let removed = cache_manager.prune_expired()?;
+            let bytes_after = cache_manager.status()?.stats.total_bytes;
+            println!(
+                "Removed {removed} expired entries, freed {} bytes.",
+                bytes_before.saturating_sub(bytes_after)
+            );
+        }
+        CacheSubcommand::Snapshot(args) => {
+            let file = File::create(&args.output)
+                .with_context(|| format!("failed to create {}", args.output.display()))?;
+            let written = cache_manager.snapshot(BufWriter::new(file))?;
+            println!("Wrote {written} entries to {}", args.output.display());
+        }
+        CacheSubcommand::Warm(args) => {
+            let file = File::open(&args.input)
+                .with_context(|| format!("failed to open {}", args.input.display()))?;
+            let outcome = cache_manager.warm(BufReader::new(file), args.overwrite)?;
+            println!(
+                "Loaded {} entries (skipped {} expired, {} already cached)",
+                outcome.loaded, outcome.skipped_expired, outcome.skipped_existing
+            );
+        }
+        CacheSubcommand::Export { output } => {
+            let file = File::create(&output)
+                .with_context(|| format!("failed to create {}", output.display()))?;
+            let written = cache_manager.export(BufWriter::new(file))?;
+            println!("Exported {written} entries to {}", output.display());
+        }
+        CacheSubcommand::Import { input } => {
+            let file = File::open(&input)
+                .with_context(|| format!("failed to open {}", input.display()))?;
+            let outcome = cache_manager.import(BufReader::new(file))?;
+            println!(
+                "Imported {} entries (skipped {} expired, {} already cached)",
+                outcome.loaded, outcome.skipped_expired, outcome.skipped_existing
+            );
+        }
+        CacheSubcommand::Inspect { key, raw, json } => {
+            if raw {
+                let Some(value) = cache_manager.inspect_value(&key)? else {
+                    anyhow::bail!("no cache entry for key `{key}`");
+                };
+                match String::from_utf8(value) {
+                    Ok(text) => println!("{text}"),
+                    Err(err) => print!("{}", hex_dump(err.as_bytes())),
+                }
+                return Ok(());
+            }
+            let Some(meta) = cache_manager.inspect(&key)? else {
+                anyhow::bail!("no cache entry for key `{key}`");
+            };
+            let tool = meta.tool.map(CacheableTool::config_key);
+            let preview_hex = hex_string(&meta.preview);
+            if json {
+                let output = CacheEntryJson {
+                    key,
+                    size_bytes: meta.size_bytes,
+                    ttl_secs: meta.ttl_secs,
+                    inserted_epoch: meta.inserted_epoch,
+                    expires_epoch: meta.expires_epoch,
+                    last_access_epoch: meta.last_access_epoch,
+                    is_expired: meta.is_expired,
+                    tool,
+                    preview_hex,
+                };
+                let payload = serde_json::to_string_pretty(&output)?;
+                println!("{payload}");
+                return Ok(());
+            }
+            println!("Key: {key}");
+            println!("Tool: {}", tool.unwrap_or("n/a"));
+            println!("Size bytes: {}", meta.size_bytes);
+            println!("TTL secs: {}", meta.ttl_secs);
+            println!("Inserted epoch: {}", meta.inserted_epoch);
+            println!("Expires epoch: {}", meta.expires_epoch);
+            println!("Last access epoch: {}", meta.last_access_epoch);
+            println!("Expired: {}", meta.is_expired);
+            println!("Preview: {preview_hex}");
         }
     }
 
     Ok(())
 }
+
+/// Formats `bytes` as offset-prefixed rows of 16 hex bytes.
+fn hex_dump(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for (row, chunk) in bytes.chunks(16).enumerate() {
+        out.push_str(&format!("{:08x}  {}\n", row * 16, hex_string(chunk)));
+    }
+    out
+}
+
+/// Formats `bytes` as space-separated hex pairs.
+fn hex_string(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn format_latency(latency: Option<CacheLatencySnapshot>) -> String {
+    match latency {
+        Some(latency) => format!(
+            "avg={:?} min={:?} max={:?} (n={})",
+            latency.avg, latency.min, latency.max, latency.count
+        ),
+        None => "n/a".to_string(),
+    }
+}
@@ -0,0 +1,95 @@
+use clap::Parser;
+use codex_common::CliConfigOverrides;
+use codex_core::config::Config;
+use codex_core::config::ConfigOverrides;
+use codex_core::config::types::GrepBackend;
+use codex_core::ripgrep;
+use codex_core::ripgrep::RipgrepProbe;
+
+#[derive(Debug, Parser)]
+pub(crate) struct DoctorCommand {
+    #[clap(flatten)]
+    pub(crate) config_overrides: CliConfigOverrides,
+}
+
+pub(crate) async fn run_doctor_command(cmd: DoctorCommand) -> anyhow::Result<()> {
+    let cli_overrides = cmd
+        .config_overrides
+        .parse_overrides()
+        .map_err(anyhow::Error::msg)?;
+    let config = Config::load_with_cli_overrides_and_harness_overrides(
+        cli_overrides,
+        ConfigOverrides::default(),
+    )
+    .await?;
+
+    for line in format_grep_diagnostics(&ripgrep::probe(), config.grep_backend) {
+        println!("{line}");
+    }
+    Ok(())
+}
+
+fn format_grep_diagnostics(probe: &RipgrepProbe, backend: GrepBackend) -> Vec<String> {
+    let ripgrep_line = match (&probe.path, &probe.version) {
+        (Some(path), Some(version)) => format!("ripgrep: found at {} ({version})", path.display()),
+        (Some(path), None) => format!("ripgrep: found at {} (version unknown)", path.display()),
+        (None, _) => "ripgrep: not found on PATH".to_string(),
+    };
+    let configured = match backend {
+        GrepBackend::Auto => "auto",
+        GrepBackend::Ripgrep => "ripgrep",
+        GrepBackend::Builtin => "builtin",
+    };
+    let effective = if ripgrep::uses_builtin(backend, probe.available()) {
+        "builtin"
+    } else {
+        "ripgrep"
+    };
+    vec![
+        ripgrep_line,
+        format!("grep_files backend: {effective} (grep_backend = \"{configured}\")"),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::path::PathBuf;
+
+    #[test]
+    fn reports_builtin_fallback_without_ripgrep() {
+        let probe = RipgrepProbe {
+            path: None,
+            version: None,
+        };
+
+        assert_eq!(
+            format_grep_diagnostics(&probe, GrepBackend::Auto),
+            vec![
+                "ripgrep: not found on PATH".to_string(),
+                "grep_files backend: builtin (grep_backend = \"auto\")".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_ripgrep_path_and_version() {
+        let probe = RipgrepProbe {
+            path: Some(PathBuf::from("/usr/bin/rg")),
+            version: Some("ripgrep 14.1.0".to_string()),
+        };
+
+        assert_eq!(
+            format_grep_diagnostics(&probe, GrepBackend::Auto),
+            vec![
+                "ripgrep: found at /usr/bin/rg (ripgrep 14.1.0)".to_string(),
+                "grep_files backend: ripgrep (grep_backend = \"auto\")".to_string(),
+            ]
+        );
+        assert_eq!(
+            format_grep_diagnostics(&probe, GrepBackend::Builtin)[1],
+            "grep_files backend: builtin (grep_backend = \"builtin\")"
+        );
+    }
+}
@@ -1,10 +1,25 @@
 use clap::Parser;
 use codex_common::CliConfigOverrides;
 use codex_core::AuthManager;
+use codex_core::cache::manager::CacheManager;
 use codex_core::config::Config;
 use codex_core::config::ConfigOverrides;
 use codex_core::semantic::index::SemanticIndex;
+use codex_core::telemetry::prometheus;
+use codex_protocol::num_format::format_with_separators;
+use notify::EventKind;
+use notify::RecommendedWatcher;
+use notify::RecursiveMode;
+use notify::Watcher;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::io::IsTerminal;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
 
 #[derive(Debug, Parser)]
 pub(crate) struct IndexCommand {
@@ -18,11 +33,65 @@ pub(crate) struct IndexCommand {
 #[derive(Debug, clap::Subcommand)]
 pub(crate) enum IndexSubcommand {
     /// Build the semantic index for this workspace.
-    Build,
+    Build(IndexBuildArgs),
     /// Show semantic index stats.
-    Stats,
+    Stats(IndexStatsArgs),
     /// Clear the semantic index for this workspace.
     Clear,
+    /// Clear the semantic index and build it again.
+    Rebuild(IndexRebuildArgs),
+    /// Watch the workspace and re-index changed files until Ctrl-C.
+    Watch,
+    /// Print cache and index metrics in the Prometheus text format.
+    Metrics,
+}
+
+#[derive(Debug, clap::Args)]
+pub(crate) struct IndexBuildArgs {
+    /// Count the files and chunks that would be indexed without calling the
+    /// embeddings API or writing the index.
+    #[arg(long)]
+    pub(crate) dry_run: bool,
+
+    /// Embedding price per 1,000 tokens, used to project the cost of a dry run
+    /// or, with `semantic_index.track_tokens`, to estimate the cost of a build.
+    #[arg(long, value_name = "USD")]
+    pub(crate) cost_per_1k_tokens: Option<f64>,
+
+    /// Only re-embed files changed between this git revision and HEAD. Falls
+    /// back to a full build when the revision or repository is unavailable.
+    #[arg(long, value_name = "REV", conflicts_with = "dry_run")]
+    pub(crate) since: Option<String>,
+}
+
+#[derive(Debug, clap::Args)]
+pub(crate) struct IndexRebuildArgs {
+    /// Skip the prompt warning that the existing index will be deleted.
+    #[arg(long)]
+    pub(crate) no_confirm: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub(crate) struct IndexStatsArgs {
+    /// Print stats as JSON, including whether the index is stale.
+    #[arg(long)]
+    pub(crate) json: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct IndexStatsJson {
+    index_dir: PathBuf,
+    file_count: usize,
+    chunk_count: usize,
+    embedding_model: Option<String>,
+    embedding_dim: Option<usize>,
+    last_build_at: Option<String>,
+    git_commit: Option<String>,
+    total_embedding_bytes: Option<u64>,
+    avg_chunk_lines: Option<f64>,
+    /// True when a workspace file changed after the last build, or when the
+    /// index has no build metadata.
+    stale: bool,
 }
 
 pub(crate) async fn run_index_command(cmd: IndexCommand) -> anyhow::Result<()> {
@@ -35,6 +104,8 @@ pub(crate) async fn run_index_command(cmd: IndexCommand) -> anyhow::Result<()> {
         ConfigOverrides::default(),
     )
     .await?;
+    #[cfg(feature = "otel")]
+    let _otel = crate::otel::init_tracing(&config)?;
 
     let auth_manager = Arc::new(AuthManager::new(
         config.codex_home.clone(),
@@ -49,17 +120,67 @@ pub(crate) async fn run_index_command(cmd: IndexCommand) -> anyhow::Result<()> {
     );
 
     match cmd.subcommand {
-        IndexSubcommand::Build => {
-            let stats = index.build().await?;
+        IndexSubcommand::Build(args) if args.dry_run => {
+            let estimate = index.estimate_build()?;
+            println!("Dry run: no embeddings requested, index not written");
+            println!("Files:            {}", estimate.file_count);
+            println!("Chunks:           {}", estimate.chunk_count);
+            println!(
+                "Chunk size:       {} lines",
+                config.semantic_index.chunk.max_lines
+            );
+            println!("Lines:            {}", estimate.line_count);
+            println!(
+                "Avg line length:  {:.1} bytes",
+                estimate.average_line_length()
+            );
+            println!("Estimated tokens: {}", estimate.estimated_tokens);
+            println!(
+                "Embedding model:  {}",
+                config.semantic_index.embedding_model
+            );
+            if let Some(cost_per_1k_tokens) = args.cost_per_1k_tokens {
+                println!(
+                    "Projected cost:   ${:.4}",
+                    estimate.projected_cost(cost_per_1k_tokens)
+                );
+            }
+        }
+        IndexSubcommand::Build(args) => {
+            let stats = match args.since.as_deref() {
+                Some(rev) => index.build_since(rev).await?,
+                None => index.build().await?,
+            };
             println!("Index dir: {}", config.semantic_index.dir.display());
             println!("Files: {}", stats.file_count);
             println!("Chunks: {}", stats.chunk_count);
+            if stats.reused_embeddings > 0 {
+                println!("Reused embeddings: {}", stats.reused_embeddings);
+            }
+            print_tokens_used(stats.tokens_used, args.cost_per_1k_tokens);
             if let Some(model) = stats.embedding_model {
                 println!("Embedding model: {model}");
             }
         }
-        IndexSubcommand::Stats => {
+        IndexSubcommand::Stats(args) => {
             let stats = index.stats()?;
+            if args.json {
+                let output = IndexStatsJson {
+                    index_dir: config.semantic_index.dir.as_path().to_path_buf(),
+                    file_count: stats.file_count,
+                    chunk_count: stats.chunk_count,
+                    embedding_model: stats.embedding_model,
+                    embedding_dim: stats.embedding_dim,
+                    last_build_at: stats.created_at.map(|created_at| created_at.to_rfc3339()),
+                    git_commit: stats.git_commit,
+                    total_embedding_bytes: stats.total_embedding_bytes,
+                    avg_chunk_lines: stats.avg_chunk_lines,
+                    stale: stats.stale,
+                };
+                let payload = serde_json::to_string_pretty(&output)?;
+                println!("{payload}");
+                return Ok(());
+            }
             println!("Index dir: {}", config.semantic_index.dir.display());
             println!("Files: {}", stats.file_count);
             println!("Chunks: {}", stats.chunk_count);
@@ -69,15 +190,259 @@ pub(crate) async fn run_index_command(cmd: IndexCommand) -> anyhow::Result<()> {
             if let Some(dim) = stats.embedding_dim {
                 println!("Embedding dim: {dim}");
             }
+            if let Some(bytes) = stats.total_embedding_bytes {
+                println!("Embedding bytes: {bytes}");
+            }
+            if let Some(avg_lines) = stats.avg_chunk_lines {
+                println!("Avg chunk lines: {avg_lines:.1}");
+            }
             if let Some(created_at) = stats.created_at {
                 println!("Created at: {}", created_at.to_rfc3339());
             }
+            if let Some(git_commit) = &stats.git_commit {
+                let short = git_commit.get(..7).unwrap_or(git_commit);
+                println!("Git commit: {short}");
+            }
+            if stats.stale {
+                println!(
+                    "Warning: index may be stale ({} files modified since last build)",
+                    index.modified_file_count()?
+                );
+            }
         }
         IndexSubcommand::Clear => {
             index.clear()?;
             println!("Index cleared");
         }
+        IndexSubcommand::Rebuild(args) => {
+            let index_dir = config.semantic_index.dir.as_path();
+            if !args.no_confirm && !confirm_rebuild(index_dir)? {
+                println!("Rebuild cancelled");
+                return Ok(());
+            }
+            index.clear()?;
+            println!("Index cleared");
+            let stats = index.build().await?;
+            println!("Index dir: {}", index_dir.display());
+            println!("Files: {}", stats.file_count);
+            println!("Chunks: {}", stats.chunk_count);
+            if stats.reused_embeddings > 0 {
+                println!("Reused embeddings: {}", stats.reused_embeddings);
+            }
+            print_tokens_used(stats.tokens_used, None);
+            if let Some(model) = stats.embedding_model {
+                println!("Embedding model: {model}");
+            }
+        }
+        IndexSubcommand::Metrics => {
+            let stats = index.stats()?;
+            let telemetry = CacheManager::new(config.cache.clone(), None)?
+                .status()?
+                .telemetry;
+            print!("{}", prometheus::format(&telemetry));
+            print!("{}", prometheus::format_index(&stats));
+        }
+        IndexSubcommand::Watch => watch_index(&index, &config).await?,
     }
 
     Ok(())
 }
+
+/// Prints the API-reported token usage of a build, with an estimated cost
+/// when a price is known.
+fn print_tokens_used(tokens_used: Option<u64>, cost_per_1k_tokens: Option<f64>) {
+    let Some(tokens_used) = tokens_used else {
+        return;
+    };
+    let tokens = format_with_separators(i64::try_from(tokens_used).unwrap_or(i64::MAX));
+    match cost_per_1k_tokens {
+        Some(cost_per_1k_tokens) => {
+            let cost = tokens_used as f64 / 1000.0 * cost_per_1k_tokens;
+            let cost_per_1m_tokens = cost_per_1k_tokens * 1000.0;
+            println!("Tokens used: {tokens} (est. cost: ${cost:.4} @ ${cost_per_1m_tokens:.2}/1M)");
+        }
+        None => println!("Tokens used: {tokens}"),
+    }
+}
+
+fn confirm_rebuild(index_dir: &Path) -> anyhow::Result<bool> {
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!("refusing to delete the index without confirmation; pass --no-confirm");
+    }
+    print!(
+        "This deletes the existing index at {} before rebuilding. Continue? [y/N] ",
+        index_dir.display()
+    );
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+async fn watch_index(index: &SemanticIndex, config: &Config) -> anyhow::Result<()> {
+    let (_watcher, rx) = watch_workspace(&config.cwd, config.semantic_index.dir.as_path())?;
+    println!("Watching {} (Ctrl-C to stop)", config.cwd.display());
+    let shutdown = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    apply_debounced_changes(
+        index,
+        rx,
+        Duration::from_millis(config.semantic_index.watch_debounce_ms),
+        shutdown,
+    )
+    .await;
+    println!("Stopped watching");
+    Ok(())
+}
+
+/// Starts watching `root` recursively and returns the watcher, which stops
+/// when dropped, with the channel its changed paths are sent to.
+fn watch_workspace(
+    root: &Path,
+    index_dir: &Path,
+) -> anyhow::Result<(RecommendedWatcher, mpsc::UnboundedReceiver<PathBuf>)> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    // Writes to the index itself would otherwise re-trigger an update cycle.
+    let index_dir = index_dir.to_path_buf();
+    let git_dir = root.join(".git");
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        let Ok(event) = res else {
+            return;
+        };
+        if matches!(
+            event.kind,
+            EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+        ) {
+            for path in event.paths {
+                if path.starts_with(&index_dir) || path.starts_with(&git_dir) {
+                    continue;
+                }
+                let _ = tx.send(path);
+            }
+        }
+    })?;
+    watcher.watch(root, RecursiveMode::Recursive)?;
+    Ok((watcher, rx))
+}
+
+/// Collects paths from `rx` until none arrive for `debounce`, re-indexes them
+/// with [`SemanticIndex::update_files`], and prints one status line per
+/// update, until `shutdown` completes or the channel closes.
+async fn apply_debounced_changes(
+    index: &SemanticIndex,
+    mut rx: mpsc::UnboundedReceiver<PathBuf>,
+    debounce: Duration,
+    shutdown: impl Future<Output = ()>,
+) {
+    tokio::pin!(shutdown);
+    loop {
+        let first = tokio::select! {
+            _ = &mut shutdown => None,
+            path = rx.recv() => path,
+        };
+        let Some(first) = first else {
+            break;
+        };
+        let mut changed = BTreeSet::from([first]);
+        let window = tokio::time::sleep(debounce);
+        tokio::pin!(window);
+        loop {
+            tokio::select! {
+                _ = &mut window => break,
+                Some(path) = rx.recv() => {
+                    changed.insert(path);
+                }
+            }
+        }
+
+        let paths: Vec<PathBuf> = changed.into_iter().collect();
+        match index.update_files(&paths).await {
+            Ok(stats) => println!(
+                "Updated {} changed path(s); files: {}, chunks: {}",
+                paths.len(),
+                stats.file_count,
+                stats.chunk_count
+            ),
+            Err(err) => println!("Update failed: {err:#}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codex_core::ModelProviderInfo;
+    use codex_core::WireApi;
+    use codex_core::semantic::config::DEFAULT_SEMANTIC_INDEX_DIR;
+    use codex_core::semantic::config::SemanticIndexConfig;
+    use serde_json::json;
+    use std::time::Instant;
+    use tempfile::tempdir;
+    use tokio::sync::oneshot;
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+    use wiremock::matchers::method;
+    use wiremock::matchers::path;
+
+    async fn index_with_mock_embeddings(workspace: &Path, server: &MockServer) -> SemanticIndex {
+        Mock::given(method("POST"))
+            .and(path("/embeddings"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [{ "index": 0, "embedding": [1.0, 0.0] }],
+            })))
+            .mount(server)
+            .await;
+        let provider = ModelProviderInfo {
+            name: "mock".into(),
+            base_url: Some(server.uri()),
+            env_key: None,
+            env_key_instructions: None,
+            experimental_bearer_token: None,
+            wire_api: WireApi::Responses,
+            query_params: None,
+            http_headers: None,
+            env_http_headers: None,
+            request_max_retries: Some(0),
+            stream_max_retries: Some(0),
+            stream_idle_timeout_ms: Some(5_000),
+            requires_openai_auth: false,
+        };
+        let config = SemanticIndexConfig::new(workspace, None).expect("config");
+        SemanticIndex::new(workspace.to_path_buf(), config, provider, None)
+    }
+
+    #[tokio::test]
+    async fn watch_indexes_new_file_within_debounce_window() {
+        let workspace = tempdir().expect("tempdir");
+        // Watcher events carry canonical paths (e.g. `/private/var` on macOS).
+        let root = workspace.path().canonicalize().expect("canonicalize");
+        let server = MockServer::start().await;
+        let index = index_with_mock_embeddings(&root, &server).await;
+        index.build().await.expect("build");
+        let debounce = Duration::from_millis(100);
+        let index_dir = root.join(DEFAULT_SEMANTIC_INDEX_DIR);
+        let (_watcher, rx) = watch_workspace(&root, &index_dir).expect("watch");
+        let (stop_tx, stop_rx) = oneshot::channel::<()>();
+
+        let watch = apply_debounced_changes(&index, rx, debounce, async {
+            let _ = stop_rx.await;
+        });
+        let check = async {
+            let written = Instant::now();
+            std::fs::write(root.join("notes.txt"), "some text\n").expect("write");
+            while index.stats().expect("stats").file_count == 0 {
+                assert!(
+                    written.elapsed() <= debounce + Duration::from_millis(200),
+                    "index was not updated within the debounce window"
+                );
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            let _ = stop_tx.send(());
+        };
+        tokio::join!(watch, check);
+
+        assert_eq!(index.stats().expect("stats").file_count, 1);
+    }
+}
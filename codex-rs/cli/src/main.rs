@@ -31,13 +31,17 @@ use std::path::PathBuf;
 use supports_color::Stream;
 
 mod cache_cmd;
+mod doctor_cmd;
 mod index_cmd;
 mod mcp_cmd;
+#[cfg(feature = "otel")]
+mod otel;
 mod search_cmd;
 #[cfg(not(windows))]
 mod wsl_paths;
 
 use crate::cache_cmd::CacheCommand;
+use crate::doctor_cmd::DoctorCommand;
 use crate::index_cmd::IndexCommand;
 use crate::mcp_cmd::McpCli;
 use crate::search_cmd::SearchCommand;
@@ -116,6 +120,9 @@ enum Subcommand {
     /// Search the semantic index for this workspace.
     Search(SearchCommand),
 
+    /// Report ripgrep availability and which grep_files backend is in use.
+    Doctor(DoctorCommand),
+
     /// Run commands within a Codex-provided sandbox.
     #[clap(visible_alias = "debug")]
     Sandbox(SandboxArgs),
@@ -585,6 +592,13 @@ async fn cli_main(codex_linux_sandbox_exe: Option<PathBuf>) -> anyhow::Result<()
             );
             search_cmd::run_search_command(search_cli).await?;
         }
+        Some(Subcommand::Doctor(mut doctor_cli)) => {
+            prepend_config_flags(
+                &mut doctor_cli.config_overrides,
+                root_config_overrides.clone(),
+            );
+            doctor_cmd::run_doctor_command(doctor_cli).await?;
+        }
         Some(Subcommand::Cloud(mut cloud_cli)) => {
             prepend_config_flags(
                 &mut cloud_cli.config_overrides,
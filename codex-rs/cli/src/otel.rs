@@ -0,0 +1,18 @@
+use codex_core::config::Config;
+use codex_otel::otel_provider::OtelProvider;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+/// Exports the semantic index spans (`semantic_index.build`,
+/// `semantic_index.search`, and their children) through the `[otel]`
+/// `trace_exporter`, when one is configured. Spans are flushed when the
+/// returned provider is dropped, so hold it until the command finishes.
+pub(crate) fn init_tracing(config: &Config) -> anyhow::Result<Option<OtelProvider>> {
+    let otel = codex_core::otel_init::build_provider(config, env!("CARGO_PKG_VERSION"))
+        .map_err(|err| anyhow::anyhow!("could not create otel exporter: {err}"))?;
+    let otel_tracing_layer = otel.as_ref().and_then(|otel| otel.tracing_layer());
+    let _ = tracing_subscriber::registry()
+        .with(otel_tracing_layer)
+        .try_init();
+    Ok(otel)
+}
@@ -1,15 +1,21 @@
 use anyhow::Context;
 use anyhow::Result;
 use clap::Parser;
+use clap::ValueEnum;
 use codex_common::CliConfigOverrides;
 use codex_core::AuthManager;
 use codex_core::config::Config;
 use codex_core::config::ConfigOverrides;
 use codex_core::semantic::index::SearchHit;
+use codex_core::semantic::index::SearchMode;
 use codex_core::semantic::index::SemanticIndex;
+use codex_core::semantic::vector_store::index_path_to_native;
+use rustyline::DefaultEditor;
+use rustyline::error::ReadlineError;
 use serde::Serialize;
 use std::fs;
 use std::path::Path;
+use std::path::PathBuf;
 use std::sync::Arc;
 
 #[derive(Debug, Parser)]
@@ -18,22 +24,113 @@ pub(crate) struct SearchCommand {
     #[arg(value_name = "QUERY", num_args = 1..)]
     pub(crate) query: Vec<String>,
 
-    /// Number of top matches to return (defaults to config).
+    /// Number of top matches to return (defaults to config). Must be at
+    /// least 1; larger values are clamped to
+    /// `semantic_index.retrieve.max_top_k` (default 100).
     #[arg(long, value_name = "N")]
     pub(crate) topk: Option<usize>,
 
-    /// Output results as JSON.
+    /// Lines of surrounding context to show around each match (defaults to config).
+    #[arg(long, value_name = "N")]
+    pub(crate) context: Option<usize>,
+
+    /// Ranking strategy: embeddings, full-text keywords, or both fused.
+    #[arg(long, value_enum, default_value_t = SearchModeArg::Semantic)]
+    pub(crate) mode: SearchModeArg,
+
+    /// Fuse semantic results with a literal ripgrep search of the workspace
+    /// for the query terms instead of ranking by `--mode`.
+    #[arg(long, conflicts_with_all = ["mode", "batch"])]
+    pub(crate) hybrid: bool,
+
+    /// Weight of the semantic ranking in `--hybrid` fusion, from 0.0
+    /// (lexical only) to 1.0 (semantic only).
+    #[arg(
+        long,
+        value_name = "WEIGHT",
+        default_value_t = DEFAULT_FUSION_WEIGHT,
+        requires = "hybrid"
+    )]
+    pub(crate) fusion_weight: f32,
+
+    /// Report only file paths, line ranges, and scores without reading
+    /// snippets from disk.
     #[arg(long)]
+    pub(crate) no_snippet: bool,
+
+    /// Output results as JSON (same as `--format json`).
+    #[arg(long, conflicts_with = "format")]
     pub(crate) json: bool,
 
+    /// Output format. CSV omits snippets.
+    #[arg(long, value_enum, default_value_t = SearchFormatArg::Text)]
+    pub(crate) format: SearchFormatArg,
+
+    /// Start an interactive prompt that runs one search per line. Type
+    /// `/topk N` to change the result count and `exit` or `quit` to leave.
+    #[arg(long, short = 'i', conflicts_with_all = ["json", "format"])]
+    pub(crate) interactive: bool,
+
+    /// Run one search per non-empty line of FILE and print the results
+    /// grouped by query.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["query", "interactive"])]
+    pub(crate) batch: Option<PathBuf>,
+
+    /// Maximum number of `--batch` queries embedded at once.
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = DEFAULT_BATCH_CONCURRENCY,
+        requires = "batch"
+    )]
+    pub(crate) concurrency: usize,
+
     #[clap(flatten)]
     pub(crate) config_overrides: CliConfigOverrides,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "kebab-case")]
+pub(crate) enum SearchModeArg {
+    Semantic,
+    Keyword,
+    Hybrid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "kebab-case")]
+pub(crate) enum SearchFormatArg {
+    Text,
+    Json,
+    Csv,
+}
+
+/// How each query is answered.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SearchStrategy {
+    Index(SearchMode),
+    /// Semantic results fused with a literal ripgrep search.
+    Grep {
+        semantic_weight: f32,
+    },
+}
+
+impl From<SearchModeArg> for SearchMode {
+    fn from(value: SearchModeArg) -> Self {
+        match value {
+            SearchModeArg::Semantic => SearchMode::Semantic,
+            SearchModeArg::Keyword => SearchMode::Keyword,
+            SearchModeArg::Hybrid => SearchMode::Hybrid,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct SnippetLine {
     line_number: usize,
     text: String,
+    /// True for lines outside the matched chunk, shown only as context.
+    is_context: bool,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -53,6 +150,27 @@ struct SearchResultsJson {
     results: Vec<SearchResultJson>,
 }
 
+/// Results of one query from a `--batch` file; a failed query keeps its error
+/// message instead.
+#[derive(Debug)]
+struct BatchQueryResults {
+    query: String,
+    results: std::result::Result<Vec<SearchResult>, String>,
+}
+
+#[derive(Debug, Serialize)]
+struct BatchResultsJson {
+    top_k: usize,
+    queries: Vec<BatchQueryJson>,
+}
+
+#[derive(Debug, Serialize)]
+struct BatchQueryJson {
+    query: String,
+    results: Vec<SearchResultJson>,
+    error: Option<String>,
+}
+
 #[derive(Debug, Serialize)]
 struct SearchResultJson {
     file_path: String,
@@ -63,17 +181,87 @@ struct SearchResultJson {
     snippet_error: Option<String>,
 }
 
+#[derive(Debug, Serialize)]
+struct SearchResultCsv<'a> {
+    file_path: &'a str,
+    start_line: usize,
+    end_line: usize,
+    score: f32,
+}
+
 #[derive(Debug, Serialize)]
 struct SnippetLineJson {
     line_number: usize,
     text: String,
+    is_context: bool,
+}
+
+/// File under `CODEX_HOME` holding the interactive search history.
+const SEARCH_HISTORY_FILE: &str = "search_history";
+/// Default cap on concurrently embedded `--batch` queries.
+const DEFAULT_BATCH_CONCURRENCY: usize = 4;
+/// Default semantic weight for `--hybrid`; both rankings count equally.
+const DEFAULT_FUSION_WEIGHT: f32 = 0.5;
+
+/// One line of input to the interactive search prompt.
+#[derive(Debug, PartialEq, Eq)]
+enum ReplInput<'a> {
+    Empty,
+    Exit,
+    TopK(usize),
+    Query(&'a str),
+    Invalid(String),
+}
+
+fn parse_repl_input(line: &str) -> ReplInput<'_> {
+    let line = line.trim();
+    if line.is_empty() {
+        return ReplInput::Empty;
+    }
+    if matches!(line, "exit" | "quit") {
+        return ReplInput::Exit;
+    }
+    if let Some(arg) = line.strip_prefix("/topk") {
+        return match arg.trim().parse::<usize>() {
+            Ok(top_k) if top_k > 0 => ReplInput::TopK(top_k),
+            _ => ReplInput::Invalid("usage: /topk N (N > 0)".to_string()),
+        };
+    }
+    ReplInput::Query(line)
 }
 
 pub(crate) async fn run_search_command(cmd: SearchCommand) -> Result<()> {
     let query = cmd.query.join(" ").trim().to_string();
-    if query.is_empty() {
+    if query.is_empty() && !cmd.interactive && cmd.batch.is_none() {
         anyhow::bail!("search query cannot be empty");
     }
+    if cmd.topk == Some(0) {
+        anyhow::bail!("--topk must be at least 1");
+    }
+    if !(0.0..=1.0).contains(&cmd.fusion_weight) {
+        anyhow::bail!("--fusion-weight must be between 0.0 and 1.0");
+    }
+    let strategy = if cmd.hybrid {
+        SearchStrategy::Grep {
+            semantic_weight: cmd.fusion_weight,
+        }
+    } else {
+        SearchStrategy::Index(cmd.mode.into())
+    };
+    let format = if cmd.json {
+        SearchFormatArg::Json
+    } else {
+        cmd.format
+    };
+    let batch_queries = match cmd.batch.as_deref() {
+        Some(path) => {
+            if format == SearchFormatArg::Csv {
+                anyhow::bail!("--batch does not support --format csv");
+            }
+            Some(read_batch_queries(path)?)
+        }
+        None => None,
+    };
 
     let cli_overrides = cmd
         .config_overrides
@@ -84,6 +272,8 @@ pub(crate) async fn run_search_command(cmd: SearchCommand) -> Result<()> {
         ConfigOverrides::default(),
     )
     .await?;
+    #[cfg(feature = "otel")]
+    let _otel = crate::otel::init_tracing(&config)?;
 
     let auth_manager = Arc::new(AuthManager::new(
         config.codex_home.clone(),
@@ -95,17 +285,115 @@ pub(crate) async fn run_search_command(cmd: SearchCommand) -> Result<()> {
         config.semantic_index.clone(),
         config.model_provider.clone(),
         Some(auth_manager),
-    );
+    )
+    .with_grep_backend(config.grep_backend);
 
-    let top_k = cmd.topk.unwrap_or(config.semantic_index.retrieve.top_k);
-    let hits = index.search(&query, top_k).await?;
-    let results = build_search_results(
-        config.cwd.as_path(),
-        hits,
-        config.semantic_index.retrieve.max_chars,
-    );
+    let max_top_k = config.semantic_index.retrieve.max_top_k;
+    let top_k = cmd
+        .topk
+        .unwrap_or(config.semantic_index.retrieve.top_k)
+        .min(max_top_k);
+    let context_lines = cmd
+        .context
+        .unwrap_or(config.semantic_index.retrieve.context_lines);
+    let snippets = !cmd.no_snippet;
+
+    if cmd.interactive {
+        let mut top_k = top_k;
+        let mut editor = DefaultEditor::new()?;
+        let history_path = config.codex_home.join(SEARCH_HISTORY_FILE);
+        // A missing history file just means this is the first session.
+        let _ = editor.load_history(&history_path);
+        let mut pending = (!query.is_empty()).then_some(query);
+        loop {
+            let line = match pending.take() {
+                Some(query) => query,
+                None => match editor.readline("> ") {
+                    Ok(line) => line,
+                    Err(ReadlineError::Interrupted) => continue,
+                    Err(ReadlineError::Eof) => break,
+                    Err(err) => return Err(err.into()),
+                },
+            };
+            match parse_repl_input(&line) {
+                ReplInput::Empty => continue,
+                ReplInput::Exit => break,
+                ReplInput::TopK(value) => {
+                    top_k = value.min(max_top_k);
+                    println!("top_k = {top_k}");
+                }
+                ReplInput::Invalid(message) => println!("{message}"),
+                ReplInput::Query(query) => match run_query(
+                    &index,
+                    &config,
+                    query,
+                    top_k,
+                    strategy,
+                    context_lines,
+                    snippets,
+                )
+                .await
+                {
+                    Ok(results) => {
+                        for line in format_search_results(&results) {
+                            println!("{line}");
+                        }
+                    }
+                    Err(err) => println!("Search failed: {err:#}"),
+                },
+            }
+            let _ = editor.add_history_entry(line.trim());
+        }
+        editor
+            .save_history(&history_path)
+            .with_context(|| format!("failed to save {}", history_path.display()))?;
+        return Ok(());
+    }
 
-    if cmd.json {
+    if let Some(queries) = batch_queries {
+        let batch = index
+            .search_batch(&queries, top_k, cmd.mode.into(), cmd.concurrency)
+            .await?;
+        let blocks: Vec<BatchQueryResults> = queries
+            .into_iter()
+            .zip(batch)
+            .map(|(query, hits)| BatchQueryResults {
+                query,
+                results: hits
+                    .map(|hits| search_results(&config, hits, context_lines, snippets))
+                    .map_err(|err| err.to_string()),
+            })
+            .collect();
+        if format == SearchFormatArg::Json {
+            let output = BatchResultsJson {
+                top_k,
+                queries: blocks.into_iter().map(BatchQueryJson::from).collect(),
+            };
+            let payload = serde_json::to_string_pretty(&output)?;
+            println!("{payload}");
+            return Ok(());
+        }
+        for line in format_batch_results(&blocks) {
+            println!("{line}");
+        }
+        return Ok(());
+    }
+
+    let results = run_query(
+        &index,
+        &config,
+        &query,
+        top_k,
+        strategy,
+        context_lines,
+        snippets,
+    )
+    .await?;
+
+    if format == SearchFormatArg::Csv {
+        return write_csv(std::io::stdout(), &results);
+    }
+    if format == SearchFormatArg::Json {
         let output = SearchResultsJson {
             query,
             top_k,
@@ -123,17 +411,106 @@ pub(crate) async fn run_search_command(cmd: SearchCommand) -> Result<()> {
     Ok(())
 }
 
+async fn run_query(
+    index: &SemanticIndex,
+    config: &Config,
+    query: &str,
+    top_k: usize,
+    strategy: SearchStrategy,
+    context_lines: usize,
+    snippets: bool,
+) -> Result<Vec<SearchResult>> {
+    let hits = match strategy {
+        SearchStrategy::Index(mode) => index.search(query, top_k, mode).await?,
+        SearchStrategy::Grep { semantic_weight } => {
+            index
+                .search_with_grep(query, top_k, semantic_weight)
+                .await?
+        }
+    };
+    Ok(search_results(config, hits, context_lines, snippets))
+}
+
+fn search_results(
+    config: &Config,
+    hits: Vec<SearchHit>,
+    context_lines: usize,
+    snippets: bool,
+) -> Vec<SearchResult> {
+    if !snippets {
+        return hits
+            .into_iter()
+            .map(SearchResult::without_snippet)
+            .collect();
+    }
+    build_search_results(
+        config.cwd.as_path(),
+        hits,
+        context_lines,
+        config.semantic_index.retrieve.max_chars,
+    )
+}
+
+/// Reads one query per non-empty line of `path`.
+fn read_batch_queries(path: &Path) -> Result<Vec<String>> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("failed to read batch file {}", path.display()))?;
+    let queries: Vec<String> = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect();
+    if queries.is_empty() {
+        anyhow::bail!("batch file {} contains no queries", path.display());
+    }
+    Ok(queries)
+}
+
+impl SearchResult {
+    fn without_snippet(hit: SearchHit) -> Self {
+        Self {
+            file_path: hit.file_path,
+            start_line: hit.start_line,
+            end_line: hit.end_line,
+            score: hit.score,
+            snippet: Vec::new(),
+            snippet_error: None,
+        }
+    }
+}
+
 fn build_search_results(
     workspace_root: &Path,
     hits: Vec<SearchHit>,
+    context_lines: usize,
     max_chars: usize,
 ) -> Vec<SearchResult> {
     hits.into_iter()
         .map(|hit| {
             let file_path = hit.file_path.clone();
-            let full_path = workspace_root.join(&file_path);
-            let snippet_result =
-                read_snippet_lines(&full_path, hit.start_line, hit.end_line, max_chars);
+            // Stored chunk text covers only the chunk itself, so context lines
+            // still require reading the file.
+            let snippet_result = match hit.chunk_text.as_deref() {
+                Some(text) if context_lines == 0 => Ok(snippet_lines_from_text(
+                    text,
+                    hit.start_line,
+                    hit.start_line,
+                    hit.end_line,
+                    0,
+                    max_chars,
+                )),
+                _ => {
+                    let full_path = workspace_root.join(index_path_to_native(&file_path));
+                    read_snippet_lines(
+                        &full_path,
+                        hit.start_line,
+                        hit.end_line,
+                        context_lines,
+                        max_chars,
+                    )
+                }
+            };
             let (snippet, snippet_error) = match snippet_result {
                 Ok(lines) => (lines, None),
                 Err(err) => (Vec::new(), Some(err.to_string())),
@@ -154,6 +531,7 @@ fn read_snippet_lines(
     path: &Path,
     start_line: usize,
     end_line: usize,
+    context_lines: usize,
     max_chars: usize,
 ) -> Result<Vec<SnippetLine>> {
     let path_display = path.display();
@@ -162,9 +540,33 @@ fn read_snippet_lines(
         return Ok(Vec::new());
     }
     let contents = String::from_utf8_lossy(&bytes);
+    Ok(snippet_lines_from_text(
+        &contents,
+        1,
+        start_line,
+        end_line,
+        context_lines,
+        max_chars,
+    ))
+}
+
+/// Collects snippet lines from `contents`, whose first line is `first_line_number`.
+///
+/// Lines within `context_lines` of `[start_line, end_line]` are included and
+/// flagged as context.
+fn snippet_lines_from_text(
+    contents: &str,
+    first_line_number: usize,
+    start_line: usize,
+    end_line: usize,
+    context_lines: usize,
+    max_chars: usize,
+) -> Vec<SnippetLine> {
     let mut out = Vec::new();
-    let start = start_line.max(1);
-    let end = end_line.max(start);
+    let chunk_start = start_line.max(1);
+    let chunk_end = end_line.max(chunk_start);
+    let start = chunk_start.saturating_sub(context_lines).max(1);
+    let end = chunk_end.saturating_add(context_lines);
     let mut remaining = if max_chars == 0 {
         usize::MAX
     } else {
@@ -172,7 +574,7 @@ fn read_snippet_lines(
     };
 
     for (idx, line) in contents.lines().enumerate() {
-        let line_number = idx + 1;
+        let line_number = idx + first_line_number;
         if line_number < start {
             continue;
         }
@@ -190,13 +592,37 @@ fn read_snippet_lines(
         if remaining != usize::MAX {
             remaining = remaining.saturating_sub(text.len());
         }
-        out.push(SnippetLine { line_number, text });
+        let is_context = line_number < chunk_start || line_number > chunk_end;
+        out.push(SnippetLine {
+            line_number,
+            text,
+            is_context,
+        });
         if remaining == 0 {
             break;
         }
     }
 
-    Ok(out)
+    out
+}
+
+/// Writes a `file_path,start_line,end_line,score` header and one row per
+/// result; snippets are omitted.
+fn write_csv<W: std::io::Write>(writer: W, results: &[SearchResult]) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    if results.is_empty() {
+        writer.write_record(["file_path", "start_line", "end_line", "score"])?;
+    }
+    for result in results {
+        writer.serialize(SearchResultCsv {
+            file_path: &result.file_path,
+            start_line: result.start_line,
+            end_line: result.end_line,
+            score: result.score,
+        })?;
+    }
+    writer.flush()?;
+    Ok(())
 }
 
 fn format_search_results(results: &[SearchResult]) -> Vec<String> {
@@ -213,25 +639,58 @@ fn format_search_results(results: &[SearchResult]) -> Vec<String> {
         lines.push(format!(
             "{file_path}:{start_line}-{end_line} score={score:.3}"
         ));
-        if result.snippet.is_empty() {
-            let message = result
-                .snippet_error
-                .as_deref()
-                .map(|err| format!("(snippet unavailable: {err})"))
-                .unwrap_or_else(|| "(no snippet)".to_string());
-            lines.push(format!("  {message}"));
+        if let Some(err) = result.snippet_error.as_deref() {
+            lines.push(format!("  (snippet unavailable: {err})"));
             continue;
         }
-        let width = result.end_line.to_string().len().max(1);
+        let last_line = result
+            .snippet
+            .last()
+            .map_or(result.end_line, |line| line.line_number);
+        let width = last_line.to_string().len().max(1);
         for snippet_line in &result.snippet {
             let line_number = snippet_line.line_number;
             let text = &snippet_line.text;
-            lines.push(format!("  {line_number:>width$} | {text}"));
+            let separator = if snippet_line.is_context { '·' } else { '|' };
+            lines.push(format!("  {line_number:>width$} {separator} {text}"));
+        }
+    }
+    lines
+}
+
+fn format_batch_results(blocks: &[BatchQueryResults]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (index, block) in blocks.iter().enumerate() {
+        if index > 0 {
+            lines.push(String::new());
+        }
+        let query = &block.query;
+        lines.push(format!("Query: {query}"));
+        match &block.results {
+            Ok(results) => lines.extend(format_search_results(results)),
+            Err(err) => lines.push(format!("Search failed: {err}")),
         }
     }
     lines
 }
 
+impl From<BatchQueryResults> for BatchQueryJson {
+    fn from(block: BatchQueryResults) -> Self {
+        let (results, error) = match block.results {
+            Ok(results) => (
+                results.into_iter().map(SearchResultJson::from).collect(),
+                None,
+            ),
+            Err(err) => (Vec::new(), Some(err)),
+        };
+        Self {
+            query: block.query,
+            results,
+            error,
+        }
+    }
+}
+
 impl From<SearchResult> for SearchResultJson {
     fn from(result: SearchResult) -> Self {
         Self {
@@ -245,6 +704,7 @@ impl From<SearchResult> for SearchResultJson {
                 .map(|line| SnippetLineJson {
                     line_number: line.line_number,
                     text: line.text,
+                    is_context: line.is_context,
                 })
                 .collect(),
             snippet_error: result.snippet_error,
@@ -258,16 +718,70 @@ mod tests {
     use pretty_assertions::assert_eq;
     use tempfile::tempdir;
 
+    #[test]
+    fn parse_repl_input_recognizes_commands() {
+        assert_eq!(parse_repl_input("  "), ReplInput::Empty);
+        assert_eq!(parse_repl_input("quit"), ReplInput::Exit);
+        assert_eq!(parse_repl_input(" exit "), ReplInput::Exit);
+        assert_eq!(parse_repl_input("/topk 7"), ReplInput::TopK(7));
+        assert_eq!(
+            parse_repl_input("/topk zero"),
+            ReplInput::Invalid("usage: /topk N (N > 0)".to_string())
+        );
+        assert_eq!(parse_repl_input("/topk 0"), parse_repl_input("/topk"));
+        assert_eq!(
+            parse_repl_input(" where is auth handled "),
+            ReplInput::Query("where is auth handled")
+        );
+    }
+
+    #[test]
+    fn write_csv_quotes_fields_and_omits_snippets() -> Result<()> {
+        let result = |file_path: &str, score: f32| SearchResult {
+            file_path: file_path.to_string(),
+            start_line: 3,
+            end_line: 9,
+            score,
+            snippet: vec![SnippetLine {
+                line_number: 3,
+                text: "fn main() {}".to_string(),
+                is_context: false,
+            }],
+            snippet_error: None,
+        };
+        let mut out = Vec::new();
+
+        write_csv(
+            &mut out,
+            &[result("src/main.rs", 0.5), result("docs/a,\"b\".md", 0.25)],
+        )?;
+
+        assert_eq!(
+            String::from_utf8(out)?,
+            "file_path,start_line,end_line,score\n\
+             src/main.rs,3,9,0.5\n\
+             \"docs/a,\"\"b\"\".md\",3,9,0.25\n"
+        );
+        let mut empty = Vec::new();
+        write_csv(&mut empty, &[])?;
+        assert_eq!(
+            String::from_utf8(empty)?,
+            "file_path,start_line,end_line,score\n"
+        );
+        Ok(())
+    }
+
     #[test]
     fn read_snippet_lines_truncates_to_max_chars() -> Result<()> {
         let dir = tempdir()?;
         let path = dir.path().join("sample.txt");
         fs::write(&path, "abcdef\n")?;
 
-        let lines = read_snippet_lines(&path, 1, 1, 3)?;
+        let lines = read_snippet_lines(&path, 1, 1, 0, 3)?;
         let expected = vec![SnippetLine {
             line_number: 1,
             text: "abc".to_string(),
+            is_context: false,
         }];
         assert_eq!(lines, expected);
         Ok(())
@@ -285,8 +799,9 @@ mod tests {
             end_line: 3,
             score: 0.42,
             chunk_id: "chunk-1".to_string(),
+            chunk_text: None,
         };
-        let results = build_search_results(dir.path(), vec![hit], 1024);
+        let results = build_search_results(dir.path(), vec![hit], 0, 1024);
         let rendered = format_search_results(&results);
 
         assert_eq!(
@@ -299,4 +814,144 @@ mod tests {
         );
         Ok(())
     }
+
+    #[test]
+    fn format_search_results_prints_header_only_without_snippet() {
+        let hit = SearchHit {
+            file_path: "missing.rs".to_string(),
+            start_line: 4,
+            end_line: 9,
+            score: 0.75,
+            chunk_id: "chunk-1".to_string(),
+            chunk_text: None,
+        };
+        let results = vec![SearchResult::without_snippet(hit)];
+
+        assert_eq!(results[0].snippet, Vec::new());
+        assert_eq!(results[0].snippet_error, None);
+        assert_eq!(
+            format_search_results(&results),
+            vec!["missing.rs:4-9 score=0.750".to_string()]
+        );
+    }
+
+    #[test]
+    fn context_lines_surround_chunk_and_render_as_context() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("sample.rs");
+        fs::write(&path, "one\ntwo\nthree\nfour\nfive\n")?;
+
+        let hit = SearchHit {
+            file_path: "sample.rs".to_string(),
+            start_line: 2,
+            end_line: 3,
+            score: 0.42,
+            chunk_id: "chunk-1".to_string(),
+            chunk_text: Some("two\nthree".to_string()),
+        };
+        let results = build_search_results(dir.path(), vec![hit], 1, 1024);
+
+        assert_eq!(
+            results[0].snippet,
+            vec![
+                SnippetLine {
+                    line_number: 1,
+                    text: "one".to_string(),
+                    is_context: true,
+                },
+                SnippetLine {
+                    line_number: 2,
+                    text: "two".to_string(),
+                    is_context: false,
+                },
+                SnippetLine {
+                    line_number: 3,
+                    text: "three".to_string(),
+                    is_context: false,
+                },
+                SnippetLine {
+                    line_number: 4,
+                    text: "four".to_string(),
+                    is_context: true,
+                },
+            ]
+        );
+        assert_eq!(
+            format_search_results(&results),
+            vec![
+                "sample.rs:2-3 score=0.420".to_string(),
+                "  1 · one".to_string(),
+                "  2 | two".to_string(),
+                "  3 | three".to_string(),
+                "  4 · four".to_string(),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn build_search_results_resolves_windows_style_paths() -> Result<()> {
+        let dir = tempdir()?;
+        fs::create_dir(dir.path().join("src"))?;
+        fs::write(dir.path().join("src").join("sample.rs"), "one\ntwo\n")?;
+
+        let hit = SearchHit {
+            file_path: "src\\sample.rs".to_string(),
+            start_line: 2,
+            end_line: 2,
+            score: 0.42,
+            chunk_id: "chunk-1".to_string(),
+            chunk_text: None,
+        };
+        let results = build_search_results(dir.path(), vec![hit], 0, 1024);
+
+        assert_eq!(results[0].snippet_error, None);
+        assert_eq!(
+            results[0].snippet,
+            vec![SnippetLine {
+                line_number: 2,
+                text: "two".to_string(),
+                is_context: false,
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn build_search_results_prefers_chunk_text() {
+        let dir = tempdir().expect("tempdir");
+        let hit = SearchHit {
+            file_path: "missing.rs".to_string(),
+            start_line: 10,
+            end_line: 11,
+            score: 0.5,
+            chunk_id: "chunk-1".to_string(),
+            chunk_text: Some("ten\neleven".to_string()),
+        };
+
+        let results = build_search_results(dir.path(), vec![hit], 0, 1024);
+
+        assert_eq!(
+            results,
+            vec![SearchResult {
+                file_path: "missing.rs".to_string(),
+                start_line: 10,
+                end_line: 11,
+                score: 0.5,
+                snippet: vec![
+                    SnippetLine {
+                        line_number: 10,
+                        text: "ten".to_string(),
+                        is_context: false,
+                    },
+                    SnippetLine {
+                        line_number: 11,
+                        text: "eleven".to_string(),
+                        is_context: false,
+                    },
+                ],
+                snippet_error: None,
+            }]
+        );
+    }
 }
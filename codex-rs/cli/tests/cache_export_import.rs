@@ -0,0 +1,58 @@
+use assert_cmd::Command;
+use pretty_assertions::assert_eq;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use tempfile::TempDir;
+
+fn codex(codex_home: &TempDir, args: &[&str]) -> Result<String, Box<dyn std::error::Error>> {
+    let output = Command::cargo_bin("codex")?
+        .env("CODEX_HOME", codex_home.path())
+        // Outside a git repository, so `inspect` keys are not namespaced.
+        .current_dir(codex_home.path())
+        .args(["cache"])
+        .args(args)
+        .output()?;
+    assert!(
+        output.status.success(),
+        "codex cache {args:?} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+#[test]
+fn cache_export_then_import_restores_entries() -> Result<(), Box<dyn std::error::Error>> {
+    let codex_home = TempDir::new()?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let snapshot = codex_home.path().join("snapshot.jsonl");
+    std::fs::write(
+        &snapshot,
+        format!(
+            "{{\"key\":\"alpha\",\"value_b64\":\"b25l\",\"ttl_secs\":3600,\"inserted_epoch\":{now}}}\n\
+             {{\"key\":\"bravo\",\"value_b64\":\"dHdv\",\"ttl_secs\":3600,\"inserted_epoch\":{now}}}\n"
+        ),
+    )?;
+    let archive = codex_home.path().join("backup.tar.zst");
+    let archive_arg = archive.to_string_lossy().to_string();
+
+    codex(&codex_home, &["warm", &snapshot.to_string_lossy()])?;
+    let exported = codex(&codex_home, &["export", &archive_arg])?;
+    codex(&codex_home, &["clear"])?;
+    let imported = codex(&codex_home, &["import", &archive_arg])?;
+
+    assert!(exported.starts_with("Exported 2 entries"), "{exported}");
+    assert_eq!(
+        imported.trim(),
+        "Imported 2 entries (skipped 0 expired, 0 already cached)"
+    );
+    assert_eq!(
+        codex(&codex_home, &["inspect", "alpha", "--raw"])?.trim(),
+        "one"
+    );
+    assert_eq!(
+        codex(&codex_home, &["inspect", "bravo", "--raw"])?.trim(),
+        "two"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,18 @@
+use assert_cmd::Command;
+use tempfile::TempDir;
+
+#[test]
+fn cache_prune_reports_removed_entries_and_freed_bytes() -> Result<(), Box<dyn std::error::Error>> {
+    let codex_home = TempDir::new()?;
+
+    let output = Command::cargo_bin("codex")?
+        .env("CODEX_HOME", codex_home.path())
+        .args(["cache", "prune"])
+        .output()?;
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.trim(), "Removed 0 expired entries, freed 0 bytes.");
+
+    Ok(())
+}
@@ -0,0 +1,31 @@
+use assert_cmd::Command;
+use pretty_assertions::assert_eq;
+use serde_json::Value;
+use tempfile::TempDir;
+
+#[test]
+fn cache_status_json_reports_expected_fields() -> Result<(), Box<dyn std::error::Error>> {
+    let codex_home = TempDir::new()?;
+
+    let output = Command::cargo_bin("codex")?
+        .env("CODEX_HOME", codex_home.path())
+        .args(["cache", "status", "--json"])
+        .output()?;
+
+    assert!(output.status.success());
+    let status: Value = serde_json::from_slice(&output.stdout)?;
+    for field in ["enabled", "dir", "max_bytes", "hit_rate"] {
+        assert!(status.get(field).is_some(), "missing {field}: {status}");
+    }
+    assert_eq!(status["entries"], Value::from(0));
+    assert_eq!(status["total_bytes"], Value::from(0));
+    let tools = status["by_tool"]
+        .as_array()
+        .ok_or("by_tool is not an array")?
+        .iter()
+        .filter_map(|tool| tool["tool"].as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(tools, vec!["read_file", "list_dir", "grep_files"]);
+
+    Ok(())
+}
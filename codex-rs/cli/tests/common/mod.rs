@@ -0,0 +1,36 @@
+use chrono::Utc;
+use codex_core::semantic::config::DEFAULT_SEMANTIC_INDEX_DIR;
+use codex_core::semantic::vector_store::ChunkEntry;
+use codex_core::semantic::vector_store::StoreMode;
+use codex_core::semantic::vector_store::VectorStore;
+use std::path::Path;
+
+/// Writes each `(path, text)` file into `workspace` and seeds the semantic
+/// index with one chunk per file, so `codex search` can run without
+/// embedding anything.
+pub fn seed_index(workspace: &Path, files: &[(&str, &str)]) -> anyhow::Result<()> {
+    let mut chunks = Vec::with_capacity(files.len());
+    for (file_path, text) in files {
+        std::fs::write(workspace.join(file_path), format!("{text}\n"))?;
+        chunks.push(chunk(file_path, text));
+    }
+    let store = VectorStore::open(
+        &workspace.join(DEFAULT_SEMANTIC_INDEX_DIR),
+        StoreMode::CreateOrOpen,
+    )?;
+    store.store_chunks(&chunks)?;
+    Ok(())
+}
+
+fn chunk(file_path: &str, text: &str) -> ChunkEntry {
+    ChunkEntry {
+        file_path: file_path.to_string(),
+        chunk_id: format!("{file_path}-chunk"),
+        start_line: 1,
+        end_line: 1,
+        text_hash: format!("{file_path}-hash"),
+        text: text.to_string(),
+        embedding: vec![1.0, 0.0],
+        updated_at: Utc::now(),
+    }
+}
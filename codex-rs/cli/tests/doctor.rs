@@ -0,0 +1,27 @@
+use assert_cmd::Command;
+use tempfile::TempDir;
+
+#[test]
+fn doctor_reports_missing_ripgrep_and_builtin_fallback() -> Result<(), Box<dyn std::error::Error>> {
+    let codex_home = TempDir::new()?;
+    let empty_path = TempDir::new()?;
+
+    let output = Command::cargo_bin("codex")?
+        .env("CODEX_HOME", codex_home.path())
+        .env("PATH", empty_path.path())
+        .arg("doctor")
+        .output()?;
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("ripgrep: not found on PATH"),
+        "stdout: {stdout}"
+    );
+    assert!(
+        stdout.contains("grep_files backend: builtin (grep_backend = \"auto\")"),
+        "stdout: {stdout}"
+    );
+
+    Ok(())
+}
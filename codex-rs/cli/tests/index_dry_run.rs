@@ -0,0 +1,47 @@
+use assert_cmd::Command;
+use pretty_assertions::assert_eq;
+use tempfile::TempDir;
+
+#[test]
+fn index_build_dry_run_reports_estimate_without_writing_index()
+-> Result<(), Box<dyn std::error::Error>> {
+    let codex_home = TempDir::new()?;
+    let workspace = TempDir::new()?;
+    std::fs::write(workspace.path().join("main.rs"), "fn main() {}\n")?;
+
+    let output = Command::cargo_bin("codex")?
+        .env("CODEX_HOME", codex_home.path())
+        .current_dir(workspace.path())
+        .args([
+            "index",
+            "build",
+            "--dry-run",
+            "--cost-per-1k-tokens",
+            "1000",
+        ])
+        .output()?;
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout
+        .lines()
+        .filter(|line| {
+            line.starts_with("Files:")
+                || line.starts_with("Chunks:")
+                || line.starts_with("Estimated tokens:")
+                || line.starts_with("Projected cost:")
+        })
+        .collect();
+    assert_eq!(
+        lines,
+        vec![
+            "Files:            1",
+            "Chunks:           1",
+            "Estimated tokens: 3",
+            "Projected cost:   $3.0000",
+        ]
+    );
+    assert!(!workspace.path().join(".codex-index").exists());
+
+    Ok(())
+}
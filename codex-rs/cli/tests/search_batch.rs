@@ -0,0 +1,47 @@
+mod common;
+
+use assert_cmd::Command;
+use common::seed_index;
+use pretty_assertions::assert_eq;
+use tempfile::TempDir;
+
+#[test]
+fn search_batch_prints_results_for_each_query() -> Result<(), Box<dyn std::error::Error>> {
+    let codex_home = TempDir::new()?;
+    let workspace = TempDir::new()?;
+    seed_index(
+        workspace.path(),
+        &[
+            ("alpha.rs", "fn parse_config() {}"),
+            ("beta.rs", "fn render_widget() {}"),
+        ],
+    )?;
+    let queries = workspace.path().join("queries.txt");
+    std::fs::write(&queries, "parse_config\n\nrender_widget\n")?;
+
+    let output = Command::cargo_bin("codex")?
+        .env("CODEX_HOME", codex_home.path())
+        .current_dir(workspace.path())
+        .args(["search", "--mode", "keyword", "--no-snippet", "--batch"])
+        .arg(&queries)
+        .output()?;
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let blocks: Vec<&str> = stdout.trim_end().split("\n\n").collect();
+    assert_eq!(blocks.len(), 2);
+    assert!(
+        blocks[0].starts_with("Query: parse_config\nalpha.rs:1-1 score="),
+        "{stdout}"
+    );
+    assert!(
+        blocks[1].starts_with("Query: render_widget\nbeta.rs:1-1 score="),
+        "{stdout}"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use assert_cmd::Command;
+use common::seed_index;
+use pretty_assertions::assert_eq;
+use tempfile::TempDir;
+
+#[test]
+fn search_rejects_zero_topk() -> Result<(), Box<dyn std::error::Error>> {
+    let codex_home = TempDir::new()?;
+    let workspace = TempDir::new()?;
+
+    let output = Command::cargo_bin("codex")?
+        .env("CODEX_HOME", codex_home.path())
+        .current_dir(workspace.path())
+        .args(["search", "--topk", "0", "parse_config"])
+        .output()?;
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--topk must be at least 1"), "{stderr}");
+
+    Ok(())
+}
+
+#[test]
+fn search_clamps_topk_to_configured_maximum() -> Result<(), Box<dyn std::error::Error>> {
+    let codex_home = TempDir::new()?;
+    let workspace = TempDir::new()?;
+    seed_index(
+        workspace.path(),
+        &[
+            ("alpha.rs", "fn parse_config() {}"),
+            ("beta.rs", "fn parse_args() {}"),
+        ],
+    )?;
+
+    let output = Command::cargo_bin("codex")?
+        .env("CODEX_HOME", codex_home.path())
+        .current_dir(workspace.path())
+        .args([
+            "search",
+            "-c",
+            "semantic_index.retrieve.max_top_k=1",
+            "--mode",
+            "keyword",
+            "--no-snippet",
+            "--json",
+            "--topk",
+            "50",
+            "fn",
+        ])
+        .output()?;
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(json["top_k"], 1);
+    assert_eq!(json["results"].as_array().map(Vec::len), Some(1));
+
+    Ok(())
+}
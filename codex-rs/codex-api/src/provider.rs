@@ -92,10 +92,12 @@ impl Provider {
     }
 
     pub fn is_azure_responses_endpoint(&self) -> bool {
-        if self.wire != WireApi::Responses {
-            return false;
-        }
+        self.wire == WireApi::Responses && self.is_azure_endpoint()
+    }
 
+    /// Whether this provider points at Azure OpenAI, judged by its name or
+    /// base URL, regardless of wire API.
+    pub fn is_azure_endpoint(&self) -> bool {
         if self.name.eq_ignore_ascii_case("azure") {
             return true;
         }
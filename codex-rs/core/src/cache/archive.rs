@@ -0,0 +1,182 @@
+//! Compressed cache backups: a zstd-compressed tar holding `index.json` and an
+//! `entries/` subtree laid out like the on-disk cache.
+
+use crate::cache::snapshot::remaining_ttl;
+use crate::cache::store::CacheEntry;
+use crate::cache::store::CacheStoreSnapshotEntry;
+use crate::cache::store::sharded_entry_path;
+use crate::cache::store::validate_entry_key;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+const ARCHIVE_INDEX_PATH: &str = "index.json";
+const ARCHIVE_ENTRIES_DIR: &str = "entries";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ArchiveIndex {
+    entries: BTreeMap<String, ArchiveIndexEntry>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct ArchiveIndexEntry {
+    ttl_secs: u64,
+    inserted_epoch: u64,
+}
+
+/// Writes `entries` as a `.tar.zst` archive, returning the number written.
+pub fn write_archive<W: Write>(
+    writer: W,
+    entries: &[CacheStoreSnapshotEntry],
+) -> std::io::Result<usize> {
+    let mut builder = tar::Builder::new(zstd::Encoder::new(writer, 0)?);
+    let mut index = ArchiveIndex::default();
+    for snapshot in entries {
+        let entry = &snapshot.entry;
+        append_file(
+            &mut builder,
+            &entry_archive_path(&entry.key),
+            &entry.value,
+            snapshot.inserted_epoch,
+        )?;
+        index.entries.insert(
+            entry.key.clone(),
+            ArchiveIndexEntry {
+                ttl_secs: entry.ttl.as_secs(),
+                inserted_epoch: snapshot.inserted_epoch,
+            },
+        );
+    }
+    let index_json = serde_json::to_vec_pretty(&index)?;
+    append_file(&mut builder, Path::new(ARCHIVE_INDEX_PATH), &index_json, 0)?;
+    builder.into_inner()?.finish()?.flush()?;
+    Ok(entries.len())
+}
+
+/// Reads an archive written by [`write_archive`]. Entries whose TTL has run
+/// out by `now` come back as `None`; the rest keep their remaining TTL. An
+/// index key that is not a valid entry name rejects the whole archive.
+pub fn read_archive<R: Read>(reader: R, now: u64) -> std::io::Result<Vec<Option<CacheEntry>>> {
+    let mut archive = tar::Archive::new(zstd::Decoder::new(reader)?);
+    let mut files = HashMap::new();
+    for file in archive.entries()? {
+        let mut file = file?;
+        let path = file.path()?.into_owned();
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+        files.insert(path, contents);
+    }
+
+    let index_json = files.remove(Path::new(ARCHIVE_INDEX_PATH)).ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("cache archive is missing {ARCHIVE_INDEX_PATH}"),
+        )
+    })?;
+    let index: ArchiveIndex = serde_json::from_slice(&index_json).map_err(|err| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("invalid cache archive index: {err}"),
+        )
+    })?;
+    for key in index.entries.keys() {
+        validate_entry_key(key)?;
+    }
+
+    let mut entries = Vec::with_capacity(index.entries.len());
+    for (key, meta) in index.entries {
+        let Some(ttl) = remaining_ttl(meta.inserted_epoch, meta.ttl_secs, now) else {
+            entries.push(None);
+            continue;
+        };
+        let value = files.remove(&entry_archive_path(&key)).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("cache archive is missing the value for {key}"),
+            )
+        })?;
+        entries.push(Some(CacheEntry {
+            key,
+            value,
+            ttl,
+            tool: None,
+        }));
+    }
+    Ok(entries)
+}
+
+fn entry_archive_path(key: &str) -> PathBuf {
+    sharded_entry_path(Path::new(ARCHIVE_ENTRIES_DIR), key)
+}
+
+fn append_file<W: Write>(
+    builder: &mut tar::Builder<W>,
+    path: &Path,
+    contents: &[u8],
+    mtime: u64,
+) -> std::io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(mtime);
+    builder.append_data(&mut header, path, contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::time::Duration;
+
+    fn snapshot_entry(key: &str, value: &[u8], ttl_secs: u64) -> CacheStoreSnapshotEntry {
+        CacheStoreSnapshotEntry {
+            entry: CacheEntry {
+                key: key.to_string(),
+                value: value.to_vec(),
+                ttl: Duration::from_secs(ttl_secs),
+                tool: None,
+            },
+            inserted_epoch: 1_000,
+        }
+    }
+
+    #[test]
+    fn archive_round_trips_live_entries() -> std::io::Result<()> {
+        let mut archive = Vec::new();
+        let written = write_archive(
+            &mut archive,
+            &[
+                snapshot_entry("alpha", b"one", 60),
+                snapshot_entry("bravo", b"two", 10),
+            ],
+        )?;
+
+        let entries = read_archive(archive.as_slice(), 1_020)?;
+
+        assert_eq!(written, 2);
+        assert_eq!(entries.len(), 2);
+        let alpha = entries[0].as_ref().expect("alpha is live");
+        assert_eq!(alpha.key, "alpha");
+        assert_eq!(alpha.value, b"one".to_vec());
+        assert_eq!(alpha.ttl, Duration::from_secs(40));
+        assert!(entries[1].is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn archive_without_index_is_rejected() -> std::io::Result<()> {
+        let mut builder = tar::Builder::new(zstd::Encoder::new(Vec::new(), 0)?);
+        append_file(&mut builder, &entry_archive_path("alpha"), b"one", 0)?;
+        let archive = builder.into_inner()?.finish()?;
+
+        let err = read_archive(archive.as_slice(), 0).expect_err("missing index");
+
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        Ok(())
+    }
+}
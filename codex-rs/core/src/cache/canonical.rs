@@ -0,0 +1,45 @@
+use serde_json::Map;
+use serde_json::Value as JsonValue;
+
+/// Returns `value` with every object's keys in lexicographic order, so equal
+/// arguments hash to the same cache key regardless of how they were built.
+pub fn canonical_json(value: &JsonValue) -> JsonValue {
+    match value {
+        JsonValue::Object(map) => {
+            let mut keys = map.keys().collect::<Vec<_>>();
+            keys.sort();
+            let mut sorted = Map::new();
+            for key in keys {
+                if let Some(val) = map.get(key) {
+                    sorted.insert(key.clone(), canonical_json(val));
+                }
+            }
+            JsonValue::Object(sorted)
+        }
+        JsonValue::Array(items) => JsonValue::Array(items.iter().map(canonical_json).collect()),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    #[test]
+    fn canonical_json_sorts_nested_keys_and_is_idempotent() {
+        let value = json!({
+            "z": [{ "b": 1, "a": 2 }],
+            "a": { "y": null, "x": "s" },
+        });
+
+        let canonical = canonical_json(&value);
+
+        assert_eq!(
+            serde_json::to_string(&canonical).expect("serialize"),
+            r#"{"a":{"x":"s","y":null},"z":[{"a":2,"b":1}]}"#
+        );
+        assert_eq!(canonical_json(&canonical), canonical);
+    }
+}
@@ -1,15 +1,22 @@
 use crate::cache::LOG_TARGET;
 use codex_utils_absolute_path::AbsolutePathBuf;
 use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+use std::fmt;
 use std::path::Path;
+use std::str::FromStr;
 use std::time::Duration;
 use tracing::debug;
 
 pub const DEFAULT_CACHE_DIR_NAME: &str = "cache";
 pub const DEFAULT_CACHE_MAX_BYTES: u64 = 256 * 1024 * 1024;
+pub const DEFAULT_CACHE_MAX_ENTRY_BYTES: u64 = 10 * 1024 * 1024;
 pub const DEFAULT_CACHE_DEFAULT_TTL_SECS: u64 = 60;
 pub const DEFAULT_CACHE_READ_FILE_TTL_SECS: u64 = 300;
 pub const DEFAULT_CACHE_GREP_FILES_TTL_SECS: u64 = 10;
+pub const DEFAULT_CACHE_S3_PREFIX: &str = "codex-cache/";
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum CacheableTool {
@@ -27,6 +34,14 @@ impl CacheableTool {
         }
     }
 
+    /// Parses a [`CacheableTool::config_key`] back into the tool.
+    pub fn from_config_key(key: &str) -> Option<Self> {
+        Self::all()
+            .iter()
+            .copied()
+            .find(|tool| tool.config_key() == key)
+    }
+
     pub fn all() -> &'static [CacheableTool; 3] {
         &[
             CacheableTool::ReadFile,
@@ -36,11 +51,49 @@ impl CacheableTool {
     }
 }
 
+impl fmt::Display for CacheableTool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.config_key())
+    }
+}
+
+impl FromStr for CacheableTool {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_config_key(s).ok_or_else(|| format!("unknown cacheable tool: {s}"))
+    }
+}
+
+impl Serialize for CacheableTool {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.config_key())
+    }
+}
+
+impl<'de> Deserialize<'de> for CacheableTool {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let key = String::deserialize(deserializer)?;
+        key.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CacheConfig {
     pub enabled: bool,
     pub dir: AbsolutePathBuf,
     pub max_bytes: u64,
+    /// Values larger than this are never cached.
+    pub max_entry_bytes: u64,
+    /// Serve reads from an existing cache directory without ever writing.
+    pub read_only: bool,
+    /// Append a CRC32 to each stored value and drop entries that fail it.
+    pub checksum: bool,
+    /// Store entries in this S3 bucket instead of `dir`. Requires the
+    /// `s3-cache` feature.
+    pub s3_bucket: Option<String>,
+    /// Prepended to every key to form the S3 object key.
+    pub s3_prefix: String,
     pub default_ttl: Duration,
     pub tool_ttl: CacheToolTtl,
 }
@@ -73,6 +126,15 @@ impl CacheConfig {
             enabled: cache.enabled.unwrap_or(true),
             dir,
             max_bytes: cache.max_bytes.unwrap_or(DEFAULT_CACHE_MAX_BYTES),
+            max_entry_bytes: cache
+                .max_entry_bytes
+                .unwrap_or(DEFAULT_CACHE_MAX_ENTRY_BYTES),
+            read_only: cache.read_only.unwrap_or(false),
+            checksum: cache.checksum.unwrap_or(false),
+            s3_bucket: cache.s3_bucket,
+            s3_prefix: cache
+                .s3_prefix
+                .unwrap_or_else(|| DEFAULT_CACHE_S3_PREFIX.to_string()),
             default_ttl,
             tool_ttl,
         })
@@ -127,6 +189,11 @@ pub struct CacheConfigToml {
     pub enabled: Option<bool>,
     pub dir: Option<AbsolutePathBuf>,
     pub max_bytes: Option<u64>,
+    pub max_entry_bytes: Option<u64>,
+    pub read_only: Option<bool>,
+    pub checksum: Option<bool>,
+    pub s3_bucket: Option<String>,
+    pub s3_prefix: Option<String>,
     pub default_ttl_sec: Option<u64>,
     #[serde(default)]
     pub tool_ttl_sec: CacheToolTtlToml,
@@ -145,6 +212,25 @@ mod tests {
     use pretty_assertions::assert_eq;
     use tempfile::tempdir;
 
+    #[test]
+    fn cacheable_tool_round_trips_through_strings() {
+        for tool in CacheableTool::all() {
+            let key = tool.to_string();
+            assert_eq!(key, tool.config_key());
+            assert_eq!(key.parse::<CacheableTool>(), Ok(*tool));
+
+            let json = serde_json::to_string(tool).expect("serialize");
+            assert_eq!(json, format!("\"{key}\""));
+            let parsed: CacheableTool = serde_json::from_str(&json).expect("deserialize");
+            assert_eq!(parsed, *tool);
+        }
+        assert_eq!(
+            "shell".parse::<CacheableTool>(),
+            Err("unknown cacheable tool: shell".to_string())
+        );
+        assert!(serde_json::from_str::<CacheableTool>("\"shell\"").is_err());
+    }
+
     #[test]
     fn defaults_use_codex_home_and_tool_overrides() {
         let codex_home = tempdir().expect("tempdir");
@@ -157,6 +243,11 @@ mod tests {
         assert!(config.enabled);
         assert_eq!(config.dir, expected_dir);
         assert_eq!(config.max_bytes, DEFAULT_CACHE_MAX_BYTES);
+        assert_eq!(config.max_entry_bytes, DEFAULT_CACHE_MAX_ENTRY_BYTES);
+        assert!(!config.read_only);
+        assert!(!config.checksum);
+        assert_eq!(config.s3_bucket, None);
+        assert_eq!(config.s3_prefix, DEFAULT_CACHE_S3_PREFIX);
         assert_eq!(
             config.default_ttl,
             Duration::from_secs(DEFAULT_CACHE_DEFAULT_TTL_SECS)
@@ -185,6 +276,11 @@ mod tests {
             enabled: Some(false),
             dir: Some(cache_dir.clone()),
             max_bytes: Some(1024),
+            max_entry_bytes: Some(512),
+            read_only: Some(true),
+            checksum: Some(true),
+            s3_bucket: Some("ci-cache".to_string()),
+            s3_prefix: Some("runs/".to_string()),
             default_ttl_sec: Some(5),
             tool_ttl_sec: CacheToolTtlToml {
                 read_file: Some(1),
@@ -198,6 +294,11 @@ mod tests {
         assert!(!config.enabled);
         assert_eq!(config.dir, cache_dir);
         assert_eq!(config.max_bytes, 1024);
+        assert_eq!(config.max_entry_bytes, 512);
+        assert!(config.read_only);
+        assert!(config.checksum);
+        assert_eq!(config.s3_bucket.as_deref(), Some("ci-cache"));
+        assert_eq!(config.s3_prefix, "runs/");
         assert_eq!(config.default_ttl, Duration::from_secs(5));
         assert_eq!(
             config.ttl_for(CacheableTool::ReadFile),
@@ -1,47 +1,165 @@
 use crate::cache::LOG_TARGET;
+use crate::cache::archive::read_archive;
+use crate::cache::archive::write_archive;
 use crate::cache::config::CacheConfig;
 use crate::cache::config::CacheableTool;
+#[cfg(feature = "s3-cache")]
+use crate::cache::s3_store::S3CacheStore;
+use crate::cache::snapshot::read_snapshot;
+use crate::cache::snapshot::write_snapshot;
 use crate::cache::store::CacheEntry;
 use crate::cache::store::CacheStore;
 use crate::cache::store::CacheStorePutOutcome;
 use crate::cache::store::CacheStoreStats;
 use crate::cache::store::DiskCacheStore;
+use crate::cache::store::now_epoch_secs;
+use crate::cache::store::read_only_error;
+use crate::cache::store::validate_entry_key;
+use crate::cache::store::write_atomic;
+use crate::cache::tool_cache::read_git_head;
+use crate::cache::tool_cache::resolve_git_dir;
 use crate::telemetry::CacheTelemetry;
 use crate::telemetry::CacheTelemetrySnapshot;
+use crate::telemetry::PersistedCacheTelemetry;
 use codex_utils_absolute_path::AbsolutePathBuf;
+use sha2::Digest;
+use sha2::Sha256;
+use std::borrow::Cow;
+use std::io::BufRead;
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
 use std::time::Duration;
+use std::time::Instant;
+use tracing::debug;
 use tracing::warn;
 
+/// File in the cache dir holding telemetry counters from previous runs.
+const TELEMETRY_FILE_NAME: &str = "telemetry.json";
+/// Hex characters of the namespace digest prepended to every key.
+pub(crate) const NAMESPACE_PREFIX_LEN: usize = 8;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct CacheStatus {
     pub enabled: bool,
+    pub read_only: bool,
     pub dir: AbsolutePathBuf,
     pub max_bytes: u64,
     pub stats: CacheStoreStats,
     pub telemetry: CacheTelemetrySnapshot,
 }
 
+/// Index metadata for a single cache entry, as reported by
+/// [`CacheManager::inspect`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheEntryMeta {
+    pub size_bytes: u64,
+    pub ttl_secs: u64,
+    pub inserted_epoch: u64,
+    pub expires_epoch: u64,
+    pub last_access_epoch: u64,
+    pub is_expired: bool,
+    /// The tool the entry was stored for, or `None` for warmed, imported,
+    /// or older entries that did not record it.
+    pub tool: Option<CacheableTool>,
+    /// The first [`INSPECT_PREVIEW_BYTES`] bytes of the stored value.
+    pub preview: Vec<u8>,
+}
+
+pub const INSPECT_PREVIEW_BYTES: usize = 64;
+
+/// Result of loading a snapshot with [`CacheManager::warm`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheWarmOutcome {
+    pub loaded: usize,
+    pub skipped_expired: usize,
+    pub skipped_existing: usize,
+}
+
 pub struct CacheManager {
     config: CacheConfig,
+    /// Current size limit; starts at `config.max_bytes` and changes with
+    /// [`CacheManager::resize`].
+    max_bytes: AtomicU64,
     store: Arc<dyn CacheStore>,
     telemetry: CacheTelemetry,
+    /// Where telemetry is saved on drop; `None` keeps it in memory only.
+    telemetry_path: Option<PathBuf>,
+    /// `<digest>:` prepended to keys so namespaces never share entries.
+    key_prefix: Option<String>,
 }
 
 impl CacheManager {
-    pub fn new(config: CacheConfig) -> std::io::Result<Self> {
-        let store = DiskCacheStore::new(config.dir.as_path(), config.max_bytes)?;
-        Ok(Self {
+    /// Opens the cache described by `config`. When `namespace` is set, keys
+    /// are prefixed with a digest of it so entries from other namespaces are
+    /// never served.
+    pub fn new(config: CacheConfig, namespace: Option<String>) -> std::io::Result<Self> {
+        let store: Arc<dyn CacheStore> = if let Some(bucket) = config.s3_bucket.as_deref() {
+            s3_store(&config, bucket)?
+        } else if config.read_only {
+            Arc::new(DiskCacheStore::open_read_only(
+                config.dir.as_path(),
+                config.max_bytes,
+            )?)
+        } else {
+            Arc::new(
+                DiskCacheStore::new(config.dir.as_path(), config.max_bytes)?
+                    .with_max_entry_bytes(config.max_entry_bytes)
+                    .with_checksums(config.checksum),
+            )
+        };
+        let telemetry_path = config.dir.as_path().join(TELEMETRY_FILE_NAME);
+        let mut manager = Self::with_store(config, store);
+        manager.telemetry = load_telemetry(&telemetry_path);
+        manager.telemetry_path = Some(telemetry_path);
+        manager.key_prefix = namespace.as_deref().map(namespace_key_prefix);
+        Ok(manager)
+    }
+
+    /// Opens the cache namespaced by the branch checked out in
+    /// `workspace_root`, so entries cached on one branch are not served on
+    /// another. Falls back to no namespace outside a git repository or on a
+    /// detached `HEAD`, whose commit would otherwise start a new namespace
+    /// with every commit.
+    pub fn with_git_branch_namespace(
+        config: CacheConfig,
+        workspace_root: &Path,
+    ) -> std::io::Result<Self> {
+        Self::new(config, git_branch_namespace(workspace_root))
+    }
+
+    /// Builds a manager around an existing store instead of the on-disk one
+    /// described by `config.dir`.
+    pub fn with_store(config: CacheConfig, store: Arc<dyn CacheStore>) -> Self {
+        Self {
+            max_bytes: AtomicU64::new(config.max_bytes),
             config,
-            store: Arc::new(store),
+            store,
             telemetry: CacheTelemetry::default(),
-        })
+            telemetry_path: None,
+            key_prefix: None,
+        }
+    }
+
+    fn namespaced_key<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        match &self.key_prefix {
+            Some(prefix) => Cow::Owned(format!("{prefix}{key}")),
+            None => Cow::Borrowed(key),
+        }
     }
 
     pub fn enabled(&self) -> bool {
         self.config.enabled
     }
 
+    pub fn read_only(&self) -> bool {
+        self.config.read_only
+    }
+
     pub fn ttl_for(&self, tool: CacheableTool) -> Duration {
         self.config.ttl_for(tool)
     }
@@ -50,9 +168,11 @@ impl CacheManager {
         if !self.enabled() {
             return None;
         }
-        match self.store.get(key) {
+        let started = Instant::now();
+        match self.store.get(&self.namespaced_key(key)) {
             Ok(Some(entry)) => {
                 self.telemetry.record_hit(tool);
+                self.telemetry.record_hit_latency(tool, started.elapsed());
                 Some(entry.value)
             }
             Ok(None) => {
@@ -66,14 +186,24 @@ impl CacheManager {
         }
     }
 
+    /// Stores `value` under `key`. Does nothing when the cache is disabled or
+    /// read-only.
     pub fn put(&self, key: String, value: Vec<u8>, ttl: Duration, tool: CacheableTool) {
-        if !self.enabled() {
+        if !self.enabled() || self.config.read_only {
             return;
         }
-        let entry = CacheEntry { key, value, ttl };
+        let key = self.namespaced_key(&key).into_owned();
+        let entry = CacheEntry {
+            key,
+            value,
+            ttl,
+            tool: Some(tool),
+        };
+        let started = Instant::now();
         match self.store.put(entry) {
             Ok(CacheStorePutOutcome { evicted }) => {
                 self.telemetry.record_store(tool);
+                self.telemetry.record_store_latency(tool, started.elapsed());
                 for _ in 0..evicted {
                     self.telemetry.record_eviction(tool);
                 }
@@ -84,18 +214,834 @@ impl CacheManager {
         }
     }
 
+    /// Stores `value` only when no live entry exists for `key`.
+    /// Returns whether the value was written.
+    pub fn put_if_absent(
+        &self,
+        key: String,
+        value: Vec<u8>,
+        ttl: Duration,
+        tool: CacheableTool,
+    ) -> bool {
+        if !self.enabled() || self.config.read_only {
+            return false;
+        }
+        match self.store.get(&self.namespaced_key(&key)) {
+            Ok(Some(_)) => false,
+            Ok(None) => {
+                self.put(key, value, ttl, tool);
+                true
+            }
+            Err(err) => {
+                warn!(target: LOG_TARGET, "cache lookup failed: {err}");
+                false
+            }
+        }
+    }
+
+    /// Writes every live entry to `writer` as JSON Lines, returning the count.
+    pub fn snapshot<W: Write>(&self, writer: W) -> std::io::Result<usize> {
+        let entries = self.store.snapshot()?;
+        write_snapshot(writer, &entries)
+    }
+
+    /// Loads entries from a snapshot written by [`CacheManager::snapshot`].
+    ///
+    /// Entries keep the remainder of their original TTL; already expired ones
+    /// are skipped. Unless `overwrite` is set, existing live entries win. A
+    /// snapshot with any key that is not a valid entry name is rejected with
+    /// [`std::io::ErrorKind::InvalidData`] before anything is written.
+    /// Warmed entries are not attributed to any tool in telemetry.
+    pub fn warm<R: BufRead>(
+        &self,
+        reader: R,
+        overwrite: bool,
+    ) -> std::io::Result<CacheWarmOutcome> {
+        if self.config.read_only {
+            return Err(read_only_error());
+        }
+        let records = read_snapshot(reader)?;
+        for record in &records {
+            validate_entry_key(&record.key)?;
+        }
+        let now = now_epoch_secs();
+        let entries = records
+            .into_iter()
+            .map(|record| record.into_live_entry(now))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        self.load_entries(entries, overwrite)
+    }
+
+    /// Writes all live entries to a `.tar.zst` archive, returning how many
+    /// were written.
+    pub fn export<W: Write>(&self, writer: W) -> std::io::Result<usize> {
+        let entries = self.store.snapshot()?;
+        write_archive(writer, &entries)
+    }
+
+    /// Restores entries from an archive written by [`CacheManager::export`],
+    /// skipping expired entries and keys that are already cached.
+    pub fn import<R: Read>(&self, reader: R) -> std::io::Result<CacheWarmOutcome> {
+        if self.config.read_only {
+            return Err(read_only_error());
+        }
+        let entries = read_archive(reader, now_epoch_secs())?;
+        self.load_entries(entries, false)
+    }
+
+    /// Stores each live entry; `None` marks one that had already expired.
+    fn load_entries(
+        &self,
+        entries: Vec<Option<CacheEntry>>,
+        overwrite: bool,
+    ) -> std::io::Result<CacheWarmOutcome> {
+        let mut outcome = CacheWarmOutcome::default();
+        for entry in entries {
+            let Some(entry) = entry else {
+                outcome.skipped_expired += 1;
+                continue;
+            };
+            if !overwrite && self.store.get(&entry.key)?.is_some() {
+                outcome.skipped_existing += 1;
+                continue;
+            }
+            self.store.put(entry)?;
+            outcome.loaded += 1;
+        }
+        Ok(outcome)
+    }
+
+    /// Returns metadata for `key` without refreshing its access time.
+    /// Expired entries are reported rather than removed.
+    pub fn inspect(&self, key: &str) -> std::io::Result<Option<CacheEntryMeta>> {
+        Ok(self
+            .store
+            .inspect(&self.namespaced_key(key))?
+            .map(|entry| CacheEntryMeta {
+                size_bytes: entry.size_bytes,
+                ttl_secs: entry.ttl_secs,
+                inserted_epoch: entry.inserted_epoch,
+                expires_epoch: entry.inserted_epoch.saturating_add(entry.ttl_secs),
+                last_access_epoch: entry.last_access_epoch,
+                is_expired: entry.is_expired,
+                tool: entry.tool,
+                preview: entry
+                    .value
+                    .into_iter()
+                    .take(INSPECT_PREVIEW_BYTES)
+                    .collect(),
+            }))
+    }
+
+    /// Returns the stored value for `key`, even when expired.
+    pub fn inspect_value(&self, key: &str) -> std::io::Result<Option<Vec<u8>>> {
+        Ok(self
+            .store
+            .inspect(&self.namespaced_key(key))?
+            .map(|entry| entry.value))
+    }
+
+    /// Removes every entry. Does nothing when the cache is read-only.
     pub fn clear(&self) -> std::io::Result<()> {
+        if self.config.read_only {
+            return Ok(());
+        }
         self.store.clear()
     }
 
+    /// Removes expired entries while keeping valid ones, returning the count removed.
+    pub fn prune_expired(&self) -> std::io::Result<usize> {
+        if self.config.read_only {
+            return Err(read_only_error());
+        }
+        self.store.prune_expired()
+    }
+
+    /// Changes the cache size limit without reopening the cache. Shrinking
+    /// below the current size evicts the oldest entries until it fits.
+    pub fn resize(&self, new_max_bytes: u64) -> std::io::Result<()> {
+        if self.config.read_only {
+            return Err(read_only_error());
+        }
+        let evicted = self.store.resize(new_max_bytes)?;
+        self.max_bytes.store(new_max_bytes, Ordering::Relaxed);
+        if evicted > 0 {
+            debug!(
+                target: LOG_TARGET,
+                evicted,
+                max_bytes = new_max_bytes,
+                "evicted cache entries after resize"
+            );
+        }
+        Ok(())
+    }
+
+    /// Zeroes the in-memory telemetry counters without touching cached data.
+    pub fn reset_telemetry(&self) {
+        self.telemetry.reset();
+    }
+
     pub fn status(&self) -> std::io::Result<CacheStatus> {
         let stats = self.store.stats()?;
         Ok(CacheStatus {
             enabled: self.enabled(),
+            read_only: self.config.read_only,
             dir: self.config.dir.clone(),
-            max_bytes: self.config.max_bytes,
+            max_bytes: self.max_bytes.load(Ordering::Relaxed),
             stats,
             telemetry: self.telemetry.snapshot(),
         })
     }
 }
+
+impl Drop for CacheManager {
+    fn drop(&mut self) {
+        let Some(path) = self.telemetry_path.as_deref() else {
+            return;
+        };
+        if self.config.read_only {
+            return;
+        }
+        if let Err(err) = save_telemetry(path, &self.telemetry) {
+            warn!(target: LOG_TARGET, "failed to save cache telemetry: {err}");
+        }
+    }
+}
+
+/// Opens the S3 store configured by `cache.s3_bucket`.
+#[cfg(feature = "s3-cache")]
+fn s3_store(config: &CacheConfig, bucket: &str) -> std::io::Result<Arc<dyn CacheStore>> {
+    Ok(Arc::new(
+        S3CacheStore::new(bucket, &config.s3_prefix)?
+            .with_max_entry_bytes(config.max_entry_bytes)
+            .with_read_only(config.read_only),
+    ))
+}
+
+#[cfg(not(feature = "s3-cache"))]
+fn s3_store(_config: &CacheConfig, _bucket: &str) -> std::io::Result<Arc<dyn CacheStore>> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "cache.s3_bucket requires codex to be built with the `s3-cache` feature",
+    ))
+}
+
+/// Loads persisted counters, starting from zero when the file is missing or
+/// unreadable.
+fn load_telemetry(path: &Path) -> CacheTelemetry {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                warn!(target: LOG_TARGET, "failed to read cache telemetry: {err}");
+            }
+            return CacheTelemetry::default();
+        }
+    };
+    match serde_json::from_slice::<PersistedCacheTelemetry>(&bytes) {
+        Ok(persisted) => CacheTelemetry::from_persisted(&persisted),
+        Err(err) => {
+            warn!(target: LOG_TARGET, "ignoring corrupt cache telemetry: {err}");
+            CacheTelemetry::default()
+        }
+    }
+}
+
+/// The ref `HEAD` points at, e.g. `refs/heads/main`, or `None` when
+/// `workspace_root` is not in a git repository or `HEAD` is detached.
+fn git_branch_namespace(workspace_root: &Path) -> Option<String> {
+    let head = read_git_head(&resolve_git_dir(workspace_root)?)?;
+    head.strip_prefix("ref:")
+        .map(|branch| branch.trim().to_string())
+}
+
+/// `<first 8 hex chars of sha256(namespace)>:`.
+fn namespace_key_prefix(namespace: &str) -> String {
+    let digest = format!("{:x}", Sha256::digest(namespace.as_bytes()));
+    format!("{}:", &digest[..NAMESPACE_PREFIX_LEN])
+}
+
+fn save_telemetry(path: &Path, telemetry: &CacheTelemetry) -> std::io::Result<()> {
+    let bytes = serde_json::to_vec(&telemetry.to_persisted())
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+    write_atomic(path, &bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::telemetry::CacheToolTelemetrySnapshot;
+    use pretty_assertions::assert_eq;
+    use tempfile::tempdir;
+
+    #[test]
+    fn get_records_telemetry_for_requested_tool_only() -> std::io::Result<()> {
+        let codex_home = tempdir()?;
+        let config = CacheConfig::new(codex_home.path(), None)?;
+        let manager = CacheManager::new(config, None)?;
+
+        assert_eq!(manager.get("missing", CacheableTool::GrepFiles), None);
+
+        let by_tool = manager.status()?.telemetry.by_tool;
+        let expected = CacheableTool::all()
+            .iter()
+            .map(|tool| CacheToolTelemetrySnapshot {
+                tool: *tool,
+                hits: 0,
+                misses: u64::from(*tool == CacheableTool::GrepFiles),
+                stores: 0,
+                evictions: 0,
+                hit_rate: (*tool == CacheableTool::GrepFiles).then_some(0.0),
+                hit_latency: None,
+                store_latency: None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(by_tool, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn resize_evicts_oldest_entries_until_under_limit() -> std::io::Result<()> {
+        let codex_home = tempdir()?;
+        let config = CacheConfig::new(codex_home.path(), None)?;
+        let manager = CacheManager::new(config, None)?;
+        for key in ["alpha", "bravo", "charlie"] {
+            manager.put(
+                key.to_string(),
+                vec![0; 100],
+                Duration::from_secs(60),
+                CacheableTool::ReadFile,
+            );
+        }
+        assert_eq!(manager.status()?.stats.entries, 3);
+
+        manager.resize(250)?;
+
+        let status = manager.status()?;
+        assert_eq!(status.max_bytes, 250);
+        assert!(status.stats.total_bytes <= 250);
+        assert_eq!(status.stats.entries, 2);
+
+        manager.resize(1024)?;
+        manager.put(
+            "delta".to_string(),
+            vec![0; 100],
+            Duration::from_secs(60),
+            CacheableTool::ReadFile,
+        );
+        assert_eq!(manager.status()?.stats.entries, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn telemetry_survives_manager_restart() -> std::io::Result<()> {
+        let codex_home = tempdir()?;
+        let config = CacheConfig::new(codex_home.path(), None)?;
+        {
+            let manager = CacheManager::new(config.clone(), None)?;
+            manager.put(
+                "alpha".to_string(),
+                b"one".to_vec(),
+                Duration::from_secs(60),
+                CacheableTool::ReadFile,
+            );
+            manager.get("alpha", CacheableTool::ReadFile);
+            manager.get("alpha", CacheableTool::ReadFile);
+            manager.get("missing", CacheableTool::GrepFiles);
+        }
+
+        let telemetry = CacheManager::new(config, None)?.status()?.telemetry;
+
+        assert_eq!(telemetry.hits, 2);
+        assert_eq!(telemetry.misses, 1);
+        assert_eq!(telemetry.stores, 1);
+        assert_eq!(telemetry.by_tool[0].hits, 2);
+        assert_eq!(telemetry.by_tool[2].misses, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn corrupt_telemetry_file_starts_from_zero() -> std::io::Result<()> {
+        let codex_home = tempdir()?;
+        let config = CacheConfig::new(codex_home.path(), None)?;
+        std::fs::create_dir_all(config.dir.as_path())?;
+        std::fs::write(config.dir.as_path().join(TELEMETRY_FILE_NAME), b"{not json")?;
+
+        let telemetry = CacheManager::new(config, None)?.status()?.telemetry;
+
+        assert_eq!(telemetry.hits, 0);
+        assert_eq!(telemetry.misses, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn snapshot_round_trips_through_clear_and_warm() -> std::io::Result<()> {
+        let codex_home = tempdir()?;
+        let config = CacheConfig::new(codex_home.path(), None)?;
+        let manager = CacheManager::new(config, None)?;
+        for (key, value) in [
+            ("alpha", b"one".to_vec()),
+            ("bravo", vec![0u8, 159, 146, 150]),
+        ] {
+            manager.put(
+                key.to_string(),
+                value,
+                Duration::from_secs(60),
+                CacheableTool::ReadFile,
+            );
+        }
+        let mut snapshot = Vec::new();
+        assert_eq!(manager.snapshot(&mut snapshot)?, 2);
+
+        manager.clear()?;
+        assert_eq!(manager.get("alpha", CacheableTool::ReadFile), None);
+        let outcome = manager.warm(snapshot.as_slice(), false)?;
+
+        assert_eq!(
+            outcome,
+            CacheWarmOutcome {
+                loaded: 2,
+                skipped_expired: 0,
+                skipped_existing: 0,
+            }
+        );
+        assert_eq!(
+            manager.get("alpha", CacheableTool::ReadFile),
+            Some(b"one".to_vec())
+        );
+        assert_eq!(
+            manager.get("bravo", CacheableTool::ReadFile),
+            Some(vec![0u8, 159, 146, 150])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn warm_keeps_existing_entries_unless_overwriting() -> std::io::Result<()> {
+        let codex_home = tempdir()?;
+        let config = CacheConfig::new(codex_home.path(), None)?;
+        let manager = CacheManager::new(config, None)?;
+        let ttl = Duration::from_secs(60);
+        manager.put(
+            "alpha".to_string(),
+            b"old".to_vec(),
+            ttl,
+            CacheableTool::ReadFile,
+        );
+        let mut snapshot = Vec::new();
+        manager.snapshot(&mut snapshot)?;
+        manager.put(
+            "alpha".to_string(),
+            b"new".to_vec(),
+            ttl,
+            CacheableTool::ReadFile,
+        );
+
+        let kept = manager.warm(snapshot.as_slice(), false)?;
+        assert_eq!(kept.skipped_existing, 1);
+        assert_eq!(
+            manager.get("alpha", CacheableTool::ReadFile),
+            Some(b"new".to_vec())
+        );
+
+        manager.warm(snapshot.as_slice(), true)?;
+        assert_eq!(
+            manager.get("alpha", CacheableTool::ReadFile),
+            Some(b"old".to_vec())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn warm_rejects_keys_that_escape_the_entries_dir() -> std::io::Result<()> {
+        let codex_home = tempdir()?;
+        let config = CacheConfig::new(codex_home.path(), None)?;
+        let cache_dir = config.dir.to_path_buf();
+        let manager = CacheManager::new(config, None)?;
+        let record = |key: &str| {
+            serde_json::json!({
+                "key": key,
+                "value_b64": "cGF5bG9hZA==",
+                "ttl_secs": 60,
+                "inserted_epoch": now_epoch_secs(),
+            })
+            .to_string()
+        };
+        let snapshot = format!("{}\n{}\n", record("alpha"), record("../x"));
+
+        let err = manager
+            .warm(snapshot.as_bytes(), true)
+            .expect_err("traversal key should be rejected");
+
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(manager.get("alpha", CacheableTool::ReadFile), None);
+        assert!(!cache_dir.join("x").exists());
+        assert!(!codex_home.path().join("x").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn import_rejects_archive_keys_that_escape_the_entries_dir() -> std::io::Result<()> {
+        let codex_home = tempdir()?;
+        let config = CacheConfig::new(codex_home.path(), None)?;
+        let cache_dir = config.dir.to_path_buf();
+        let manager = CacheManager::new(config, None)?;
+        let index = serde_json::json!({
+            "entries": {
+                "ab:../x": { "ttl_secs": 60, "inserted_epoch": now_epoch_secs() },
+            },
+        })
+        .to_string();
+        let mut builder = tar::Builder::new(zstd::Encoder::new(Vec::new(), 0)?);
+        let mut header = tar::Header::new_gnu();
+        header.set_size(index.len() as u64);
+        header.set_mode(0o644);
+        builder.append_data(&mut header, "index.json", index.as_bytes())?;
+        // `append_data` refuses `..` components, so set the path `read_archive`
+        // looks up for this key directly in the header.
+        let value_path = b"entries/ab/../../x";
+        let mut header = tar::Header::new_old();
+        header.as_old_mut().name[..value_path.len()].copy_from_slice(value_path);
+        header.set_size(b"payload".len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append(&header, &b"payload"[..])?;
+        let archive = builder.into_inner()?.finish()?;
+
+        let err = manager
+            .import(archive.as_slice())
+            .expect_err("traversal key should be rejected");
+
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(!cache_dir.join("x").exists());
+        assert!(!codex_home.path().join("x").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn import_restores_exported_entries_after_clear() -> std::io::Result<()> {
+        let codex_home = tempdir()?;
+        let config = CacheConfig::new(codex_home.path(), None)?;
+        let manager = CacheManager::new(config, None)?;
+        let ttl = Duration::from_secs(60);
+        manager.put(
+            "alpha".to_string(),
+            b"one".to_vec(),
+            ttl,
+            CacheableTool::ReadFile,
+        );
+        manager.put(
+            "bravo".to_string(),
+            b"two".to_vec(),
+            ttl,
+            CacheableTool::ListDir,
+        );
+        let mut archive = Vec::new();
+        assert_eq!(manager.export(&mut archive)?, 2);
+
+        manager.clear()?;
+        let outcome = manager.import(archive.as_slice())?;
+        let again = manager.import(archive.as_slice())?;
+
+        assert_eq!(outcome.loaded, 2);
+        assert_eq!(again.skipped_existing, 2);
+        assert_eq!(
+            manager.get("alpha", CacheableTool::ReadFile),
+            Some(b"one".to_vec())
+        );
+        assert_eq!(
+            manager.get("bravo", CacheableTool::ListDir),
+            Some(b"two".to_vec())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn inspect_reports_entry_metadata() -> std::io::Result<()> {
+        let codex_home = tempdir()?;
+        let config = CacheConfig::new(codex_home.path(), None)?;
+        let manager = CacheManager::new(config, None)?;
+        let before = now_epoch_secs();
+        manager.put(
+            "alpha".to_string(),
+            b"payload".to_vec(),
+            Duration::from_secs(60),
+            CacheableTool::ReadFile,
+        );
+        manager.put(
+            "expired".to_string(),
+            b"stale".to_vec(),
+            Duration::ZERO,
+            CacheableTool::ReadFile,
+        );
+
+        let meta = manager
+            .inspect("alpha")?
+            .ok_or_else(|| std::io::Error::other("entry missing"))?;
+        let after = now_epoch_secs();
+
+        assert_eq!(meta.size_bytes, b"payload".len() as u64);
+        assert_eq!(meta.ttl_secs, 60);
+        assert!((before..=after).contains(&meta.inserted_epoch));
+        assert_eq!(meta.expires_epoch, meta.inserted_epoch + 60);
+        assert_eq!(meta.last_access_epoch, meta.inserted_epoch);
+        assert!(!meta.is_expired);
+        assert_eq!(meta.tool, Some(CacheableTool::ReadFile));
+        assert_eq!(meta.preview, b"payload".to_vec());
+        assert_eq!(manager.inspect_value("alpha")?, Some(b"payload".to_vec()));
+        assert_eq!(
+            manager.inspect("expired")?.map(|meta| meta.is_expired),
+            Some(true)
+        );
+        assert_eq!(manager.inspect("missing")?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn inspect_reports_stored_tool_and_truncates_preview() -> std::io::Result<()> {
+        let codex_home = tempdir()?;
+        let config = CacheConfig::new(codex_home.path(), None)?;
+        let manager = CacheManager::new(config, None)?;
+        manager.put(
+            "alpha".to_string(),
+            vec![0xab; INSPECT_PREVIEW_BYTES * 2],
+            Duration::from_secs(60),
+            CacheableTool::GrepFiles,
+        );
+
+        let meta = manager
+            .inspect("alpha")?
+            .ok_or_else(|| std::io::Error::other("entry missing"))?;
+
+        assert_eq!(meta.tool, Some(CacheableTool::GrepFiles));
+        assert_eq!(meta.preview, vec![0xab; INSPECT_PREVIEW_BYTES]);
+        Ok(())
+    }
+
+    #[test]
+    fn read_only_manager_serves_reads_and_skips_writes() -> std::io::Result<()> {
+        let codex_home = tempdir()?;
+        let config = CacheConfig::new(codex_home.path(), None)?;
+        let writable = CacheManager::new(config.clone(), None)?;
+        let ttl = Duration::from_secs(60);
+        writable.put(
+            "alpha".to_string(),
+            b"warm".to_vec(),
+            ttl,
+            CacheableTool::ReadFile,
+        );
+
+        let manager = CacheManager::new(
+            CacheConfig {
+                read_only: true,
+                ..config
+            },
+            None,
+        )?;
+        manager.put(
+            "bravo".to_string(),
+            b"ignored".to_vec(),
+            ttl,
+            CacheableTool::ReadFile,
+        );
+
+        assert_eq!(
+            manager.get("alpha", CacheableTool::ReadFile),
+            Some(b"warm".to_vec())
+        );
+        assert_eq!(manager.get("bravo", CacheableTool::ReadFile), None);
+        assert_eq!(manager.status()?.telemetry.stores, 0);
+        assert!(manager.status()?.read_only);
+        manager.clear()?;
+        assert_eq!(
+            writable.get("alpha", CacheableTool::ReadFile),
+            Some(b"warm".to_vec())
+        );
+        Ok(())
+    }
+
+    /// Contents and modification time of every file under `root`.
+    fn files_on_disk(
+        root: &std::path::Path,
+    ) -> std::collections::BTreeMap<std::path::PathBuf, (Vec<u8>, std::time::SystemTime)> {
+        walkdir::WalkDir::new(root)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| {
+                let path = entry.path().to_path_buf();
+                let contents = std::fs::read(&path).expect("read cache file");
+                let modified = std::fs::metadata(&path)
+                    .and_then(|metadata| metadata.modified())
+                    .expect("cache file mtime");
+                (path, (contents, modified))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn read_only_manager_leaves_disk_untouched() -> std::io::Result<()> {
+        let codex_home = tempdir()?;
+        let config = CacheConfig::new(codex_home.path(), None)?;
+        let ttl = Duration::from_secs(60);
+        CacheManager::new(config.clone(), None)?.put(
+            "alpha".to_string(),
+            b"warm".to_vec(),
+            ttl,
+            CacheableTool::ReadFile,
+        );
+        let before = files_on_disk(codex_home.path());
+
+        let manager = CacheManager::new(
+            CacheConfig {
+                read_only: true,
+                ..config
+            },
+            None,
+        )?;
+        assert_eq!(
+            manager.get("alpha", CacheableTool::ReadFile),
+            Some(b"warm".to_vec())
+        );
+        manager.put(
+            "bravo".to_string(),
+            b"ignored".to_vec(),
+            ttl,
+            CacheableTool::ReadFile,
+        );
+        manager.clear()?;
+
+        assert_eq!(files_on_disk(codex_home.path()), before);
+        Ok(())
+    }
+
+    #[test]
+    fn namespaces_do_not_share_entries() -> std::io::Result<()> {
+        let codex_home = tempdir()?;
+        let config = CacheConfig::new(codex_home.path(), None)?;
+        let ttl = Duration::from_secs(60);
+        let main = CacheManager::new(config.clone(), Some("refs/heads/main".to_string()))?;
+        main.put(
+            "alpha".to_string(),
+            b"main".to_vec(),
+            ttl,
+            CacheableTool::ReadFile,
+        );
+        drop(main);
+
+        let feature = CacheManager::new(config.clone(), Some("refs/heads/feature/x".to_string()))?;
+        assert_eq!(feature.get("alpha", CacheableTool::ReadFile), None);
+        drop(feature);
+
+        let main = CacheManager::new(config, Some("refs/heads/main".to_string()))?;
+        assert_eq!(
+            main.get("alpha", CacheableTool::ReadFile),
+            Some(b"main".to_vec())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn git_branch_namespace_reads_head() -> std::io::Result<()> {
+        let codex_home = tempdir()?;
+        let workspace = tempdir()?;
+        let git_dir = workspace.path().join(".git");
+        std::fs::create_dir_all(&git_dir)?;
+        std::fs::write(git_dir.join("HEAD"), "ref: refs/heads/main\n")?;
+        let config = CacheConfig::new(codex_home.path(), None)?;
+
+        let manager = CacheManager::with_git_branch_namespace(config, workspace.path())?;
+
+        let prefix = namespace_key_prefix("refs/heads/main");
+        assert_eq!(manager.key_prefix.as_deref(), Some(prefix.as_str()));
+        assert_eq!(prefix.len(), NAMESPACE_PREFIX_LEN + 1);
+        assert_eq!(manager.namespaced_key("alpha"), format!("{prefix}alpha"));
+        Ok(())
+    }
+
+    #[test]
+    fn detached_head_has_no_namespace() -> std::io::Result<()> {
+        let codex_home = tempdir()?;
+        let workspace = tempdir()?;
+        let git_dir = workspace.path().join(".git");
+        std::fs::create_dir_all(&git_dir)?;
+        std::fs::write(
+            git_dir.join("HEAD"),
+            "0123456789abcdef0123456789abcdef01234567\n",
+        )?;
+        let config = CacheConfig::new(codex_home.path(), None)?;
+
+        let manager = CacheManager::with_git_branch_namespace(config, workspace.path())?;
+
+        assert_eq!(manager.key_prefix, None);
+        assert_eq!(manager.namespaced_key("alpha"), "alpha");
+        Ok(())
+    }
+
+    #[test]
+    fn inspect_takes_the_plain_key_in_a_namespace() -> std::io::Result<()> {
+        let codex_home = tempdir()?;
+        let config = CacheConfig::new(codex_home.path(), None)?;
+        let manager = CacheManager::new(config, Some("refs/heads/main".to_string()))?;
+        manager.put(
+            "alpha".to_string(),
+            b"payload".to_vec(),
+            Duration::from_secs(60),
+            CacheableTool::ReadFile,
+        );
+
+        assert!(manager.inspect("alpha")?.is_some());
+        assert_eq!(manager.inspect_value("alpha")?, Some(b"payload".to_vec()));
+        Ok(())
+    }
+
+    #[test]
+    fn prune_expired_empties_store_of_zero_ttl_entries() -> std::io::Result<()> {
+        let codex_home = tempdir()?;
+        let config = CacheConfig::new(codex_home.path(), None)?;
+        let manager = CacheManager::new(config, None)?;
+        for key in ["alpha", "bravo", "charlie"] {
+            manager.put(
+                key.to_string(),
+                b"stale".to_vec(),
+                Duration::ZERO,
+                CacheableTool::ListDir,
+            );
+        }
+
+        assert_eq!(manager.prune_expired()?, 3);
+
+        let stats = manager.status()?.stats;
+        assert_eq!(stats.entries, 0);
+        assert_eq!(stats.total_bytes, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn prune_removes_only_expired_entries() -> std::io::Result<()> {
+        let codex_home = tempdir()?;
+        let config = CacheConfig::new(codex_home.path(), None)?;
+        let manager = CacheManager::new(config, None)?;
+        manager.put(
+            "expired".to_string(),
+            b"stale".to_vec(),
+            Duration::ZERO,
+            CacheableTool::ReadFile,
+        );
+        manager.put(
+            "valid".to_string(),
+            b"fresh".to_vec(),
+            Duration::from_secs(60),
+            CacheableTool::ReadFile,
+        );
+
+        assert_eq!(manager.prune_expired()?, 1);
+
+        assert_eq!(manager.status()?.stats.entries, 1);
+        assert_eq!(
+            manager.get("valid", CacheableTool::ReadFile),
+            Some(b"fresh".to_vec())
+        );
+        Ok(())
+    }
+}
@@ -1,5 +1,10 @@
+pub mod archive;
+pub mod canonical;
 pub mod config;
 pub mod manager;
+#[cfg(feature = "s3-cache")]
+pub mod s3_store;
+pub mod snapshot;
 pub mod store;
 pub mod tool_cache;
 
@@ -0,0 +1,641 @@
+//! [`CacheStore`] backed by an S3 bucket, so CI runners can share cached tool
+//! results. Built only with the `s3-cache` feature.
+//!
+//! Each entry is one object at `<prefix><key>`; its TTL, insertion time, and
+//! owning tool travel as user metadata, so expiry works without a separate
+//! index.
+
+use crate::cache::LOG_TARGET;
+use crate::cache::config::CacheableTool;
+use crate::cache::store::CacheEntry;
+use crate::cache::store::CacheStore;
+use crate::cache::store::CacheStoreInspection;
+use crate::cache::store::CacheStorePutOutcome;
+use crate::cache::store::CacheStoreSnapshotEntry;
+use crate::cache::store::CacheStoreStats;
+use crate::cache::store::now_epoch_secs;
+use crate::cache::store::read_only_error;
+use aws_sdk_s3::Client;
+use aws_sdk_s3::primitives::ByteStream;
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use tracing::warn;
+
+const TTL_SECS_METADATA: &str = "ttl-secs";
+const INSERTED_EPOCH_METADATA: &str = "inserted-epoch";
+const TOOL_METADATA: &str = "tool";
+
+/// Expiry metadata stored alongside each object.
+#[derive(Debug, Clone, Copy)]
+struct ObjectMeta {
+    ttl_secs: u64,
+    inserted_epoch: u64,
+    /// The tool the entry was stored for; absent on objects written before
+    /// it was recorded and on warmed or imported entries.
+    tool: Option<CacheableTool>,
+}
+
+impl ObjectMeta {
+    /// Reads the metadata written by [`S3CacheStore::put`]. Objects without
+    /// it were not written by this store and are treated as expired.
+    fn from_metadata(metadata: Option<&HashMap<String, String>>) -> Option<Self> {
+        let metadata = metadata?;
+        Some(Self {
+            ttl_secs: metadata.get(TTL_SECS_METADATA)?.parse().ok()?,
+            inserted_epoch: metadata.get(INSERTED_EPOCH_METADATA)?.parse().ok()?,
+            tool: metadata
+                .get(TOOL_METADATA)
+                .and_then(|tool| tool.parse().ok()),
+        })
+    }
+
+    fn is_expired_at(&self, now: u64) -> bool {
+        if self.ttl_secs == 0 {
+            return true;
+        }
+        now.saturating_sub(self.inserted_epoch) > self.ttl_secs
+    }
+}
+
+pub struct S3CacheStore {
+    client: Client,
+    bucket: String,
+    prefix: String,
+    max_entry_bytes: u64,
+    /// Never upload or delete objects, including expired ones.
+    read_only: bool,
+    /// Drives the async S3 client behind the synchronous [`CacheStore`]
+    /// API. Always `Some` until dropped.
+    runtime: Option<Runtime>,
+}
+
+impl S3CacheStore {
+    /// Connects to `bucket` using the default AWS credential and region
+    /// chain (environment, profile, instance metadata).
+    pub fn new(bucket: &str, prefix: &str) -> std::io::Result<Self> {
+        let runtime = cache_runtime()?;
+        let config = runtime.block_on(aws_config::load_defaults(
+            aws_config::BehaviorVersion::latest(),
+        ));
+        Ok(Self::from_parts(
+            Client::new(&config),
+            bucket,
+            prefix,
+            runtime,
+        ))
+    }
+
+    /// Uses an already configured client, e.g. one pointed at a local
+    /// S3-compatible endpoint.
+    pub fn with_client(client: Client, bucket: &str, prefix: &str) -> std::io::Result<Self> {
+        Ok(Self::from_parts(client, bucket, prefix, cache_runtime()?))
+    }
+
+    fn from_parts(client: Client, bucket: &str, prefix: &str, runtime: Runtime) -> Self {
+        Self {
+            client,
+            bucket: bucket.to_string(),
+            prefix: prefix.to_string(),
+            max_entry_bytes: u64::MAX,
+            read_only: false,
+            runtime: Some(runtime),
+        }
+    }
+
+    pub fn with_max_entry_bytes(mut self, max_entry_bytes: u64) -> Self {
+        self.max_entry_bytes = max_entry_bytes;
+        self
+    }
+
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    fn object_key(&self, key: &str) -> String {
+        format!("{}{key}", self.prefix)
+    }
+
+    /// Runs `future` on the store's runtime and waits for it. The future is
+    /// spawned rather than blocked on so this also works when called from
+    /// inside another Tokio runtime.
+    fn run<T, F>(&self, future: F) -> std::io::Result<T>
+    where
+        T: Send + 'static,
+        F: Future<Output = std::io::Result<T>> + Send + 'static,
+    {
+        let runtime = self
+            .runtime
+            .as_ref()
+            .ok_or_else(|| std::io::Error::other("S3 cache runtime is shut down"))?;
+        let (tx, rx) = std::sync::mpsc::channel();
+        runtime.spawn(async move {
+            let _ = tx.send(future.await);
+        });
+        rx.recv()
+            .map_err(|_| std::io::Error::other("S3 cache request was cancelled"))?
+    }
+
+    fn request(&self) -> S3Request {
+        S3Request {
+            client: self.client.clone(),
+            bucket: self.bucket.clone(),
+        }
+    }
+}
+
+impl Drop for S3CacheStore {
+    fn drop(&mut self) {
+        // Dropping a runtime blocks, which panics inside another runtime.
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+impl CacheStore for S3CacheStore {
+    fn get(&self, key: &str) -> std::io::Result<Option<CacheEntry>> {
+        let request = self.request();
+        let object_key = self.object_key(key);
+        let key = key.to_string();
+        let read_only = self.read_only;
+        self.run(async move {
+            let Some(meta) = request.head(&object_key).await? else {
+                return Ok(None);
+            };
+            if meta.is_expired_at(now_epoch_secs()) {
+                if !read_only {
+                    request.delete(&object_key).await?;
+                }
+                return Ok(None);
+            }
+            Ok(request
+                .download(&object_key)
+                .await?
+                .map(|value| CacheEntry {
+                    key,
+                    value,
+                    ttl: Duration::from_secs(meta.ttl_secs),
+                    tool: meta.tool,
+                }))
+        })
+    }
+
+    fn put(&self, entry: CacheEntry) -> std::io::Result<CacheStorePutOutcome> {
+        if self.read_only {
+            return Ok(CacheStorePutOutcome { evicted: 0 });
+        }
+        if entry.value.len() as u64 > self.max_entry_bytes {
+            warn!(
+                target: LOG_TARGET,
+                key = %entry.key,
+                size_bytes = entry.value.len(),
+                max_entry_bytes = self.max_entry_bytes,
+                "skipping oversized cache entry"
+            );
+            return Ok(CacheStorePutOutcome { evicted: 0 });
+        }
+        let request = self.request();
+        let object_key = self.object_key(&entry.key);
+        self.run(async move {
+            let mut put = request
+                .client
+                .put_object()
+                .bucket(&request.bucket)
+                .key(object_key)
+                .metadata(TTL_SECS_METADATA, entry.ttl.as_secs().to_string())
+                .metadata(INSERTED_EPOCH_METADATA, now_epoch_secs().to_string());
+            if let Some(tool) = entry.tool {
+                put = put.metadata(TOOL_METADATA, tool.config_key());
+            }
+            put.body(ByteStream::from(entry.value))
+                .send()
+                .await
+                .map_err(std::io::Error::other)?;
+            Ok(CacheStorePutOutcome { evicted: 0 })
+        })
+    }
+
+    fn remove(&self, key: &str) -> std::io::Result<()> {
+        if self.read_only {
+            return Err(read_only_error());
+        }
+        let request = self.request();
+        let object_key = self.object_key(key);
+        self.run(async move { request.delete(&object_key).await })
+    }
+
+    fn clear(&self) -> std::io::Result<()> {
+        if self.read_only {
+            return Err(read_only_error());
+        }
+        let request = self.request();
+        let prefix = self.prefix.clone();
+        self.run(async move {
+            for (object_key, _) in request.list(&prefix).await? {
+                request.delete(&object_key).await?;
+            }
+            Ok(())
+        })
+    }
+
+    fn prune_expired(&self) -> std::io::Result<usize> {
+        if self.read_only {
+            return Err(read_only_error());
+        }
+        let request = self.request();
+        let prefix = self.prefix.clone();
+        self.run(async move {
+            let now = now_epoch_secs();
+            let mut removed = 0;
+            for (object_key, _) in request.list(&prefix).await? {
+                let expired = request
+                    .head(&object_key)
+                    .await?
+                    .is_some_and(|meta| meta.is_expired_at(now));
+                if expired {
+                    request.delete(&object_key).await?;
+                    removed += 1;
+                }
+            }
+            Ok(removed)
+        })
+    }
+
+    fn snapshot(&self) -> std::io::Result<Vec<CacheStoreSnapshotEntry>> {
+        let request = self.request();
+        let prefix = self.prefix.clone();
+        self.run(async move {
+            let now = now_epoch_secs();
+            let mut snapshot = Vec::new();
+            for (object_key, _) in request.list(&prefix).await? {
+                let Some(meta) = request.head(&object_key).await? else {
+                    continue;
+                };
+                if meta.is_expired_at(now) {
+                    continue;
+                }
+                let Some(value) = request.download(&object_key).await? else {
+                    continue;
+                };
+                snapshot.push(CacheStoreSnapshotEntry {
+                    entry: CacheEntry {
+                        key: object_key[prefix.len()..].to_string(),
+                        value,
+                        ttl: Duration::from_secs(meta.ttl_secs),
+                        tool: meta.tool,
+                    },
+                    inserted_epoch: meta.inserted_epoch,
+                });
+            }
+            snapshot.sort_by(|a, b| a.entry.key.cmp(&b.entry.key));
+            Ok(snapshot)
+        })
+    }
+
+    fn inspect(&self, key: &str) -> std::io::Result<Option<CacheStoreInspection>> {
+        let request = self.request();
+        let object_key = self.object_key(key);
+        self.run(async move {
+            let Some(meta) = request.head(&object_key).await? else {
+                return Ok(None);
+            };
+            Ok(request
+                .download(&object_key)
+                .await?
+                .map(|value| CacheStoreInspection {
+                    size_bytes: value.len() as u64,
+                    value,
+                    ttl_secs: meta.ttl_secs,
+                    inserted_epoch: meta.inserted_epoch,
+                    // S3 does not track reads, so the last access is the
+                    // last write.
+                    last_access_epoch: meta.inserted_epoch,
+                    is_expired: meta.is_expired_at(now_epoch_secs()),
+                    tool: meta.tool,
+                }))
+        })
+    }
+
+    fn stats(&self) -> std::io::Result<CacheStoreStats> {
+        let request = self.request();
+        let prefix = self.prefix.clone();
+        self.run(async move {
+            let objects = request.list(&prefix).await?;
+            Ok(CacheStoreStats {
+                entries: objects.len(),
+                total_bytes: objects.iter().map(|(_, size)| size).sum(),
+            })
+        })
+    }
+}
+
+/// The client and bucket, cloned into each spawned request.
+struct S3Request {
+    client: Client,
+    bucket: String,
+}
+
+impl S3Request {
+    /// Returns the entry's expiry metadata, or `None` when the object does
+    /// not exist. Objects without metadata report as already expired.
+    async fn head(&self, object_key: &str) -> std::io::Result<Option<ObjectMeta>> {
+        match self
+            .client
+            .head_object()
+            .bucket(&self.bucket)
+            .key(object_key)
+            .send()
+            .await
+        {
+            Ok(output) => Ok(Some(
+                ObjectMeta::from_metadata(output.metadata()).unwrap_or(ObjectMeta {
+                    ttl_secs: 0,
+                    inserted_epoch: 0,
+                    tool: None,
+                }),
+            )),
+            Err(err) if err.as_service_error().is_some_and(|err| err.is_not_found()) => Ok(None),
+            Err(err) => Err(std::io::Error::other(err)),
+        }
+    }
+
+    async fn download(&self, object_key: &str) -> std::io::Result<Option<Vec<u8>>> {
+        match self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(object_key)
+            .send()
+            .await
+        {
+            Ok(output) => {
+                let bytes = output.body.collect().await.map_err(std::io::Error::other)?;
+                Ok(Some(bytes.into_bytes().to_vec()))
+            }
+            Err(err)
+                if err
+                    .as_service_error()
+                    .is_some_and(|err| err.is_no_such_key()) =>
+            {
+                Ok(None)
+            }
+            Err(err) => Err(std::io::Error::other(err)),
+        }
+    }
+
+    async fn delete(&self, object_key: &str) -> std::io::Result<()> {
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(object_key)
+            .send()
+            .await
+            .map_err(std::io::Error::other)?;
+        Ok(())
+    }
+
+    /// Lists every object under `prefix` with its size in bytes.
+    async fn list(&self, prefix: &str) -> std::io::Result<Vec<(String, u64)>> {
+        let mut objects = Vec::new();
+        let mut continuation_token = None;
+        loop {
+            let output = self
+                .client
+                .list_objects_v2()
+                .bucket(&self.bucket)
+                .prefix(prefix)
+                .set_continuation_token(continuation_token)
+                .send()
+                .await
+                .map_err(std::io::Error::other)?;
+            for object in output.contents() {
+                if let Some(key) = object.key() {
+                    let size = object.size().unwrap_or(0).max(0) as u64;
+                    objects.push((key.to_string(), size));
+                }
+            }
+            continuation_token = output.next_continuation_token().map(str::to_string);
+            if continuation_token.is_none() {
+                return Ok(objects);
+            }
+        }
+    }
+}
+
+fn cache_runtime() -> std::io::Result<Runtime> {
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .thread_name("codex-s3-cache")
+        .enable_all()
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_s3::config::BehaviorVersion;
+    use aws_sdk_s3::config::Credentials;
+    use aws_sdk_s3::config::Region;
+    use aws_sdk_s3::config::RequestChecksumCalculation;
+    use pretty_assertions::assert_eq;
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+    use wiremock::matchers::body_bytes;
+    use wiremock::matchers::header;
+    use wiremock::matchers::method;
+    use wiremock::matchers::path;
+    use wiremock::matchers::path_regex;
+    use wiremock::matchers::query_param;
+
+    fn store_for(server: &MockServer) -> S3CacheStore {
+        let config = aws_sdk_s3::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .credentials_provider(Credentials::new("test", "test", None, None, "test"))
+            .endpoint_url(server.uri())
+            .force_path_style(true)
+            .request_checksum_calculation(RequestChecksumCalculation::WhenRequired)
+            .build();
+        S3CacheStore::with_client(Client::from_conf(config), "ci-bucket", "codex/")
+            .expect("s3 store")
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn put_uploads_value_with_ttl_metadata() {
+        let server = MockServer::start().await;
+        Mock::given(method("PUT"))
+            .and(path("/ci-bucket/codex/alpha"))
+            .and(header("x-amz-meta-ttl-secs", "60"))
+            .and(body_bytes(b"cached".to_vec()))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let store = store_for(&server);
+
+        let outcome = store
+            .put(CacheEntry {
+                key: "alpha".to_string(),
+                value: b"cached".to_vec(),
+                ttl: Duration::from_secs(60),
+                tool: None,
+            })
+            .expect("put");
+
+        assert_eq!(outcome, CacheStorePutOutcome { evicted: 0 });
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn put_records_the_tool_as_metadata() {
+        let server = MockServer::start().await;
+        Mock::given(method("PUT"))
+            .and(path("/ci-bucket/codex/alpha"))
+            .and(header("x-amz-meta-tool", "grep_files"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let store = store_for(&server);
+
+        store
+            .put(CacheEntry {
+                key: "alpha".to_string(),
+                value: b"cached".to_vec(),
+                ttl: Duration::from_secs(60),
+                tool: Some(CacheableTool::GrepFiles),
+            })
+            .expect("put");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn inspect_reports_the_tool_from_metadata() {
+        let server = MockServer::start().await;
+        let metadata = |template: ResponseTemplate| {
+            template
+                .insert_header("x-amz-meta-ttl-secs", "60")
+                .insert_header("x-amz-meta-inserted-epoch", now_epoch_secs().to_string())
+                .insert_header("x-amz-meta-tool", "grep_files")
+        };
+        Mock::given(method("HEAD"))
+            .and(path("/ci-bucket/codex/alpha"))
+            .respond_with(metadata(ResponseTemplate::new(200)))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/ci-bucket/codex/alpha"))
+            .respond_with(metadata(ResponseTemplate::new(200)).set_body_bytes(b"cached".to_vec()))
+            .mount(&server)
+            .await;
+        let store = store_for(&server);
+
+        let inspection = store.inspect("alpha").expect("inspect").expect("entry");
+        assert_eq!(inspection.tool, Some(CacheableTool::GrepFiles));
+        assert_eq!(inspection.value, b"cached".to_vec());
+        let entry = store.get("alpha").expect("get").expect("cached entry");
+        assert_eq!(entry.tool, Some(CacheableTool::GrepFiles));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn get_checks_existence_before_downloading() {
+        let server = MockServer::start().await;
+        let metadata = |template: ResponseTemplate| {
+            template
+                .insert_header("x-amz-meta-ttl-secs", "60")
+                .insert_header("x-amz-meta-inserted-epoch", now_epoch_secs().to_string())
+        };
+        Mock::given(method("HEAD"))
+            .and(path("/ci-bucket/codex/alpha"))
+            .respond_with(metadata(ResponseTemplate::new(200)))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/ci-bucket/codex/alpha"))
+            .respond_with(metadata(ResponseTemplate::new(200)).set_body_bytes(b"cached".to_vec()))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("HEAD"))
+            .and(path("/ci-bucket/codex/missing"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/ci-bucket/codex/missing"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(0)
+            .mount(&server)
+            .await;
+        let store = store_for(&server);
+
+        let entry = store.get("alpha").expect("get").expect("cached entry");
+        assert_eq!(entry.value, b"cached".to_vec());
+        assert_eq!(entry.ttl, Duration::from_secs(60));
+        assert!(store.get("missing").expect("get missing").is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn expired_objects_are_deleted_instead_of_downloaded() {
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/ci-bucket/codex/stale"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("x-amz-meta-ttl-secs", "1")
+                    .insert_header("x-amz-meta-inserted-epoch", "0"),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/ci-bucket/codex/stale"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let store = store_for(&server);
+
+        assert!(store.get("stale").expect("get").is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn clear_deletes_every_object_under_the_prefix() {
+        let server = MockServer::start().await;
+        let listing = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <Name>ci-bucket</Name>
+  <Prefix>codex/</Prefix>
+  <KeyCount>2</KeyCount>
+  <IsTruncated>false</IsTruncated>
+  <Contents><Key>codex/alpha</Key><Size>6</Size></Contents>
+  <Contents><Key>codex/beta</Key><Size>4</Size></Contents>
+</ListBucketResult>"#;
+        Mock::given(method("GET"))
+            .and(path_regex("^/ci-bucket/?$"))
+            .and(query_param("list-type", "2"))
+            .and(query_param("prefix", "codex/"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(listing))
+            .mount(&server)
+            .await;
+        for key in ["alpha", "beta"] {
+            Mock::given(method("DELETE"))
+                .and(path(format!("/ci-bucket/codex/{key}")))
+                .respond_with(ResponseTemplate::new(204))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+        let store = store_for(&server);
+
+        assert_eq!(
+            store.stats().expect("stats"),
+            CacheStoreStats {
+                entries: 2,
+                total_bytes: 10,
+            }
+        );
+        store.clear().expect("clear");
+    }
+}
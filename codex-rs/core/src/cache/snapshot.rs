@@ -0,0 +1,118 @@
+use crate::cache::store::CacheEntry;
+use crate::cache::store::CacheStoreSnapshotEntry;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde::Deserialize;
+use serde::Serialize;
+use std::io::BufRead;
+use std::io::Write;
+use std::time::Duration;
+
+/// One line of a cache snapshot file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheSnapshotRecord {
+    pub key: String,
+    pub value_b64: String,
+    pub ttl_secs: u64,
+    pub inserted_epoch: u64,
+}
+
+impl CacheSnapshotRecord {
+    pub fn from_entry(entry: &CacheStoreSnapshotEntry) -> Self {
+        Self {
+            key: entry.entry.key.clone(),
+            value_b64: STANDARD.encode(&entry.entry.value),
+            ttl_secs: entry.entry.ttl.as_secs(),
+            inserted_epoch: entry.inserted_epoch,
+        }
+    }
+
+    /// Converts the record back into an entry whose TTL is whatever remains
+    /// of the original lifetime at `now`, or `None` if it has already expired.
+    pub fn into_live_entry(self, now: u64) -> std::io::Result<Option<CacheEntry>> {
+        let Some(ttl) = remaining_ttl(self.inserted_epoch, self.ttl_secs, now) else {
+            return Ok(None);
+        };
+        let value = STANDARD.decode(self.value_b64.as_bytes()).map_err(|err| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("invalid snapshot value for {}: {err}", self.key),
+            )
+        })?;
+        Ok(Some(CacheEntry {
+            key: self.key,
+            value,
+            ttl,
+            tool: None,
+        }))
+    }
+}
+
+/// What is left at `now` of a TTL that started at `inserted_epoch`, or `None`
+/// once it has run out.
+pub(crate) fn remaining_ttl(inserted_epoch: u64, ttl_secs: u64, now: u64) -> Option<Duration> {
+    let expires_at = inserted_epoch.saturating_add(ttl_secs);
+    if ttl_secs == 0 || expires_at <= now {
+        return None;
+    }
+    Some(Duration::from_secs(expires_at - now))
+}
+
+/// Writes `entries` as JSON Lines, returning the number of records written.
+pub fn write_snapshot<W: Write>(
+    mut writer: W,
+    entries: &[CacheStoreSnapshotEntry],
+) -> std::io::Result<usize> {
+    for entry in entries {
+        let record = CacheSnapshotRecord::from_entry(entry);
+        serde_json::to_writer(&mut writer, &record)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    Ok(entries.len())
+}
+
+/// Reads JSON Lines snapshot records, skipping blank lines.
+pub fn read_snapshot<R: BufRead>(reader: R) -> std::io::Result<Vec<CacheSnapshotRecord>> {
+    let mut records = Vec::new();
+    for (idx, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = serde_json::from_str(&line).map_err(|err| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("invalid snapshot record on line {}: {err}", idx + 1),
+            )
+        })?;
+        records.push(record);
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn expired_records_are_skipped_and_ttl_is_remaining_lifetime() -> std::io::Result<()> {
+        let record = CacheSnapshotRecord {
+            key: "alpha".to_string(),
+            value_b64: STANDARD.encode(b"one"),
+            ttl_secs: 60,
+            inserted_epoch: 1_000,
+        };
+
+        let live = record
+            .clone()
+            .into_live_entry(1_020)?
+            .map(|entry| entry.ttl);
+        let expired = record.into_live_entry(1_060)?;
+
+        assert_eq!(live, Some(Duration::from_secs(40)));
+        assert!(expired.is_none());
+        Ok(())
+    }
+}
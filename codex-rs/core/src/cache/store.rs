@@ -1,20 +1,54 @@
 use crate::cache::LOG_TARGET;
+use crate::cache::config::CacheableTool;
+use crate::cache::manager::NAMESPACE_PREFIX_LEN;
 use serde::Deserialize;
 use serde::Serialize;
 use std::collections::HashMap;
+use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Mutex;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
 use std::time::Duration;
 use std::time::SystemTime;
 use std::time::UNIX_EPOCH;
 use tracing::warn;
 
+const TEMP_EXTENSION: &str = "tmp";
+/// Temp files younger than this may belong to a write still in progress in
+/// another process, so opening a store leaves them alone.
+const STALE_TEMP_FILE_AGE: Duration = Duration::from_secs(60 * 60);
+const SHARD_PREFIX_LEN: usize = 2;
+const CHECKSUM_LEN: usize = std::mem::size_of::<u32>();
+
 #[derive(Debug, Clone)]
 pub struct CacheEntry {
     pub key: String,
     pub value: Vec<u8>,
     pub ttl: Duration,
+    /// The tool whose result this is, when known.
+    pub tool: Option<CacheableTool>,
+}
+
+/// A live cache entry together with the time it was inserted.
+#[derive(Debug, Clone)]
+pub struct CacheStoreSnapshotEntry {
+    pub entry: CacheEntry,
+    pub inserted_epoch: u64,
+}
+
+/// An entry's index metadata and value, read without refreshing its access
+/// time or evicting it when expired.
+#[derive(Debug, Clone)]
+pub struct CacheStoreInspection {
+    pub value: Vec<u8>,
+    pub size_bytes: u64,
+    pub ttl_secs: u64,
+    pub inserted_epoch: u64,
+    pub last_access_epoch: u64,
+    pub is_expired: bool,
+    pub tool: Option<CacheableTool>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -33,7 +67,19 @@ pub trait CacheStore: Send + Sync {
     fn put(&self, entry: CacheEntry) -> std::io::Result<CacheStorePutOutcome>;
     fn remove(&self, key: &str) -> std::io::Result<()>;
     fn clear(&self) -> std::io::Result<()>;
+    /// Removes expired entries, returning how many were removed.
+    fn prune_expired(&self) -> std::io::Result<usize>;
+    /// Returns every non-expired entry, including its value.
+    fn snapshot(&self) -> std::io::Result<Vec<CacheStoreSnapshotEntry>>;
+    /// Returns a single entry for debugging, including expired ones.
+    fn inspect(&self, key: &str) -> std::io::Result<Option<CacheStoreInspection>>;
     fn stats(&self) -> std::io::Result<CacheStoreStats>;
+    /// Changes the size limit, evicting the oldest entries until the store
+    /// fits it. Returns how many entries were evicted. Stores without a size
+    /// limit ignore it.
+    fn resize(&self, _max_bytes: u64) -> std::io::Result<usize> {
+        Ok(0)
+    }
 }
 
 #[derive(Debug)]
@@ -41,7 +87,11 @@ pub struct DiskCacheStore {
     inner: Mutex<CacheIndex>,
     index_path: PathBuf,
     entries_path: PathBuf,
-    max_bytes: u64,
+    max_bytes: AtomicU64,
+    max_entry_bytes: u64,
+    read_only: bool,
+    /// Whether `put` appends a CRC32 trailer to new entries.
+    checksums: bool,
 }
 
 impl DiskCacheStore {
@@ -49,6 +99,8 @@ impl DiskCacheStore {
         std::fs::create_dir_all(cache_dir)?;
         let entries_path = cache_dir.join("entries");
         std::fs::create_dir_all(&entries_path)?;
+        migrate_flat_entries(&entries_path)?;
+        remove_stale_temp_files(&entries_path);
         let index_path = cache_dir.join("index.json");
         let mut index = Self::load_index(&index_path).unwrap_or_else(|err| {
             warn!(
@@ -63,10 +115,52 @@ impl DiskCacheStore {
             inner: Mutex::new(index),
             index_path,
             entries_path,
-            max_bytes,
+            max_bytes: AtomicU64::new(max_bytes),
+            max_entry_bytes: u64::MAX,
+            read_only: false,
+            checksums: false,
+        })
+    }
+
+    /// Opens an existing cache directory without ever writing to it.
+    ///
+    /// Expired or corrupt entries are skipped rather than removed, access
+    /// times are not updated, and every mutating operation either does
+    /// nothing (`put`) or fails with [`std::io::ErrorKind::PermissionDenied`].
+    pub fn open_read_only(cache_dir: &Path, max_bytes: u64) -> std::io::Result<Self> {
+        let index_path = cache_dir.join("index.json");
+        let index = Self::load_index(&index_path).unwrap_or_else(|err| {
+            warn!(
+                target: LOG_TARGET,
+                "failed to load cache index: {err}"
+            );
+            CacheIndex::default()
+        });
+        Ok(Self {
+            inner: Mutex::new(index),
+            index_path,
+            entries_path: cache_dir.join("entries"),
+            max_bytes: AtomicU64::new(max_bytes),
+            max_entry_bytes: u64::MAX,
+            read_only: true,
+            checksums: false,
         })
     }
 
+    /// Rejects values larger than `max_entry_bytes` so a single huge entry
+    /// cannot evict the rest of the cache.
+    pub fn with_max_entry_bytes(mut self, max_entry_bytes: u64) -> Self {
+        self.max_entry_bytes = max_entry_bytes;
+        self
+    }
+
+    /// Controls whether new entries carry a CRC32 trailer. Entries already on
+    /// disk are verified according to how they were written either way.
+    pub fn with_checksums(mut self, checksums: bool) -> Self {
+        self.checksums = checksums;
+        self
+    }
+
     fn load_index(path: &Path) -> std::io::Result<CacheIndex> {
         let bytes = match std::fs::read(path) {
             Ok(bytes) => bytes,
@@ -85,14 +179,11 @@ impl DiskCacheStore {
         let bytes = serde_json::to_vec(index).map_err(|err| {
             std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{err}"))
         })?;
-        let tmp_path = self.index_path.with_extension("json.tmp");
-        std::fs::write(&tmp_path, bytes)?;
-        std::fs::rename(tmp_path, &self.index_path)?;
-        Ok(())
+        write_atomic(&self.index_path, &bytes)
     }
 
     fn entry_path(&self, key: &str) -> PathBuf {
-        self.entries_path.join(key)
+        sharded_entry_path(&self.entries_path, key)
     }
 }
 
@@ -102,54 +193,76 @@ impl CacheStore for DiskCacheStore {
             .inner
             .lock()
             .map_err(|_| std::io::Error::other("cache lock poisoned"))?;
-        let (ttl_secs, value) = {
+        let (ttl_secs, tool, value) = {
             let entry = match index.entries.get_mut(key) {
                 Some(entry) => entry,
                 None => return Ok(None),
             };
-            if entry.is_expired() {
-                let _ = index.remove_entry(key, &self.entries_path);
-                self.persist_index(&index)?;
-                return Ok(None);
-            }
             let entry_path = self.entry_path(key);
-            let value = match std::fs::read(&entry_path) {
-                Ok(value) => value,
-                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            let value = if entry.is_expired() {
+                None
+            } else {
+                read_entry_file(&entry_path, entry.checksum)?
+            };
+            let Some(value) = value else {
+                if !self.read_only {
                     let _ = index.remove_entry(key, &self.entries_path);
                     self.persist_index(&index)?;
-                    return Ok(None);
                 }
-                Err(err) => return Err(err),
+                return Ok(None);
             };
-            entry.last_access_epoch = now_epoch_secs();
-            (entry.ttl_secs, value)
+            if !self.read_only {
+                entry.last_access_epoch = now_epoch_secs();
+            }
+            (entry.ttl_secs, entry.tool, value)
         };
-        self.persist_index(&index)?;
+        if !self.read_only {
+            self.persist_index(&index)?;
+        }
         Ok(Some(CacheEntry {
             key: key.to_string(),
             value,
             ttl: Duration::from_secs(ttl_secs),
+            tool,
         }))
     }
 
     fn put(&self, entry: CacheEntry) -> std::io::Result<CacheStorePutOutcome> {
-        if self.max_bytes == 0 {
+        if self.read_only {
+            return Ok(CacheStorePutOutcome { evicted: 0 });
+        }
+        if entry.value.len() as u64 > self.max_entry_bytes {
+            warn!(
+                target: LOG_TARGET,
+                key = %entry.key,
+                size_bytes = entry.value.len(),
+                max_entry_bytes = self.max_entry_bytes,
+                "skipping oversized cache entry"
+            );
+            return Ok(CacheStorePutOutcome { evicted: 0 });
+        }
+        let max_bytes = self.max_bytes.load(Ordering::Relaxed);
+        if max_bytes == 0 {
             return Ok(CacheStorePutOutcome { evicted: 0 });
         }
         let mut index = self
             .inner
             .lock()
             .map_err(|_| std::io::Error::other("cache lock poisoned"))?;
-        let size_bytes = entry.value.len() as u64;
-        if size_bytes > self.max_bytes {
+        let checksum = self.checksums.then(|| crc32fast::hash(&entry.value));
+        let mut file_bytes = entry.value;
+        if let Some(checksum) = checksum {
+            file_bytes.extend_from_slice(&checksum.to_le_bytes());
+        }
+        let size_bytes = file_bytes.len() as u64;
+        if size_bytes > max_bytes {
             return Ok(CacheStorePutOutcome { evicted: 0 });
         }
         if index.entries.contains_key(&entry.key) {
             index.remove_entry(&entry.key, &self.entries_path)?;
         }
         let mut evicted = 0;
-        while index.total_bytes + size_bytes > self.max_bytes {
+        while index.total_bytes + size_bytes > max_bytes {
             let Some((oldest_key, _)) = index.oldest_entry() else {
                 break;
             };
@@ -157,7 +270,10 @@ impl CacheStore for DiskCacheStore {
             evicted += 1;
         }
         let entry_path = self.entry_path(&entry.key);
-        std::fs::write(&entry_path, &entry.value)?;
+        if let Some(shard_dir) = entry_path.parent() {
+            std::fs::create_dir_all(shard_dir)?;
+        }
+        write_atomic(&entry_path, &file_bytes)?;
         index.total_bytes += size_bytes;
         index.entries.insert(
             entry.key.clone(),
@@ -166,6 +282,8 @@ impl CacheStore for DiskCacheStore {
                 inserted_epoch: now_epoch_secs(),
                 last_access_epoch: now_epoch_secs(),
                 ttl_secs: entry.ttl.as_secs(),
+                checksum,
+                tool: entry.tool,
             },
         );
         self.persist_index(&index)?;
@@ -173,6 +291,9 @@ impl CacheStore for DiskCacheStore {
     }
 
     fn remove(&self, key: &str) -> std::io::Result<()> {
+        if self.read_only {
+            return Err(read_only_error());
+        }
         let mut index = self
             .inner
             .lock()
@@ -183,6 +304,9 @@ impl CacheStore for DiskCacheStore {
     }
 
     fn clear(&self) -> std::io::Result<()> {
+        if self.read_only {
+            return Err(read_only_error());
+        }
         let mut index = self
             .inner
             .lock()
@@ -192,6 +316,71 @@ impl CacheStore for DiskCacheStore {
         Ok(())
     }
 
+    fn prune_expired(&self) -> std::io::Result<usize> {
+        if self.read_only {
+            return Err(read_only_error());
+        }
+        let mut index = self
+            .inner
+            .lock()
+            .map_err(|_| std::io::Error::other("cache lock poisoned"))?;
+        let removed = index.prune_expired(&self.entries_path)?;
+        if removed > 0 {
+            self.persist_index(&index)?;
+        }
+        Ok(removed)
+    }
+
+    fn snapshot(&self) -> std::io::Result<Vec<CacheStoreSnapshotEntry>> {
+        let index = self
+            .inner
+            .lock()
+            .map_err(|_| std::io::Error::other("cache lock poisoned"))?;
+        let now = now_epoch_secs();
+        let mut entries = Vec::new();
+        for (key, entry) in &index.entries {
+            if entry.is_expired_at(now) {
+                continue;
+            }
+            let Some(value) = read_entry_file(&self.entry_path(key), entry.checksum)? else {
+                continue;
+            };
+            entries.push(CacheStoreSnapshotEntry {
+                entry: CacheEntry {
+                    key: key.clone(),
+                    value,
+                    ttl: Duration::from_secs(entry.ttl_secs),
+                    tool: entry.tool,
+                },
+                inserted_epoch: entry.inserted_epoch,
+            });
+        }
+        entries.sort_by(|a, b| a.entry.key.cmp(&b.entry.key));
+        Ok(entries)
+    }
+
+    fn inspect(&self, key: &str) -> std::io::Result<Option<CacheStoreInspection>> {
+        let index = self
+            .inner
+            .lock()
+            .map_err(|_| std::io::Error::other("cache lock poisoned"))?;
+        let Some(entry) = index.entries.get(key) else {
+            return Ok(None);
+        };
+        let Some(value) = read_entry_file(&self.entry_path(key), entry.checksum)? else {
+            return Ok(None);
+        };
+        Ok(Some(CacheStoreInspection {
+            value,
+            size_bytes: entry.size_bytes,
+            ttl_secs: entry.ttl_secs,
+            inserted_epoch: entry.inserted_epoch,
+            last_access_epoch: entry.last_access_epoch,
+            is_expired: entry.is_expired(),
+            tool: entry.tool,
+        }))
+    }
+
     fn stats(&self) -> std::io::Result<CacheStoreStats> {
         let index = self
             .inner
@@ -202,6 +391,146 @@ impl CacheStore for DiskCacheStore {
             total_bytes: index.total_bytes,
         })
     }
+
+    fn resize(&self, max_bytes: u64) -> std::io::Result<usize> {
+        if self.read_only {
+            return Err(read_only_error());
+        }
+        let mut index = self
+            .inner
+            .lock()
+            .map_err(|_| std::io::Error::other("cache lock poisoned"))?;
+        self.max_bytes.store(max_bytes, Ordering::Relaxed);
+        let mut evicted = 0;
+        while index.total_bytes > max_bytes {
+            let Some((oldest_key, _)) = index.oldest_entry() else {
+                break;
+            };
+            index.remove_entry(&oldest_key, &self.entries_path)?;
+            evicted += 1;
+        }
+        if evicted > 0 {
+            self.persist_index(&index)?;
+        }
+        Ok(evicted)
+    }
+}
+
+/// A process-local cache store with no size limit, for tests and callers that
+/// need a cache without touching the filesystem.
+#[derive(Debug, Default)]
+pub struct InMemoryCacheStore {
+    entries: Mutex<InMemoryEntries>,
+}
+
+/// Entry metadata and value, keyed by cache key.
+type InMemoryEntries = HashMap<String, (CacheIndexEntry, Vec<u8>)>;
+
+impl InMemoryCacheStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> std::io::Result<std::sync::MutexGuard<'_, InMemoryEntries>> {
+        self.entries
+            .lock()
+            .map_err(|_| std::io::Error::other("cache lock poisoned"))
+    }
+}
+
+impl CacheStore for InMemoryCacheStore {
+    fn get(&self, key: &str) -> std::io::Result<Option<CacheEntry>> {
+        let mut entries = self.lock()?;
+        let Some((meta, value)) = entries.get_mut(key) else {
+            return Ok(None);
+        };
+        if meta.is_expired() {
+            entries.remove(key);
+            return Ok(None);
+        }
+        meta.last_access_epoch = now_epoch_secs();
+        Ok(Some(CacheEntry {
+            key: key.to_string(),
+            value: value.clone(),
+            ttl: Duration::from_secs(meta.ttl_secs),
+            tool: meta.tool,
+        }))
+    }
+
+    fn put(&self, entry: CacheEntry) -> std::io::Result<CacheStorePutOutcome> {
+        let now = now_epoch_secs();
+        let meta = CacheIndexEntry {
+            size_bytes: entry.value.len() as u64,
+            inserted_epoch: now,
+            last_access_epoch: now,
+            ttl_secs: entry.ttl.as_secs(),
+            checksum: None,
+            tool: entry.tool,
+        };
+        self.lock()?.insert(entry.key, (meta, entry.value));
+        Ok(CacheStorePutOutcome { evicted: 0 })
+    }
+
+    fn remove(&self, key: &str) -> std::io::Result<()> {
+        self.lock()?.remove(key);
+        Ok(())
+    }
+
+    fn clear(&self) -> std::io::Result<()> {
+        self.lock()?.clear();
+        Ok(())
+    }
+
+    fn prune_expired(&self) -> std::io::Result<usize> {
+        let mut entries = self.lock()?;
+        let now = now_epoch_secs();
+        let before = entries.len();
+        entries.retain(|_, (meta, _)| !meta.is_expired_at(now));
+        Ok(before - entries.len())
+    }
+
+    fn snapshot(&self) -> std::io::Result<Vec<CacheStoreSnapshotEntry>> {
+        let entries = self.lock()?;
+        let now = now_epoch_secs();
+        let mut snapshot = entries
+            .iter()
+            .filter(|(_, (meta, _))| !meta.is_expired_at(now))
+            .map(|(key, (meta, value))| CacheStoreSnapshotEntry {
+                entry: CacheEntry {
+                    key: key.clone(),
+                    value: value.clone(),
+                    ttl: Duration::from_secs(meta.ttl_secs),
+                    tool: meta.tool,
+                },
+                inserted_epoch: meta.inserted_epoch,
+            })
+            .collect::<Vec<_>>();
+        snapshot.sort_by(|a, b| a.entry.key.cmp(&b.entry.key));
+        Ok(snapshot)
+    }
+
+    fn inspect(&self, key: &str) -> std::io::Result<Option<CacheStoreInspection>> {
+        Ok(self
+            .lock()?
+            .get(key)
+            .map(|(meta, value)| CacheStoreInspection {
+                value: value.clone(),
+                size_bytes: meta.size_bytes,
+                ttl_secs: meta.ttl_secs,
+                inserted_epoch: meta.inserted_epoch,
+                last_access_epoch: meta.last_access_epoch,
+                is_expired: meta.is_expired(),
+                tool: meta.tool,
+            }))
+    }
+
+    fn stats(&self) -> std::io::Result<CacheStoreStats> {
+        let entries = self.lock()?;
+        Ok(CacheStoreStats {
+            entries: entries.len(),
+            total_bytes: entries.values().map(|(meta, _)| meta.size_bytes).sum(),
+        })
+    }
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
@@ -214,7 +543,7 @@ impl CacheIndex {
     fn remove_entry(&mut self, key: &str, entries_path: &Path) -> std::io::Result<()> {
         if let Some(entry) = self.entries.remove(key) {
             self.total_bytes = self.total_bytes.saturating_sub(entry.size_bytes);
-            let entry_path = entries_path.join(key);
+            let entry_path = sharded_entry_path(entries_path, key);
             let _ = std::fs::remove_file(entry_path);
         }
         Ok(())
@@ -222,7 +551,7 @@ impl CacheIndex {
 
     fn clear(&mut self, entries_path: &Path) -> std::io::Result<()> {
         for key in self.entries.keys() {
-            let _ = std::fs::remove_file(entries_path.join(key));
+            let _ = std::fs::remove_file(sharded_entry_path(entries_path, key));
         }
         self.entries.clear();
         self.total_bytes = 0;
@@ -236,7 +565,7 @@ impl CacheIndex {
             .map(|(key, entry)| (key.clone(), entry))
     }
 
-    fn prune_expired(&mut self, entries_path: &Path) -> std::io::Result<()> {
+    fn prune_expired(&mut self, entries_path: &Path) -> std::io::Result<usize> {
         let now = now_epoch_secs();
         let expired_keys = self
             .entries
@@ -249,10 +578,11 @@ impl CacheIndex {
                 }
             })
             .collect::<Vec<_>>();
+        let removed = expired_keys.len();
         for key in expired_keys {
             self.remove_entry(&key, entries_path)?;
         }
-        Ok(())
+        Ok(removed)
     }
 
     fn recalculate_bytes(&mut self, entries_path: &Path) -> std::io::Result<()> {
@@ -261,7 +591,7 @@ impl CacheIndex {
             .entries
             .iter()
             .filter_map(|(key, entry)| {
-                let path = entries_path.join(key);
+                let path = sharded_entry_path(entries_path, key);
                 match std::fs::metadata(&path) {
                     Ok(metadata) => {
                         total = total.saturating_add(metadata.len());
@@ -286,6 +616,14 @@ struct CacheIndexEntry {
     inserted_epoch: u64,
     last_access_epoch: u64,
     ttl_secs: u64,
+    /// CRC32 of the value, also appended to the entry file. `None` marks
+    /// entries written before checksums existed, which are not verified.
+    #[serde(default)]
+    checksum: Option<u32>,
+    /// The tool passed to `CacheManager::put`. `None` for entries written
+    /// before this was recorded and for warmed or imported entries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool: Option<CacheableTool>,
 }
 
 impl CacheIndexEntry {
@@ -301,7 +639,154 @@ impl CacheIndexEntry {
     }
 }
 
-fn now_epoch_secs() -> u64 {
+/// Error returned by mutating operations on a read-only cache.
+pub(crate) fn read_only_error() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::PermissionDenied, "cache is read-only")
+}
+
+/// Reads an entry file, verifying and stripping its CRC32 trailer.
+///
+/// Returns `Ok(None)` when the file is missing or fails validation, so callers
+/// can treat corruption exactly like a miss.
+fn read_entry_file(path: &Path, checksum: Option<u32>) -> std::io::Result<Option<Vec<u8>>> {
+    let mut bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let Some(expected) = checksum else {
+        return Ok(Some(bytes));
+    };
+    let Some(payload_len) = bytes.len().checked_sub(CHECKSUM_LEN) else {
+        warn!(target: LOG_TARGET, path = %path.display(), "cache entry is truncated");
+        return Ok(None);
+    };
+    let mut trailer = [0u8; CHECKSUM_LEN];
+    trailer.copy_from_slice(&bytes[payload_len..]);
+    bytes.truncate(payload_len);
+    if u32::from_le_bytes(trailer) != expected || crc32fast::hash(&bytes) != expected {
+        warn!(
+            target: LOG_TARGET,
+            path = %path.display(),
+            "cache entry failed checksum validation"
+        );
+        return Ok(None);
+    }
+    Ok(Some(bytes))
+}
+
+/// Writes `bytes` to a uniquely named sibling temp file and renames it over
+/// `path`, so a process killed mid-write never leaves a partially written
+/// entry behind and concurrent writers never share a temp file.
+pub(crate) fn write_atomic(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let prefix = path
+        .file_name()
+        .map(|name| format!("{}.", name.to_string_lossy()))
+        .unwrap_or_default();
+    let mut tmp = tempfile::Builder::new()
+        .prefix(&prefix)
+        .suffix(&format!(".{TEMP_EXTENSION}"))
+        .tempfile_in(dir)?;
+    tmp.write_all(bytes)?;
+    tmp.persist(path).map_err(|err| err.error)?;
+    Ok(())
+}
+
+/// Entry files live in `entries/<first two key chars>/<key>` so no single
+/// directory grows to millions of entries. Namespaced keys
+/// (`<namespace>:<key>`) are sharded the same way under
+/// `entries/<namespace>/`, which keeps `:` out of file names.
+pub(crate) fn sharded_entry_path(entries_path: &Path, key: &str) -> PathBuf {
+    if let Some((namespace, key)) = key.split_once(':') {
+        return sharded_entry_path(&entries_path.join(namespace), key);
+    }
+    let shard = key.get(..SHARD_PREFIX_LEN).unwrap_or(key);
+    entries_path.join(shard).join(key)
+}
+
+/// Rejects keys that would not map to a file inside a shard directory, such
+/// as `../x` or `ab:/etc/passwd`, so keys read from snapshots and archives can
+/// never write outside `entries/`. A key is an optional namespace prefix of
+/// [`NAMESPACE_PREFIX_LEN`] hex characters plus `:`, followed by a single file
+/// name that does not start with `.`.
+pub(crate) fn validate_entry_key(key: &str) -> std::io::Result<()> {
+    let (namespace, name) = match key.split_once(':') {
+        Some((namespace, name)) => (Some(namespace), name),
+        None => (None, key),
+    };
+    let namespace_ok = namespace.is_none_or(|namespace| {
+        namespace.len() == NAMESPACE_PREFIX_LEN
+            && namespace.bytes().all(|byte| byte.is_ascii_hexdigit())
+    });
+    let name_ok =
+        !name.is_empty() && !name.starts_with('.') && !name.contains(['/', '\\', ':', '\0']);
+    if namespace_ok && name_ok {
+        Ok(())
+    } else {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("invalid cache key: {key:?}"),
+        ))
+    }
+}
+
+/// Moves entry files written by older builds directly under `entries/` into
+/// their shard directories. Runs on every open but only touches flat files, so
+/// it is a no-op once a cache has been migrated.
+fn migrate_flat_entries(entries_path: &Path) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(entries_path)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == TEMP_EXTENSION) {
+            if is_stale_temp_file(&entry) {
+                let _ = std::fs::remove_file(path);
+            }
+            continue;
+        }
+        let key = entry.file_name().to_string_lossy().into_owned();
+        let target = sharded_entry_path(entries_path, &key);
+        if let Some(shard_dir) = target.parent() {
+            std::fs::create_dir_all(shard_dir)?;
+        }
+        std::fs::rename(path, target)?;
+    }
+    Ok(())
+}
+
+/// Removes temp files left behind by writes that were interrupted.
+///
+/// Only temp files older than [`STALE_TEMP_FILE_AGE`] are removed, and the
+/// walk descends into namespace directories as well as shards.
+fn remove_stale_temp_files(dir: &Path) {
+    let Ok(read_dir) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in read_dir.flatten() {
+        let path = entry.path();
+        if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+            remove_stale_temp_files(&path);
+        } else if path.extension().is_some_and(|ext| ext == TEMP_EXTENSION)
+            && is_stale_temp_file(&entry)
+        {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+fn is_stale_temp_file(entry: &std::fs::DirEntry) -> bool {
+    entry
+        .metadata()
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age >= STALE_TEMP_FILE_AGE)
+}
+
+pub(crate) fn now_epoch_secs() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default()
@@ -312,6 +797,8 @@ fn now_epoch_secs() -> u64 {
 mod tests {
     use super::*;
     use pretty_assertions::assert_eq;
+    use sha2::Digest;
+    use sha2::Sha256;
     use tempfile::tempdir;
 
     #[test]
@@ -322,6 +809,7 @@ mod tests {
             key: "alpha".to_string(),
             value: b"one".to_vec(),
             ttl: Duration::from_secs(60),
+            tool: None,
         };
 
         store.put(entry)?;
@@ -338,11 +826,13 @@ mod tests {
             key: "alpha".to_string(),
             value: b"123456".to_vec(),
             ttl: Duration::from_secs(60),
+            tool: None,
         })?;
         store.put(CacheEntry {
             key: "bravo".to_string(),
             value: b"abcdef".to_vec(),
             ttl: Duration::from_secs(60),
+            tool: None,
         })?;
 
         assert!(store.get("alpha")?.is_none());
@@ -350,6 +840,72 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn rejects_entries_over_max_entry_bytes() -> std::io::Result<()> {
+        let dir = tempdir()?;
+        let store = DiskCacheStore::new(dir.path(), 1024)?.with_max_entry_bytes(8);
+        store.put(CacheEntry {
+            key: "small".to_string(),
+            value: b"fits".to_vec(),
+            ttl: Duration::from_secs(60),
+            tool: None,
+        })?;
+
+        let outcome = store.put(CacheEntry {
+            key: "huge".to_string(),
+            value: vec![0u8; 9],
+            ttl: Duration::from_secs(60),
+            tool: None,
+        })?;
+        store.put(CacheEntry {
+            key: "after".to_string(),
+            value: b"also".to_vec(),
+            ttl: Duration::from_secs(60),
+            tool: None,
+        })?;
+
+        assert_eq!(outcome, CacheStorePutOutcome { evicted: 0 });
+        assert!(store.get("huge")?.is_none());
+        assert!(store.get("small")?.is_some());
+        assert!(store.get("after")?.is_some());
+        assert_eq!(store.stats()?.entries, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn read_only_store_serves_reads_without_writing() -> std::io::Result<()> {
+        let dir = tempdir()?;
+        let writable = DiskCacheStore::new(dir.path(), 1024)?;
+        writable.put(CacheEntry {
+            key: "alpha".to_string(),
+            value: b"payload".to_vec(),
+            ttl: Duration::from_secs(60),
+            tool: None,
+        })?;
+        let index_before = std::fs::read(dir.path().join("index.json"))?;
+
+        let store = DiskCacheStore::open_read_only(dir.path(), 1024)?;
+        let outcome = store.put(CacheEntry {
+            key: "bravo".to_string(),
+            value: b"ignored".to_vec(),
+            ttl: Duration::from_secs(60),
+            tool: None,
+        })?;
+
+        assert_eq!(outcome, CacheStorePutOutcome { evicted: 0 });
+        assert_eq!(
+            store.get("alpha")?.map(|entry| entry.value),
+            Some(b"payload".to_vec())
+        );
+        assert!(store.get("bravo")?.is_none());
+        assert_eq!(
+            store.clear().map_err(|err| err.kind()),
+            Err(std::io::ErrorKind::PermissionDenied)
+        );
+        assert_eq!(std::fs::read(dir.path().join("index.json"))?, index_before);
+        Ok(())
+    }
+
     #[test]
     fn expired_entries_are_not_returned() -> std::io::Result<()> {
         let dir = tempdir()?;
@@ -358,12 +914,345 @@ mod tests {
             key: "alpha".to_string(),
             value: b"stale".to_vec(),
             ttl: Duration::from_secs(0),
+            tool: None,
         })?;
 
         assert!(store.get("alpha")?.is_none());
         Ok(())
     }
 
+    #[test]
+    fn interrupted_write_never_exposes_partial_entry() -> std::io::Result<()> {
+        let dir = tempdir()?;
+        let value = vec![7u8; 64 * 1024];
+        {
+            let store = DiskCacheStore::new(dir.path(), 1024 * 1024)?;
+            store.put(CacheEntry {
+                key: "alpha".to_string(),
+                value: value.clone(),
+                ttl: Duration::from_secs(60),
+                tool: None,
+            })?;
+        }
+        // Simulate a writer killed mid-write: a truncated temp file for an
+        // existing entry and one for an entry that never made it to the index.
+        let entries = dir.path().join("entries");
+        std::fs::create_dir_all(entries.join("br"))?;
+        let stale = SystemTime::now() - STALE_TEMP_FILE_AGE - Duration::from_secs(1);
+        for tmp_path in [
+            entries.join("al").join("alpha.tmp"),
+            entries.join("br").join("bravo.tmp"),
+        ] {
+            std::fs::write(&tmp_path, &value[..1024])?;
+            std::fs::File::options()
+                .write(true)
+                .open(&tmp_path)?
+                .set_modified(stale)?;
+        }
+
+        let store = DiskCacheStore::new(dir.path(), 1024 * 1024)?;
+
+        assert_eq!(store.get("alpha")?.map(|entry| entry.value), Some(value));
+        assert!(store.get("bravo")?.is_none());
+        assert!(!entries.join("al").join("alpha.tmp").exists());
+        assert!(!entries.join("br").join("bravo.tmp").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn validate_entry_key_rejects_path_components() {
+        let digest = "f".repeat(64);
+        for key in ["alpha", "0123abcd:alpha", digest.as_str()] {
+            assert!(validate_entry_key(key).is_ok(), "{key}");
+        }
+        for key in [
+            "",
+            "../x",
+            "..x",
+            ".hidden",
+            "a/b",
+            "a\\b",
+            "/etc/passwd",
+            "ab:../../../../.bashrc",
+            "0123abcd:",
+            "0123abcd:a/b",
+            "0123abcd:x:y",
+            "C:\\x",
+        ] {
+            assert_eq!(
+                validate_entry_key(key).map_err(|err| err.kind()),
+                Err(std::io::ErrorKind::InvalidData),
+                "{key}"
+            );
+        }
+    }
+
+    #[test]
+    fn opening_store_keeps_recent_temp_files() -> std::io::Result<()> {
+        let dir = tempdir()?;
+        let in_flight = dir.path().join("entries").join("al").join("alpha.tmp");
+        std::fs::create_dir_all(in_flight.parent().expect("shard dir"))?;
+        std::fs::write(&in_flight, b"partial")?;
+
+        let _store = DiskCacheStore::new(dir.path(), 1024 * 1024)?;
+
+        assert!(in_flight.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn stores_sharing_a_dir_always_publish_a_whole_index() -> std::io::Result<()> {
+        let dir = tempdir()?;
+        let handles = (0..4)
+            .map(|writer| {
+                let cache_dir = dir.path().to_path_buf();
+                std::thread::spawn(move || {
+                    let store = DiskCacheStore::new(&cache_dir, 64 * 1024 * 1024)?;
+                    for entry in 0..25 {
+                        store.put(CacheEntry {
+                            key: format!("w{writer}-{entry}"),
+                            value: vec![1u8; 16],
+                            ttl: Duration::from_secs(60),
+                            tool: None,
+                        })?;
+                    }
+                    Ok::<_, std::io::Error>(())
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().expect("writer thread")?;
+        }
+
+        DiskCacheStore::load_index(&dir.path().join("index.json"))?;
+        let leftover_temp_files = std::fs::read_dir(dir.path())?
+            .flatten()
+            .filter(|entry| {
+                entry
+                    .path()
+                    .extension()
+                    .is_some_and(|ext| ext == TEMP_EXTENSION)
+            })
+            .count();
+        assert_eq!(leftover_temp_files, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn concurrent_writes_to_one_key_use_separate_temp_files() -> std::io::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("alpha");
+        let handles = (0..8u8)
+            .map(|byte| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..50 {
+                        write_atomic(&path, &vec![byte; 4096])?;
+                    }
+                    Ok::<_, std::io::Error>(())
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().expect("writer thread")?;
+        }
+
+        let written = std::fs::read(&path)?;
+        assert_eq!(written.len(), 4096);
+        assert!(written.iter().all(|byte| *byte == written[0]));
+        assert_eq!(std::fs::read_dir(dir.path())?.count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn concurrent_reads_see_whole_entries_only() -> std::io::Result<()> {
+        let dir = tempdir()?;
+        let store = std::sync::Arc::new(DiskCacheStore::new(dir.path(), 64 * 1024 * 1024)?);
+        let small = vec![1u8; 16];
+        let large = vec![2u8; 4 * 1024 * 1024];
+        store.put(CacheEntry {
+            key: "alpha".to_string(),
+            value: small.clone(),
+            ttl: Duration::from_secs(60),
+            tool: None,
+        })?;
+
+        let writer = {
+            let store = std::sync::Arc::clone(&store);
+            let large = large.clone();
+            std::thread::spawn(move || {
+                store.put(CacheEntry {
+                    key: "alpha".to_string(),
+                    value: large,
+                    ttl: Duration::from_secs(60),
+                    tool: None,
+                })
+            })
+        };
+        for _ in 0..16 {
+            if let Some(entry) = store.get("alpha")? {
+                assert!(entry.value == small || entry.value == large);
+            }
+        }
+        writer
+            .join()
+            .map_err(|_| std::io::Error::other("writer panicked"))??;
+
+        assert_eq!(store.get("alpha")?.map(|entry| entry.value), Some(large));
+        Ok(())
+    }
+
+    #[test]
+    fn shards_entries_by_key_prefix() -> std::io::Result<()> {
+        let dir = tempdir()?;
+        let store = DiskCacheStore::new(dir.path(), 1024 * 1024)?;
+        for idx in 0..512u32 {
+            let key = format!("{:x}", Sha256::digest(idx.to_le_bytes()));
+            store.put(CacheEntry {
+                key,
+                value: b"value".to_vec(),
+                ttl: Duration::from_secs(60),
+                tool: None,
+            })?;
+        }
+
+        let entries = dir.path().join("entries");
+        let mut shard_count = 0;
+        for shard in std::fs::read_dir(&entries)? {
+            let shard = shard?;
+            assert!(shard.file_type()?.is_dir());
+            let name = shard.file_name().to_string_lossy().into_owned();
+            assert_eq!(name.len(), 2);
+            for entry in std::fs::read_dir(shard.path())? {
+                let key = entry?.file_name().to_string_lossy().into_owned();
+                assert!(key.starts_with(&name));
+            }
+            shard_count += 1;
+        }
+        assert!(shard_count > 128, "only {shard_count} shards used");
+        assert_eq!(store.stats()?.entries, 512);
+        Ok(())
+    }
+
+    #[test]
+    fn migrates_flat_entries_into_shards() -> std::io::Result<()> {
+        let dir = tempdir()?;
+        {
+            let store = DiskCacheStore::new(dir.path(), 1024)?;
+            store.put(CacheEntry {
+                key: "alpha".to_string(),
+                value: b"one".to_vec(),
+                ttl: Duration::from_secs(60),
+                tool: None,
+            })?;
+        }
+        let entries = dir.path().join("entries");
+        std::fs::rename(entries.join("al").join("alpha"), entries.join("alpha"))?;
+
+        let store = DiskCacheStore::new(dir.path(), 1024)?;
+
+        assert!(!entries.join("alpha").exists());
+        assert_eq!(
+            store.get("alpha")?.map(|entry| entry.value),
+            Some(b"one".to_vec())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn namespaced_keys_are_sharded_under_their_namespace() {
+        let entries = Path::new("entries");
+
+        assert_eq!(
+            sharded_entry_path(entries, "1a2b3c4d:alpha"),
+            entries.join("1a2b3c4d").join("al").join("alpha")
+        );
+        assert_eq!(
+            sharded_entry_path(entries, "alpha"),
+            entries.join("al").join("alpha")
+        );
+    }
+
+    #[test]
+    fn corrupted_entry_is_treated_as_miss() -> std::io::Result<()> {
+        let dir = tempdir()?;
+        let store = DiskCacheStore::new(dir.path(), 1024)?.with_checksums(true);
+        store.put(CacheEntry {
+            key: "alpha".to_string(),
+            value: b"payload".to_vec(),
+            ttl: Duration::from_secs(60),
+            tool: None,
+        })?;
+        let entry_path = dir.path().join("entries").join("al").join("alpha");
+        let mut bytes = std::fs::read(&entry_path)?;
+        bytes[0] ^= 0b0000_0100;
+        std::fs::write(&entry_path, bytes)?;
+
+        assert!(store.get("alpha")?.is_none());
+        assert_eq!(store.stats()?.entries, 0);
+        assert!(!entry_path.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn entries_written_without_checksums_have_no_trailer() -> std::io::Result<()> {
+        let dir = tempdir()?;
+        let store = DiskCacheStore::new(dir.path(), 1024)?;
+        store.put(CacheEntry {
+            key: "alpha".to_string(),
+            value: b"payload".to_vec(),
+            ttl: Duration::from_secs(60),
+            tool: None,
+        })?;
+
+        let entry_path = dir.path().join("entries").join("al").join("alpha");
+        assert_eq!(std::fs::read(&entry_path)?, b"payload");
+        assert_eq!(store.stats()?.total_bytes, 7);
+        assert_eq!(
+            store.get("alpha")?.map(|entry| entry.value),
+            Some(b"payload".to_vec())
+        );
+
+        let checked = DiskCacheStore::new(dir.path(), 1024)?.with_checksums(true);
+        assert_eq!(
+            checked.get("alpha")?.map(|entry| entry.value),
+            Some(b"payload".to_vec())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn legacy_entries_without_checksum_are_returned() -> std::io::Result<()> {
+        let dir = tempdir()?;
+        let entries = dir.path().join("entries");
+        std::fs::create_dir_all(entries.join("al"))?;
+        std::fs::write(entries.join("al").join("alpha"), b"legacy")?;
+        let now = now_epoch_secs();
+        std::fs::write(
+            dir.path().join("index.json"),
+            serde_json::json!({
+                "entries": {
+                    "alpha": {
+                        "size_bytes": 6,
+                        "inserted_epoch": now,
+                        "last_access_epoch": now,
+                        "ttl_secs": 60,
+                    },
+                },
+                "total_bytes": 6,
+            })
+            .to_string(),
+        )?;
+
+        let store = DiskCacheStore::new(dir.path(), 1024)?;
+
+        assert_eq!(
+            store.get("alpha")?.map(|entry| entry.value),
+            Some(b"legacy".to_vec())
+        );
+        Ok(())
+    }
+
     #[test]
     fn clear_removes_entries() -> std::io::Result<()> {
         let dir = tempdir()?;
@@ -372,6 +1261,7 @@ mod tests {
             key: "alpha".to_string(),
             value: b"one".to_vec(),
             ttl: Duration::from_secs(60),
+            tool: None,
         })?;
         store.clear()?;
 
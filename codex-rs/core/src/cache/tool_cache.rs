@@ -1,7 +1,9 @@
+use crate::cache::canonical::canonical_json;
 use serde_json::Value as JsonValue;
 use sha2::Digest;
 use sha2::Sha256;
 use std::path::Path;
+use std::path::PathBuf;
 use std::time::UNIX_EPOCH;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -49,6 +51,35 @@ pub async fn build_tool_cache_key_for_path(
     build_tool_cache_key(tool_name, args, workspace_root, target_path, stamp)
 }
 
+/// Builds a cache key for a directory listing rooted at `dir_path`.
+///
+/// The stamp combines the directory's own mtime with a digest of its direct
+/// entry names. A directory's mtime only changes when direct entries are
+/// added, removed, or renamed, so edits inside nested subdirectories (or to the
+/// contents of direct files) do not invalidate the key; such staleness is
+/// bounded by the `list_dir` TTL instead.
+pub async fn build_tool_cache_key_for_dir(
+    tool_name: &str,
+    args: &JsonValue,
+    workspace_root: &Path,
+    dir_path: &Path,
+) -> std::io::Result<String> {
+    let metadata = tokio::fs::metadata(dir_path).await?;
+    let stamp = stamp_from_metadata(&metadata)?;
+    let mut names = Vec::new();
+    let mut read_dir = tokio::fs::read_dir(dir_path).await?;
+    while let Some(entry) = read_dir.next_entry().await? {
+        names.push(entry.file_name().to_string_lossy().into_owned());
+    }
+    names.sort();
+    let entries_digest = format!("{:x}", Sha256::digest(names.join("\n").as_bytes()));
+    let keyed_args = serde_json::json!({
+        "args": args,
+        "entries_digest": entries_digest,
+    });
+    build_tool_cache_key(tool_name, &keyed_args, workspace_root, dir_path, stamp)
+}
+
 pub fn stamp_from_metadata(metadata: &std::fs::Metadata) -> std::io::Result<PathStamp> {
     let mtime = metadata.modified().unwrap_or(UNIX_EPOCH);
     let duration = mtime.duration_since(UNIX_EPOCH).unwrap_or_default();
@@ -58,24 +89,62 @@ pub fn stamp_from_metadata(metadata: &std::fs::Metadata) -> std::io::Result<Path
     })
 }
 
-fn canonical_json(value: &JsonValue) -> JsonValue {
-    match value {
-        JsonValue::Object(map) => {
-            let mut sorted = serde_json::Map::new();
-            let mut keys = map.keys().cloned().collect::<Vec<_>>();
-            keys.sort();
-            for key in keys {
-                if let Some(val) = map.get(&key) {
-                    sorted.insert(key, canonical_json(val));
+/// Finds the git directory for `workspace_root`, walking up through parent
+/// directories and following `.git` files used by worktrees and submodules.
+pub fn resolve_git_dir(workspace_root: &Path) -> Option<PathBuf> {
+    let mut cursor = workspace_root.to_path_buf();
+    loop {
+        let candidate = cursor.join(".git");
+        match std::fs::metadata(&candidate) {
+            Ok(metadata) => {
+                if metadata.is_dir() {
+                    return Some(candidate);
+                }
+                if metadata.is_file() {
+                    return parse_gitdir_file(&candidate, &cursor);
                 }
             }
-            JsonValue::Object(sorted)
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(_) => return None,
+        }
+
+        if let Some(parent) = cursor.parent() {
+            cursor = parent.to_path_buf();
+        } else {
+            return None;
         }
-        JsonValue::Array(items) => JsonValue::Array(items.iter().map(canonical_json).collect()),
-        other => other.clone(),
     }
 }
 
+/// Reads the trimmed contents of `HEAD` in `git_dir`, e.g.
+/// `ref: refs/heads/main` or a detached commit id.
+pub fn read_git_head(git_dir: &Path) -> Option<String> {
+    std::fs::read_to_string(git_dir.join("HEAD"))
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+fn parse_gitdir_file(path: &Path, repo_root: &Path) -> Option<PathBuf> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    for line in contents.lines() {
+        let trimmed = line.trim();
+        if let Some(rest) = trimmed.strip_prefix("gitdir:") {
+            let gitdir = rest.trim();
+            if gitdir.is_empty() {
+                return None;
+            }
+            let candidate = PathBuf::from(gitdir);
+            return if candidate.is_absolute() {
+                Some(candidate)
+            } else {
+                Some(repo_root.join(candidate))
+            };
+        }
+    }
+    None
+}
+
 fn normalize_path(path: &Path) -> String {
     path.to_string_lossy().replace('\\', "/")
 }
@@ -150,4 +219,48 @@ mod tests {
 
         assert_ne!(key_a, key_b);
     }
+
+    #[tokio::test]
+    async fn dir_cache_key_is_stable_for_unchanged_dir() -> std::io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(dir.path().join("a.txt"), "a")?;
+        let args = serde_json::json!({"dir_path": dir.path()});
+
+        let key_a = build_tool_cache_key_for_dir("list_dir", &args, dir.path(), dir.path()).await?;
+        let key_b = build_tool_cache_key_for_dir("list_dir", &args, dir.path(), dir.path()).await?;
+
+        assert_eq!(key_a, key_b);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn dir_cache_key_changes_with_new_direct_entry() -> std::io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(dir.path().join("a.txt"), "a")?;
+        let args = serde_json::json!({"dir_path": dir.path()});
+
+        let before =
+            build_tool_cache_key_for_dir("list_dir", &args, dir.path(), dir.path()).await?;
+        std::fs::write(dir.path().join("b.txt"), "b")?;
+        let after = build_tool_cache_key_for_dir("list_dir", &args, dir.path(), dir.path()).await?;
+
+        assert_ne!(before, after);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn dir_cache_key_ignores_nested_changes() -> std::io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let nested = dir.path().join("nested");
+        std::fs::create_dir(&nested)?;
+        let args = serde_json::json!({"dir_path": dir.path()});
+
+        let before =
+            build_tool_cache_key_for_dir("list_dir", &args, dir.path(), dir.path()).await?;
+        std::fs::write(nested.join("child.txt"), "child")?;
+        let after = build_tool_cache_key_for_dir("list_dir", &args, dir.path(), dir.path()).await?;
+
+        assert_eq!(before, after);
+        Ok(())
+    }
 }
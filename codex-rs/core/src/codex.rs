@@ -659,7 +659,10 @@ impl Session {
                     .map(Arc::new);
         }
         let state = SessionState::new(session_configuration.clone());
-        let cache_manager = Arc::new(CacheManager::new(config.cache.clone())?);
+        let cache_manager = Arc::new(CacheManager::with_git_branch_namespace(
+            config.cache.clone(),
+            &config.cwd,
+        )?);
 
         let services = SessionServices {
             mcp_connection_manager: Arc::new(RwLock::new(McpConnectionManager::default())),
@@ -3148,7 +3151,7 @@ mod tests {
         let state = SessionState::new(session_configuration.clone());
         let skills_manager = Arc::new(SkillsManager::new(config.codex_home.clone()));
         let cache_manager = Arc::new(
-            CacheManager::new(config.cache.clone())
+            CacheManager::new(config.cache.clone(), None)
                 .unwrap_or_else(|err| panic!("cache manager init failed: {err}")),
         );
 
@@ -3239,7 +3242,7 @@ mod tests {
         let state = SessionState::new(session_configuration.clone());
         let skills_manager = Arc::new(SkillsManager::new(config.codex_home.clone()));
         let cache_manager = Arc::new(
-            CacheManager::new(config.cache.clone())
+            CacheManager::new(config.cache.clone(), None)
                 .unwrap_or_else(|err| panic!("cache manager init failed: {err}")),
         );
 
@@ -2,6 +2,7 @@ use crate::auth::AuthCredentialsStoreMode;
 use crate::cache::config::CacheConfig;
 use crate::cache::config::CacheConfigToml;
 use crate::config::types::DEFAULT_OTEL_ENVIRONMENT;
+use crate::config::types::GrepBackend;
 use crate::config::types::History;
 use crate::config::types::McpServerConfig;
 use crate::config::types::Notice;
@@ -228,6 +229,9 @@ pub struct Config {
     /// Settings for semantic indexing.
     pub semantic_index: SemanticIndexConfig,
 
+    /// Search implementation used by the `grep_files` tool.
+    pub grep_backend: GrepBackend,
+
     /// Settings that govern if and what will be written to `~/.codex/history.jsonl`.
     pub history: History,
 
@@ -700,6 +704,9 @@ pub struct ConfigToml {
     #[serde(default)]
     pub semantic_index: Option<SemanticIndexConfigToml>,
 
+    /// `grep_files` search implementation: "auto" (default), "ripgrep", or "builtin".
+    pub grep_backend: Option<GrepBackend>,
+
     /// Profile to use from the `profiles` map.
     pub profile: Option<String>,
 
@@ -1305,6 +1312,7 @@ impl Config {
             codex_home,
             cache,
             semantic_index,
+            grep_backend: cfg.grep_backend.unwrap_or_default(),
             history,
             file_opener: cfg.file_opener.unwrap_or(UriBasedFileOpener::VsCode),
             codex_linux_sandbox_exe,
@@ -1470,7 +1478,9 @@ mod tests {
     use crate::cache::config::DEFAULT_CACHE_DIR_NAME;
     use crate::cache::config::DEFAULT_CACHE_GREP_FILES_TTL_SECS;
     use crate::cache::config::DEFAULT_CACHE_MAX_BYTES;
+    use crate::cache::config::DEFAULT_CACHE_MAX_ENTRY_BYTES;
     use crate::cache::config::DEFAULT_CACHE_READ_FILE_TTL_SECS;
+    use crate::cache::config::DEFAULT_CACHE_S3_PREFIX;
     use crate::config::edit::ConfigEdit;
     use crate::config::edit::ConfigEditsBuilder;
     use crate::config::edit::apply_blocking;
@@ -1481,9 +1491,17 @@ mod tests {
     use crate::semantic::config::ChunkingConfig;
     use crate::semantic::config::DEFAULT_SEMANTIC_INDEX_CHUNK_MAX_LINES;
     use crate::semantic::config::DEFAULT_SEMANTIC_INDEX_DIR;
+    use crate::semantic::config::DEFAULT_SEMANTIC_INDEX_INITIAL_BACKOFF_MS;
+    use crate::semantic::config::DEFAULT_SEMANTIC_INDEX_MAX_BATCH_SIZE;
+    use crate::semantic::config::DEFAULT_SEMANTIC_INDEX_MAX_RETRIES;
     use crate::semantic::config::DEFAULT_SEMANTIC_INDEX_MODEL;
+    use crate::semantic::config::DEFAULT_SEMANTIC_INDEX_RETRIEVE_CONTEXT_LINES;
     use crate::semantic::config::DEFAULT_SEMANTIC_INDEX_RETRIEVE_MAX_CHARS;
+    use crate::semantic::config::DEFAULT_SEMANTIC_INDEX_RETRIEVE_MAX_TOP_K;
+    use crate::semantic::config::DEFAULT_SEMANTIC_INDEX_RETRIEVE_QUERY_CACHE_SIZE;
+    use crate::semantic::config::DEFAULT_SEMANTIC_INDEX_RETRIEVE_RECENCY_BOOST_WEIGHT;
     use crate::semantic::config::DEFAULT_SEMANTIC_INDEX_RETRIEVE_TOP_K;
+    use crate::semantic::config::DEFAULT_SEMANTIC_INDEX_WATCH_DEBOUNCE_MS;
     use crate::semantic::config::RetrieveConfig;
     use crate::semantic::config::SemanticIndexConfig;
 
@@ -1502,6 +1520,11 @@ mod tests {
             dir: AbsolutePathBuf::resolve_path_against_base(DEFAULT_CACHE_DIR_NAME, codex_home)
                 .expect("cache dir"),
             max_bytes: DEFAULT_CACHE_MAX_BYTES,
+            max_entry_bytes: DEFAULT_CACHE_MAX_ENTRY_BYTES,
+            read_only: false,
+            checksum: false,
+            s3_bucket: None,
+            s3_prefix: DEFAULT_CACHE_S3_PREFIX.to_string(),
             default_ttl: Duration::from_secs(DEFAULT_CACHE_DEFAULT_TTL_SECS),
             tool_ttl: CacheToolTtl {
                 read_file: Some(Duration::from_secs(DEFAULT_CACHE_READ_FILE_TTL_SECS)),
@@ -1520,12 +1543,32 @@ mod tests {
             )
             .expect("semantic index dir"),
             embedding_model: DEFAULT_SEMANTIC_INDEX_MODEL.to_string(),
+            embedding_backend: None,
+            max_retries: DEFAULT_SEMANTIC_INDEX_MAX_RETRIES,
+            initial_backoff_ms: DEFAULT_SEMANTIC_INDEX_INITIAL_BACKOFF_MS,
+            max_batch_size: DEFAULT_SEMANTIC_INDEX_MAX_BATCH_SIZE,
+            max_requests_per_minute: None,
+            track_tokens: false,
+            watch_debounce_ms: DEFAULT_SEMANTIC_INDEX_WATCH_DEBOUNCE_MS,
+            metric: Default::default(),
+            quantization: Default::default(),
+            max_file_size_bytes: None,
+            include_patterns: Vec::new(),
+            exclude_patterns: Vec::new(),
+            include_extensions: Vec::new(),
+            exclude_extensions: Vec::new(),
+            skip_dirs: Default::default(),
+            sqlite_pragmas: Default::default(),
             chunk: ChunkingConfig {
                 max_lines: DEFAULT_SEMANTIC_INDEX_CHUNK_MAX_LINES,
             },
             retrieve: RetrieveConfig {
                 top_k: DEFAULT_SEMANTIC_INDEX_RETRIEVE_TOP_K,
+                max_top_k: DEFAULT_SEMANTIC_INDEX_RETRIEVE_MAX_TOP_K,
                 max_chars: DEFAULT_SEMANTIC_INDEX_RETRIEVE_MAX_CHARS,
+                recency_boost_weight: DEFAULT_SEMANTIC_INDEX_RETRIEVE_RECENCY_BOOST_WEIGHT,
+                context_lines: DEFAULT_SEMANTIC_INDEX_RETRIEVE_CONTEXT_LINES,
+                query_cache_size: DEFAULT_SEMANTIC_INDEX_RETRIEVE_QUERY_CACHE_SIZE,
             },
         }
     }
@@ -3138,6 +3181,7 @@ model_verbosity = "high"
                 codex_home: fixture.codex_home(),
                 cache: default_cache_config(fixture.codex_home().as_path()),
                 semantic_index: default_semantic_index_config(fixture.cwd().as_path()),
+                grep_backend: GrepBackend::Auto,
                 history: History::default(),
                 file_opener: UriBasedFileOpener::VsCode,
                 codex_linux_sandbox_exe: None,
@@ -3215,6 +3259,7 @@ model_verbosity = "high"
             codex_home: fixture.codex_home(),
             cache: default_cache_config(fixture.codex_home().as_path()),
             semantic_index: default_semantic_index_config(fixture.cwd().as_path()),
+            grep_backend: GrepBackend::Auto,
             history: History::default(),
             file_opener: UriBasedFileOpener::VsCode,
             codex_linux_sandbox_exe: None,
@@ -3307,6 +3352,7 @@ model_verbosity = "high"
             codex_home: fixture.codex_home(),
             cache: default_cache_config(fixture.codex_home().as_path()),
             semantic_index: default_semantic_index_config(fixture.cwd().as_path()),
+            grep_backend: GrepBackend::Auto,
             history: History::default(),
             file_opener: UriBasedFileOpener::VsCode,
             codex_linux_sandbox_exe: None,
@@ -3385,6 +3431,7 @@ model_verbosity = "high"
             codex_home: fixture.codex_home(),
             cache: default_cache_config(fixture.codex_home().as_path()),
             semantic_index: default_semantic_index_config(fixture.cwd().as_path()),
+            grep_backend: GrepBackend::Auto,
             history: History::default(),
             file_opener: UriBasedFileOpener::VsCode,
             codex_linux_sandbox_exe: None,
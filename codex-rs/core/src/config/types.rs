@@ -252,6 +252,17 @@ impl UriBasedFileOpener {
     }
 }
 
+/// Search implementation used by the `grep_files` tool.
+#[derive(Deserialize, Debug, Copy, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GrepBackend {
+    /// Use ripgrep when it is on `PATH`, otherwise the builtin search.
+    #[default]
+    Auto,
+    Ripgrep,
+    Builtin,
+}
+
 /// Settings that govern if and what will be written to `~/.codex/history.jsonl`.
 #[derive(Deserialize, Debug, Clone, PartialEq, Default)]
 pub struct History {
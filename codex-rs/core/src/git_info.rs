@@ -197,6 +197,49 @@ async fn run_git_command_with_timeout(args: &[&str], cwd: &Path) -> Option<std::
     }
 }
 
+/// Full hash of the commit checked out in `cwd`, or `None` outside a git
+/// repository (or before the first commit).
+pub async fn head_commit_hash(cwd: &Path) -> Option<String> {
+    let output = run_git_command_with_timeout(&["rev-parse", "HEAD"], cwd).await?;
+    if !output.status.success() {
+        return None;
+    }
+    let hash = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (!hash.is_empty()).then_some(hash)
+}
+
+/// Paths (relative to `cwd`) that differ between `rev` and `HEAD`, limited to
+/// the `cwd` subtree. Renames are reported as a deletion plus an addition so
+/// both paths appear. Returns `None` when git fails or `rev` is unknown.
+pub async fn changed_paths_since(cwd: &Path, rev: &str) -> Option<Vec<PathBuf>> {
+    if rev.starts_with('-') {
+        return None;
+    }
+    let output = run_git_command_with_timeout(
+        &[
+            "diff",
+            "--name-only",
+            "--no-renames",
+            "--relative",
+            rev,
+            "HEAD",
+        ],
+        cwd,
+    )
+    .await?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(
+        String::from_utf8(output.stdout)
+            .ok()?
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(PathBuf::from)
+            .collect(),
+    )
+}
+
 async fn get_git_remotes(cwd: &Path) -> Option<Vec<String>> {
     let output = run_git_command_with_timeout(&["remote"], cwd).await?;
     if !output.status.success() {
@@ -738,6 +781,39 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_changed_paths_since_lists_committed_changes() {
+        skip_if_sandbox!();
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let repo_path = create_test_git_repo(&temp_dir).await;
+
+        fs::write(repo_path.join("test.txt"), "updated").unwrap();
+        fs::write(repo_path.join("new.txt"), "new").unwrap();
+        Command::new("git")
+            .args(["add", "."])
+            .current_dir(&repo_path)
+            .output()
+            .await
+            .expect("git add");
+        Command::new("git")
+            .args(["commit", "-m", "second"])
+            .current_dir(&repo_path)
+            .output()
+            .await
+            .expect("git commit");
+
+        let mut changed = changed_paths_since(&repo_path, "HEAD~1")
+            .await
+            .expect("diff succeeds");
+        changed.sort();
+        assert_eq!(
+            changed,
+            vec![PathBuf::from("new.txt"), PathBuf::from("test.txt")]
+        );
+        assert_eq!(changed_paths_since(&repo_path, "no-such-rev").await, None);
+        assert_eq!(changed_paths_since(&repo_path, "--cached").await, None);
+    }
+
     async fn create_test_git_repo_with_remote(temp_dir: &TempDir) -> (PathBuf, String) {
         let repo_path = create_test_git_repo(temp_dir).await;
         let remote_path = temp_dir.path().join("remote.git");
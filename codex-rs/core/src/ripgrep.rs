@@ -0,0 +1,83 @@
+//! Detection of the `rg` binary used by the `grep_files` tool.
+
+use crate::config::types::GrepBackend;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// What was found when looking for ripgrep on `PATH`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RipgrepProbe {
+    pub path: Option<PathBuf>,
+    /// First line of `rg --version`, when it ran successfully.
+    pub version: Option<String>,
+}
+
+impl RipgrepProbe {
+    pub fn available(&self) -> bool {
+        self.path.is_some()
+    }
+}
+
+/// Looks up `rg` on `PATH` and asks it for its version.
+pub fn probe() -> RipgrepProbe {
+    let Ok(path) = which::which("rg") else {
+        return RipgrepProbe {
+            path: None,
+            version: None,
+        };
+    };
+    let version = Command::new(&path)
+        .arg("--version")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .next()
+                .map(|line| line.trim().to_string())
+        });
+    RipgrepProbe {
+        path: Some(path),
+        version,
+    }
+}
+
+/// Whether `rg` is on `PATH`; checked once per process.
+pub fn installed() -> bool {
+    static INSTALLED: OnceLock<bool> = OnceLock::new();
+    *INSTALLED.get_or_init(|| which::which("rg").is_ok())
+}
+
+/// Whether `grep_files` falls back to its builtin search for `backend`.
+pub fn uses_builtin(backend: GrepBackend, ripgrep_installed: bool) -> bool {
+    match backend {
+        GrepBackend::Auto => !ripgrep_installed,
+        GrepBackend::Ripgrep => false,
+        GrepBackend::Builtin => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_backend_falls_back_only_without_ripgrep() {
+        assert!(!uses_builtin(GrepBackend::Auto, true));
+        assert!(uses_builtin(GrepBackend::Auto, false));
+        assert!(!uses_builtin(GrepBackend::Ripgrep, false));
+        assert!(uses_builtin(GrepBackend::Builtin, true));
+    }
+
+    #[test]
+    fn probe_reports_version_when_found() {
+        let probe = probe();
+        assert_eq!(probe.available(), installed());
+        if probe.available() {
+            let version = probe.version.expect("version");
+            assert!(version.starts_with("ripgrep"), "{version}");
+        }
+    }
+}
@@ -1,6 +1,12 @@
 use crate::semantic::LOG_TARGET;
+use crate::semantic::embedding::EmbeddingBackend;
+use crate::semantic::index::DistanceMetric;
+use crate::semantic::vector_store::EmbeddingQuantization;
 use codex_utils_absolute_path::AbsolutePathBuf;
 use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
 use std::path::Path;
 use tracing::debug;
 
@@ -8,13 +14,59 @@ pub const DEFAULT_SEMANTIC_INDEX_DIR: &str = ".codex-index";
 pub const DEFAULT_SEMANTIC_INDEX_MODEL: &str = "text-embedding-3-small";
 pub const DEFAULT_SEMANTIC_INDEX_CHUNK_MAX_LINES: usize = 120;
 pub const DEFAULT_SEMANTIC_INDEX_RETRIEVE_TOP_K: usize = 8;
+pub const DEFAULT_SEMANTIC_INDEX_RETRIEVE_MAX_TOP_K: usize = 100;
 pub const DEFAULT_SEMANTIC_INDEX_RETRIEVE_MAX_CHARS: usize = 12_000;
+pub const DEFAULT_SEMANTIC_INDEX_RETRIEVE_RECENCY_BOOST_WEIGHT: f32 = 0.0;
+pub const DEFAULT_SEMANTIC_INDEX_RETRIEVE_CONTEXT_LINES: usize = 0;
+pub const DEFAULT_SEMANTIC_INDEX_RETRIEVE_QUERY_CACHE_SIZE: usize = 64;
+pub const DEFAULT_SEMANTIC_INDEX_MAX_RETRIES: u32 = 3;
+pub const DEFAULT_SEMANTIC_INDEX_INITIAL_BACKOFF_MS: u64 = 500;
+pub const DEFAULT_SEMANTIC_INDEX_MAX_BATCH_SIZE: usize = 64;
+pub const DEFAULT_SEMANTIC_INDEX_WATCH_DEBOUNCE_MS: u64 = 500;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct SemanticIndexConfig {
     pub enabled: bool,
     pub dir: AbsolutePathBuf,
     pub embedding_model: String,
+    /// Embeddings API flavor; `None` detects Azure OpenAI from the provider.
+    pub embedding_backend: Option<EmbeddingBackend>,
+    /// Retries for rate-limited or transient embedding request failures.
+    pub max_retries: u32,
+    /// Delay before the first embedding retry; doubles on each attempt.
+    pub initial_backoff_ms: u64,
+    /// Maximum chunk texts sent in a single embeddings request; a file with
+    /// more chunks is embedded over several requests.
+    pub max_batch_size: usize,
+    /// Requests per minute allowed against the embeddings endpoint; `None`
+    /// leaves requests unthrottled.
+    pub max_requests_per_minute: Option<u32>,
+    /// Sum the token usage reported by the embeddings API during builds.
+    pub track_tokens: bool,
+    /// How long `codex index watch` collects file changes before re-indexing.
+    pub watch_debounce_ms: u64,
+    /// Similarity used to rank chunks; recorded in the index at build time.
+    pub metric: DistanceMetric,
+    /// Encoding of stored embeddings; recorded in the index at build time.
+    pub quantization: EmbeddingQuantization,
+    /// Files larger than this many bytes are skipped; `None` indexes every
+    /// size.
+    pub max_file_size_bytes: Option<u64>,
+    /// When non-empty, only files matching one of these globs are indexed.
+    pub include_patterns: Vec<String>,
+    /// Files and directories matching any of these globs are not indexed.
+    pub exclude_patterns: Vec<String>,
+    /// When non-empty, only files with one of these extensions are indexed.
+    /// Stored lowercase without the leading dot; multi-part extensions such
+    /// as `min.js` are allowed.
+    pub include_extensions: Vec<String>,
+    /// Files with any of these extensions are not indexed, even when listed
+    /// in `include_extensions`.
+    pub exclude_extensions: Vec<String>,
+    /// Directory names (not paths) skipped wherever they appear.
+    pub skip_dirs: HashSet<String>,
+    /// SQLite pragmas applied to the index database after it is opened.
+    pub sqlite_pragmas: BTreeMap<String, String>,
     pub chunk: ChunkingConfig,
     pub retrieve: RetrieveConfig,
 }
@@ -43,10 +95,28 @@ impl SemanticIndexConfig {
                 .retrieve
                 .top_k
                 .unwrap_or(DEFAULT_SEMANTIC_INDEX_RETRIEVE_TOP_K),
+            max_top_k: semantic
+                .retrieve
+                .max_top_k
+                .unwrap_or(DEFAULT_SEMANTIC_INDEX_RETRIEVE_MAX_TOP_K)
+                .max(1),
             max_chars: semantic
                 .retrieve
                 .max_chars
                 .unwrap_or(DEFAULT_SEMANTIC_INDEX_RETRIEVE_MAX_CHARS),
+            recency_boost_weight: semantic
+                .retrieve
+                .recency_boost_weight
+                .unwrap_or(DEFAULT_SEMANTIC_INDEX_RETRIEVE_RECENCY_BOOST_WEIGHT)
+                .clamp(0.0, 1.0),
+            context_lines: semantic
+                .retrieve
+                .context_lines
+                .unwrap_or(DEFAULT_SEMANTIC_INDEX_RETRIEVE_CONTEXT_LINES),
+            query_cache_size: semantic
+                .retrieve
+                .query_cache_size
+                .unwrap_or(DEFAULT_SEMANTIC_INDEX_RETRIEVE_QUERY_CACHE_SIZE),
         };
 
         debug!(
@@ -59,7 +129,11 @@ impl SemanticIndexConfig {
                 .unwrap_or(DEFAULT_SEMANTIC_INDEX_MODEL),
             chunk_max_lines = chunk.max_lines,
             retrieve_top_k = retrieve.top_k,
+            retrieve_max_top_k = retrieve.max_top_k,
             retrieve_max_chars = retrieve.max_chars,
+            retrieve_recency_boost_weight = retrieve.recency_boost_weight,
+            retrieve_context_lines = retrieve.context_lines,
+            retrieve_query_cache_size = retrieve.query_cache_size,
             "loaded semantic index config",
         );
 
@@ -69,28 +143,89 @@ impl SemanticIndexConfig {
             embedding_model: semantic
                 .embedding_model
                 .unwrap_or_else(|| DEFAULT_SEMANTIC_INDEX_MODEL.to_string()),
+            embedding_backend: semantic.embedding_backend,
+            max_retries: semantic
+                .max_retries
+                .unwrap_or(DEFAULT_SEMANTIC_INDEX_MAX_RETRIES),
+            initial_backoff_ms: semantic
+                .initial_backoff_ms
+                .unwrap_or(DEFAULT_SEMANTIC_INDEX_INITIAL_BACKOFF_MS),
+            max_batch_size: semantic
+                .max_batch_size
+                .unwrap_or(DEFAULT_SEMANTIC_INDEX_MAX_BATCH_SIZE)
+                .max(1),
+            max_requests_per_minute: semantic.max_requests_per_minute,
+            track_tokens: semantic.track_tokens.unwrap_or(false),
+            watch_debounce_ms: semantic
+                .watch_debounce_ms
+                .unwrap_or(DEFAULT_SEMANTIC_INDEX_WATCH_DEBOUNCE_MS),
+            metric: semantic.metric.unwrap_or_default(),
+            quantization: semantic.quantization.unwrap_or_default(),
+            max_file_size_bytes: semantic.max_file_size_bytes,
+            include_patterns: semantic.include_patterns,
+            exclude_patterns: semantic.exclude_patterns,
+            include_extensions: normalize_extensions(semantic.include_extensions),
+            exclude_extensions: normalize_extensions(semantic.exclude_extensions),
+            skip_dirs: semantic.skip_dirs.into_iter().collect(),
+            sqlite_pragmas: semantic
+                .sqlite_pragmas
+                .unwrap_or_default()
+                .into_iter()
+                .collect(),
             chunk,
             retrieve,
         })
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ChunkingConfig {
     pub max_lines: usize,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct RetrieveConfig {
     pub top_k: usize,
+    /// Upper bound on the result count of a single search; larger requests
+    /// are clamped to it.
+    pub max_top_k: usize,
     pub max_chars: usize,
+    /// Weight in `[0, 1]` blending file recency into the cosine score.
+    pub recency_boost_weight: f32,
+    /// Lines of surrounding context shown around each search snippet.
+    pub context_lines: usize,
+    /// Query embeddings kept in memory so repeated queries skip the
+    /// embeddings API; 0 disables the cache.
+    pub query_cache_size: usize,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Default)]
 pub struct SemanticIndexConfigToml {
     pub enabled: Option<bool>,
     pub dir: Option<std::path::PathBuf>,
     pub embedding_model: Option<String>,
+    pub embedding_backend: Option<EmbeddingBackend>,
+    pub max_retries: Option<u32>,
+    pub initial_backoff_ms: Option<u64>,
+    #[serde(alias = "batch_size")]
+    pub max_batch_size: Option<usize>,
+    pub max_requests_per_minute: Option<u32>,
+    pub track_tokens: Option<bool>,
+    pub watch_debounce_ms: Option<u64>,
+    pub metric: Option<DistanceMetric>,
+    pub quantization: Option<EmbeddingQuantization>,
+    pub max_file_size_bytes: Option<u64>,
+    #[serde(default)]
+    pub include_patterns: Vec<String>,
+    #[serde(default)]
+    pub exclude_patterns: Vec<String>,
+    #[serde(default)]
+    pub include_extensions: Vec<String>,
+    #[serde(default)]
+    pub exclude_extensions: Vec<String>,
+    #[serde(default)]
+    pub skip_dirs: Vec<String>,
+    pub sqlite_pragmas: Option<HashMap<String, String>>,
     #[serde(default)]
     pub chunk: ChunkingConfigToml,
     #[serde(default)]
@@ -102,10 +237,24 @@ pub struct ChunkingConfigToml {
     pub max_lines: Option<usize>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Default)]
 pub struct RetrieveConfigToml {
     pub top_k: Option<usize>,
+    pub max_top_k: Option<usize>,
     pub max_chars: Option<usize>,
+    pub recency_boost_weight: Option<f32>,
+    pub context_lines: Option<usize>,
+    pub query_cache_size: Option<usize>,
+}
+
+/// Lowercases extensions and drops a leading `.`, so `.RS` and `rs` match
+/// the same files. Blank entries are discarded.
+fn normalize_extensions(extensions: Vec<String>) -> Vec<String> {
+    extensions
+        .into_iter()
+        .map(|ext| ext.trim().trim_start_matches('.').to_ascii_lowercase())
+        .filter(|ext| !ext.is_empty())
+        .collect()
 }
 
 #[cfg(test)]
@@ -129,15 +278,53 @@ mod tests {
         assert!(config.enabled);
         assert_eq!(config.dir, expected_dir);
         assert_eq!(config.embedding_model, DEFAULT_SEMANTIC_INDEX_MODEL);
+        assert_eq!(config.embedding_backend, None);
+        assert_eq!(config.max_retries, DEFAULT_SEMANTIC_INDEX_MAX_RETRIES);
+        assert_eq!(
+            config.initial_backoff_ms,
+            DEFAULT_SEMANTIC_INDEX_INITIAL_BACKOFF_MS
+        );
+        assert_eq!(config.max_batch_size, DEFAULT_SEMANTIC_INDEX_MAX_BATCH_SIZE);
+        assert_eq!(config.max_requests_per_minute, None);
+        assert!(!config.track_tokens);
+        assert_eq!(
+            config.watch_debounce_ms,
+            DEFAULT_SEMANTIC_INDEX_WATCH_DEBOUNCE_MS
+        );
+        assert_eq!(config.metric, DistanceMetric::Cosine);
+        assert_eq!(config.quantization, EmbeddingQuantization::None);
+        assert_eq!(config.max_file_size_bytes, None);
+        assert!(config.include_patterns.is_empty());
+        assert!(config.exclude_patterns.is_empty());
+        assert!(config.include_extensions.is_empty());
+        assert!(config.exclude_extensions.is_empty());
+        assert!(config.skip_dirs.is_empty());
+        assert!(config.sqlite_pragmas.is_empty());
         assert_eq!(
             config.chunk.max_lines,
             DEFAULT_SEMANTIC_INDEX_CHUNK_MAX_LINES
         );
         assert_eq!(config.retrieve.top_k, DEFAULT_SEMANTIC_INDEX_RETRIEVE_TOP_K);
+        assert_eq!(
+            config.retrieve.max_top_k,
+            DEFAULT_SEMANTIC_INDEX_RETRIEVE_MAX_TOP_K
+        );
         assert_eq!(
             config.retrieve.max_chars,
             DEFAULT_SEMANTIC_INDEX_RETRIEVE_MAX_CHARS
         );
+        assert_eq!(
+            config.retrieve.recency_boost_weight,
+            DEFAULT_SEMANTIC_INDEX_RETRIEVE_RECENCY_BOOST_WEIGHT
+        );
+        assert_eq!(
+            config.retrieve.context_lines,
+            DEFAULT_SEMANTIC_INDEX_RETRIEVE_CONTEXT_LINES
+        );
+        assert_eq!(
+            config.retrieve.query_cache_size,
+            DEFAULT_SEMANTIC_INDEX_RETRIEVE_QUERY_CACHE_SIZE
+        );
     }
 
     #[test]
@@ -147,12 +334,35 @@ mod tests {
             enabled: Some(false),
             dir: Some(std::path::PathBuf::from("custom-index")),
             embedding_model: Some("model-x".to_string()),
+            embedding_backend: Some(EmbeddingBackend::Azure),
+            max_retries: Some(1),
+            initial_backoff_ms: Some(50),
+            max_batch_size: Some(16),
+            max_requests_per_minute: Some(3_000),
+            track_tokens: Some(true),
+            watch_debounce_ms: Some(250),
+            metric: Some(DistanceMetric::DotProduct),
+            quantization: Some(EmbeddingQuantization::F16),
+            max_file_size_bytes: Some(1_048_576),
+            include_patterns: vec!["*.rs".to_string()],
+            exclude_patterns: vec!["*.min.js".to_string()],
+            include_extensions: vec![".RS".to_string(), "py".to_string()],
+            exclude_extensions: vec![".min.js".to_string(), " ".to_string()],
+            skip_dirs: vec!["vendor".to_string(), "vendor".to_string()],
+            sqlite_pragmas: Some(HashMap::from([(
+                "cache_size".to_string(),
+                "-8000".to_string(),
+            )])),
             chunk: ChunkingConfigToml {
                 max_lines: Some(42),
             },
             retrieve: RetrieveConfigToml {
                 top_k: Some(5),
+                max_top_k: Some(20),
                 max_chars: Some(1024),
+                recency_boost_weight: Some(0.25),
+                context_lines: Some(2),
+                query_cache_size: Some(0),
             },
         };
 
@@ -165,8 +375,34 @@ mod tests {
         assert!(!config.enabled);
         assert_eq!(config.dir, expected_dir);
         assert_eq!(config.embedding_model, "model-x");
+        assert_eq!(config.embedding_backend, Some(EmbeddingBackend::Azure));
+        assert_eq!(config.max_retries, 1);
+        assert_eq!(config.initial_backoff_ms, 50);
+        assert_eq!(config.max_batch_size, 16);
+        assert_eq!(config.max_requests_per_minute, Some(3_000));
+        assert!(config.track_tokens);
+        assert_eq!(config.watch_debounce_ms, 250);
+        assert_eq!(config.metric, DistanceMetric::DotProduct);
+        assert_eq!(config.quantization, EmbeddingQuantization::F16);
+        assert_eq!(config.max_file_size_bytes, Some(1_048_576));
+        assert_eq!(config.include_patterns, vec!["*.rs".to_string()]);
+        assert_eq!(config.exclude_patterns, vec!["*.min.js".to_string()]);
+        assert_eq!(
+            config.include_extensions,
+            vec!["rs".to_string(), "py".to_string()]
+        );
+        assert_eq!(config.exclude_extensions, vec!["min.js".to_string()]);
+        assert_eq!(config.skip_dirs, HashSet::from(["vendor".to_string()]));
+        assert_eq!(
+            config.sqlite_pragmas,
+            BTreeMap::from([("cache_size".to_string(), "-8000".to_string())])
+        );
         assert_eq!(config.chunk.max_lines, 42);
         assert_eq!(config.retrieve.top_k, 5);
+        assert_eq!(config.retrieve.max_top_k, 20);
         assert_eq!(config.retrieve.max_chars, 1024);
+        assert_eq!(config.retrieve.recency_boost_weight, 0.25);
+        assert_eq!(config.retrieve.context_lines, 2);
+        assert_eq!(config.retrieve.query_cache_size, 0);
     }
 }
@@ -1,21 +1,153 @@
 use crate::api_bridge::auth_provider_from_auth;
 use crate::auth::AuthManager;
 use crate::default_client::build_reqwest_client;
+use crate::model_provider_info::DEFAULT_OLLAMA_PORT;
 use crate::model_provider_info::ModelProviderInfo;
+use crate::model_provider_info::OLLAMA_OSS_PROVIDER_ID;
+use crate::semantic::LOG_TARGET;
+use crate::semantic::config::DEFAULT_SEMANTIC_INDEX_INITIAL_BACKOFF_MS;
+use crate::semantic::config::DEFAULT_SEMANTIC_INDEX_MAX_BATCH_SIZE;
+use crate::semantic::config::DEFAULT_SEMANTIC_INDEX_MAX_RETRIES;
 use anyhow::Context;
 use anyhow::Result;
 use codex_api::AuthProvider;
 use codex_api::Provider;
+use rand::Rng;
+use reqwest::StatusCode;
 use reqwest::header::AUTHORIZATION;
 use reqwest::header::HeaderMap;
+use reqwest::header::RETRY_AFTER;
 use serde::Deserialize;
 use serde::Serialize;
 use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::warn;
+
+/// Upper bound on a single retry delay, computed or server-requested.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// `api-version` sent to Azure OpenAI when the provider's `query_params`
+/// do not set one.
+const AZURE_EMBEDDINGS_API_VERSION: &str = "2024-10-21";
+const AZURE_API_KEY_HEADER: &str = "api-key";
+
+/// Which embeddings API flavor a provider speaks. OpenAI and Azure share
+/// request and response bodies and differ in URL and auth header; Ollama's
+/// native API embeds one input per request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum EmbeddingBackend {
+    /// `POST {base_url}/embeddings` with a bearer token.
+    #[default]
+    #[serde(rename = "openai")]
+    OpenAi,
+    /// `POST {base_url}/deployments/{model}/embeddings?api-version=...` with
+    /// an `api-key` header; the configured model names the deployment.
+    #[serde(rename = "azure")]
+    Azure,
+    /// `POST {base_url without /v1}/api/embeddings` with one
+    /// `{"model", "prompt"}` body per input.
+    #[serde(rename = "ollama")]
+    Ollama,
+}
+
+impl EmbeddingBackend {
+    fn detect(provider: &Provider) -> Self {
+        if provider.is_azure_endpoint() {
+            Self::Azure
+        } else if is_ollama_endpoint(provider) {
+            Self::Ollama
+        } else {
+            Self::OpenAi
+        }
+    }
+}
+
+/// Treats providers named `ollama` or listening on Ollama's default port as
+/// Ollama servers.
+fn is_ollama_endpoint(provider: &Provider) -> bool {
+    provider.name.eq_ignore_ascii_case(OLLAMA_OSS_PROVIDER_ID)
+        || reqwest::Url::parse(&provider.base_url)
+            .is_ok_and(|url| url.port() == Some(DEFAULT_OLLAMA_PORT))
+}
 
 pub struct EmbeddingClient {
     provider: Provider,
-    auth_header: Option<String>,
+    backend: EmbeddingBackend,
+    api_token: Option<String>,
     client: reqwest::Client,
+    retry: EmbeddingRetryPolicy,
+    max_batch_size: usize,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    /// Sum of `usage.total_tokens` across responses; `None` when token
+    /// tracking is off.
+    tokens_used: Option<AtomicU64>,
+}
+
+/// Token bucket holding a single token that refills every `interval`, so
+/// requests are spaced evenly. Share one instance (via `Arc`) between every
+/// client that talks to the same endpoint.
+#[derive(Debug)]
+pub struct RateLimiter {
+    interval: Duration,
+    next_slot: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    /// Returns `None` for a zero limit, which means unlimited.
+    pub fn per_minute(max_requests_per_minute: u32) -> Option<Self> {
+        if max_requests_per_minute == 0 {
+            return None;
+        }
+        Some(Self {
+            interval: Duration::from_secs(60) / max_requests_per_minute,
+            next_slot: Mutex::new(Instant::now()),
+        })
+    }
+
+    /// Waits until the caller may send one request.
+    pub async fn acquire(&self) {
+        let slot = {
+            let mut next_slot = self
+                .next_slot
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            let slot = (*next_slot).max(Instant::now());
+            *next_slot = slot + self.interval;
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+}
+
+/// How [`EmbeddingClient::embed`] retries rate-limited and transient server
+/// errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmbeddingRetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+}
+
+impl Default for EmbeddingRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: DEFAULT_SEMANTIC_INDEX_MAX_RETRIES,
+            initial_backoff: Duration::from_millis(DEFAULT_SEMANTIC_INDEX_INITIAL_BACKOFF_MS),
+        }
+    }
+}
+
+impl EmbeddingRetryPolicy {
+    /// Exponential backoff with +/-10% jitter, capped at [`MAX_BACKOFF`];
+    /// `retry` starts at 0.
+    fn backoff(&self, retry: u32) -> Duration {
+        let base = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry));
+        MAX_BACKOFF.min(base.mul_f64(rand::rng().random_range(0.9..1.1)))
+    }
 }
 
 impl EmbeddingClient {
@@ -28,59 +160,260 @@ impl EmbeddingClient {
             .to_api_provider(auth.as_ref().map(|a| a.mode))
             .context("failed to resolve embedding provider")?;
         let auth_provider = auth_provider_from_auth(auth, &provider).await?;
-        let auth_header = auth_provider
-            .bearer_token()
-            .map(|token| format!("Bearer {token}"));
+        let api_token = auth_provider.bearer_token();
         let client = build_reqwest_client();
         Ok(Self {
+            backend: EmbeddingBackend::detect(&provider_info),
             provider: provider_info,
-            auth_header,
+            api_token,
             client,
+            retry: EmbeddingRetryPolicy::default(),
+            max_batch_size: DEFAULT_SEMANTIC_INDEX_MAX_BATCH_SIZE,
+            rate_limiter: None,
+            tokens_used: None,
         })
     }
 
+    /// Overrides the backend detected from the provider's name and URL.
+    pub fn with_backend(mut self, backend: EmbeddingBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry: EmbeddingRetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Caps how many inputs are sent per request; larger inputs are split.
+    pub fn with_max_batch_size(mut self, max_batch_size: usize) -> Self {
+        self.max_batch_size = max_batch_size.max(1);
+        self
+    }
+
+    /// Accumulates the `usage.total_tokens` reported by each response so it
+    /// can be read back with [`Self::total_tokens_used`].
+    pub fn with_token_tracking(mut self, track_tokens: bool) -> Self {
+        self.tokens_used = track_tokens.then(|| AtomicU64::new(0));
+        self
+    }
+
+    /// Tokens billed so far, as reported by the API. Always 0 unless token
+    /// tracking is enabled.
+    pub fn total_tokens_used(&self) -> u64 {
+        self.tokens_used
+            .as_ref()
+            .map_or(0, |tokens| tokens.load(Ordering::Relaxed))
+    }
+
+    /// Acquires a permit from `rate_limiter` before every request, retries
+    /// included.
+    pub fn with_rate_limiter(mut self, rate_limiter: Option<Arc<RateLimiter>>) -> Self {
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
+    /// Embeds `inputs`, returning one vector per input in the same order.
     pub async fn embed(&self, model: &str, inputs: &[String]) -> Result<Vec<Vec<f32>>> {
-        let url = self.provider.url_for_path("embeddings");
         let mut headers = HeaderMap::new();
         headers.extend(self.provider.headers.clone());
-        if let Some(auth_header) = &self.auth_header
-            && let Ok(value) = auth_header.parse()
-        {
-            headers.insert(AUTHORIZATION, value);
+        let url = match self.backend {
+            EmbeddingBackend::OpenAi | EmbeddingBackend::Ollama => {
+                if let Some(token) = &self.api_token
+                    && let Ok(value) = format!("Bearer {token}").parse()
+                {
+                    headers.insert(AUTHORIZATION, value);
+                }
+                if self.backend == EmbeddingBackend::Ollama {
+                    ollama_embeddings_url(&self.provider)
+                } else {
+                    self.provider.url_for_path("embeddings")
+                }
+            }
+            EmbeddingBackend::Azure => {
+                if let Some(token) = &self.api_token
+                    && let Ok(value) = token.parse()
+                {
+                    headers.insert(AZURE_API_KEY_HEADER, value);
+                }
+                azure_embeddings_url(&self.provider, model)
+            }
+        };
+        let mut items = Vec::with_capacity(inputs.len());
+        for (batch_index, batch) in inputs.chunks(self.max_batch_size).enumerate() {
+            // Response indices are relative to the batch that produced them.
+            let offset = batch_index * self.max_batch_size;
+            let response = if self.backend == EmbeddingBackend::Ollama {
+                self.embed_each_with_ollama(&url, &headers, model, batch)
+                    .await?
+            } else {
+                self.embed_batch(&url, &headers, model, batch).await?
+            };
+            items.extend(response.into_iter().map(|item| EmbeddingItem {
+                index: offset + item.index,
+                embedding: item.embedding,
+            }));
         }
+        items.sort_by_key(|item| item.index);
+        Ok(items.into_iter().map(|item| item.embedding).collect())
+    }
+
+    async fn embed_batch(
+        &self,
+        url: &str,
+        headers: &HeaderMap,
+        model: &str,
+        inputs: &[String],
+    ) -> Result<Vec<EmbeddingItem>> {
         let payload = EmbeddingRequest {
             model,
             input: inputs,
         };
-        let response = self
-            .client
-            .post(url)
-            .headers(headers)
-            .json(&payload)
-            .send()
-            .await
-            .context("failed to send embeddings request")?;
-        if !response.status().is_success() {
+        let response = self.send_with_retry(url, headers, &payload).await?;
+        let data: EmbeddingResponse = response.json().await?;
+        if let (Some(tokens_used), Some(usage)) = (&self.tokens_used, &data.usage) {
+            tokens_used.fetch_add(usage.total_tokens, Ordering::Relaxed);
+        }
+        Ok(data.data)
+    }
+
+    /// Ollama's `/api/embeddings` takes a single prompt, so each input is sent
+    /// on its own and the results are numbered like one batch response.
+    async fn embed_each_with_ollama(
+        &self,
+        url: &str,
+        headers: &HeaderMap,
+        model: &str,
+        inputs: &[String],
+    ) -> Result<Vec<EmbeddingItem>> {
+        let mut items = Vec::with_capacity(inputs.len());
+        for (index, prompt) in inputs.iter().enumerate() {
+            let payload = OllamaEmbeddingRequest { model, prompt };
+            let response = self.send_with_retry(url, headers, &payload).await?;
+            let data: OllamaEmbeddingResponse = response.json().await?;
+            items.push(EmbeddingItem {
+                index,
+                embedding: data.embedding,
+            });
+        }
+        Ok(items)
+    }
+
+    /// Posts `payload`, retrying rate-limited and transient server errors per
+    /// the retry policy.
+    async fn send_with_retry<T: Serialize + ?Sized>(
+        &self,
+        url: &str,
+        headers: &HeaderMap,
+        payload: &T,
+    ) -> Result<reqwest::Response> {
+        let mut retries = 0;
+        loop {
+            if let Some(rate_limiter) = &self.rate_limiter {
+                rate_limiter.acquire().await;
+            }
+            let response = self
+                .client
+                .post(url)
+                .headers(headers.clone())
+                .json(payload)
+                .send()
+                .await
+                .context("failed to send embeddings request")?;
             let status = response.status();
+            if status.is_success() {
+                return Ok(response);
+            }
+            if retries < self.retry.max_retries && is_retryable(status) {
+                let delay = retry_after(response.headers())
+                    .filter(|_| status == StatusCode::TOO_MANY_REQUESTS)
+                    .unwrap_or_else(|| self.retry.backoff(retries));
+                retries += 1;
+                warn!(
+                    target: LOG_TARGET,
+                    "embeddings request failed with {status}; retry {retries}/{} in {delay:?}",
+                    self.retry.max_retries
+                );
+                tokio::time::sleep(delay).await;
+                continue;
+            }
             let body = response.text().await.unwrap_or_default();
             anyhow::bail!("embeddings request failed with {status}: {body}");
         }
-        let data: EmbeddingResponse = response.json().await?;
-        let mut embeddings = data.data;
-        embeddings.sort_by_key(|item| item.index);
-        Ok(embeddings.into_iter().map(|item| item.embedding).collect())
     }
 }
 
+/// Ollama serves its native API beside the OpenAI-compatible `/v1` routes,
+/// so a `/v1` suffix on the provider's base URL is dropped.
+fn ollama_embeddings_url(provider: &Provider) -> String {
+    let base = provider.base_url.trim_end_matches('/');
+    let base = base.strip_suffix("/v1").unwrap_or(base);
+    format!("{base}/api/embeddings")
+}
+
+fn azure_embeddings_url(provider: &Provider, deployment: &str) -> String {
+    let url = provider.url_for_path(&format!("deployments/{deployment}/embeddings"));
+    let has_api_version = provider
+        .query_params
+        .as_ref()
+        .is_some_and(|params| params.contains_key("api-version"));
+    if has_api_version {
+        return url;
+    }
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!("{url}{separator}api-version={AZURE_EMBEDDINGS_API_VERSION}")
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+    )
+}
+
+/// Parses a `Retry-After` header given in whole seconds, capped at
+/// [`MAX_BACKOFF`].
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let seconds = headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(MAX_BACKOFF.min(Duration::from_secs(seconds)))
+}
+
 #[derive(Debug, Serialize)]
 struct EmbeddingRequest<'a> {
     model: &'a str,
     input: &'a [String],
 }
 
+#[derive(Debug, Serialize)]
+struct OllamaEmbeddingRequest<'a> {
+    model: &'a str,
+    prompt: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaEmbeddingResponse {
+    embedding: Vec<f32>,
+}
+
 #[derive(Debug, Deserialize)]
 struct EmbeddingResponse {
     data: Vec<EmbeddingItem>,
+    #[serde(default)]
+    usage: Option<UsageInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UsageInfo {
+    total_tokens: u64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -88,3 +421,336 @@ struct EmbeddingItem {
     index: usize,
     embedding: Vec<f32>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_provider_info::WireApi;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::Request;
+    use wiremock::Respond;
+    use wiremock::ResponseTemplate;
+    use wiremock::matchers::header;
+    use wiremock::matchers::method;
+    use wiremock::matchers::path;
+    use wiremock::matchers::query_param;
+
+    fn provider_for(base_url: String) -> ModelProviderInfo {
+        ModelProviderInfo {
+            name: "mock".into(),
+            base_url: Some(base_url),
+            env_key: None,
+            env_key_instructions: None,
+            experimental_bearer_token: None,
+            wire_api: WireApi::Responses,
+            query_params: None,
+            http_headers: None,
+            env_http_headers: None,
+            request_max_retries: Some(0),
+            stream_max_retries: Some(0),
+            stream_idle_timeout_ms: Some(5_000),
+            requires_openai_auth: false,
+        }
+    }
+
+    async fn client_for(server: &MockServer) -> EmbeddingClient {
+        EmbeddingClient::new(provider_for(server.uri()), None)
+            .await
+            .expect("embedding client")
+            .with_retry_policy(EmbeddingRetryPolicy {
+                max_retries: 3,
+                initial_backoff: Duration::from_millis(1),
+            })
+    }
+
+    #[tokio::test]
+    async fn retries_rate_limited_request_once() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/embeddings"))
+            .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "0"))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/embeddings"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [{ "index": 0, "embedding": [0.5, 0.25] }],
+            })))
+            .mount(&server)
+            .await;
+
+        let embeddings = client_for(&server)
+            .await
+            .embed("model", &["hello".to_string()])
+            .await
+            .expect("embed succeeds after retry");
+
+        assert_eq!(embeddings, vec![vec![0.5, 0.25]]);
+        let requests = server.received_requests().await.expect("requests");
+        assert_eq!(requests.len(), 2);
+    }
+
+    /// Embeds each input as `[n]`, where `n` is parsed from the input text,
+    /// returning items in reverse order to exercise index-based sorting.
+    struct EchoEmbeddings;
+
+    impl Respond for EchoEmbeddings {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            let body: serde_json::Value =
+                serde_json::from_slice(&request.body).expect("request body");
+            let inputs = body["input"].as_array().expect("input array");
+            let data = inputs
+                .iter()
+                .enumerate()
+                .rev()
+                .map(|(index, input)| {
+                    let value: f32 = input.as_str().expect("input").parse().expect("number");
+                    json!({ "index": index, "embedding": [value] })
+                })
+                .collect::<Vec<_>>();
+            ResponseTemplate::new(200).set_body_json(json!({ "data": data }))
+        }
+    }
+
+    #[tokio::test]
+    async fn splits_inputs_into_batches_and_preserves_order() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/embeddings"))
+            .respond_with(EchoEmbeddings)
+            .mount(&server)
+            .await;
+        let inputs = (0..5).map(|n| n.to_string()).collect::<Vec<_>>();
+
+        let embeddings = client_for(&server)
+            .await
+            .with_max_batch_size(2)
+            .embed("model", &inputs)
+            .await
+            .expect("embed succeeds");
+
+        assert_eq!(
+            embeddings,
+            vec![vec![0.0], vec![1.0], vec![2.0], vec![3.0], vec![4.0]]
+        );
+        let requests = server.received_requests().await.expect("requests");
+        assert_eq!(requests.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn tracks_reported_token_usage_when_enabled() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/embeddings"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [{ "index": 0, "embedding": [1.0] }],
+                "usage": { "prompt_tokens": 7, "total_tokens": 7 },
+            })))
+            .mount(&server)
+            .await;
+        let inputs = vec!["text".to_string()];
+
+        let tracking = client_for(&server).await.with_token_tracking(true);
+        tracking.embed("model", &inputs).await.expect("embed");
+        tracking.embed("model", &inputs).await.expect("embed");
+        let untracked = client_for(&server).await;
+        untracked.embed("model", &inputs).await.expect("embed");
+
+        assert_eq!(tracking.total_tokens_used(), 14);
+        assert_eq!(untracked.total_tokens_used(), 0);
+    }
+
+    #[tokio::test]
+    async fn shared_rate_limiter_spaces_requests() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/embeddings"))
+            .respond_with(EchoEmbeddings)
+            .mount(&server)
+            .await;
+        // 1200 requests per minute leaves 50ms between requests.
+        let rate_limiter = RateLimiter::per_minute(1_200).map(Arc::new);
+        let first = client_for(&server)
+            .await
+            .with_max_batch_size(1)
+            .with_rate_limiter(rate_limiter.clone());
+        let second = client_for(&server)
+            .await
+            .with_max_batch_size(1)
+            .with_rate_limiter(rate_limiter);
+        let inputs = vec!["1".to_string(), "2".to_string()];
+
+        let started = Instant::now();
+        let (a, b) = tokio::join!(
+            first.embed("model", &inputs),
+            second.embed("model", &inputs)
+        );
+
+        a.expect("first embed");
+        b.expect("second embed");
+        assert!(
+            started.elapsed() >= Duration::from_millis(150),
+            "four requests finished in {:?}",
+            started.elapsed()
+        );
+        let requests = server.received_requests().await.expect("requests");
+        assert_eq!(requests.len(), 4);
+    }
+
+    #[test]
+    fn zero_rate_limit_is_unlimited() {
+        assert!(RateLimiter::per_minute(0).is_none());
+    }
+
+    #[tokio::test]
+    async fn azure_backend_uses_deployment_url_and_api_key() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/openai/deployments/embed-deployment/embeddings"))
+            .and(query_param("api-version", "2024-06-01"))
+            .and(header("api-key", "azure-secret"))
+            .respond_with(EchoEmbeddings)
+            .mount(&server)
+            .await;
+        let provider = ModelProviderInfo {
+            name: "Azure".into(),
+            experimental_bearer_token: Some("azure-secret".to_string()),
+            query_params: Some(HashMap::from([(
+                "api-version".to_string(),
+                "2024-06-01".to_string(),
+            )])),
+            ..provider_for(format!("{}/openai", server.uri()))
+        };
+        let client = EmbeddingClient::new(provider, None)
+            .await
+            .expect("embedding client");
+        assert_eq!(client.backend, EmbeddingBackend::Azure);
+
+        let embeddings = client
+            .embed("embed-deployment", &["7".to_string()])
+            .await
+            .expect("embed succeeds");
+
+        assert_eq!(embeddings, vec![vec![7.0]]);
+        let requests = server.received_requests().await.expect("requests");
+        assert_eq!(requests.len(), 1);
+        assert!(!requests[0].headers.contains_key(AUTHORIZATION));
+    }
+
+    #[tokio::test]
+    async fn azure_backend_defaults_api_version() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/deployments/embed-deployment/embeddings"))
+            .and(query_param("api-version", AZURE_EMBEDDINGS_API_VERSION))
+            .respond_with(EchoEmbeddings)
+            .mount(&server)
+            .await;
+
+        let embeddings = client_for(&server)
+            .await
+            .with_backend(EmbeddingBackend::Azure)
+            .embed("embed-deployment", &["3".to_string()])
+            .await
+            .expect("embed succeeds");
+
+        assert_eq!(embeddings, vec![vec![3.0]]);
+    }
+
+    /// Ollama-style handler embedding `prompt` as `[n]`.
+    struct EchoOllamaEmbeddings;
+
+    impl Respond for EchoOllamaEmbeddings {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            let body: serde_json::Value =
+                serde_json::from_slice(&request.body).expect("request body");
+            let value: f32 = body["prompt"]
+                .as_str()
+                .and_then(|prompt| prompt.parse().ok())
+                .expect("numeric prompt");
+            ResponseTemplate::new(200).set_body_json(json!({ "embedding": [value] }))
+        }
+    }
+
+    #[tokio::test]
+    async fn ollama_backend_sends_one_request_per_input() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/embeddings"))
+            .respond_with(EchoOllamaEmbeddings)
+            .mount(&server)
+            .await;
+        let provider = ModelProviderInfo {
+            name: "Ollama".into(),
+            ..provider_for(format!("{}/v1/", server.uri()))
+        };
+        let client = EmbeddingClient::new(provider, None)
+            .await
+            .expect("embedding client")
+            .with_max_batch_size(2);
+        assert_eq!(client.backend, EmbeddingBackend::Ollama);
+
+        let inputs = ["1", "2", "3"].map(String::from);
+        let embeddings = client
+            .embed("nomic-embed-text", &inputs)
+            .await
+            .expect("embed succeeds");
+
+        assert_eq!(embeddings, vec![vec![1.0], vec![2.0], vec![3.0]]);
+        let requests = server.received_requests().await.expect("requests");
+        assert_eq!(requests.len(), 3);
+        let body: serde_json::Value =
+            serde_json::from_slice(&requests[0].body).expect("request body");
+        assert_eq!(body, json!({ "model": "nomic-embed-text", "prompt": "1" }));
+    }
+
+    #[test]
+    fn detects_ollama_by_default_port() {
+        let provider = provider_for("http://localhost:11434/v1".to_string())
+            .to_api_provider(None)
+            .expect("api provider");
+
+        assert_eq!(
+            EmbeddingBackend::detect(&provider),
+            EmbeddingBackend::Ollama
+        );
+        assert_eq!(
+            ollama_embeddings_url(&provider),
+            "http://localhost:11434/api/embeddings"
+        );
+    }
+
+    #[test]
+    fn retry_delays_are_capped() {
+        let policy = EmbeddingRetryPolicy::default();
+        assert_eq!(policy.backoff(40), MAX_BACKOFF);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, "86400".parse().expect("header value"));
+        assert_eq!(retry_after(&headers), Some(MAX_BACKOFF));
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_client_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/embeddings"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&server)
+            .await;
+
+        let result = client_for(&server)
+            .await
+            .embed("model", &["hello".to_string()])
+            .await;
+
+        assert!(result.is_err());
+        let requests = server.received_requests().await.expect("requests");
+        assert_eq!(requests.len(), 1);
+    }
+}
@@ -0,0 +1,67 @@
+use crate::semantic::index::DistanceMetric;
+use std::io;
+use std::path::PathBuf;
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, SemanticIndexError>;
+
+/// Failures reported by [`crate::semantic::index::SemanticIndex`].
+#[derive(Debug, Error)]
+pub enum SemanticIndexError {
+    #[error("semantic index is disabled; enable it under [semantic_index]")]
+    IndexDisabled,
+
+    #[error("semantic index not found at {}; build it with `codex index build`", .0.display())]
+    IndexNotFound(PathBuf),
+
+    #[error("search query cannot be empty")]
+    EmptyQuery,
+
+    #[error("top_k must be at least 1")]
+    InvalidTopK,
+
+    /// The embeddings API failed or returned an unusable response.
+    #[error("embedding request failed: {0:#}")]
+    EmbeddingFailed(anyhow::Error),
+
+    #[error(
+        "semantic index embeddings have {actual} dimensions but the index metadata expects \
+         {expected}; rebuild it with `codex index build`"
+    )]
+    DimensionMismatch { expected: usize, actual: usize },
+
+    #[error(
+        "semantic index was built with `{indexed}` but `semantic_index.embedding_model` is \
+         `{configured}`; rebuild it with `codex index build`"
+    )]
+    ModelMismatch { indexed: String, configured: String },
+
+    #[error(
+        "semantic index was built with the `{}` metric but `semantic_index.metric` is `{}`; \
+         rebuild it with `codex index build`",
+        .indexed.as_str(),
+        .configured.as_str()
+    )]
+    MetricMismatch {
+        indexed: DistanceMetric,
+        configured: DistanceMetric,
+    },
+
+    /// The literal search half of [`crate::semantic::index::SemanticIndex::search_with_grep`]
+    /// failed.
+    #[error("lexical search failed: {0}")]
+    GrepFailed(String),
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    /// Reading or writing the SQLite store failed.
+    #[error("semantic index store error: {0:#}")]
+    Store(anyhow::Error),
+}
+
+impl From<anyhow::Error> for SemanticIndexError {
+    fn from(err: anyhow::Error) -> Self {
+        SemanticIndexError::Store(err)
+    }
+}
@@ -0,0 +1,280 @@
+//! Approximate nearest-neighbor candidate selection backed by an in-memory
+//! HNSW graph.
+//!
+//! The graph is built over unit-length vectors, so it orders neighbors by
+//! cosine similarity and is only consulted for the cosine metric; dot-product
+//! and Euclidean searches keep every candidate. The graph only narrows the
+//! candidate set; callers re-rank the returned chunks with the configured
+//! metric, so scores stay identical to the brute-force path.
+
+use crate::semantic::index::DistanceMetric;
+use crate::semantic::vector_store::EmbeddingRecord;
+use instant_distance::Builder;
+use instant_distance::HnswMap;
+use instant_distance::Point;
+use instant_distance::Search;
+use sha2::Digest;
+use sha2::Sha256;
+use std::collections::HashSet;
+use std::sync::PoisonError;
+use std::sync::RwLock;
+
+/// Fixed seed so the same index always produces the same graph.
+const GRAPH_SEED: u64 = 0x5eed;
+
+/// Unit-length embedding, so cosine distance reduces to `1 - dot`.
+#[derive(Debug, Clone)]
+struct NormalizedEmbedding(Vec<f32>);
+
+impl NormalizedEmbedding {
+    fn new(embedding: &[f32]) -> Self {
+        let norm = embedding
+            .iter()
+            .map(|value| value * value)
+            .sum::<f32>()
+            .sqrt();
+        if norm == 0.0 {
+            return Self(embedding.to_vec());
+        }
+        Self(embedding.iter().map(|value| value / norm).collect())
+    }
+}
+
+impl Point for NormalizedEmbedding {
+    fn distance(&self, other: &Self) -> f32 {
+        let dot: f32 = self.0.iter().zip(&other.0).map(|(a, b)| a * b).sum();
+        1.0 - dot
+    }
+}
+
+/// HNSW graph over chunk embeddings, keyed by chunk id.
+pub(crate) struct HnswIndex {
+    graph: HnswMap<NormalizedEmbedding, String>,
+    /// [`chunk_ids_digest`] of the records the graph was built from.
+    chunk_ids_digest: [u8; 32],
+}
+
+impl HnswIndex {
+    pub(crate) fn build(records: &[EmbeddingRecord]) -> Self {
+        let points = records
+            .iter()
+            .map(|record| NormalizedEmbedding::new(&record.embedding))
+            .collect();
+        let chunk_ids = records
+            .iter()
+            .map(|record| record.chunk_id.clone())
+            .collect();
+        Self {
+            graph: Builder::default().seed(GRAPH_SEED).build(points, chunk_ids),
+            chunk_ids_digest: chunk_ids_digest(records),
+        }
+    }
+
+    /// Returns the ids of the (approximately) `limit` closest chunks.
+    pub(crate) fn nearest(&self, query: &[f32], limit: usize) -> HashSet<String> {
+        let query = NormalizedEmbedding::new(query);
+        let mut search = Search::default();
+        self.graph
+            .search(&query, &mut search)
+            .take(limit)
+            .map(|item| item.value.clone())
+            .collect()
+    }
+}
+
+/// Digest of the records' chunk ids in order. Chunk ids change with chunk
+/// text, so a graph built from records with a different digest may return ids
+/// that are no longer stored, even when the chunk count is unchanged.
+fn chunk_ids_digest(records: &[EmbeddingRecord]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for record in records {
+        hasher.update(record.chunk_id.as_bytes());
+        hasher.update([0]);
+    }
+    hasher.finalize().into()
+}
+
+/// Lazily built [`HnswIndex`] shared across searches on one
+/// [`crate::semantic::index::SemanticIndex`].
+#[derive(Default)]
+pub(crate) struct HnswCache {
+    index: RwLock<Option<HnswIndex>>,
+}
+
+impl HnswCache {
+    /// Keeps only the candidates among the `limit` nearest neighbors of
+    /// `query`, building the graph on first use or when the stored chunk ids
+    /// no longer match it (another process may have rebuilt or updated the
+    /// index). Metrics other than cosine rank by
+    /// magnitude as well as direction, which the graph cannot see, so their
+    /// candidates are returned unchanged.
+    pub(crate) fn select(
+        &self,
+        query: &[f32],
+        candidates: Vec<EmbeddingRecord>,
+        limit: usize,
+        metric: DistanceMetric,
+    ) -> Vec<EmbeddingRecord> {
+        if metric != DistanceMetric::Cosine || candidates.len() <= limit {
+            return candidates;
+        }
+        let digest = chunk_ids_digest(&candidates);
+        let nearest = {
+            let cached = self.index.read().unwrap_or_else(PoisonError::into_inner);
+            cached
+                .as_ref()
+                .filter(|index| index.chunk_ids_digest == digest)
+                .map(|index| index.nearest(query, limit))
+        };
+        let nearest = match nearest {
+            Some(nearest) => nearest,
+            None => {
+                let index = HnswIndex::build(&candidates);
+                let nearest = index.nearest(query, limit);
+                *self.index.write().unwrap_or_else(PoisonError::into_inner) = Some(index);
+                nearest
+            }
+        };
+        candidates
+            .into_iter()
+            .filter(|candidate| nearest.contains(&candidate.chunk_id))
+            .collect()
+    }
+
+    /// Drops the cached graph so the next search rebuilds it.
+    pub(crate) fn invalidate(&self) {
+        *self.index.write().unwrap_or_else(PoisonError::into_inner) = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::index::rank_candidates;
+    use pretty_assertions::assert_eq;
+
+    fn records(count: usize) -> Vec<EmbeddingRecord> {
+        (0..count)
+            .map(|i| {
+                let angle = i as f32 * 0.37;
+                EmbeddingRecord {
+                    file_path: format!("file{i}.rs"),
+                    chunk_id: format!("chunk-{i}"),
+                    start_line: 1,
+                    end_line: 1,
+                    chunk_text: None,
+                    mtime: None,
+                    embedding: vec![angle.cos(), angle.sin(), (i % 7) as f32 * 0.1],
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn approximate_results_match_brute_force() {
+        let candidates = records(200);
+        let cache = HnswCache::default();
+        let top_k = 5;
+
+        for query in [[1.0, 0.0, 0.0], [0.2, 0.9, 0.3], [-0.7, -0.1, 0.5]] {
+            let mut exact =
+                rank_candidates(&query, candidates.clone(), DistanceMetric::Cosine, 0.0);
+            exact.truncate(top_k);
+            let narrowed = cache.select(
+                &query,
+                candidates.clone(),
+                top_k * 4,
+                DistanceMetric::Cosine,
+            );
+            let mut approximate = rank_candidates(&query, narrowed, DistanceMetric::Cosine, 0.0);
+            approximate.truncate(top_k);
+
+            assert_eq!(approximate, exact);
+        }
+    }
+
+    #[test]
+    fn dot_product_keeps_long_vectors_the_graph_would_drop() {
+        // Long vectors pointing slightly away from the query win on dot
+        // product but are far from it by cosine.
+        let candidates: Vec<EmbeddingRecord> = records(200)
+            .into_iter()
+            .map(|mut record| {
+                if record.chunk_id.ends_with('7') {
+                    for value in &mut record.embedding {
+                        *value *= 50.0;
+                    }
+                }
+                record
+            })
+            .collect();
+        let query = [1.0, 0.0, 0.0];
+        let top_k = 5;
+        let chunk_ids = |metric| {
+            rank_candidates(&query, candidates.clone(), metric, 0.0)
+                .into_iter()
+                .take(top_k)
+                .map(|hit| hit.chunk_id)
+                .collect::<Vec<_>>()
+        };
+        assert_ne!(
+            chunk_ids(DistanceMetric::DotProduct),
+            chunk_ids(DistanceMetric::Cosine)
+        );
+        let mut exact =
+            rank_candidates(&query, candidates.clone(), DistanceMetric::DotProduct, 0.0);
+        exact.truncate(top_k);
+
+        let cache = HnswCache::default();
+        let narrowed = cache.select(&query, candidates, top_k, DistanceMetric::DotProduct);
+        let mut approximate = rank_candidates(&query, narrowed, DistanceMetric::DotProduct, 0.0);
+        approximate.truncate(top_k);
+
+        assert_eq!(approximate, exact);
+    }
+
+    #[test]
+    fn rebuilds_graph_when_chunk_count_changes() {
+        let cache = HnswCache::default();
+        let query = [1.0, 0.0, 0.0];
+        cache.select(&query, records(50), 4, DistanceMetric::Cosine);
+
+        let grown = records(60);
+        let digest = chunk_ids_digest(&grown);
+        let selected = cache.select(&query, grown, 4, DistanceMetric::Cosine);
+
+        assert_eq!(selected.len(), 4);
+        assert_eq!(cached_digest(&cache), Some(digest));
+    }
+
+    #[test]
+    fn rebuilds_graph_when_a_chunk_id_changes_at_equal_count() {
+        let cache = HnswCache::default();
+        let query = [1.0, 0.0, 0.0];
+        cache.select(&query, records(50), 4, DistanceMetric::Cosine);
+
+        // `chunk-0` is the exact match for the query; a stale graph would
+        // still return its old id and the replacement would be dropped.
+        let mut edited = records(50);
+        edited[0].chunk_id = "chunk-0-edited".to_string();
+        let digest = chunk_ids_digest(&edited);
+        let selected = cache.select(&query, edited, 4, DistanceMetric::Cosine);
+
+        assert_eq!(selected.len(), 4);
+        assert!(
+            selected
+                .iter()
+                .any(|record| record.chunk_id == "chunk-0-edited")
+        );
+        assert_eq!(cached_digest(&cache), Some(digest));
+    }
+
+    fn cached_digest(cache: &HnswCache) -> Option<[u8; 32]> {
+        cache
+            .index
+            .read()
+            .expect("lock")
+            .as_ref()
+            .map(|index| index.chunk_ids_digest)
+    }
+}
@@ -1,30 +1,98 @@
 use crate::AuthManager;
+use crate::cache::tool_cache::resolve_git_dir;
+use crate::config::types::GrepBackend;
+use crate::git_info::changed_paths_since;
+use crate::git_info::head_commit_hash;
 use crate::model_provider_info::ModelProviderInfo;
 use crate::semantic::LOG_TARGET;
+use crate::semantic::config::ChunkingConfig;
 use crate::semantic::config::SemanticIndexConfig;
 use crate::semantic::embedding::EmbeddingClient;
+use crate::semantic::embedding::EmbeddingRetryPolicy;
+use crate::semantic::embedding::RateLimiter;
+use crate::semantic::error::Result;
+use crate::semantic::error::SemanticIndexError;
+#[cfg(feature = "hnsw")]
+use crate::semantic::hnsw::HnswCache;
 use crate::semantic::vector_store::ChunkEntry;
+use crate::semantic::vector_store::EmbeddingRecord;
 use crate::semantic::vector_store::FileEntry;
 use crate::semantic::vector_store::IndexMeta;
 use crate::semantic::vector_store::IndexStats;
 use crate::semantic::vector_store::StoreMode;
 use crate::semantic::vector_store::VectorStore;
-use anyhow::Context;
-use anyhow::Result;
+use crate::semantic::vector_store::normalize_index_path;
+use crate::tools::handlers::LineMatch;
+use crate::tools::handlers::find_literal_lines;
+use crate::truncate::approx_token_count;
+use chrono::DateTime;
 use chrono::Utc;
+use encoding_rs::Encoding;
+use encoding_rs::UTF_8;
+use futures::Stream;
+use futures::StreamExt;
+use futures::stream;
+use globset::Glob;
+use globset::GlobSet;
+use globset::GlobSetBuilder;
+use ignore::Match;
+use ignore::gitignore::Gitignore;
+use ignore::gitignore::GitignoreBuilder;
+use lru::LruCache;
+use serde::Deserialize;
 use sha2::Digest;
 use sha2::Sha256;
+use std::borrow::Cow;
+use std::cell::RefCell;
 use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::ffi::OsStr;
 use std::fs;
+use std::io;
+use std::num::NonZeroUsize;
 use std::path::Path;
 use std::path::PathBuf;
+use std::str::FromStr;
 use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::time::Duration;
+use tracing::Instrument;
+use tracing::Span;
+use tracing::debug;
+use tracing::field;
 use tracing::info;
+use tracing::info_span;
+use tracing::instrument;
 use tracing::warn;
 use walkdir::DirEntry;
 use walkdir::WalkDir;
 
 const SCHEMA_VERSION: i32 = 1;
+/// Damping constant for Reciprocal Rank Fusion, as in the original paper.
+const RRF_K: f32 = 60.0;
+/// How many candidates per ranking feed hybrid fusion, relative to `top_k`.
+const HYBRID_POOL_FACTOR: usize = 4;
+/// Most matching lines [`SemanticIndex::search_with_grep`] reads from the
+/// literal search.
+const GREP_MATCH_LIMIT: usize = 1_000;
+/// Embeddings read per query by [`SemanticIndex::search_stream`].
+const STREAM_PAGE_SIZE: usize = 512;
+/// Workspace-root file listing paths to leave out of the index.
+const CODEXIGNORE_FILE: &str = ".codexignore";
+const GITIGNORE_FILE: &str = ".gitignore";
+/// Leading bytes of a file inspected when deciding whether it is text.
+const BINARY_SAMPLE_BYTES: usize = 8 * 1024;
+/// Share of control characters and invalid UTF-8 in the sample above which
+/// a file is treated as binary.
+const MAX_NON_TEXT_RATIO: f64 = 0.3;
+/// How many approximate neighbors are re-ranked exactly, relative to `top_k`.
+#[cfg(feature = "hnsw")]
+const ANN_CANDIDATE_FACTOR: usize = 4;
+/// How often `build` logs the running count of discovered files.
+const DISCOVERY_LOG_INTERVAL: usize = 1_000;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct SearchHit {
@@ -33,6 +101,47 @@ pub struct SearchHit {
     pub end_line: usize,
     pub score: f32,
     pub chunk_id: String,
+    /// Text of the matched chunk, when the index stores it.
+    pub chunk_text: Option<String>,
+}
+
+/// Projected size of an index build, computed by [`SemanticIndex::estimate_build`]
+/// without calling the embeddings API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BuildEstimate {
+    pub file_count: usize,
+    pub chunk_count: usize,
+    pub line_count: usize,
+    /// Total bytes of chunk text that would be sent for embedding.
+    pub chunk_bytes: usize,
+    pub estimated_tokens: usize,
+}
+
+impl BuildEstimate {
+    /// Average length of an indexed line, in bytes.
+    pub fn average_line_length(&self) -> f64 {
+        if self.line_count == 0 {
+            return 0.0;
+        }
+        self.chunk_bytes as f64 / self.line_count as f64
+    }
+
+    /// Projected embedding cost for a price given per 1,000 tokens.
+    pub fn projected_cost(&self, cost_per_1k_tokens: f64) -> f64 {
+        self.estimated_tokens as f64 / 1000.0 * cost_per_1k_tokens
+    }
+}
+
+/// Ranking strategy used by [`SemanticIndex::search`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchMode {
+    /// Rank chunks by cosine similarity to the query embedding.
+    #[default]
+    Semantic,
+    /// Rank chunks by FTS5 full-text relevance; no embedding request is made.
+    Keyword,
+    /// Fuse semantic and keyword rankings with Reciprocal Rank Fusion.
+    Hybrid,
 }
 
 pub struct SemanticIndex {
@@ -40,8 +149,21 @@ pub struct SemanticIndex {
     config: SemanticIndexConfig,
     provider: ModelProviderInfo,
     auth_manager: Option<Arc<AuthManager>>,
+    /// Shared by every embedding client this index creates.
+    rate_limiter: Option<Arc<RateLimiter>>,
+    #[cfg(feature = "hnsw")]
+    hnsw: HnswCache,
+    /// Recent query embeddings; `None` when `retrieve.query_cache_size` is 0.
+    query_cache: Option<QueryCache>,
+    /// Search used for the literal half of [`Self::search_with_grep`].
+    grep_backend: GrepBackend,
 }
 
+/// Query string to its embedding, bounded to the most recently used
+/// `retrieve.query_cache_size` queries. A plain mutex keeps lookups usable
+/// from current-thread runtimes, where blocking on an async lock panics.
+type QueryCache = Mutex<LruCache<String, Vec<f32>>>;
+
 impl SemanticIndex {
     pub fn new(
         workspace_root: PathBuf,
@@ -49,25 +171,54 @@ impl SemanticIndex {
         provider: ModelProviderInfo,
         auth_manager: Option<Arc<AuthManager>>,
     ) -> Self {
+        let rate_limiter = config
+            .max_requests_per_minute
+            .and_then(RateLimiter::per_minute)
+            .map(Arc::new);
+        let query_cache = NonZeroUsize::new(config.retrieve.query_cache_size)
+            .map(|capacity| Mutex::new(LruCache::new(capacity)));
         Self {
             workspace_root,
             config,
             provider,
             auth_manager,
+            rate_limiter,
+            #[cfg(feature = "hnsw")]
+            hnsw: HnswCache::default(),
+            query_cache,
+            grep_backend: GrepBackend::default(),
         }
     }
 
+    /// Uses `backend` (the top-level `grep_backend` setting) for the literal
+    /// search in [`Self::search_with_grep`].
+    pub fn with_grep_backend(mut self, backend: GrepBackend) -> Self {
+        self.grep_backend = backend;
+        self
+    }
+
+    #[instrument(
+        name = "semantic_index.build",
+        skip_all,
+        fields(
+            workspace = %self.workspace_root.display(),
+            file_count = field::Empty,
+            chunk_count = field::Empty,
+        )
+    )]
     pub async fn build(&self) -> Result<IndexStats> {
         if !self.config.enabled {
-            anyhow::bail!("semantic index is disabled; enable it under [semantic_index]");
+            return Err(SemanticIndexError::IndexDisabled);
         }
         let index_dir = self.config.dir.as_path();
-        let store = VectorStore::open(index_dir, StoreMode::Reset)?;
-        let embedder =
-            EmbeddingClient::new(self.provider.clone(), self.auth_manager.clone()).await?;
+        let store = self.open_store(StoreMode::Reset)?;
+        store.set_quantization(self.config.quantization);
+        self.invalidate_ann();
+        let embedder = self.embedding_client().await?;
         let workspace_fingerprint = fingerprint_workspace(&self.workspace_root);
         let created_at = Utc::now();
         let mut embedding_dim: Option<usize> = None;
+        let mut reused_embeddings = 0;
 
         info!(
             target: LOG_TARGET,
@@ -75,12 +226,19 @@ impl SemanticIndex {
             "starting semantic index build",
         );
 
-        let files = collect_files(&self.workspace_root, index_dir)?;
+        let mut discovered = 0usize;
+        let files = collect_files_with_callback(&self.workspace_root, &self.config, |_| {
+            discovered += 1;
+            if discovered.is_multiple_of(DISCOVERY_LOG_INTERVAL) {
+                debug!(target: LOG_TARGET, discovered, "discovering workspace files");
+            }
+        })?;
+        let mut file_entries = Vec::with_capacity(files.len());
         for file_path in files {
             let relative = file_path
                 .strip_prefix(&self.workspace_root)
                 .unwrap_or(&file_path);
-            let relative_display = relative.to_string_lossy().to_string();
+            let relative_display = normalize_index_path(&relative.to_string_lossy());
             let metadata = match fs::metadata(&file_path) {
                 Ok(metadata) => metadata,
                 Err(err) => {
@@ -92,13 +250,9 @@ impl SemanticIndex {
                     continue;
                 }
             };
-            let size = metadata.len();
-            let modified = metadata
-                .modified()
-                .ok()
-                .and_then(|ts| ts.duration_since(std::time::UNIX_EPOCH).ok())
-                .map(|ts| ts.as_secs() as i64)
-                .unwrap_or(0);
+            if self.exceeds_size_limit(&file_path, &metadata) {
+                continue;
+            }
             let bytes = match fs::read(&file_path) {
                 Ok(bytes) => bytes,
                 Err(err) => {
@@ -110,239 +264,2710 @@ impl SemanticIndex {
                     continue;
                 }
             };
-            if bytes.is_empty() || bytes.contains(&0) {
+            let Some((file_entry, chunks)) =
+                self.prepare_file(&relative_display, &bytes, &metadata)
+            else {
                 continue;
-            }
-            let contents = String::from_utf8_lossy(&bytes);
-            let lines: Vec<String> = contents.lines().map(ToString::to_string).collect();
-            let chunks = chunk_lines(&lines, self.config.chunk.max_lines);
-            if chunks.is_empty() {
-                continue;
-            }
-
-            let content_hash = hash_bytes(&bytes);
-            store.store_file(&FileEntry {
-                path: relative_display.clone(),
-                content_hash,
-                mtime: modified,
-                size,
-            })?;
-
-            let chunk_texts: Vec<String> = chunks.iter().map(|chunk| chunk.text.clone()).collect();
-            let embeddings = embedder
-                .embed(&self.config.embedding_model, &chunk_texts)
-                .await
-                .with_context(|| format!("embedding failed for {}", file_path.display()))?;
-            if embeddings.len() != chunks.len() {
-                anyhow::bail!(
-                    "embedding response mismatch for {} (expected {}, got {})",
-                    file_path.display(),
-                    chunks.len(),
-                    embeddings.len()
-                );
-            }
-            for (chunk, embedding) in chunks.into_iter().zip(embeddings) {
-                if let Some(dim) = embedding_dim {
-                    if dim != embedding.len() {
-                        anyhow::bail!(
-                            "embedding dimension changed from {dim} to {}",
-                            embedding.len()
-                        );
-                    }
-                } else {
-                    embedding_dim = Some(embedding.len());
-                }
-                let text_hash = hash_string(&chunk.text);
-                let chunk_id = chunk_id(
+            };
+            let file_span = info_span!("semantic_index.file", path = %relative_display);
+            let (chunk_entries, reused) = self
+                .embed_chunks(
+                    &embedder,
+                    &store,
+                    &file_path,
                     &relative_display,
-                    chunk.start_line,
-                    chunk.end_line,
-                    &text_hash,
-                );
-                store.store_chunk(&ChunkEntry {
-                    file_path: relative_display.clone(),
-                    chunk_id,
-                    start_line: chunk.start_line,
-                    end_line: chunk.end_line,
-                    text_hash,
-                    embedding,
-                    updated_at: created_at,
-                })?;
+                    chunks,
+                    created_at,
+                )
+                .instrument(file_span)
+                .await?;
+            reused_embeddings += reused;
+            for chunk in &chunk_entries {
+                let dim = *embedding_dim.get_or_insert(chunk.embedding.len());
+                if dim != chunk.embedding.len() {
+                    return Err(SemanticIndexError::DimensionMismatch {
+                        expected: dim,
+                        actual: chunk.embedding.len(),
+                    });
+                }
             }
+            file_entries.push(file_entry);
+            store.store_chunks(&chunk_entries)?;
         }
+        store.store_files(&file_entries)?;
 
         let meta = IndexMeta {
             schema_version: SCHEMA_VERSION,
             embedding_model: self.config.embedding_model.clone(),
             dim: embedding_dim.unwrap_or(0),
             chunk_size: self.config.chunk.max_lines,
+            metric: self.config.metric,
             created_at,
             workspace_fingerprint,
+            git_commit: head_commit_hash(&self.workspace_root).await,
+            quantization: self.config.quantization,
         };
         store.store_meta(&meta)?;
-        let stats = store.stats()?;
+        let stats = IndexStats {
+            reused_embeddings,
+            tokens_used: self
+                .config
+                .track_tokens
+                .then(|| embedder.total_tokens_used()),
+            ..store.stats()?
+        };
+        Span::current()
+            .record("file_count", stats.file_count)
+            .record("chunk_count", stats.chunk_count);
         info!(
             target: LOG_TARGET,
             files = stats.file_count,
             chunks = stats.chunk_count,
+            reused_embeddings,
             "semantic index build complete",
         );
         Ok(stats)
     }
 
+    /// Walks the workspace and chunks files exactly as [`Self::build`] would,
+    /// without embedding anything or touching the index store.
+    pub fn estimate_build(&self) -> Result<BuildEstimate> {
+        if !self.config.enabled {
+            return Err(SemanticIndexError::IndexDisabled);
+        }
+        let mut estimate = BuildEstimate::default();
+        for file_path in collect_files(&self.workspace_root, &self.config)? {
+            if fs::metadata(&file_path)
+                .is_ok_and(|metadata| self.exceeds_size_limit(&file_path, &metadata))
+            {
+                continue;
+            }
+            let bytes = match fs::read(&file_path) {
+                Ok(bytes) => bytes,
+                Err(err) => {
+                    warn!(
+                        target: LOG_TARGET,
+                        path = %file_path.display(),
+                        "skipping unreadable file: {err}",
+                    );
+                    continue;
+                }
+            };
+            let Some(contents) = decode_indexable_text(&bytes) else {
+                continue;
+            };
+            let chunks = chunk_text(&contents, self.config.chunk);
+            if chunks.is_empty() {
+                continue;
+            }
+            estimate.file_count += 1;
+            for chunk in &chunks {
+                estimate.chunk_count += 1;
+                estimate.line_count += chunk.end_line + 1 - chunk.start_line;
+                estimate.chunk_bytes += chunk.text.len();
+                estimate.estimated_tokens += approx_token_count(&chunk.text);
+            }
+        }
+        Ok(estimate)
+    }
+
+    /// Re-indexes a single file in an existing index. Unchanged files are left
+    /// alone, new files are added, and deleted (or now empty, binary, or
+    /// oversized) files
+    /// are removed along with their chunks.
+    pub async fn update_file(&self, file_path: &Path) -> Result<()> {
+        if !self.config.enabled {
+            return Err(SemanticIndexError::IndexDisabled);
+        }
+        let store = self.open_store(StoreMode::OpenExisting)?;
+        self.reindex_file(&store, &mut None, file_path).await
+    }
+
+    /// Does the work of [`Self::update_file`] against an open `store`. The
+    /// embedding client is created on the first file that needs embedding and
+    /// kept in `embedder` for the rest of the batch.
+    async fn reindex_file(
+        &self,
+        store: &VectorStore,
+        embedder: &mut Option<EmbeddingClient>,
+        file_path: &Path,
+    ) -> Result<()> {
+        let file_path = self.workspace_root.join(file_path);
+        let relative_display = normalize_index_path(
+            &file_path
+                .strip_prefix(&self.workspace_root)
+                .unwrap_or(&file_path)
+                .to_string_lossy(),
+        );
+        self.invalidate_ann();
+
+        let metadata = match fs::metadata(&file_path) {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                store.delete_file(&relative_display)?;
+                return Ok(());
+            }
+            Err(err) => return Err(io_error_for(&file_path, "read metadata for", err)),
+        };
+        if self.exceeds_size_limit(&file_path, &metadata) {
+            store.delete_file(&relative_display)?;
+            return Ok(());
+        }
+        let bytes = match fs::read(&file_path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                store.delete_file(&relative_display)?;
+                return Ok(());
+            }
+            Err(err) => return Err(io_error_for(&file_path, "read", err)),
+        };
+        let stored = store.get_file(&relative_display)?;
+        if stored.is_some_and(|file| file.content_hash == hash_bytes(&bytes)) {
+            return Ok(());
+        }
+        let Some((file_entry, chunks)) = self.prepare_file(&relative_display, &bytes, &metadata)
+        else {
+            store.delete_file(&relative_display)?;
+            return Ok(());
+        };
+
+        let client = match embedder.take() {
+            Some(client) => client,
+            None => self.embedding_client().await?,
+        };
+        let embedder = embedder.insert(client);
+        let (chunk_entries, _) = self
+            .embed_chunks(
+                embedder,
+                store,
+                &file_path,
+                &relative_display,
+                chunks,
+                Utc::now(),
+            )
+            .await?;
+        if let Some(dim) = store.stats()?.embedding_dim.filter(|dim| *dim > 0)
+            && let Some(chunk) = chunk_entries
+                .iter()
+                .find(|chunk| chunk.embedding.len() != dim)
+        {
+            return Err(SemanticIndexError::DimensionMismatch {
+                expected: dim,
+                actual: chunk.embedding.len(),
+            });
+        }
+        store.replace_file(&file_entry, &chunk_entries)?;
+        Ok(())
+    }
+
+    /// Re-indexes each of `paths` like [`Self::update_file`], sharing one
+    /// store and embedding client across the batch, and returns the
+    /// resulting store stats, without the workspace walk behind
+    /// [`IndexStats::stale`]. Paths inside the index directory, `.git`, or a
+    /// `skip_dirs` directory, or matched by `.gitignore` or `.codexignore`,
+    /// are ignored, so raw file-watcher events can be passed through
+    /// unfiltered. Afterwards the index metadata records the update's start
+    /// time and the current `HEAD`, so it is no longer reported as stale.
+    pub async fn update_files(&self, paths: &[PathBuf]) -> Result<IndexStats> {
+        if !self.config.enabled {
+            return Err(SemanticIndexError::IndexDisabled);
+        }
+        let updated_at = Utc::now();
+        let index_dir = self.config.dir.as_path();
+        let filter = PathFilter::load(&self.workspace_root, &self.config);
+        let store = self.open_store(StoreMode::OpenExisting)?;
+        let mut embedder = None;
+        for path in paths {
+            let path = self.workspace_root.join(path);
+            if path.is_dir()
+                || filter.in_skipped_dir(path.strip_prefix(&self.workspace_root).unwrap_or(&path))
+                || is_excluded_path(&path, &self.workspace_root, index_dir, &filter, false)
+            {
+                continue;
+            }
+            self.reindex_file(&store, &mut embedder, &path).await?;
+        }
+        let git_commit = head_commit_hash(&self.workspace_root).await;
+        store.record_update(updated_at, git_commit.as_deref())?;
+        Ok(store.stats()?)
+    }
+
+    /// Incrementally re-indexes only the files that changed between `rev` and
+    /// `HEAD`, falling back to a full [`Self::build`] when the workspace is not
+    /// in a git repository, `rev` cannot be resolved, or no index exists yet.
+    pub async fn build_since(&self, rev: &str) -> Result<IndexStats> {
+        if !self.config.enabled {
+            return Err(SemanticIndexError::IndexDisabled);
+        }
+        if resolve_git_dir(&self.workspace_root).is_none() {
+            warn!(
+                target: LOG_TARGET,
+                workspace = %self.workspace_root.display(),
+                "workspace is not a git repository; running a full semantic index build"
+            );
+            return self.build().await;
+        }
+        if !VectorStore::exists(self.config.dir.as_path()) {
+            info!(
+                target: LOG_TARGET,
+                "no existing semantic index; running a full build"
+            );
+            return self.build().await;
+        }
+        let Some(changed) = changed_paths_since(&self.workspace_root, rev).await else {
+            warn!(
+                target: LOG_TARGET,
+                rev,
+                "could not diff against revision; running a full semantic index build"
+            );
+            return self.build().await;
+        };
+        info!(
+            target: LOG_TARGET,
+            rev,
+            changed_files = changed.len(),
+            "updating semantic index from git diff"
+        );
+        self.update_files(&changed).await
+    }
+
+    /// Returns true when any indexable workspace file was modified after
+    /// `built_at`. Only file metadata is read; nothing is re-chunked.
+    pub fn modified_since(&self, built_at: DateTime<Utc>) -> Result<bool> {
+        Ok(self.files_modified_since(Some(built_at))?.next().is_some())
+    }
+
+    /// Returns true when the index predates changes to the workspace, or has
+    /// never been built.
+    pub fn is_stale(&self) -> Result<bool> {
+        let store = self.open_store(StoreMode::OpenExisting)?;
+        let built_at = store.stats()?.created_at;
+        Ok(self.files_modified_since(built_at)?.next().is_some())
+    }
+
+    /// Counts indexable workspace files modified after the last build.
+    pub fn modified_file_count(&self) -> Result<usize> {
+        let store = self.open_store(StoreMode::OpenExisting)?;
+        let built_at = store.stats()?.created_at;
+        Ok(self.files_modified_since(built_at)?.count())
+    }
+
+    /// Indexable files whose mtime is after `built_at`; every file when
+    /// `built_at` is `None`.
+    fn files_modified_since(
+        &self,
+        built_at: Option<DateTime<Utc>>,
+    ) -> Result<impl Iterator<Item = PathBuf> + use<>> {
+        let built_at = built_at.map(std::time::SystemTime::from);
+        let files = collect_files(&self.workspace_root, &self.config)?;
+        Ok(files.into_iter().filter(move |file_path| {
+            let Some(built_at) = built_at else {
+                return true;
+            };
+            fs::metadata(file_path)
+                .and_then(|metadata| metadata.modified())
+                .is_ok_and(|modified| modified > built_at)
+        }))
+    }
+
+    /// Returns the store stats with [`IndexStats::stale`] filled in.
     pub fn stats(&self) -> Result<IndexStats> {
-        let store = VectorStore::open(self.config.dir.as_path(), StoreMode::OpenExisting)?;
-        store.stats()
+        let store = self.open_store(StoreMode::OpenExisting)?;
+        let mut stats = store.stats()?;
+        stats.stale = self
+            .files_modified_since(stats.created_at)?
+            .next()
+            .is_some();
+        Ok(stats)
     }
 
     pub fn clear(&self) -> Result<()> {
-        VectorStore::clear(self.config.dir.as_path())
+        self.invalidate_ann();
+        VectorStore::clear(self.config.dir.as_path())?;
+        Ok(())
     }
 
-    pub async fn search(&self, query: &str, top_k: usize) -> Result<Vec<SearchHit>> {
+    #[instrument(
+        name = "semantic_index.search",
+        skip_all,
+        fields(
+            query_len = query.len(),
+            top_k = top_k,
+            mode = ?mode,
+            candidates = field::Empty,
+            result_count = field::Empty,
+        )
+    )]
+    pub async fn search(
+        &self,
+        query: &str,
+        top_k: usize,
+        mode: SearchMode,
+    ) -> Result<Vec<SearchHit>> {
         if !self.config.enabled {
-            anyhow::bail!("semantic index is disabled; enable it under [semantic_index]");
+            return Err(SemanticIndexError::IndexDisabled);
         }
         if query.trim().is_empty() {
-            return Ok(Vec::new());
+            return Err(SemanticIndexError::EmptyQuery);
         }
-        let store = VectorStore::open(self.config.dir.as_path(), StoreMode::OpenExisting)?;
-        let embedder =
-            EmbeddingClient::new(self.provider.clone(), self.auth_manager.clone()).await?;
-        let embedding = embedder
-            .embed(&self.config.embedding_model, &[query.to_string()])
-            .await?
-            .into_iter()
-            .next()
-            .context("missing embedding result")?;
+        let top_k = self.bounded_top_k(top_k)?;
+        let store = self.open_store(StoreMode::OpenExisting)?;
+        let metric = self.validate_store(&store, mode != SearchMode::Keyword)?;
         let candidates = store.list_embeddings()?;
-        let mut scored: Vec<SearchHit> = candidates
-            .into_iter()
-            .filter_map(|candidate| {
-                let score = cosine_similarity(&embedding, &candidate.embedding)?;
-                Some(SearchHit {
-                    file_path: candidate.file_path,
-                    start_line: candidate.start_line,
-                    end_line: candidate.end_line,
-                    score,
-                    chunk_id: candidate.chunk_id,
-                })
-            })
-            .collect();
-        scored.sort_by(score_cmp);
-        scored.truncate(top_k);
+        Span::current().record("candidates", candidates.len());
+        let embedder = self.query_embedder(mode).await?;
+        let ranking = self.query_ranking(embedder.as_ref(), query, mode).await?;
+        let scored = self.rank_query(&store, metric, candidates, query, ranking, top_k)?;
+        Span::current().record("result_count", scored.len());
+        debug!(target: LOG_TARGET, "semantic search complete");
         Ok(scored)
     }
-}
 
-fn collect_files(workspace_root: &Path, index_dir: &Path) -> Result<Vec<PathBuf>> {
-    let walker = WalkDir::new(workspace_root)
-        .follow_links(true)
-        .into_iter()
-        .filter_entry(|entry| !should_skip_entry(entry, workspace_root, index_dir));
-    let mut files = Vec::new();
-    for entry in walker {
-        let entry = entry?;
-        if entry.file_type().is_file() {
-            files.push(entry.path().to_path_buf());
+    /// Semantic search fused with a literal grep of the workspace for the
+    /// query's terms, so exact identifiers the embeddings rank poorly still
+    /// surface. Matching lines count toward the indexed chunks containing
+    /// them (lines outside every chunk are ignored), and the two rankings
+    /// are combined with Reciprocal Rank Fusion, the semantic list weighted
+    /// by `semantic_weight` (clamped to `0.0..=1.0`) and the lexical list by
+    /// the remainder.
+    pub async fn search_with_grep(
+        &self,
+        query: &str,
+        top_k: usize,
+        semantic_weight: f32,
+    ) -> Result<Vec<SearchHit>> {
+        if !self.config.enabled {
+            return Err(SemanticIndexError::IndexDisabled);
+        }
+        if query.trim().is_empty() {
+            return Err(SemanticIndexError::EmptyQuery);
         }
+        let top_k = self.bounded_top_k(top_k)?;
+        let store = self.open_store(StoreMode::OpenExisting)?;
+        let metric = self.validate_store(&store, true)?;
+        let candidates = store.list_embeddings()?;
+        let embedder = self.embedding_client().await?;
+        let embedding = self.embed_query(&embedder, query).await?;
+        let semantic = rank_candidates(
+            &embedding,
+            candidates,
+            metric,
+            self.config.retrieve.recency_boost_weight,
+        );
+        let semantic_ids: Vec<String> = semantic
+            .iter()
+            .take(top_k.saturating_mul(HYBRID_POOL_FACTOR))
+            .map(|hit| hit.chunk_id.clone())
+            .collect();
+        let terms: Vec<&str> = query.split_whitespace().collect();
+        let lines = find_literal_lines(
+            &self.workspace_root,
+            &terms,
+            GREP_MATCH_LIMIT,
+            self.grep_backend,
+        )
+        .await
+        .map_err(|err| SemanticIndexError::GrepFailed(err.to_string()))?;
+        let lexical_ids = rank_chunks_by_line_matches(&semantic, &lines);
+        let weight = semantic_weight.clamp(0.0, 1.0);
+        let mut fused = fuse_weighted_rankings(
+            &[
+                (weight, semantic_ids.as_slice()),
+                (1.0 - weight, lexical_ids.as_slice()),
+            ],
+            semantic,
+        );
+        fused.truncate(top_k);
+        Ok(fused)
     }
-    Ok(files)
-}
 
-fn should_skip_entry(entry: &DirEntry, workspace_root: &Path, index_dir: &Path) -> bool {
-    let path = entry.path();
-    if path == index_dir {
-        return true;
+    /// Runs [`Self::search`] for each of `queries` against one snapshot of the
+    /// index, sharing a single embedding client and embedding at most
+    /// `concurrency` queries at a time. Results are returned in query order;
+    /// a failing query yields an error in its slot without aborting the rest.
+    pub async fn search_batch(
+        &self,
+        queries: &[String],
+        top_k: usize,
+        mode: SearchMode,
+        concurrency: usize,
+    ) -> Result<Vec<Result<Vec<SearchHit>>>> {
+        if !self.config.enabled {
+            return Err(SemanticIndexError::IndexDisabled);
+        }
+        let top_k = self.bounded_top_k(top_k)?;
+        let store = self.open_store(StoreMode::OpenExisting)?;
+        let metric = self.validate_store(&store, mode != SearchMode::Keyword)?;
+        let candidates = store.list_embeddings()?;
+        let embedder = self.query_embedder(mode).await?;
+        let rankings: Vec<Result<QueryRanking>> = stream::iter(queries)
+            .map(|query| self.query_ranking(embedder.as_ref(), query, mode))
+            .buffered(concurrency.max(1))
+            .collect()
+            .await;
+        Ok(queries
+            .iter()
+            .zip(rankings)
+            .map(|(query, ranking)| {
+                self.rank_query(&store, metric, candidates.clone(), query, ranking?, top_k)
+            })
+            .collect())
     }
-    if path.starts_with(index_dir) {
-        return true;
+
+    /// Semantic search that reads embeddings in pages of
+    /// [`STREAM_PAGE_SIZE`] and keeps only the best `top_k` hits in memory,
+    /// so memory use is O(`top_k`) rather than O(chunks). Results match
+    /// [`Self::search`] in [`SearchMode::Semantic`] without the HNSW feature;
+    /// the stream yields them best-first once every page has been scored.
+    pub async fn search_stream(
+        &self,
+        query: &str,
+        top_k: usize,
+    ) -> Result<impl Stream<Item = SearchHit> + use<>> {
+        if !self.config.enabled {
+            return Err(SemanticIndexError::IndexDisabled);
+        }
+        if query.trim().is_empty() {
+            return Err(SemanticIndexError::EmptyQuery);
+        }
+        let top_k = self.bounded_top_k(top_k)?;
+        let store = self.open_store(StoreMode::OpenExisting)?;
+        let metric = self.validate_store(&store, true)?;
+        let embedder = self.embedding_client().await?;
+        let embedding = self.embed_query(&embedder, query).await?;
+        let weight = self.config.retrieve.recency_boost_weight.clamp(0.0, 1.0);
+        let mtime_range = store.chunk_mtime_range()?;
+        let mut top_hits = TopHits::new(top_k);
+        let mut after_rowid = 0;
+        loop {
+            let page = store.list_embeddings_page(after_rowid, STREAM_PAGE_SIZE)?;
+            let Some((last_rowid, _)) = page.last() else {
+                break;
+            };
+            after_rowid = *last_rowid;
+            for (_, candidate) in page {
+                if let Some(hit) =
+                    score_candidate(&embedding, candidate, metric, weight, mtime_range)
+                {
+                    top_hits.push(hit);
+                }
+            }
+        }
+        Ok(stream::iter(top_hits.into_sorted_vec()))
     }
-    if let Ok(relative) = path.strip_prefix(workspace_root)
-        && relative.components().any(|comp| comp.as_os_str() == ".git")
-    {
-        return true;
+
+    /// Narrows `candidates` to the approximate nearest neighbors of `query`
+    /// using the cached HNSW graph when `metric` is cosine. The survivors are
+    /// re-ranked exactly, so only chunks outside the neighborhood (e.g. ones a
+    /// recency boost would have promoted) can be missed.
+    #[cfg(feature = "hnsw")]
+    fn nearest_candidates(
+        &self,
+        query: &[f32],
+        candidates: Vec<EmbeddingRecord>,
+        top_k: usize,
+        metric: DistanceMetric,
+    ) -> Vec<EmbeddingRecord> {
+        self.hnsw.select(
+            query,
+            candidates,
+            top_k.saturating_mul(ANN_CANDIDATE_FACTOR),
+            metric,
+        )
     }
-    false
-}
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-struct Chunk {
-    start_line: usize,
-    end_line: usize,
-    text: String,
-}
+    #[cfg(not(feature = "hnsw"))]
+    fn nearest_candidates(
+        &self,
+        _query: &[f32],
+        candidates: Vec<EmbeddingRecord>,
+        _top_k: usize,
+        _metric: DistanceMetric,
+    ) -> Vec<EmbeddingRecord> {
+        candidates
+    }
 
-fn chunk_lines(lines: &[String], max_lines: usize) -> Vec<Chunk> {
-    if max_lines == 0 {
-        return Vec::new();
+    fn invalidate_ann(&self) {
+        #[cfg(feature = "hnsw")]
+        self.hnsw.invalidate();
     }
-    let mut chunks = Vec::new();
-    for (chunk_index, chunk_lines) in lines.chunks(max_lines).enumerate() {
-        let start_line = chunk_index * max_lines + 1;
-        let end_line = start_line + chunk_lines.len().saturating_sub(1);
-        let text = chunk_lines.join("\n");
-        if text.trim().is_empty() {
-            continue;
+
+    /// Returns whether `max_file_size_bytes` excludes the file from the index.
+    fn exceeds_size_limit(&self, file_path: &Path, metadata: &fs::Metadata) -> bool {
+        let Some(limit) = self.config.max_file_size_bytes else {
+            return false;
+        };
+        if metadata.len() <= limit {
+            return false;
         }
-        chunks.push(Chunk {
-            start_line,
-            end_line,
-            text,
-        });
+        debug!(
+            target: LOG_TARGET,
+            path = %file_path.display(),
+            size = metadata.len(),
+            limit,
+            "skipping file larger than max_file_size_bytes",
+        );
+        true
     }
-    chunks
-}
 
-fn chunk_id(path: &str, start_line: usize, end_line: usize, text_hash: &str) -> String {
-    let input = format!("{path}:{start_line}-{end_line}:{text_hash}");
-    format!("{:x}", Sha256::digest(input.as_bytes()))
-}
+    /// Splits a file into chunks, or returns `None` for empty and binary files.
+    fn prepare_file(
+        &self,
+        relative_display: &str,
+        bytes: &[u8],
+        metadata: &fs::Metadata,
+    ) -> Option<(FileEntry, Vec<Chunk>)> {
+        let contents = decode_indexable_text(bytes)?;
+        let chunks = chunk_text(&contents, self.config.chunk);
+        if chunks.is_empty() {
+            return None;
+        }
+        let mtime = metadata
+            .modified()
+            .ok()
+            .and_then(|ts| ts.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|ts| ts.as_secs() as i64)
+            .unwrap_or(0);
+        let file = FileEntry {
+            path: relative_display.to_string(),
+            content_hash: hash_bytes(bytes),
+            mtime,
+            size: metadata.len(),
+        };
+        Some((file, chunks))
+    }
 
-fn hash_bytes(bytes: &[u8]) -> String {
-    format!("{:x}", Sha256::digest(bytes))
-}
+    /// Embeds `chunks`, requesting each distinct text only once and reusing
+    /// the embedding of any identical chunk already in `store`. Also returns
+    /// how many chunks reused an embedding.
+    async fn embed_chunks(
+        &self,
+        embedder: &EmbeddingClient,
+        store: &VectorStore,
+        file_path: &Path,
+        relative_display: &str,
+        chunks: Vec<Chunk>,
+        updated_at: DateTime<Utc>,
+    ) -> Result<(Vec<ChunkEntry>, usize)> {
+        let text_hashes: Vec<String> = chunks
+            .iter()
+            .map(|chunk| hash_string(&chunk.text))
+            .collect();
+        let mut known: HashMap<&str, Vec<f32>> = HashMap::new();
+        let mut pending_hashes = Vec::new();
+        let mut pending_texts = Vec::new();
+        for (chunk, text_hash) in chunks.iter().zip(&text_hashes) {
+            if known.contains_key(text_hash.as_str()) || pending_hashes.contains(&text_hash) {
+                continue;
+            }
+            match store.embedding_for_text_hash(text_hash)? {
+                Some(embedding) => {
+                    known.insert(text_hash.as_str(), embedding);
+                }
+                None => {
+                    pending_hashes.push(text_hash);
+                    pending_texts.push(chunk.text.clone());
+                }
+            }
+        }
+        if !pending_texts.is_empty() {
+            let embeddings = embedder
+                .embed(&self.config.embedding_model, &pending_texts)
+                .await
+                .map_err(|err| {
+                    SemanticIndexError::EmbeddingFailed(
+                        err.context(format!("embedding failed for {}", file_path.display())),
+                    )
+                })?;
+            if embeddings.len() != pending_texts.len() {
+                return Err(SemanticIndexError::EmbeddingFailed(anyhow::anyhow!(
+                    "embedding response mismatch for {} (expected {}, got {})",
+                    file_path.display(),
+                    pending_texts.len(),
+                    embeddings.len()
+                )));
+            }
+            known.extend(
+                pending_hashes
+                    .iter()
+                    .map(|text_hash| text_hash.as_str())
+                    .zip(embeddings),
+            );
+        }
+        let reused = chunks.len() - pending_texts.len();
+        let entries = chunks
+            .into_iter()
+            .zip(text_hashes.iter())
+            .filter_map(|(chunk, text_hash)| {
+                let embedding = known.get(text_hash.as_str())?.clone();
+                let text_hash = text_hash.clone();
+                Some(ChunkEntry {
+                    file_path: relative_display.to_string(),
+                    chunk_id: chunk_id(
+                        relative_display,
+                        chunk.start_line,
+                        chunk.end_line,
+                        &text_hash,
+                    ),
+                    start_line: chunk.start_line,
+                    end_line: chunk.end_line,
+                    text_hash,
+                    text: chunk.text,
+                    embedding,
+                    updated_at,
+                })
+            })
+            .collect();
+        Ok((entries, reused))
+    }
 
-fn hash_string(value: &str) -> String {
-    format!("{:x}", Sha256::digest(value.as_bytes()))
-}
+    /// Rejects a `top_k` of zero and clamps larger requests to
+    /// `retrieve.max_top_k`, so a single search never ranks an unbounded
+    /// number of hits.
+    fn bounded_top_k(&self, top_k: usize) -> Result<usize> {
+        if top_k == 0 {
+            return Err(SemanticIndexError::InvalidTopK);
+        }
+        let max_top_k = self.config.retrieve.max_top_k;
+        if top_k > max_top_k {
+            debug!(
+                target: LOG_TARGET,
+                top_k,
+                max_top_k,
+                "clamping search top_k",
+            );
+        }
+        Ok(top_k.min(max_top_k))
+    }
 
-fn fingerprint_workspace(path: &Path) -> String {
-    hash_string(path.to_string_lossy().as_ref())
-}
+    /// Checks that the stored embeddings can be compared with the configured
+    /// model and metric, returning the metric. `embeds_query` adds the model
+    /// check for searches that embed the query.
+    fn validate_store(&self, store: &VectorStore, embeds_query: bool) -> Result<DistanceMetric> {
+        if let Some((expected, actual)) = store.dimension_mismatch()? {
+            return Err(SemanticIndexError::DimensionMismatch { expected, actual });
+        }
+        if embeds_query
+            && let Some(indexed) = store.stats()?.embedding_model
+            && indexed != self.config.embedding_model
+        {
+            return Err(SemanticIndexError::ModelMismatch {
+                indexed,
+                configured: self.config.embedding_model.clone(),
+            });
+        }
+        let configured = self.config.metric;
+        match store.metric()? {
+            Some(indexed) if indexed != configured => Err(SemanticIndexError::MetricMismatch {
+                indexed,
+                configured,
+            }),
+            _ => Ok(configured),
+        }
+    }
 
-fn cosine_similarity(query: &[f32], other: &[f32]) -> Option<f32> {
-    if query.len() != other.len() || query.is_empty() {
-        return None;
+    fn open_store(&self, mode: StoreMode) -> Result<VectorStore> {
+        let dir = self.config.dir.as_path();
+        if mode == StoreMode::OpenExisting && !VectorStore::exists(dir) {
+            return Err(SemanticIndexError::IndexNotFound(dir.to_path_buf()));
+        }
+        let store = VectorStore::open(dir, mode)?;
+        for (pragma, value) in &self.config.sqlite_pragmas {
+            store.set_pragma(pragma, value)?;
+        }
+        Ok(store)
     }
-    let mut dot = 0.0_f32;
-    let mut norm_a = 0.0_f32;
-    let mut norm_b = 0.0_f32;
-    for (a, b) in query.iter().zip(other) {
-        dot += a * b;
-        norm_a += a * a;
-        norm_b += b * b;
+
+    async fn embedding_client(&self) -> Result<EmbeddingClient> {
+        let mut client = EmbeddingClient::new(self.provider.clone(), self.auth_manager.clone())
+            .await
+            .map_err(SemanticIndexError::EmbeddingFailed)?;
+        if let Some(backend) = self.config.embedding_backend {
+            client = client.with_backend(backend);
+        }
+        Ok(client
+            .with_retry_policy(EmbeddingRetryPolicy {
+                max_retries: self.config.max_retries,
+                initial_backoff: Duration::from_millis(self.config.initial_backoff_ms),
+            })
+            .with_max_batch_size(self.config.max_batch_size)
+            .with_rate_limiter(self.rate_limiter.clone())
+            .with_token_tracking(self.config.track_tokens))
     }
-    let denom = norm_a.sqrt() * norm_b.sqrt();
-    if denom == 0.0 {
-        None
-    } else {
-        Some(dot / denom)
+
+    /// Returns the client used to embed queries, or `None` when `mode` never
+    /// embeds them.
+    async fn query_embedder(&self, mode: SearchMode) -> Result<Option<EmbeddingClient>> {
+        match mode {
+            SearchMode::Keyword => Ok(None),
+            SearchMode::Semantic | SearchMode::Hybrid => Ok(Some(self.embedding_client().await?)),
+        }
     }
-}
 
-fn score_cmp(a: &SearchHit, b: &SearchHit) -> Ordering {
-    b.score
-        .partial_cmp(&a.score)
+    /// Embeds `query` with `embedder` as `mode` requires; without an
+    /// embedder the query is ranked by keywords alone.
+    async fn query_ranking(
+        &self,
+        embedder: Option<&EmbeddingClient>,
+        query: &str,
+        mode: SearchMode,
+    ) -> Result<QueryRanking> {
+        if query.trim().is_empty() {
+            return Err(SemanticIndexError::EmptyQuery);
+        }
+        let Some(embedder) = embedder else {
+            return Ok(QueryRanking::Keyword);
+        };
+        let embedding = self.embed_query(embedder, query).await?;
+        Ok(match mode {
+            SearchMode::Hybrid => QueryRanking::Hybrid(embedding),
+            SearchMode::Semantic | SearchMode::Keyword => QueryRanking::Semantic(embedding),
+        })
+    }
+
+    /// Ranks `candidates` for `query` and keeps the best `top_k`.
+    fn rank_query(
+        &self,
+        store: &VectorStore,
+        metric: DistanceMetric,
+        candidates: Vec<EmbeddingRecord>,
+        query: &str,
+        ranking: QueryRanking,
+        top_k: usize,
+    ) -> Result<Vec<SearchHit>> {
+        let recency_boost_weight = self.config.retrieve.recency_boost_weight;
+        let mut scored = match ranking {
+            QueryRanking::Semantic(embedding) => {
+                let candidates = self.nearest_candidates(&embedding, candidates, top_k, metric);
+                rank_candidates(&embedding, candidates, metric, recency_boost_weight)
+            }
+            QueryRanking::Keyword => {
+                let keyword_ids = store.fts_search(query, top_k)?;
+                let hits = candidates.into_iter().map(unscored_hit).collect();
+                fuse_rankings(&[keyword_ids], hits)
+            }
+            QueryRanking::Hybrid(embedding) => {
+                let pool = top_k.saturating_mul(HYBRID_POOL_FACTOR);
+                let semantic =
+                    rank_candidates(&embedding, candidates, metric, recency_boost_weight);
+                let semantic_ids = semantic
+                    .iter()
+                    .take(pool)
+                    .map(|hit| hit.chunk_id.clone())
+                    .collect();
+                let keyword_ids = store.fts_search(query, pool)?;
+                fuse_rankings(&[semantic_ids, keyword_ids], semantic)
+            }
+        };
+        scored.truncate(top_k);
+        Ok(scored)
+    }
+
+    /// Embeds `query`, serving repeats of a recent query from the query cache
+    /// instead of calling the embeddings API again.
+    #[instrument(name = "embedding.embed", skip_all)]
+    async fn embed_query(&self, embedder: &EmbeddingClient, query: &str) -> Result<Vec<f32>> {
+        if let Some(cache) = &self.query_cache {
+            let mut cache = cache.lock().unwrap_or_else(PoisonError::into_inner);
+            if let Some(embedding) = cache.get(query) {
+                return Ok(embedding.clone());
+            }
+        }
+        let embedding = embedder
+            .embed(&self.config.embedding_model, &[query.to_string()])
+            .await
+            .map_err(SemanticIndexError::EmbeddingFailed)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                SemanticIndexError::EmbeddingFailed(anyhow::anyhow!("missing embedding result"))
+            })?;
+        if let Some(cache) = &self.query_cache {
+            cache
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .put(query.to_string(), embedding.clone());
+        }
+        Ok(embedding)
+    }
+}
+
+/// Scores candidates against the query embedding with `metric` and sorts
+/// them best-first.
+///
+/// With a non-zero `recency_boost_weight` the final score becomes
+/// `(1 - w) * similarity + w * recency`, where `recency` is the file mtime
+/// scaled to `[0, 1]` between the oldest and newest candidate.
+pub(crate) fn rank_candidates(
+    query: &[f32],
+    candidates: Vec<EmbeddingRecord>,
+    metric: DistanceMetric,
+    recency_boost_weight: f32,
+) -> Vec<SearchHit> {
+    let weight = recency_boost_weight.clamp(0.0, 1.0);
+    let mtime_range = candidates
+        .iter()
+        .filter_map(|candidate| candidate.mtime)
+        .fold(None, |range: Option<(i64, i64)>, mtime| match range {
+            Some((min, max)) => Some((min.min(mtime), max.max(mtime))),
+            None => Some((mtime, mtime)),
+        });
+    let mut scored: Vec<SearchHit> = candidates
+        .into_iter()
+        .filter_map(|candidate| score_candidate(query, candidate, metric, weight, mtime_range))
+        .collect();
+    scored.sort_by(score_cmp);
+    scored
+}
+
+/// Scores one candidate as [`rank_candidates`] does, with `mtime_range`
+/// spanning all candidates.
+fn score_candidate(
+    query: &[f32],
+    candidate: EmbeddingRecord,
+    metric: DistanceMetric,
+    weight: f32,
+    mtime_range: Option<(i64, i64)>,
+) -> Option<SearchHit> {
+    let similarity = score(query, &candidate.embedding, metric)?;
+    let score = if weight > 0.0 {
+        let recency = recency_factor(candidate.mtime, mtime_range);
+        (1.0 - weight) * similarity + weight * recency
+    } else {
+        similarity
+    };
+    Some(SearchHit {
+        file_path: candidate.file_path,
+        start_line: candidate.start_line,
+        end_line: candidate.end_line,
+        score,
+        chunk_id: candidate.chunk_id,
+        chunk_text: candidate.chunk_text,
+    })
+}
+
+/// How [`SemanticIndex::rank_query`] ranks one query.
+enum QueryRanking {
+    /// Full-text keyword matches only.
+    Keyword,
+    /// Similarity to the query embedding.
+    Semantic(Vec<f32>),
+    /// Keyword and embedding rankings fused with Reciprocal Rank Fusion.
+    Hybrid(Vec<f32>),
+}
+
+/// Keeps the best `limit` hits seen so far, ordered by [`score_cmp`].
+struct TopHits {
+    limit: usize,
+    // Max-heap on `score_cmp`, so the worst kept hit is on top.
+    heap: BinaryHeap<RankedHit>,
+}
+
+impl TopHits {
+    fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            heap: BinaryHeap::with_capacity(limit.saturating_add(1)),
+        }
+    }
+
+    fn push(&mut self, hit: SearchHit) {
+        if self.limit == 0 {
+            return;
+        }
+        self.heap.push(RankedHit(hit));
+        if self.heap.len() > self.limit {
+            self.heap.pop();
+        }
+    }
+
+    /// Returns the kept hits best-first.
+    fn into_sorted_vec(self) -> Vec<SearchHit> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|ranked| ranked.0)
+            .collect()
+    }
+}
+
+struct RankedHit(SearchHit);
+
+impl PartialEq for RankedHit {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for RankedHit {}
+
+impl PartialOrd for RankedHit {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RankedHit {
+    fn cmp(&self, other: &Self) -> Ordering {
+        score_cmp(&self.0, &other.0)
+    }
+}
+
+fn unscored_hit(record: EmbeddingRecord) -> SearchHit {
+    SearchHit {
+        file_path: record.file_path,
+        start_line: record.start_line,
+        end_line: record.end_line,
+        score: 0.0,
+        chunk_id: record.chunk_id,
+        chunk_text: record.chunk_text,
+    }
+}
+
+/// Combines ranked chunk id lists with Reciprocal Rank Fusion.
+///
+/// Each list contributes `1 / (RRF_K + rank)` to a chunk's score, so chunks
+/// ranked highly by several lists rise to the top. Only chunks present in
+/// `hits` and in at least one ranking are returned.
+fn fuse_rankings(rankings: &[Vec<String>], hits: Vec<SearchHit>) -> Vec<SearchHit> {
+    let weighted: Vec<(f32, &[String])> = rankings
+        .iter()
+        .map(|ranking| (1.0, ranking.as_slice()))
+        .collect();
+    fuse_weighted_rankings(&weighted, hits)
+}
+
+/// [`fuse_rankings`] with each list's contributions scaled by its weight.
+fn fuse_weighted_rankings(rankings: &[(f32, &[String])], hits: Vec<SearchHit>) -> Vec<SearchHit> {
+    let mut fused: HashMap<&str, f32> = HashMap::new();
+    for (weight, ranking) in rankings {
+        for (rank, chunk_id) in ranking.iter().enumerate() {
+            *fused.entry(chunk_id.as_str()).or_default() += weight / (RRF_K + rank as f32 + 1.0);
+        }
+    }
+    let mut scored: Vec<SearchHit> = hits
+        .into_iter()
+        .filter_map(|hit| {
+            let score = *fused.get(hit.chunk_id.as_str())?;
+            Some(SearchHit { score, ..hit })
+        })
+        .collect();
+    scored.sort_by(score_cmp);
+    scored
+}
+
+/// Ranks the chunks among `hits` by how many of `lines` they contain, most
+/// first, so each file and line range appears once however many lines
+/// matched inside it.
+fn rank_chunks_by_line_matches(hits: &[SearchHit], lines: &[LineMatch]) -> Vec<String> {
+    let mut chunks_by_file: HashMap<&str, Vec<&SearchHit>> = HashMap::new();
+    for hit in hits {
+        chunks_by_file
+            .entry(hit.file_path.as_str())
+            .or_default()
+            .push(hit);
+    }
+    // Chunk id -> (matching lines, order first seen).
+    let mut counts: HashMap<&str, (usize, usize)> = HashMap::new();
+    for line in lines {
+        let path = normalize_index_path(&line.path.to_string_lossy());
+        let Some(chunks) = chunks_by_file.get(path.as_str()) else {
+            continue;
+        };
+        for chunk in chunks
+            .iter()
+            .filter(|chunk| (chunk.start_line..=chunk.end_line).contains(&line.line_number))
+        {
+            let first_seen = counts.len();
+            counts
+                .entry(chunk.chunk_id.as_str())
+                .or_insert((0, first_seen))
+                .0 += 1;
+        }
+    }
+    let mut ranked: Vec<(&str, (usize, usize))> = counts.into_iter().collect();
+    // Ties keep the order the literal search reported them in.
+    ranked.sort_by(|a, b| b.1.0.cmp(&a.1.0).then(a.1.1.cmp(&b.1.1)));
+    ranked.into_iter().map(|(id, _)| id.to_string()).collect()
+}
+
+fn recency_factor(mtime: Option<i64>, range: Option<(i64, i64)>) -> f32 {
+    let (Some(mtime), Some((oldest, newest))) = (mtime, range) else {
+        return 0.0;
+    };
+    if newest <= oldest {
+        return 1.0;
+    }
+    (mtime - oldest) as f32 / (newest - oldest) as f32
+}
+
+/// Wraps `err` with the path it came from while keeping its kind.
+fn io_error_for(path: &Path, action: &str, err: io::Error) -> SemanticIndexError {
+    SemanticIndexError::Io(io::Error::new(
+        err.kind(),
+        format!("failed to {action} {}: {err}", path.display()),
+    ))
+}
+
+fn collect_files(workspace_root: &Path, config: &SemanticIndexConfig) -> Result<Vec<PathBuf>> {
+    collect_files_with_callback(workspace_root, config, |_| {})
+}
+
+/// Like [`collect_files`], but calls `on_file` once for each file as the walk
+/// discovers it, so large workspaces can report progress before the walk ends.
+fn collect_files_with_callback(
+    workspace_root: &Path,
+    config: &SemanticIndexConfig,
+    mut on_file: impl FnMut(&Path),
+) -> Result<Vec<PathBuf>> {
+    let index_dir = config.dir.as_path();
+    let filter = PathFilter::load(workspace_root, config);
+    let walker = WalkDir::new(workspace_root)
+        .follow_links(true)
+        .into_iter()
+        .filter_entry(|entry| !should_skip_entry(entry, workspace_root, index_dir, &filter));
+    let mut files = Vec::new();
+    for entry in walker {
+        // `follow_links` makes walkdir check each directory against its
+        // ancestors, so a symlink cycle surfaces as an error instead of
+        // recursing forever; skip the link and keep walking.
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) if err.loop_ancestor().is_some() => {
+                warn!(
+                    target: LOG_TARGET,
+                    path = %err.path().unwrap_or(workspace_root).display(),
+                    "skipping symlink that loops back to {}",
+                    err.loop_ancestor().unwrap_or(workspace_root).display(),
+                );
+                continue;
+            }
+            Err(err) => return Err(io::Error::from(err).into()),
+        };
+        if entry.file_type().is_file() {
+            on_file(entry.path());
+            files.push(entry.path().to_path_buf());
+        }
+    }
+    Ok(files)
+}
+
+/// Workspace-relative exclusions applied on top of the built-in `.git` and
+/// index-directory skips.
+struct PathFilter<'a> {
+    workspace_root: PathBuf,
+    codexignore: Gitignore,
+    /// `.gitignore` matchers keyed by directory, loaded as paths under each
+    /// directory are first checked.
+    gitignores: RefCell<HashMap<PathBuf, Gitignore>>,
+    /// Files must match one of these when any are configured.
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+    /// Normalized by the config: lowercase, no leading dot.
+    include_extensions: &'a [String],
+    exclude_extensions: &'a [String],
+    /// Directory names pruned wherever they appear.
+    skip_dirs: &'a HashSet<String>,
+}
+
+impl<'a> PathFilter<'a> {
+    fn load(workspace_root: &Path, config: &'a SemanticIndexConfig) -> Self {
+        Self {
+            workspace_root: workspace_root.to_path_buf(),
+            codexignore: load_codexignore(workspace_root),
+            gitignores: RefCell::default(),
+            include: build_glob_set("include_patterns", &config.include_patterns),
+            exclude: build_glob_set("exclude_patterns", &config.exclude_patterns),
+            include_extensions: &config.include_extensions,
+            exclude_extensions: &config.exclude_extensions,
+            skip_dirs: &config.skip_dirs,
+        }
+    }
+
+    /// Whether the file name's extension rules it out. An empty include list
+    /// allows every extension; the exclude list wins over the include list.
+    fn excludes_extension(&self, relative: &Path) -> bool {
+        if self.include_extensions.is_empty() && self.exclude_extensions.is_empty() {
+            return false;
+        }
+        let Some(name) = relative.file_name() else {
+            return false;
+        };
+        let name = name.to_string_lossy().to_ascii_lowercase();
+        let has_extension = |ext: &String| {
+            name.strip_suffix(ext.as_str())
+                .and_then(|stem| stem.strip_suffix('.'))
+                .is_some_and(|stem| !stem.is_empty())
+        };
+        if self.exclude_extensions.iter().any(has_extension) {
+            return true;
+        }
+        !self.include_extensions.is_empty() && !self.include_extensions.iter().any(has_extension)
+    }
+
+    fn skips_dir_name(&self, name: &OsStr) -> bool {
+        name.to_str()
+            .is_some_and(|name| self.skip_dirs.contains(name))
+    }
+
+    /// Whether any directory above the workspace-relative `relative` is listed
+    /// in `skip_dirs`.
+    fn in_skipped_dir(&self, relative: &Path) -> bool {
+        relative.parent().is_some_and(|parent| {
+            parent
+                .components()
+                .any(|comp| self.skips_dir_name(comp.as_os_str()))
+        })
+    }
+
+    fn excludes(&self, relative: &Path, is_dir: bool) -> bool {
+        if self
+            .exclude
+            .as_ref()
+            .is_some_and(|exclude| exclude.is_match(relative))
+        {
+            return true;
+        }
+        if !is_dir
+            && (self
+                .include
+                .as_ref()
+                .is_some_and(|include| !include.is_match(relative))
+                || self.excludes_extension(relative))
+        {
+            return true;
+        }
+        self.codexignore
+            .matched_path_or_any_parents(relative, is_dir)
+            .is_ignore()
+            || self.gitignored(relative, is_dir)
+    }
+
+    /// Whether the closest `.gitignore` with a rule for `relative`, in its own
+    /// directory or any directory above it up to the workspace root, ignores
+    /// it.
+    fn gitignored(&self, relative: &Path, is_dir: bool) -> bool {
+        let path = self.workspace_root.join(relative);
+        let mut gitignores = self.gitignores.borrow_mut();
+        for dir in path.ancestors().skip(1) {
+            if !dir.starts_with(&self.workspace_root) {
+                break;
+            }
+            let gitignore = gitignores
+                .entry(dir.to_path_buf())
+                .or_insert_with(|| load_gitignore(dir));
+            match gitignore.matched_path_or_any_parents(&path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
+}
+
+/// Compiles `patterns` into a glob set, or `None` when there are none.
+/// Invalid patterns are logged and skipped.
+fn build_glob_set(setting: &str, patterns: &[String]) -> Option<GlobSet> {
+    if patterns.is_empty() {
+        return None;
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        match Glob::new(pattern) {
+            Ok(glob) => {
+                builder.add(glob);
+            }
+            Err(err) => warn!(
+                target: LOG_TARGET,
+                "ignoring invalid semantic_index.{setting} pattern `{pattern}`: {err}",
+            ),
+        }
+    }
+    builder.build().ok().filter(|set| !set.is_empty())
+}
+
+/// Loads `.codexignore` from the workspace root: indexing-only exclusions in
+/// `.gitignore` syntax (one pattern per line, `#` comments, `!` negations).
+/// A missing file excludes nothing; unparsable lines are logged and skipped.
+fn load_codexignore(workspace_root: &Path) -> Gitignore {
+    let path = workspace_root.join(CODEXIGNORE_FILE);
+    if !path.is_file() {
+        return Gitignore::empty();
+    }
+    let mut builder = GitignoreBuilder::new(workspace_root);
+    if let Some(err) = builder.add(&path) {
+        warn!(
+            target: LOG_TARGET,
+            path = %path.display(),
+            "ignoring invalid .codexignore entries: {err}",
+        );
+    }
+    builder.build().unwrap_or_else(|err| {
+        warn!(
+            target: LOG_TARGET,
+            path = %path.display(),
+            "failed to load .codexignore: {err}",
+        );
+        Gitignore::empty()
+    })
+}
+
+/// Loads the `.gitignore` in `dir`, if any. Unparsable lines are logged and
+/// skipped.
+fn load_gitignore(dir: &Path) -> Gitignore {
+    let path = dir.join(GITIGNORE_FILE);
+    if !path.is_file() {
+        return Gitignore::empty();
+    }
+    let (gitignore, err) = Gitignore::new(&path);
+    if let Some(err) = err {
+        warn!(
+            target: LOG_TARGET,
+            path = %path.display(),
+            "ignoring invalid .gitignore entries: {err}",
+        );
+    }
+    gitignore
+}
+
+fn should_skip_entry(
+    entry: &DirEntry,
+    workspace_root: &Path,
+    index_dir: &Path,
+    filter: &PathFilter,
+) -> bool {
+    if entry.depth() > 0 && entry.file_type().is_dir() && filter.skips_dir_name(entry.file_name()) {
+        return true;
+    }
+    is_excluded_path(
+        entry.path(),
+        workspace_root,
+        index_dir,
+        filter,
+        entry.file_type().is_dir(),
+    )
+}
+
+fn is_excluded_path(
+    path: &Path,
+    workspace_root: &Path,
+    index_dir: &Path,
+    filter: &PathFilter,
+    is_dir: bool,
+) -> bool {
+    if path.starts_with(index_dir) {
+        return true;
+    }
+    let Ok(relative) = path.strip_prefix(workspace_root) else {
+        return false;
+    };
+    if relative.as_os_str().is_empty() {
+        return false;
+    }
+    relative.components().any(|comp| comp.as_os_str() == ".git")
+        || filter.excludes(relative, is_dir)
+}
+
+/// A contiguous run of lines cut from a file for embedding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    /// First line of the chunk, 1-based.
+    pub start_line: usize,
+    /// Last line of the chunk, inclusive.
+    pub end_line: usize,
+    pub text: String,
+}
+
+/// How [`score`] compares a query embedding with a candidate. The index
+/// records the metric it was built for in its metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DistanceMetric {
+    /// Cosine similarity in `[-1, 1]`.
+    #[default]
+    Cosine,
+    /// Raw dot product, equivalent to cosine for unit-length embeddings but
+    /// without the normalization.
+    DotProduct,
+    /// Negated Euclidean distance, so that closer vectors still score higher.
+    Euclidean,
+}
+
+impl DistanceMetric {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DistanceMetric::Cosine => "cosine",
+            DistanceMetric::DotProduct => "dot_product",
+            DistanceMetric::Euclidean => "euclidean",
+        }
+    }
+}
+
+impl FromStr for DistanceMetric {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> anyhow::Result<Self> {
+        match value {
+            "cosine" => Ok(DistanceMetric::Cosine),
+            "dot_product" => Ok(DistanceMetric::DotProduct),
+            "euclidean" => Ok(DistanceMetric::Euclidean),
+            other => anyhow::bail!("unknown similarity metric `{other}`"),
+        }
+    }
+}
+
+/// Splits `text` into chunks of at most `config.max_lines` lines, skipping
+/// chunks that are entirely whitespace. This is the chunking used when
+/// building the index.
+///
+/// ```
+/// use codex_core::semantic::config::ChunkingConfig;
+/// use codex_core::semantic::index::chunk_text;
+///
+/// let chunks = chunk_text("one\ntwo\nthree", ChunkingConfig { max_lines: 2 });
+/// assert_eq!(chunks.len(), 2);
+/// assert_eq!((chunks[1].start_line, chunks[1].end_line), (3, 3));
+/// assert_eq!(chunks[1].text, "three");
+/// ```
+pub fn chunk_text(text: &str, config: ChunkingConfig) -> Vec<Chunk> {
+    let lines: Vec<String> = text.lines().map(ToString::to_string).collect();
+    chunk_lines(&lines, config.max_lines)
+}
+
+/// Scores `candidate` against `query` with `metric`; higher is closer.
+/// Returns `None` when the dimensions differ or either vector is empty (or,
+/// for cosine, has zero length).
+///
+/// ```
+/// use codex_core::semantic::index::DistanceMetric;
+/// use codex_core::semantic::index::score;
+///
+/// assert_eq!(score(&[1.0, 0.0], &[2.0, 0.0], DistanceMetric::Cosine), Some(1.0));
+/// assert_eq!(score(&[1.0, 2.0], &[3.0, 4.0], DistanceMetric::DotProduct), Some(11.0));
+/// assert_eq!(score(&[0.0, 0.0], &[3.0, 4.0], DistanceMetric::Euclidean), Some(-5.0));
+/// assert_eq!(score(&[1.0], &[1.0, 0.0], DistanceMetric::Cosine), None);
+/// ```
+pub fn score(query: &[f32], candidate: &[f32], metric: DistanceMetric) -> Option<f32> {
+    let comparable = query.len() == candidate.len() && !query.is_empty();
+    let pairs = query.iter().zip(candidate);
+    match metric {
+        DistanceMetric::Cosine => cosine_similarity(query, candidate),
+        DistanceMetric::DotProduct => comparable.then(|| pairs.map(|(a, b)| a * b).sum()),
+        DistanceMetric::Euclidean => comparable.then(|| {
+            let squared: f32 = pairs.map(|(a, b)| (a - b) * (a - b)).sum();
+            -squared.sqrt()
+        }),
+    }
+}
+
+fn chunk_lines(lines: &[String], max_lines: usize) -> Vec<Chunk> {
+    if max_lines == 0 {
+        return Vec::new();
+    }
+    let mut chunks = Vec::new();
+    for (chunk_index, chunk_lines) in lines.chunks(max_lines).enumerate() {
+        let start_line = chunk_index * max_lines + 1;
+        let end_line = start_line + chunk_lines.len().saturating_sub(1);
+        let text = chunk_lines.join("\n");
+        if text.trim().is_empty() {
+            continue;
+        }
+        chunks.push(Chunk {
+            start_line,
+            end_line,
+            text,
+        });
+    }
+    chunks
+}
+
+fn chunk_id(path: &str, start_line: usize, end_line: usize, text_hash: &str) -> String {
+    let input = format!("{path}:{start_line}-{end_line}:{text_hash}");
+    format!("{:x}", Sha256::digest(input.as_bytes()))
+}
+
+/// Decodes a file for indexing, or returns `None` for empty and binary
+/// files. UTF-16 with a byte order mark is decoded; UTF-16 without one is
+/// skipped as binary, since its ASCII characters contain NUL bytes.
+fn decode_indexable_text(bytes: &[u8]) -> Option<Cow<'_, str>> {
+    if bytes.is_empty() {
+        return None;
+    }
+    if let Some((encoding, _)) = Encoding::for_bom(bytes)
+        && encoding != UTF_8
+    {
+        return Some(encoding.decode_with_bom_removal(bytes).0);
+    }
+    if looks_binary(bytes) {
+        return None;
+    }
+    Some(String::from_utf8_lossy(bytes))
+}
+
+/// Any NUL byte marks a file as binary, as does a sample in which too many
+/// bytes are control characters (other than whitespace and escape) or not
+/// valid UTF-8.
+fn looks_binary(bytes: &[u8]) -> bool {
+    if bytes.contains(&0) {
+        return true;
+    }
+    let sample = &bytes[..bytes.len().min(BINARY_SAMPLE_BYTES)];
+    let mut non_text = 0;
+    for chunk in sample.utf8_chunks() {
+        non_text += chunk.invalid().len();
+        non_text += chunk
+            .valid()
+            .bytes()
+            .filter(|byte| {
+                byte.is_ascii_control()
+                    && !matches!(byte, b'\t' | b'\n' | b'\r' | b'\x0c' | b'\x1b')
+            })
+            .count();
+    }
+    non_text as f64 > sample.len() as f64 * MAX_NON_TEXT_RATIO
+}
+
+fn hash_bytes(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+fn hash_string(value: &str) -> String {
+    format!("{:x}", Sha256::digest(value.as_bytes()))
+}
+
+fn fingerprint_workspace(path: &Path) -> String {
+    hash_string(path.to_string_lossy().as_ref())
+}
+
+fn cosine_similarity(query: &[f32], other: &[f32]) -> Option<f32> {
+    if query.len() != other.len() || query.is_empty() {
+        return None;
+    }
+    let mut dot = 0.0_f32;
+    let mut norm_a = 0.0_f32;
+    let mut norm_b = 0.0_f32;
+    for (a, b) in query.iter().zip(other) {
+        dot += a * b;
+        norm_a += a * a;
+        norm_b += b * b;
+    }
+    let denom = norm_a.sqrt() * norm_b.sqrt();
+    if denom == 0.0 {
+        None
+    } else {
+        Some(dot / denom)
+    }
+}
+
+fn score_cmp(a: &SearchHit, b: &SearchHit) -> Ordering {
+    b.score
+        .partial_cmp(&a.score)
         .unwrap_or(Ordering::Equal)
         .then_with(|| a.file_path.cmp(&b.file_path))
         .then_with(|| a.start_line.cmp(&b.start_line))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use pretty_assertions::assert_eq;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_provider_info::WireApi;
+    use crate::semantic::config::SemanticIndexConfigToml;
+    use crate::semantic::vector_store::EmbeddingQuantization;
+    use futures::StreamExt;
+    use pretty_assertions::assert_eq;
+    use proptest::prelude::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+    use tracing_test::traced_test;
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::Request;
+    use wiremock::Respond;
+    use wiremock::ResponseTemplate;
+    use wiremock::matchers::method;
+    use wiremock::matchers::path;
+
+    /// Embeds every input as `[1.0, 0.0]`.
+    struct FixedEmbeddings;
+
+    impl Respond for FixedEmbeddings {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            let body: serde_json::Value =
+                serde_json::from_slice(&request.body).expect("request body");
+            let data = body["input"]
+                .as_array()
+                .expect("input array")
+                .iter()
+                .enumerate()
+                .map(|(index, _)| json!({ "index": index, "embedding": [1.0, 0.0] }))
+                .collect::<Vec<_>>();
+            // One token per input keeps usage totals easy to predict.
+            let usage = json!({ "total_tokens": data.len() });
+            ResponseTemplate::new(200).set_body_json(json!({ "data": data, "usage": usage }))
+        }
+    }
+
+    async fn index_with_mock_embeddings(workspace: &Path, server: &MockServer) -> SemanticIndex {
+        Mock::given(method("POST"))
+            .and(path("/embeddings"))
+            .respond_with(FixedEmbeddings)
+            .mount(server)
+            .await;
+        let provider = ModelProviderInfo {
+            name: "mock".into(),
+            base_url: Some(server.uri()),
+            env_key: None,
+            env_key_instructions: None,
+            experimental_bearer_token: None,
+            wire_api: WireApi::Responses,
+            query_params: None,
+            http_headers: None,
+            env_http_headers: None,
+            request_max_retries: Some(0),
+            stream_max_retries: Some(0),
+            stream_idle_timeout_ms: Some(5_000),
+            requires_openai_auth: false,
+        };
+        let config = SemanticIndexConfig::new(workspace, None).expect("config");
+        SemanticIndex::new(workspace.to_path_buf(), config, provider, None)
+    }
+
+    fn chunks_for(index: &SemanticIndex, file_path: &str) -> Vec<(String, Option<String>)> {
+        index
+            .open_store(StoreMode::OpenExisting)
+            .expect("open store")
+            .list_embeddings()
+            .expect("list embeddings")
+            .into_iter()
+            .filter(|record| record.file_path == file_path)
+            .map(|record| (record.chunk_id, record.chunk_text))
+            .collect()
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn build_and_search_record_span_attributes() {
+        let workspace = tempdir().expect("tempdir");
+        let server = MockServer::start().await;
+        let index = index_with_mock_embeddings(workspace.path(), &server).await;
+        std::fs::write(workspace.path().join("notes.txt"), "some text\n").expect("write");
+
+        index.build().await.expect("build");
+        let hits = index
+            .search("text", 3, SearchMode::Semantic)
+            .await
+            .expect("search");
+
+        assert_eq!(hits.len(), 1);
+        assert!(logs_contain("semantic_index.build{"));
+        assert!(logs_contain("file_count=1 chunk_count=1"));
+        assert!(logs_contain("semantic_index.search{query_len=4 top_k=3"));
+        assert!(logs_contain("candidates=1 result_count=1"));
+    }
+
+    #[tokio::test]
+    async fn build_and_search_export_otel_spans() {
+        use opentelemetry::Value;
+        use opentelemetry::trace::TracerProvider as _;
+        use opentelemetry_sdk::trace::InMemorySpanExporter;
+        use opentelemetry_sdk::trace::SdkTracerProvider;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("semantic-index")));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let workspace = tempdir().expect("tempdir");
+        let server = MockServer::start().await;
+        let index = index_with_mock_embeddings(workspace.path(), &server).await;
+        std::fs::write(workspace.path().join("notes.txt"), "some text\n").expect("write");
+        index.build().await.expect("build");
+        index
+            .search("text", 3, SearchMode::Semantic)
+            .await
+            .expect("search");
+        provider.force_flush().expect("flush");
+
+        let spans = exporter.get_finished_spans().expect("finished spans");
+        let attribute = |span_name: &str, key: &str| {
+            spans
+                .iter()
+                .find(|span| span.name == span_name)
+                .and_then(|span| span.attributes.iter().find(|kv| kv.key.as_str() == key))
+                .map(|kv| kv.value.clone())
+        };
+        assert_eq!(
+            attribute("semantic_index.build", "file_count"),
+            Some(Value::I64(1))
+        );
+        assert_eq!(
+            attribute("semantic_index.build", "chunk_count"),
+            Some(Value::I64(1))
+        );
+        assert_eq!(
+            attribute("semantic_index.file", "path"),
+            Some(Value::from("notes.txt"))
+        );
+        assert_eq!(
+            attribute("semantic_index.search", "top_k"),
+            Some(Value::I64(3))
+        );
+        assert_eq!(
+            attribute("semantic_index.search", "candidates"),
+            Some(Value::I64(1))
+        );
+        assert_eq!(
+            attribute("semantic_index.search", "result_count"),
+            Some(Value::I64(1))
+        );
+        assert!(spans.iter().any(|span| span.name == "embedding.embed"));
+    }
+
+    #[tokio::test]
+    async fn update_file_embeds_only_changed_chunks() {
+        let workspace = tempdir().expect("tempdir");
+        let server = MockServer::start().await;
+        let index = index_with_mock_embeddings(workspace.path(), &server).await;
+        let max_lines = index.config.chunk.max_lines;
+        let mut lines: Vec<String> = (0..max_lines * 2).map(|i| format!("line {i}")).collect();
+        let notes = workspace.path().join("notes.txt");
+        std::fs::write(&notes, lines.join("\n")).expect("write");
+        index.build().await.expect("build");
+        let requests_after_build = server.received_requests().await.expect("requests").len();
+
+        lines[max_lines + 1] = "changed".to_string();
+        std::fs::write(&notes, lines.join("\n")).expect("rewrite");
+        index
+            .update_file(Path::new("notes.txt"))
+            .await
+            .expect("update");
+
+        let inputs: Vec<serde_json::Value> = server
+            .received_requests()
+            .await
+            .expect("requests")
+            .iter()
+            .skip(requests_after_build)
+            .flat_map(|request| {
+                let body: serde_json::Value =
+                    serde_json::from_slice(&request.body).expect("request body");
+                body["input"].as_array().cloned().unwrap_or_default()
+            })
+            .collect();
+        assert_eq!(inputs.len(), 1);
+        assert!(
+            inputs[0]
+                .as_str()
+                .is_some_and(|text| text.contains("changed")),
+            "{inputs:?}"
+        );
+        assert_eq!(chunks_for(&index, "notes.txt").len(), 2);
+    }
+
+    #[tokio::test]
+    async fn update_file_replaces_changed_chunks() {
+        let workspace = tempdir().expect("tempdir");
+        let server = MockServer::start().await;
+        let index = index_with_mock_embeddings(workspace.path(), &server).await;
+        std::fs::write(workspace.path().join("notes.txt"), "old text\n").expect("write");
+        std::fs::write(workspace.path().join("other.txt"), "other text\n").expect("write");
+        index.build().await.expect("build");
+        let old_chunks = chunks_for(&index, "notes.txt");
+        let requests_after_build = server.received_requests().await.expect("requests").len();
+
+        index
+            .update_file(Path::new("notes.txt"))
+            .await
+            .expect("unchanged update");
+        assert_eq!(
+            server.received_requests().await.expect("requests").len(),
+            requests_after_build
+        );
+
+        std::fs::write(workspace.path().join("notes.txt"), "new text\n").expect("rewrite");
+        index
+            .update_file(Path::new("notes.txt"))
+            .await
+            .expect("update");
+
+        let new_chunks = chunks_for(&index, "notes.txt");
+        assert_eq!(new_chunks.len(), 1);
+        assert!(old_chunks.iter().all(|old| !new_chunks.contains(old)));
+        assert_eq!(new_chunks[0].1.as_deref(), Some("new text"));
+        assert_eq!(chunks_for(&index, "other.txt").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn update_file_adds_new_and_removes_deleted_files() {
+        let workspace = tempdir().expect("tempdir");
+        let server = MockServer::start().await;
+        let index = index_with_mock_embeddings(workspace.path(), &server).await;
+        std::fs::write(workspace.path().join("notes.txt"), "old text\n").expect("write");
+        index.build().await.expect("build");
+
+        std::fs::write(workspace.path().join("added.txt"), "added text\n").expect("write");
+        index
+            .update_file(Path::new("added.txt"))
+            .await
+            .expect("add");
+        std::fs::remove_file(workspace.path().join("notes.txt")).expect("remove");
+        index
+            .update_file(Path::new("notes.txt"))
+            .await
+            .expect("remove");
+
+        assert_eq!(chunks_for(&index, "added.txt").len(), 1);
+        assert!(chunks_for(&index, "notes.txt").is_empty());
+        let stats = index.stats().expect("stats");
+        assert_eq!(stats.file_count, 1);
+    }
+
+    #[tokio::test]
+    async fn build_skips_files_over_max_file_size() {
+        let workspace = tempdir().expect("tempdir");
+        let server = MockServer::start().await;
+        let mut index = index_with_mock_embeddings(workspace.path(), &server).await;
+        index.config.max_file_size_bytes = Some(64);
+        std::fs::write(workspace.path().join("small.txt"), "small text\n").expect("write");
+        std::fs::write(workspace.path().join("bundle.min.js"), "x".repeat(65)).expect("write");
+
+        let stats = index.build().await.expect("build");
+
+        assert_eq!(stats.file_count, 1);
+        assert_eq!(chunks_for(&index, "small.txt").len(), 1);
+        assert!(chunks_for(&index, "bundle.min.js").is_empty());
+    }
+
+    #[tokio::test]
+    async fn build_splits_large_files_into_embedding_batches() {
+        let workspace = tempdir().expect("tempdir");
+        let server = MockServer::start().await;
+        let mut index = index_with_mock_embeddings(workspace.path(), &server).await;
+        index.config.chunk.max_lines = 1;
+        index.config.max_batch_size = 64;
+        let text = (0..200).map(|i| format!("line {i}\n")).collect::<String>();
+        std::fs::write(workspace.path().join("big.txt"), text).expect("write");
+
+        let stats = index.build().await.expect("build");
+
+        assert_eq!(stats.chunk_count, 200);
+        assert_eq!(server.received_requests().await.expect("requests").len(), 4);
+    }
+
+    #[tokio::test]
+    async fn build_reports_tokens_used_when_tracking() {
+        let workspace = tempdir().expect("tempdir");
+        let server = MockServer::start().await;
+        let mut index = index_with_mock_embeddings(workspace.path(), &server).await;
+        std::fs::write(workspace.path().join("a.txt"), "alpha\n").expect("write");
+        std::fs::write(workspace.path().join("b.txt"), "beta\n").expect("write");
+
+        assert_eq!(index.build().await.expect("build").tokens_used, None);
+
+        index.config.track_tokens = true;
+        assert_eq!(index.build().await.expect("build").tokens_used, Some(2));
+    }
+
+    #[tokio::test]
+    async fn build_embeds_identical_chunks_once() {
+        let workspace = tempdir().expect("tempdir");
+        let server = MockServer::start().await;
+        let mut index = index_with_mock_embeddings(workspace.path(), &server).await;
+        index.config.chunk.max_lines = 1;
+        std::fs::write(
+            workspace.path().join("a.txt"),
+            "// License header\nfn a() {}\n",
+        )
+        .expect("write");
+        std::fs::write(
+            workspace.path().join("b.txt"),
+            "// License header\nfn b() {}\n",
+        )
+        .expect("write");
+
+        let stats = index.build().await.expect("build");
+
+        let header_requests = server
+            .received_requests()
+            .await
+            .expect("requests")
+            .iter()
+            .flat_map(|request| {
+                let body: serde_json::Value =
+                    serde_json::from_slice(&request.body).expect("request body");
+                body["input"].as_array().cloned().unwrap_or_default()
+            })
+            .filter(|input| input == "// License header")
+            .count();
+        assert_eq!(header_requests, 1);
+        assert_eq!(stats.chunk_count, 4);
+        assert_eq!(stats.reused_embeddings, 1);
+        assert_eq!(chunks_for(&index, "b.txt").len(), 2);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    #[traced_test]
+    async fn build_skips_symlink_loops() {
+        let workspace = tempdir().expect("tempdir");
+        let server = MockServer::start().await;
+        let index = index_with_mock_embeddings(workspace.path(), &server).await;
+        std::fs::create_dir(workspace.path().join("src")).expect("mkdir");
+        std::fs::write(workspace.path().join("src/lib.rs"), "fn main() {}\n").expect("write");
+        std::os::unix::fs::symlink(workspace.path(), workspace.path().join("src/loop"))
+            .expect("symlink");
+
+        let stats = tokio::time::timeout(Duration::from_secs(10), index.build())
+            .await
+            .expect("build finishes")
+            .expect("build");
+
+        assert_eq!(stats.file_count, 1);
+        assert!(logs_contain("skipping symlink that loops back"));
+    }
+
+    #[tokio::test]
+    async fn update_files_skips_index_dir_and_returns_stats() {
+        let workspace = tempdir().expect("tempdir");
+        let server = MockServer::start().await;
+        let index = index_with_mock_embeddings(workspace.path(), &server).await;
+        std::fs::write(workspace.path().join("notes.txt"), "old text\n").expect("write");
+        index.build().await.expect("build");
+
+        std::fs::write(workspace.path().join("notes.txt"), "new text\n").expect("write");
+        std::fs::write(workspace.path().join("added.txt"), "added text\n").expect("write");
+        let index_db = index
+            .open_store(StoreMode::OpenExisting)
+            .expect("store")
+            .db_path()
+            .to_path_buf();
+        let stats = index
+            .update_files(&[
+                workspace.path().join("notes.txt"),
+                PathBuf::from("added.txt"),
+                index_db,
+            ])
+            .await
+            .expect("update files");
+
+        assert_eq!(stats.file_count, 2);
+        assert_eq!(chunks_for(&index, "added.txt").len(), 1);
+        assert_eq!(
+            chunks_for(&index, "notes.txt")[0].1.as_deref(),
+            Some("new text")
+        );
+    }
+
+    #[tokio::test]
+    async fn update_files_leaves_index_fresh() {
+        let workspace = tempdir().expect("tempdir");
+        let server = MockServer::start().await;
+        let index = index_with_mock_embeddings(workspace.path(), &server).await;
+        std::fs::write(workspace.path().join("notes.txt"), "old text\n").expect("write");
+        index.build().await.expect("build");
+
+        std::fs::write(workspace.path().join("notes.txt"), "new text\n").expect("write");
+        assert!(index.stats().expect("stats").stale);
+        index
+            .update_files(&[PathBuf::from("notes.txt")])
+            .await
+            .expect("update files");
+
+        assert!(!index.stats().expect("stats").stale);
+    }
+
+    #[tokio::test]
+    async fn update_files_skips_gitignored_paths() {
+        let workspace = tempdir().expect("tempdir");
+        let server = MockServer::start().await;
+        let index = index_with_mock_embeddings(workspace.path(), &server).await;
+        std::fs::write(workspace.path().join(GITIGNORE_FILE), "target/\n").expect("write");
+        index.build().await.expect("build");
+
+        std::fs::create_dir_all(workspace.path().join("target")).expect("mkdir");
+        std::fs::write(workspace.path().join("target/out.txt"), "built\n").expect("write");
+        std::fs::write(workspace.path().join("notes.txt"), "notes\n").expect("write");
+        let stats = index
+            .update_files(&[
+                workspace.path().join("target/out.txt"),
+                workspace.path().join("notes.txt"),
+            ])
+            .await
+            .expect("update files");
+
+        assert_eq!(stats.file_count, 2);
+        assert!(chunks_for(&index, "target/out.txt").is_empty());
+        assert_eq!(chunks_for(&index, "notes.txt").len(), 1);
+    }
+
+    fn git(workspace: &Path, args: &[&str]) {
+        let status = std::process::Command::new("git")
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(workspace)
+            .env("GIT_CONFIG_GLOBAL", "/dev/null")
+            .env("GIT_CONFIG_NOSYSTEM", "1")
+            .status()
+            .expect("run git");
+        assert!(status.success(), "git {args:?} failed");
+    }
+
+    #[tokio::test]
+    async fn build_since_only_reembeds_files_changed_since_rev() {
+        let workspace = tempdir().expect("tempdir");
+        let server = MockServer::start().await;
+        let index = index_with_mock_embeddings(workspace.path(), &server).await;
+        std::fs::write(workspace.path().join("notes.txt"), "old text\n").expect("write");
+        std::fs::write(workspace.path().join("other.txt"), "other text\n").expect("write");
+        git(workspace.path(), &["init", "-q"]);
+        git(workspace.path(), &["add", "notes.txt", "other.txt"]);
+        git(workspace.path(), &["commit", "-qm", "initial"]);
+        index.build().await.expect("build");
+        let requests_after_build = server.received_requests().await.expect("requests").len();
+        let built_commit = index.stats().expect("stats").git_commit;
+        assert!(built_commit.as_ref().is_some_and(|hash| hash.len() == 40));
+
+        std::fs::write(workspace.path().join("notes.txt"), "new text\n").expect("rewrite");
+        git(workspace.path(), &["commit", "-qam", "change notes"]);
+        let stats = index.build_since("HEAD~1").await.expect("build since");
+
+        assert_eq!(stats.file_count, 2);
+        let refreshed = index.stats().expect("stats");
+        assert!(!refreshed.stale);
+        assert!(
+            refreshed
+                .git_commit
+                .is_some_and(|hash| hash.len() == 40 && Some(&hash) != built_commit.as_ref())
+        );
+        assert_eq!(
+            server.received_requests().await.expect("requests").len(),
+            requests_after_build + 1
+        );
+        assert_eq!(
+            chunks_for(&index, "notes.txt")[0].1.as_deref(),
+            Some("new text")
+        );
+
+        index
+            .build_since("no-such-rev")
+            .await
+            .expect("full build fallback");
+        assert_eq!(
+            server.received_requests().await.expect("requests").len(),
+            requests_after_build + 3
+        );
+    }
+
+    #[tokio::test]
+    async fn modified_since_detects_files_newer_than_build() {
+        let workspace = tempdir().expect("tempdir");
+        let server = MockServer::start().await;
+        let index = index_with_mock_embeddings(workspace.path(), &server).await;
+        let notes = workspace.path().join("notes.txt");
+        std::fs::write(&notes, "text\n").expect("write");
+        index.build().await.expect("build");
+        let built_at = index
+            .stats()
+            .expect("stats")
+            .created_at
+            .expect("created_at");
+        let set_mtime = |offset: chrono::Duration| {
+            std::fs::File::options()
+                .write(true)
+                .open(&notes)
+                .expect("open")
+                .set_modified((built_at + offset).into())
+                .expect("set mtime");
+        };
+
+        set_mtime(-chrono::Duration::seconds(60));
+        assert!(!index.modified_since(built_at).expect("fresh"));
+        assert!(!index.is_stale().expect("fresh"));
+        assert!(!index.stats().expect("stats").stale);
+        set_mtime(chrono::Duration::seconds(60));
+        assert!(index.modified_since(built_at).expect("stale"));
+        assert!(index.is_stale().expect("stale"));
+        assert!(index.stats().expect("stats").stale);
+        assert_eq!(index.modified_file_count().expect("count"), 1);
+    }
+
+    #[tokio::test]
+    async fn search_reports_dimension_mismatch_before_scoring() {
+        let workspace = tempdir().expect("tempdir");
+        let config = SemanticIndexConfig::new(workspace.path(), None).expect("config");
+        let store =
+            VectorStore::open(config.dir.as_path(), StoreMode::CreateOrOpen).expect("open store");
+        store
+            .store_meta(&IndexMeta {
+                schema_version: SCHEMA_VERSION,
+                embedding_model: config.embedding_model.clone(),
+                dim: 2,
+                chunk_size: config.chunk.max_lines,
+                metric: DistanceMetric::Cosine,
+                created_at: Utc::now(),
+                workspace_fingerprint: String::new(),
+                git_commit: None,
+                quantization: EmbeddingQuantization::None,
+            })
+            .expect("store meta");
+        store
+            .store_chunk(&ChunkEntry {
+                file_path: "src/lib.rs".to_string(),
+                chunk_id: "alpha".to_string(),
+                start_line: 1,
+                end_line: 1,
+                text_hash: "hash".to_string(),
+                text: "alpha".to_string(),
+                embedding: vec![1.0],
+                updated_at: Utc::now(),
+            })
+            .expect("store chunk");
+        drop(store);
+        let index = SemanticIndex::new(
+            workspace.path().to_path_buf(),
+            config,
+            ModelProviderInfo::create_openai_provider(),
+            None,
+        );
+
+        let err = index
+            .search("alpha", 5, SearchMode::Semantic)
+            .await
+            .expect_err("dimension mismatch");
+
+        assert!(
+            matches!(
+                err,
+                SemanticIndexError::DimensionMismatch {
+                    expected: 2,
+                    actual: 1
+                }
+            ),
+            "{err}"
+        );
+        assert!(err.to_string().contains("1 dimensions"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn search_rejects_index_built_with_other_metric() {
+        let workspace = tempdir().expect("tempdir");
+        let config = SemanticIndexConfig::new(workspace.path(), None).expect("config");
+        let store =
+            VectorStore::open(config.dir.as_path(), StoreMode::CreateOrOpen).expect("open store");
+        store
+            .store_meta(&IndexMeta {
+                schema_version: SCHEMA_VERSION,
+                embedding_model: config.embedding_model.clone(),
+                dim: 2,
+                chunk_size: config.chunk.max_lines,
+                metric: DistanceMetric::Euclidean,
+                created_at: Utc::now(),
+                workspace_fingerprint: String::new(),
+                git_commit: None,
+                quantization: EmbeddingQuantization::None,
+            })
+            .expect("store meta");
+        assert_eq!(
+            store.metric().expect("metric"),
+            Some(DistanceMetric::Euclidean)
+        );
+        drop(store);
+        let index = SemanticIndex::new(
+            workspace.path().to_path_buf(),
+            config,
+            ModelProviderInfo::create_openai_provider(),
+            None,
+        );
+
+        let err = index
+            .search("alpha", 5, SearchMode::Semantic)
+            .await
+            .expect_err("metric mismatch");
+
+        assert!(
+            err.to_string().contains(
+                "built with the `euclidean` metric but `semantic_index.metric` is `cosine`"
+            ),
+            "{err}"
+        );
+    }
+
+    #[tokio::test]
+    async fn search_reports_structured_errors() {
+        let workspace = tempdir().expect("tempdir");
+        let mut config = SemanticIndexConfig::new(workspace.path(), None).expect("config");
+        let index_for = |config: SemanticIndexConfig| {
+            SemanticIndex::new(
+                workspace.path().to_path_buf(),
+                config,
+                ModelProviderInfo::create_openai_provider(),
+                None,
+            )
+        };
+
+        let err = index_for(config.clone())
+            .search("alpha", 5, SearchMode::Keyword)
+            .await
+            .expect_err("missing index");
+        assert!(
+            matches!(&err, SemanticIndexError::IndexNotFound(dir) if dir == config.dir.as_path()),
+            "{err}"
+        );
+
+        let err = index_for(config.clone())
+            .search("  ", 5, SearchMode::Keyword)
+            .await
+            .expect_err("empty query");
+        assert!(matches!(err, SemanticIndexError::EmptyQuery), "{err}");
+
+        let err = index_for(config.clone())
+            .search("alpha", 0, SearchMode::Keyword)
+            .await
+            .expect_err("zero top_k");
+        assert!(matches!(err, SemanticIndexError::InvalidTopK), "{err}");
+
+        config.enabled = false;
+        let err = index_for(config)
+            .search("alpha", 5, SearchMode::Keyword)
+            .await
+            .expect_err("disabled index");
+        assert!(matches!(err, SemanticIndexError::IndexDisabled), "{err}");
+    }
+
+    #[tokio::test]
+    async fn search_rejects_index_built_with_other_model() {
+        let workspace = tempdir().expect("tempdir");
+        let config = SemanticIndexConfig::new(workspace.path(), None).expect("config");
+        let store =
+            VectorStore::open(config.dir.as_path(), StoreMode::CreateOrOpen).expect("open store");
+        store
+            .store_meta(&IndexMeta {
+                schema_version: SCHEMA_VERSION,
+                embedding_model: "other-embedding-model".to_string(),
+                dim: 2,
+                chunk_size: config.chunk.max_lines,
+                metric: DistanceMetric::Cosine,
+                created_at: Utc::now(),
+                workspace_fingerprint: String::new(),
+                git_commit: None,
+                quantization: EmbeddingQuantization::None,
+            })
+            .expect("store meta");
+        drop(store);
+        let configured = config.embedding_model.clone();
+        let index = SemanticIndex::new(
+            workspace.path().to_path_buf(),
+            config,
+            ModelProviderInfo::create_openai_provider(),
+            None,
+        );
+
+        let err = index
+            .search("alpha", 5, SearchMode::Semantic)
+            .await
+            .expect_err("model mismatch");
+
+        match err {
+            SemanticIndexError::ModelMismatch {
+                indexed,
+                configured: actual,
+            } => {
+                assert_eq!(indexed, "other-embedding-model");
+                assert_eq!(actual, configured);
+            }
+            other => panic!("unexpected error: {other}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn search_with_grep_surfaces_exact_matches_missed_by_embeddings() {
+        let workspace = tempdir().expect("tempdir");
+        let server = MockServer::start().await;
+        let index = index_with_mock_embeddings(workspace.path(), &server).await;
+        for name in ["a.txt", "b.txt", "c.txt", "d.txt"] {
+            std::fs::write(workspace.path().join(name), "general notes\n").expect("write");
+        }
+        std::fs::write(
+            workspace.path().join("z.txt"),
+            "intro\nfn parse_config_v2() {}\n",
+        )
+        .expect("write");
+        index.build().await.expect("build");
+
+        let files = |hits: Vec<SearchHit>| -> Vec<String> {
+            hits.into_iter().map(|hit| hit.file_path).collect()
+        };
+        let semantic = index
+            .search("parse_config_v2", 2, SearchMode::Semantic)
+            .await
+            .expect("semantic search");
+        assert_eq!(files(semantic), vec!["a.txt", "b.txt"]);
+
+        let hybrid = index
+            .search_with_grep("parse_config_v2", 2, 0.5)
+            .await
+            .expect("hybrid search");
+        assert_eq!(files(hybrid), vec!["z.txt", "a.txt"]);
+
+        let semantic_only = index
+            .search_with_grep("parse_config_v2", 2, 1.0)
+            .await
+            .expect("semantic-weighted search");
+        assert_eq!(files(semantic_only), vec!["a.txt", "b.txt"]);
+    }
+
+    #[test]
+    fn rank_chunks_by_line_matches_counts_lines_per_chunk() {
+        let hit = |file_path: &str, chunk_id: &str, start_line, end_line| SearchHit {
+            file_path: file_path.to_string(),
+            start_line,
+            end_line,
+            score: 0.0,
+            chunk_id: chunk_id.to_string(),
+            chunk_text: None,
+        };
+        let hits = vec![
+            hit("src/lib.rs", "lib-1", 1, 10),
+            hit("src/lib.rs", "lib-2", 11, 20),
+            hit("src/main.rs", "main-1", 1, 5),
+        ];
+        let line = |path: &str, line_number| LineMatch {
+            path: PathBuf::from(path),
+            line_number,
+        };
+        let lines = vec![
+            line("src/main.rs", 2),
+            line("src/lib.rs", 12),
+            line("src/lib.rs", 15),
+            line("src/other.rs", 1),
+            line("src/main.rs", 40),
+        ];
+
+        assert_eq!(
+            rank_chunks_by_line_matches(&hits, &lines),
+            vec!["lib-2".to_string(), "main-1".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn repeated_query_is_embedded_once() {
+        let workspace = tempdir().expect("tempdir");
+        let server = MockServer::start().await;
+        let index = index_with_mock_embeddings(workspace.path(), &server).await;
+        std::fs::write(workspace.path().join("alpha.txt"), "parse_config\n").expect("write");
+        index.build().await.expect("build");
+        let requests_after_build = server.received_requests().await.expect("requests").len();
+
+        for _ in 0..2 {
+            index
+                .search("parse_config", 5, SearchMode::Semantic)
+                .await
+                .expect("search");
+        }
+
+        assert_eq!(
+            server.received_requests().await.expect("requests").len(),
+            requests_after_build + 1
+        );
+    }
+
+    #[tokio::test]
+    async fn search_clamps_top_k_to_configured_maximum() {
+        let workspace = tempdir().expect("tempdir");
+        let server = MockServer::start().await;
+        let mut index = index_with_mock_embeddings(workspace.path(), &server).await;
+        for name in ["alpha", "beta", "gamma"] {
+            std::fs::write(workspace.path().join(format!("{name}.txt")), name).expect("write");
+        }
+        index.build().await.expect("build");
+        index.config.retrieve.max_top_k = 2;
+
+        let hits = index
+            .search("alpha", usize::MAX, SearchMode::Semantic)
+            .await
+            .expect("search");
+
+        assert_eq!(hits.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn search_batch_returns_results_in_query_order() {
+        let workspace = tempdir().expect("tempdir");
+        let server = MockServer::start().await;
+        let index = index_with_mock_embeddings(workspace.path(), &server).await;
+        std::fs::write(workspace.path().join("alpha.txt"), "parse_config\n").expect("write");
+        std::fs::write(workspace.path().join("beta.txt"), "render_widget\n").expect("write");
+        index.build().await.expect("build");
+
+        let queries = ["render_widget", " ", "parse_config"].map(String::from);
+        let results = index
+            .search_batch(&queries, 5, SearchMode::Keyword, 2)
+            .await
+            .expect("search batch");
+
+        let files: Vec<Option<Vec<String>>> = results
+            .into_iter()
+            .map(|result| {
+                result
+                    .ok()
+                    .map(|hits| hits.into_iter().map(|hit| hit.file_path).collect())
+            })
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                Some(vec!["beta.txt".to_string()]),
+                None,
+                Some(vec!["alpha.txt".to_string()]),
+            ]
+        );
+
+        let semantic = index
+            .search_batch(&queries, 5, SearchMode::Semantic, 2)
+            .await
+            .expect("semantic batch");
+        assert_eq!(semantic.len(), 3);
+        assert!(matches!(semantic[1], Err(SemanticIndexError::EmptyQuery)));
+        assert_eq!(semantic[0].as_ref().map(Vec::len).ok(), Some(2));
+    }
+
+    #[tokio::test]
+    async fn search_stream_matches_search() {
+        let workspace = tempdir().expect("tempdir");
+        let server = MockServer::start().await;
+        let index = index_with_mock_embeddings(workspace.path(), &server).await;
+        let store = VectorStore::open(index.config.dir.as_path(), StoreMode::CreateOrOpen)
+            .expect("open store");
+        store
+            .store_meta(&IndexMeta {
+                schema_version: SCHEMA_VERSION,
+                embedding_model: index.config.embedding_model.clone(),
+                dim: 2,
+                chunk_size: index.config.chunk.max_lines,
+                metric: DistanceMetric::Cosine,
+                created_at: Utc::now(),
+                workspace_fingerprint: String::new(),
+                git_commit: None,
+                quantization: EmbeddingQuantization::None,
+            })
+            .expect("store meta");
+        for i in 0..40 {
+            let angle = i as f32 * 0.61;
+            store
+                .store_chunk(&ChunkEntry {
+                    file_path: format!("src/file{i}.rs"),
+                    chunk_id: format!("chunk-{i}"),
+                    start_line: 1,
+                    end_line: 1,
+                    text_hash: "hash".to_string(),
+                    text: format!("chunk {i}"),
+                    embedding: vec![angle.cos(), angle.sin()],
+                    updated_at: Utc::now(),
+                })
+                .expect("store chunk");
+        }
+        drop(store);
+
+        let expected = index
+            .search("query", 7, SearchMode::Semantic)
+            .await
+            .expect("search");
+        let streamed: Vec<SearchHit> = index
+            .search_stream("query", 7)
+            .await
+            .expect("search stream")
+            .collect()
+            .await;
+
+        assert_eq!(expected.len(), 7);
+        assert_eq!(streamed, expected);
+    }
+
+    #[test]
+    fn top_hits_keeps_best_in_order() {
+        let hits: Vec<SearchHit> = [0.2, 0.9, 0.5, 0.9, 0.1]
+            .into_iter()
+            .enumerate()
+            .map(|(i, score)| SearchHit {
+                file_path: format!("file{i}.rs"),
+                start_line: 1,
+                end_line: 1,
+                score,
+                chunk_id: format!("chunk-{i}"),
+                chunk_text: None,
+            })
+            .collect();
+        let mut top_hits = TopHits::new(3);
+        for hit in hits.iter().cloned() {
+            top_hits.push(hit);
+        }
+
+        let mut expected = hits;
+        expected.sort_by(score_cmp);
+        expected.truncate(3);
+        assert_eq!(top_hits.into_sorted_vec(), expected);
+    }
+
+    fn collected_paths(root: &Path, config: &SemanticIndexConfig) -> Vec<String> {
+        let mut files: Vec<String> = collect_files(root, config)
+            .expect("collect")
+            .into_iter()
+            .map(|path| {
+                path.strip_prefix(root)
+                    .expect("relative")
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn collect_files_honors_codexignore() {
+        let workspace = tempdir().expect("workspace");
+        let root = workspace.path();
+        fs::create_dir_all(root.join("tests/fixtures/large_corpus")).expect("mkdir");
+        fs::create_dir_all(root.join("src")).expect("mkdir");
+        fs::write(root.join("tests/fixtures/large_corpus/data.txt"), "x").expect("write");
+        fs::write(root.join("tests/unit.rs"), "x").expect("write");
+        fs::write(root.join("src/lib.rs"), "x").expect("write");
+        fs::write(root.join("src/debug.log"), "x").expect("write");
+        fs::write(
+            root.join(CODEXIGNORE_FILE),
+            "# indexing exclusions\ntests/fixtures/large_corpus/\n*.log\n",
+        )
+        .expect("write codexignore");
+
+        let config = SemanticIndexConfig::new(root, None).expect("config");
+
+        assert_eq!(
+            collected_paths(root, &config),
+            vec![".codexignore", "src/lib.rs", "tests/unit.rs"]
+        );
+    }
+
+    #[test]
+    fn collect_files_honors_nested_gitignores() {
+        let workspace = tempdir().expect("workspace");
+        let root = workspace.path();
+        fs::create_dir_all(root.join("target/debug")).expect("mkdir");
+        fs::create_dir_all(root.join("web/dist")).expect("mkdir");
+        fs::write(root.join("target/debug/out.rs"), "x").expect("write");
+        fs::write(root.join("web/dist/app.js"), "x").expect("write");
+        fs::write(root.join("web/keep.generated.js"), "x").expect("write");
+        fs::write(root.join("web/main.js"), "x").expect("write");
+        fs::write(root.join(GITIGNORE_FILE), "/target/\n*.generated.js\n").expect("write");
+        fs::write(
+            root.join("web").join(GITIGNORE_FILE),
+            "dist/\n!keep.generated.js\n",
+        )
+        .expect("write");
+
+        let config = SemanticIndexConfig::new(root, None).expect("config");
+
+        assert_eq!(
+            collected_paths(root, &config),
+            vec![
+                ".gitignore",
+                "web/.gitignore",
+                "web/keep.generated.js",
+                "web/main.js"
+            ]
+        );
+    }
+
+    #[test]
+    fn collect_files_with_callback_reports_each_file_once() {
+        let workspace = tempdir().expect("workspace");
+        let root = workspace.path();
+        fs::create_dir_all(root.join("src/nested")).expect("mkdir");
+        fs::write(root.join("README.md"), "x").expect("write");
+        fs::write(root.join("src/lib.rs"), "x").expect("write");
+        fs::write(root.join("src/nested/mod.rs"), "x").expect("write");
+
+        let config = SemanticIndexConfig::new(root, None).expect("config");
+        let mut seen = Vec::new();
+        let files = collect_files_with_callback(root, &config, |path| {
+            seen.push(path.to_path_buf());
+        })
+        .expect("collect");
+
+        assert_eq!(seen.len(), files.len());
+        let unique: HashSet<_> = seen.iter().collect();
+        assert_eq!(unique.len(), seen.len());
+        assert_eq!(seen, files);
+    }
+
+    #[test]
+    fn collect_files_without_codexignore_keeps_everything() {
+        let workspace = tempdir().expect("workspace");
+        fs::write(workspace.path().join("debug.log"), "x").expect("write");
+
+        let config = SemanticIndexConfig::new(workspace.path(), None).expect("config");
+
+        let files = collect_files(workspace.path(), &config).expect("collect");
+
+        assert_eq!(files, vec![workspace.path().join("debug.log")]);
+    }
+
+    #[test]
+    fn collect_files_keeps_only_include_patterns() {
+        let workspace = tempdir().expect("workspace");
+        let root = workspace.path();
+        fs::create_dir_all(root.join("src/nested")).expect("mkdir");
+        fs::write(root.join("src/lib.rs"), "x").expect("write");
+        fs::write(root.join("src/nested/mod.rs"), "x").expect("write");
+        fs::write(root.join("README.md"), "x").expect("write");
+        fs::write(root.join("Cargo.lock"), "x").expect("write");
+        let config = SemanticIndexConfig::new(
+            root,
+            Some(SemanticIndexConfigToml {
+                include_patterns: vec!["*.rs".to_string(), "*.md".to_string()],
+                ..Default::default()
+            }),
+        )
+        .expect("config");
+
+        assert_eq!(
+            collected_paths(root, &config),
+            vec!["README.md", "src/lib.rs", "src/nested/mod.rs"]
+        );
+    }
+
+    #[test]
+    fn collect_files_drops_exclude_patterns() {
+        let workspace = tempdir().expect("workspace");
+        let root = workspace.path();
+        fs::create_dir_all(root.join("web/dist")).expect("mkdir");
+        fs::write(root.join("web/app.js"), "x").expect("write");
+        fs::write(root.join("web/dist/app.min.js"), "x").expect("write");
+        fs::write(root.join("Cargo.lock"), "x").expect("write");
+        let config = SemanticIndexConfig::new(
+            root,
+            Some(SemanticIndexConfigToml {
+                exclude_patterns: vec!["*.lock".to_string(), "*.min.js".to_string()],
+                ..Default::default()
+            }),
+        )
+        .expect("config");
+
+        assert_eq!(collected_paths(root, &config), vec!["web/app.js"]);
+    }
+
+    fn extension_filter_config(
+        root: &Path,
+        include_extensions: &[&str],
+        exclude_extensions: &[&str],
+    ) -> SemanticIndexConfig {
+        SemanticIndexConfig::new(
+            root,
+            Some(SemanticIndexConfigToml {
+                include_extensions: include_extensions.iter().map(ToString::to_string).collect(),
+                exclude_extensions: exclude_extensions.iter().map(ToString::to_string).collect(),
+                ..Default::default()
+            }),
+        )
+        .expect("config")
+    }
+
+    #[test]
+    fn collect_files_filters_by_extension() {
+        let workspace = tempdir().expect("workspace");
+        let root = workspace.path();
+        fs::create_dir_all(root.join("web")).expect("mkdir");
+        for file in [
+            "lib.rs",
+            "Main.PY",
+            "README.md",
+            "Cargo.lock",
+            ".rs",
+            "web/app.js",
+            "web/app.min.js",
+        ] {
+            fs::write(root.join(file), "x").expect("write");
+        }
+
+        let include_only = extension_filter_config(root, &["rs", ".py"], &[]);
+        assert_eq!(
+            collected_paths(root, &include_only),
+            vec!["Main.PY", "lib.rs"]
+        );
+
+        let exclude_only = extension_filter_config(root, &[], &[".LOCK", "min.js"]);
+        assert_eq!(
+            collected_paths(root, &exclude_only),
+            vec![".rs", "Main.PY", "README.md", "lib.rs", "web/app.js"]
+        );
+
+        let both = extension_filter_config(root, &["js", "rs"], &["min.js"]);
+        assert_eq!(collected_paths(root, &both), vec!["lib.rs", "web/app.js"]);
+    }
+
+    #[test]
+    fn collect_files_prunes_skip_dirs() {
+        let workspace = tempdir().expect("workspace");
+        let root = workspace.path();
+        fs::create_dir_all(root.join("node_modules/pkg")).expect("mkdir");
+        fs::create_dir_all(root.join("web/node_modules/pkg")).expect("mkdir");
+        fs::write(root.join("node_modules/pkg/index.js"), "x").expect("write");
+        fs::write(root.join("web/node_modules/pkg/index.js"), "x").expect("write");
+        fs::write(root.join("web/app.js"), "x").expect("write");
+        fs::write(root.join("node_modules.md"), "x").expect("write");
+        let config = SemanticIndexConfig::new(
+            root,
+            Some(SemanticIndexConfigToml {
+                skip_dirs: vec!["node_modules".to_string()],
+                ..Default::default()
+            }),
+        )
+        .expect("config");
+
+        assert_eq!(
+            collected_paths(root, &config),
+            vec!["node_modules.md", "web/app.js"]
+        );
+        let filter = PathFilter::load(root, &config);
+        assert!(filter.in_skipped_dir(Path::new("web/node_modules/pkg/index.js")));
+        assert!(!filter.in_skipped_dir(Path::new("web/app.js")));
+    }
+
+    #[test]
+    fn estimate_build_counts_chunks_without_creating_index() {
+        let workspace = tempdir().expect("workspace");
+        fs::write(workspace.path().join("a.txt"), "one\ntwo\nthree\n").expect("write a");
+        fs::write(workspace.path().join("b.txt"), "four\n").expect("write b");
+        fs::write(workspace.path().join("empty.txt"), "").expect("write empty");
+        fs::write(workspace.path().join("bin.dat"), [0u8, 1, 2]).expect("write bin");
+        let mut config = SemanticIndexConfig::new(workspace.path(), None).expect("config");
+        config.chunk.max_lines = 2;
+        let index = SemanticIndex::new(
+            workspace.path().to_path_buf(),
+            config.clone(),
+            ModelProviderInfo::create_openai_provider(),
+            None,
+        );
+
+        let estimate = index.estimate_build().expect("estimate");
+
+        assert_eq!(
+            estimate,
+            BuildEstimate {
+                file_count: 2,
+                chunk_count: 3,
+                line_count: 4,
+                chunk_bytes: "one\ntwo".len() + "three".len() + "four".len(),
+                estimated_tokens: 2 + 2 + 1,
+            }
+        );
+        assert_eq!(estimate.projected_cost(2.0), 0.01);
+        assert!(!config.dir.as_path().exists());
+    }
 
     #[test]
     fn chunk_lines_splits_by_max_lines() {
@@ -368,6 +2993,194 @@ mod tests {
         assert_eq!(chunks, expected);
     }
 
+    fn record(file_path: &str, embedding: Vec<f32>, mtime: i64) -> EmbeddingRecord {
+        EmbeddingRecord {
+            file_path: file_path.to_string(),
+            chunk_id: format!("{file_path}-chunk"),
+            start_line: 1,
+            end_line: 1,
+            chunk_text: None,
+            mtime: Some(mtime),
+            embedding,
+        }
+    }
+
+    #[test]
+    fn rank_candidates_without_recency_uses_cosine() {
+        let candidates = vec![
+            record("old.rs", vec![1.0, 0.0], 100),
+            record("new.rs", vec![0.0, 1.0], 200),
+        ];
+
+        let ranked = rank_candidates(&[1.0, 0.1], candidates, DistanceMetric::Cosine, 0.0);
+
+        let order: Vec<&str> = ranked.iter().map(|hit| hit.file_path.as_str()).collect();
+        assert_eq!(order, vec!["old.rs", "new.rs"]);
+    }
+
+    #[test]
+    fn rank_candidates_full_recency_prefers_newest_file() {
+        let candidates = vec![
+            record("old.rs", vec![1.0, 0.0], 100),
+            record("middle.rs", vec![0.7, 0.7], 150),
+            record("new.rs", vec![0.0, 1.0], 200),
+        ];
+
+        let ranked = rank_candidates(&[1.0, 0.0], candidates, DistanceMetric::Cosine, 1.0);
+
+        let order: Vec<&str> = ranked.iter().map(|hit| hit.file_path.as_str()).collect();
+        assert_eq!(order, vec!["new.rs", "middle.rs", "old.rs"]);
+    }
+
+    #[test]
+    fn fuse_rankings_prefers_chunks_ranked_by_both_lists() {
+        let hits = vec![
+            unscored_hit(record("a.rs", vec![1.0], 1)),
+            unscored_hit(record("b.rs", vec![1.0], 1)),
+            unscored_hit(record("c.rs", vec![1.0], 1)),
+        ];
+        let semantic = vec!["a.rs-chunk".to_string(), "b.rs-chunk".to_string()];
+        let keyword = vec!["b.rs-chunk".to_string(), "c.rs-chunk".to_string()];
+
+        let fused = fuse_rankings(&[semantic, keyword], hits);
+
+        let order: Vec<&str> = fused.iter().map(|hit| hit.file_path.as_str()).collect();
+        assert_eq!(order, vec!["b.rs", "a.rs", "c.rs"]);
+    }
+
+    /// Lines as produced by `str::lines`: no newlines, often blank.
+    fn arbitrary_line() -> impl Strategy<Value = String> {
+        prop_oneof![
+            Just(String::new()),
+            "[ \t]{1,3}",
+            "[a-z]",
+            "[ a-z_(){};]{1,40}",
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn chunk_lines_covers_every_non_blank_line(
+            lines in prop::collection::vec(arbitrary_line(), 0..=500),
+            max_lines in 1usize..=200,
+        ) {
+            let chunks = chunk_lines(&lines, max_lines);
+
+            let mut previous_end = 0;
+            for chunk in &chunks {
+                prop_assert!(!chunk.text.trim().is_empty());
+                prop_assert!(chunk.end_line >= chunk.start_line);
+                prop_assert!(chunk.start_line > previous_end);
+                prop_assert!(chunk.end_line - chunk.start_line < max_lines);
+                prop_assert_eq!(
+                    &chunk.text,
+                    &lines[chunk.start_line - 1..chunk.end_line].join("\n")
+                );
+                previous_end = chunk.end_line;
+            }
+            prop_assert!(previous_end <= lines.len());
+            for (index, line) in lines.iter().enumerate() {
+                let line_number = index + 1;
+                let covering = chunks
+                    .iter()
+                    .filter(|chunk| (chunk.start_line..=chunk.end_line).contains(&line_number))
+                    .count();
+                if line.trim().is_empty() {
+                    prop_assert!(covering <= 1);
+                } else {
+                    prop_assert_eq!(covering, 1, "line {} is not covered once", line_number);
+                }
+            }
+        }
+
+        #[test]
+        fn chunk_lines_skips_whitespace_only_input(
+            lines in prop::collection::vec("[ \t]{0,4}", 0..=500),
+            max_lines in 1usize..=200,
+        ) {
+            prop_assert!(chunk_lines(&lines, max_lines).is_empty());
+        }
+    }
+
+    #[test]
+    fn decode_indexable_text_handles_utf16_with_bom() {
+        let text = "fn main() {}\n";
+        let mut utf16 = vec![0xff, 0xfe];
+        utf16.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+
+        assert_eq!(decode_indexable_text(&utf16).as_deref(), Some(text));
+        assert_eq!(decode_indexable_text(&utf16[2..]), None);
+    }
+
+    #[test]
+    fn decode_indexable_text_skips_binary_without_nul_bytes() {
+        let binary: Vec<u8> = (1..=u8::MAX).cycle().take(4096).collect();
+        assert!(!binary.contains(&0));
+
+        assert_eq!(decode_indexable_text(&binary), None);
+        assert_eq!(decode_indexable_text(b""), None);
+        assert_eq!(
+            decode_indexable_text(b"caf\xe9 au lait\n").as_deref(),
+            Some("caf\u{fffd} au lait\n")
+        );
+    }
+
+    #[test]
+    fn chunk_text_matches_chunk_lines() {
+        let text = "one\ntwo\n\n  \nfive";
+
+        let chunks = chunk_text(text, ChunkingConfig { max_lines: 2 });
+
+        assert_eq!(
+            chunks,
+            vec![
+                Chunk {
+                    start_line: 1,
+                    end_line: 2,
+                    text: "one\ntwo".to_string(),
+                },
+                Chunk {
+                    start_line: 5,
+                    end_line: 5,
+                    text: "five".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn cosine_and_dot_product_rank_unit_vectors_identically() {
+        let candidates: Vec<EmbeddingRecord> = (0..12)
+            .map(|i| {
+                let angle = i as f32 * 0.53;
+                record(&format!("file{i}.rs"), vec![angle.cos(), angle.sin()], 0)
+            })
+            .collect();
+        let query = [0.6, 0.8];
+        let ids = |metric| {
+            rank_candidates(&query, candidates.clone(), metric, 0.0)
+                .into_iter()
+                .map(|hit| hit.chunk_id)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ids(DistanceMetric::Cosine), ids(DistanceMetric::DotProduct));
+        assert_eq!(ids(DistanceMetric::Cosine), ids(DistanceMetric::Euclidean));
+    }
+
+    #[test]
+    fn score_supports_cosine_and_dot_product() {
+        let query = [3.0, 4.0];
+        let candidate = [6.0, 8.0];
+
+        assert_eq!(score(&query, &candidate, DistanceMetric::Cosine), Some(1.0));
+        assert_eq!(
+            score(&query, &candidate, DistanceMetric::DotProduct),
+            Some(50.0)
+        );
+        assert_eq!(score(&query, &[], DistanceMetric::DotProduct), None);
+    }
+
     #[test]
     fn cosine_similarity_returns_none_for_mismatch() {
         let a = vec![1.0_f32, 2.0_f32];
@@ -1,5 +1,8 @@
 pub mod config;
 pub mod embedding;
+pub mod error;
+#[cfg(feature = "hnsw")]
+mod hnsw;
 pub mod index;
 pub mod vector_store;
 
@@ -1,33 +1,163 @@
+use crate::semantic::index::DistanceMetric;
 use anyhow::Context;
 use anyhow::Result;
 use chrono::DateTime;
 use chrono::Utc;
+use half::f16;
 use rusqlite::Connection;
+use rusqlite::OptionalExtension;
+use rusqlite::Transaction;
+use rusqlite::TransactionBehavior;
 use rusqlite::params;
+use serde::Deserialize;
+use std::cell::Cell;
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
+use std::str::FromStr;
 use thiserror::Error;
 
 const DB_FILE_NAME: &str = "index.sqlite";
+/// Path reported by stores created with [`VectorStore::in_memory`].
+const IN_MEMORY_DB_PATH: &str = ":memory:";
+/// Files SQLite keeps next to the database while in WAL mode.
+const DB_SIDECAR_SUFFIXES: [&str; 2] = ["-wal", "-shm"];
+/// Pragmas that only tune performance and may be set from config.
+const ALLOWED_PRAGMAS: &[&str] = &[
+    "busy_timeout",
+    "cache_size",
+    "journal_mode",
+    "mmap_size",
+    "synchronous",
+    "temp_store",
+    "wal_autocheckpoint",
+];
+const INSERT_FILE_SQL: &str =
+    "INSERT OR REPLACE INTO files (path, content_hash, mtime, size) VALUES (?, ?, ?, ?)";
+const INSERT_CHUNK_SQL: &str = "INSERT OR REPLACE INTO chunks (file_path, chunk_id, start_line, end_line, text_hash, text, embedding, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)";
 
+/// Metadata for one index. Stored `file_path`s are workspace-relative and
+/// always use `/` as the separator, whatever platform built the index; see
+/// [`normalize_index_path`] and [`index_path_to_native`].
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct IndexMeta {
     pub schema_version: i32,
     pub embedding_model: String,
     pub dim: usize,
     pub chunk_size: usize,
+    /// Similarity metric the index was built for.
+    pub metric: DistanceMetric,
     pub created_at: DateTime<Utc>,
     pub workspace_fingerprint: String,
+    /// `HEAD` of the workspace repository when the index was built; `None`
+    /// outside a git repository.
+    pub git_commit: Option<String>,
+    /// How stored embeddings are encoded.
+    pub quantization: EmbeddingQuantization,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// How embedding values are encoded in the `embedding` BLOB column.
+/// Embeddings are always `f32` in memory; `F16` halves the stored size at the
+/// cost of roughly three significant digits of precision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddingQuantization {
+    /// Little-endian `f32`, 4 bytes per value.
+    #[default]
+    None,
+    /// Little-endian IEEE half precision, 2 bytes per value.
+    F16,
+}
+
+impl EmbeddingQuantization {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EmbeddingQuantization::None => "none",
+            EmbeddingQuantization::F16 => "f16",
+        }
+    }
+
+    /// Stored size of one embedding value.
+    pub fn bytes_per_value(self) -> usize {
+        match self {
+            EmbeddingQuantization::None => std::mem::size_of::<f32>(),
+            EmbeddingQuantization::F16 => std::mem::size_of::<f16>(),
+        }
+    }
+
+    /// Serializes `embedding` for the `embedding` BLOB column.
+    pub fn encode(self, embedding: &[f32]) -> Vec<u8> {
+        match self {
+            EmbeddingQuantization::None => encode_embedding(embedding),
+            EmbeddingQuantization::F16 => {
+                let mut buf = Vec::with_capacity(embedding.len() * self.bytes_per_value());
+                for value in embedding {
+                    buf.extend_from_slice(&f16::from_f32(*value).to_le_bytes());
+                }
+                buf
+            }
+        }
+    }
+
+    /// Inverse of [`Self::encode`], always returning `f32` values.
+    pub fn decode(self, bytes: &[u8]) -> std::result::Result<Vec<f32>, EmbeddingDecodeError> {
+        match self {
+            EmbeddingQuantization::None => decode_embedding(bytes),
+            EmbeddingQuantization::F16 => {
+                let size = self.bytes_per_value();
+                if !bytes.len().is_multiple_of(size) {
+                    return Err(EmbeddingDecodeError {
+                        len: bytes.len(),
+                        element_size: size,
+                    });
+                }
+                Ok(bytes
+                    .chunks_exact(size)
+                    .map(|chunk| f16::from_le_bytes([chunk[0], chunk[1]]).to_f32())
+                    .collect())
+            }
+        }
+    }
+}
+
+impl FromStr for EmbeddingQuantization {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> anyhow::Result<Self> {
+        match value {
+            "none" => Ok(EmbeddingQuantization::None),
+            "f16" => Ok(EmbeddingQuantization::F16),
+            other => anyhow::bail!("unknown embedding quantization `{other}`"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct IndexStats {
     pub file_count: usize,
     pub chunk_count: usize,
     pub embedding_model: Option<String>,
     pub embedding_dim: Option<usize>,
     pub created_at: Option<DateTime<Utc>>,
+    pub git_commit: Option<String>,
+    /// Storage taken by the stored embeddings (`chunk_count * dim` times 4
+    /// bytes, or 2 with `f16` quantization); `None` for an empty index.
+    pub total_embedding_bytes: Option<u64>,
+    /// Mean number of lines per chunk; `None` for an empty index.
+    pub avg_chunk_lines: Option<f64>,
+    /// Whether workspace files changed after `created_at`. Only
+    /// `SemanticIndex::stats` checks the workspace; the store alone always
+    /// reports `false`.
+    pub stale: bool,
+    /// Chunks whose embedding was copied from an identical chunk instead of
+    /// requested from the API. Only `SemanticIndex::build` reports it; the
+    /// store alone always reports 0.
+    pub reused_embeddings: usize,
+    /// Tokens the embeddings API reported for the last build, when
+    /// `semantic_index.track_tokens` is on. Like `reused_embeddings`, only
+    /// `SemanticIndex::build` reports it.
+    pub tokens_used: Option<u64>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -45,6 +175,7 @@ pub struct ChunkEntry {
     pub start_line: usize,
     pub end_line: usize,
     pub text_hash: String,
+    pub text: String,
     pub embedding: Vec<f32>,
     pub updated_at: DateTime<Utc>,
 }
@@ -55,6 +186,10 @@ pub struct EmbeddingRecord {
     pub chunk_id: String,
     pub start_line: usize,
     pub end_line: usize,
+    /// Chunk text, when the index was built with a schema that stores it.
+    pub chunk_text: Option<String>,
+    /// Modification time of the owning file, when it is recorded in `files`.
+    pub mtime: Option<i64>,
     pub embedding: Vec<f32>,
 }
 
@@ -68,8 +203,17 @@ pub enum StoreMode {
 pub struct VectorStore {
     conn: Connection,
     db_path: PathBuf,
+    /// Encoding of the `embedding` column, loaded from the metadata on open.
+    quantization: Cell<EmbeddingQuantization>,
 }
 
+// `SemanticIndex` futures hold a store across awaits and are spawned on
+// tokio, so it must stay `Send`.
+const _: fn() = || {
+    fn assert_send<T: Send>() {}
+    assert_send::<VectorStore>();
+};
+
 impl VectorStore {
     pub fn open(dir: &Path, mode: StoreMode) -> Result<Self> {
         fs::create_dir_all(dir).with_context(|| {
@@ -80,13 +224,7 @@ impl VectorStore {
         })?;
         let db_path = dir.join(DB_FILE_NAME);
         match mode {
-            StoreMode::Reset => {
-                if db_path.exists() {
-                    fs::remove_file(&db_path).with_context(|| {
-                        format!("failed to remove semantic index {}", db_path.display())
-                    })?;
-                }
-            }
+            StoreMode::Reset => remove_db_files(&db_path)?,
             StoreMode::OpenExisting => {
                 if !db_path.exists() {
                     anyhow::bail!("semantic index not found at {}", db_path.display());
@@ -97,63 +235,191 @@ impl VectorStore {
 
         let conn = Connection::open(&db_path)
             .with_context(|| format!("failed to open semantic index {}", db_path.display()))?;
-        let store = Self { conn, db_path };
+        let store = Self {
+            conn,
+            db_path,
+            quantization: Cell::new(EmbeddingQuantization::None),
+        };
+        store.init_schema()?;
+        store.quantization.set(store.stored_quantization()?);
+        Ok(store)
+    }
+
+    /// Returns whether an index database has been created under `dir`.
+    pub fn exists(dir: &Path) -> bool {
+        dir.join(DB_FILE_NAME).exists()
+    }
+
+    /// Opens a store backed by a private in-memory database that is discarded
+    /// when the store is dropped.
+    pub fn in_memory() -> Result<Self> {
+        let conn =
+            Connection::open_in_memory().context("failed to open in-memory semantic index")?;
+        let store = Self {
+            conn,
+            db_path: PathBuf::from(IN_MEMORY_DB_PATH),
+            quantization: Cell::new(EmbeddingQuantization::None),
+        };
         store.init_schema()?;
         Ok(store)
     }
 
     pub fn clear(dir: &Path) -> Result<()> {
-        let db_path = dir.join(DB_FILE_NAME);
-        if db_path.exists() {
-            fs::remove_file(&db_path).with_context(|| {
-                format!("failed to remove semantic index {}", db_path.display())
-            })?;
+        remove_db_files(&dir.join(DB_FILE_NAME))
+    }
+
+    /// Sets `pragma` to `value`; only names in [`ALLOWED_PRAGMAS`] are accepted.
+    pub fn set_pragma(&self, pragma: &str, value: &str) -> Result<()> {
+        if !ALLOWED_PRAGMAS.contains(&pragma) {
+            anyhow::bail!(
+                "unsupported semantic index pragma `{pragma}`; allowed: {}",
+                ALLOWED_PRAGMAS.join(", ")
+            );
         }
+        self.conn
+            .pragma_update(None, pragma, value)
+            .with_context(|| format!("failed to set semantic index pragma `{pragma}`"))?;
         Ok(())
     }
 
+    /// Sets how embeddings stored from now on are encoded. Call it before
+    /// storing chunks into a fresh index; an existing index keeps the
+    /// encoding recorded in its metadata.
+    pub fn set_quantization(&self, quantization: EmbeddingQuantization) {
+        self.quantization.set(quantization);
+    }
+
+    pub fn quantization(&self) -> EmbeddingQuantization {
+        self.quantization.get()
+    }
+
     pub fn store_meta(&self, meta: &IndexMeta) -> Result<()> {
         let created_at = meta.created_at.to_rfc3339();
         self.conn.execute("DELETE FROM meta", [])?;
         self.conn.execute(
-            "INSERT INTO meta (id, schema_version, embedding_model, dim, chunk_size, created_at, workspace_fingerprint)
-             VALUES (1, ?, ?, ?, ?, ?, ?)",
+            "INSERT INTO meta (id, schema_version, embedding_model, dim, chunk_size, metric, created_at, workspace_fingerprint, git_commit, quantization)
+             VALUES (1, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
             params![
                 meta.schema_version,
                 meta.embedding_model,
                 meta.dim as i64,
                 meta.chunk_size as i64,
+                meta.metric.as_str(),
                 created_at,
-                meta.workspace_fingerprint
+                meta.workspace_fingerprint,
+                meta.git_commit,
+                meta.quantization.as_str()
             ],
         )?;
+        self.quantization.set(meta.quantization);
         Ok(())
     }
 
-    pub fn store_file(&self, file: &FileEntry) -> Result<()> {
+    /// Records an incremental update: moves the build time to `updated_at` and
+    /// the commit to `git_commit`, leaving the rest of the metadata alone.
+    pub fn record_update(&self, updated_at: DateTime<Utc>, git_commit: Option<&str>) -> Result<()> {
         self.conn.execute(
-            "INSERT OR REPLACE INTO files (path, content_hash, mtime, size) VALUES (?, ?, ?, ?)",
-            params![file.path, file.content_hash, file.mtime, file.size as i64],
+            "UPDATE meta SET created_at = ?, git_commit = ? WHERE id = 1",
+            params![updated_at.to_rfc3339(), git_commit],
         )?;
         Ok(())
     }
 
+    /// Encoding recorded in the metadata; `None` before the first build and
+    /// for indexes created before quantization was recorded.
+    fn stored_quantization(&self) -> Result<EmbeddingQuantization> {
+        let quantization: Option<String> = self
+            .conn
+            .query_row("SELECT quantization FROM meta WHERE id = 1", [], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        Ok(quantization
+            .map(|quantization| quantization.parse())
+            .transpose()?
+            .unwrap_or_default())
+    }
+
+    pub fn store_file(&self, file: &FileEntry) -> Result<()> {
+        insert_file(&self.conn, file)
+    }
+
+    /// Stores `files` in a single transaction.
+    pub fn store_files(&self, files: &[FileEntry]) -> Result<()> {
+        let tx = Transaction::new_unchecked(&self.conn, TransactionBehavior::Immediate)?;
+        for file in files {
+            insert_file(&tx, file)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn get_file(&self, path: &str) -> Result<Option<FileEntry>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT path, content_hash, mtime, size FROM files WHERE path = ?",
+                params![normalize_index_path(path)],
+                |row| {
+                    Ok(FileEntry {
+                        path: row.get(0)?,
+                        content_hash: row.get(1)?,
+                        mtime: row.get(2)?,
+                        size: row.get::<_, i64>(3)? as u64,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    /// Returns the embedding of any stored chunk with `text_hash`, so chunks
+    /// with identical text can share one embedding.
+    pub fn embedding_for_text_hash(&self, text_hash: &str) -> Result<Option<Vec<f32>>> {
+        let blob: Option<Vec<u8>> = self
+            .conn
+            .query_row(
+                "SELECT embedding FROM chunks WHERE text_hash = ? LIMIT 1",
+                params![text_hash],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let quantization = self.quantization.get();
+        Ok(blob.map(|blob| quantization.decode(&blob)).transpose()?)
+    }
+
+    /// Removes `path` and all of its chunks.
+    pub fn delete_file(&self, path: &str) -> Result<()> {
+        let tx = Transaction::new_unchecked(&self.conn, TransactionBehavior::Immediate)?;
+        delete_file_rows(&tx, path)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Replaces everything stored for `file.path` with `file` and `chunks` in a
+    /// single transaction.
+    pub fn replace_file(&self, file: &FileEntry, chunks: &[ChunkEntry]) -> Result<()> {
+        let tx = Transaction::new_unchecked(&self.conn, TransactionBehavior::Immediate)?;
+        delete_file_rows(&tx, &file.path)?;
+        insert_file(&tx, file)?;
+        for chunk in chunks {
+            insert_chunk(&tx, chunk, self.quantization.get())?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
     pub fn store_chunk(&self, chunk: &ChunkEntry) -> Result<()> {
-        let updated_at = chunk.updated_at.to_rfc3339();
-        let embedding = encode_embedding(&chunk.embedding);
-        self.conn.execute(
-            "INSERT OR REPLACE INTO chunks (file_path, chunk_id, start_line, end_line, text_hash, embedding, updated_at)
-             VALUES (?, ?, ?, ?, ?, ?, ?)",
-            params![
-                chunk.file_path,
-                chunk.chunk_id,
-                chunk.start_line as i64,
-                chunk.end_line as i64,
-                chunk.text_hash,
-                embedding,
-                updated_at
-            ],
-        )?;
+        insert_chunk(&self.conn, chunk, self.quantization.get())
+    }
+
+    /// Stores `chunks` in a single transaction, so a whole file's chunks cost
+    /// one commit instead of one per chunk.
+    pub fn store_chunks(&self, chunks: &[ChunkEntry]) -> Result<()> {
+        let tx = Transaction::new_unchecked(&self.conn, TransactionBehavior::Immediate)?;
+        for chunk in chunks {
+            insert_chunk(&tx, chunk, self.quantization.get())?;
+        }
+        tx.commit()?;
         Ok(())
     }
 
@@ -168,51 +434,118 @@ impl VectorStore {
             .query_row("SELECT COUNT(*) FROM chunks", [], |row| {
                 Ok(row.get::<_, i64>(0)? as usize)
             })?;
-        let mut stmt = self
-            .conn
-            .prepare("SELECT embedding_model, dim, created_at FROM meta WHERE id = 1 LIMIT 1")?;
+        let mut stmt = self.conn.prepare(
+            "SELECT embedding_model, dim, created_at, git_commit FROM meta WHERE id = 1 LIMIT 1",
+        )?;
         let mut rows = stmt.query([])?;
         let meta_row = rows.next()?;
-        let (embedding_model, embedding_dim, created_at) = if let Some(row) = meta_row {
+        let (embedding_model, embedding_dim, created_at, git_commit) = if let Some(row) = meta_row {
             let model: String = row.get(0)?;
             let dim = row.get::<_, i64>(1)? as usize;
             let created_at: String = row.get(2)?;
             let parsed = DateTime::parse_from_rfc3339(&created_at)
                 .map(|dt| dt.with_timezone(&Utc))
                 .ok();
-            (Some(model), Some(dim), parsed)
+            (Some(model), Some(dim), parsed, row.get(3)?)
         } else {
-            (None, None, None)
+            (None, None, None, None)
         };
+        let total_embedding_bytes = embedding_dim
+            .filter(|_| chunk_count > 0)
+            .map(|dim| (chunk_count * dim * self.quantization.get().bytes_per_value()) as u64);
+        let avg_chunk_lines: Option<f64> = self.conn.query_row(
+            "SELECT AVG(end_line - start_line + 1) FROM chunks",
+            [],
+            |row| row.get(0),
+        )?;
         Ok(IndexStats {
             file_count,
             chunk_count,
             embedding_model,
             embedding_dim,
             created_at,
+            git_commit,
+            total_embedding_bytes,
+            avg_chunk_lines,
+            stale: false,
+            reused_embeddings: 0,
+            tokens_used: None,
         })
     }
 
-    pub fn list_embeddings(&self) -> Result<Vec<EmbeddingRecord>> {
-        let mut stmt = self
+    /// Returns the similarity metric recorded in the index metadata, or
+    /// `None` before the first build. Indexes created before the metric was
+    /// recorded report cosine.
+    pub fn metric(&self) -> Result<Option<DistanceMetric>> {
+        let metric: Option<String> = self
             .conn
-            .prepare("SELECT file_path, chunk_id, start_line, end_line, embedding FROM chunks")?;
-        let rows = stmt.query_map([], |row| {
-            let embedding: Vec<u8> = row.get(4)?;
-            let embedding = decode_embedding(&embedding).map_err(|err| {
-                rusqlite::Error::FromSqlConversionFailure(
-                    embedding.len(),
-                    rusqlite::types::Type::Blob,
-                    Box::new(err),
-                )
-            })?;
-            Ok(EmbeddingRecord {
-                file_path: row.get(0)?,
-                chunk_id: row.get(1)?,
-                start_line: row.get::<_, i64>(2)? as usize,
-                end_line: row.get::<_, i64>(3)? as usize,
-                embedding,
-            })
+            .query_row("SELECT metric FROM meta WHERE id = 1", [], |row| row.get(0))
+            .optional()?;
+        metric.map(|metric| metric.parse()).transpose()
+    }
+
+    /// Fails when stored embeddings are not `meta.dim` wide, e.g. because the
+    /// index was built with a different embedding model. An index without
+    /// metadata or chunks is accepted.
+    pub fn validate_dimensions(&self) -> Result<()> {
+        match self.dimension_mismatch()? {
+            Some((expected, actual)) => anyhow::bail!(
+                "semantic index embeddings have {actual} dimensions but the index metadata \
+                 expects {expected}; rebuild it with `codex index build`"
+            ),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns `(expected, actual)` when stored embeddings disagree with the
+    /// dimension recorded in the index metadata.
+    pub fn dimension_mismatch(&self) -> Result<Option<(usize, usize)>> {
+        let Some(expected) = self.stats()?.embedding_dim else {
+            return Ok(None);
+        };
+        let actual = self
+            .conn
+            .query_row(
+                "SELECT LENGTH(embedding) / ? FROM chunks LIMIT 1",
+                params![self.quantization.get().bytes_per_value() as i64],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()?;
+        Ok(actual
+            .map(|actual| actual as usize)
+            .filter(|actual| *actual != expected)
+            .map(|actual| (expected, actual)))
+    }
+
+    pub fn list_embeddings(&self) -> Result<Vec<EmbeddingRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT chunks.file_path, chunks.chunk_id, chunks.start_line, chunks.end_line, chunks.text, files.mtime, chunks.embedding
+             FROM chunks LEFT JOIN files ON files.path = chunks.file_path",
+        )?;
+        let quantization = self.quantization.get();
+        let rows = stmt.query_map([], |row| embedding_record_from_row(row, quantization))?;
+        let mut records = Vec::new();
+        for row in rows {
+            records.push(row?);
+        }
+        Ok(records)
+    }
+
+    /// Returns up to `limit` embeddings stored after `after_rowid`, each with
+    /// its chunk rowid; pass the last rowid back in to fetch the next page.
+    pub fn list_embeddings_page(
+        &self,
+        after_rowid: i64,
+        limit: usize,
+    ) -> Result<Vec<(i64, EmbeddingRecord)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT chunks.file_path, chunks.chunk_id, chunks.start_line, chunks.end_line, chunks.text, files.mtime, chunks.embedding, chunks.rowid
+             FROM chunks LEFT JOIN files ON files.path = chunks.file_path
+             WHERE chunks.rowid > ? ORDER BY chunks.rowid LIMIT ?",
+        )?;
+        let quantization = self.quantization.get();
+        let rows = stmt.query_map(params![after_rowid, limit as i64], |row| {
+            Ok((row.get(7)?, embedding_record_from_row(row, quantization)?))
         })?;
         let mut records = Vec::new();
         for row in rows {
@@ -221,7 +554,47 @@ impl VectorStore {
         Ok(records)
     }
 
+    /// Oldest and newest mtime among files that have chunks, as seen by
+    /// [`Self::list_embeddings`].
+    pub fn chunk_mtime_range(&self) -> Result<Option<(i64, i64)>> {
+        let range = self.conn.query_row(
+            "SELECT MIN(files.mtime), MAX(files.mtime)
+             FROM chunks JOIN files ON files.path = chunks.file_path",
+            [],
+            |row| Ok((row.get::<_, Option<i64>>(0)?, row.get::<_, Option<i64>>(1)?)),
+        )?;
+        Ok(match range {
+            (Some(oldest), Some(newest)) => Some((oldest, newest)),
+            _ => None,
+        })
+    }
+
+    /// Returns chunk ids whose text matches `query`, best match first.
+    ///
+    /// Each whitespace-separated term is quoted so identifiers and symbols are
+    /// matched literally instead of being parsed as FTS5 query syntax.
+    pub fn fts_search(&self, query: &str, limit: usize) -> Result<Vec<String>> {
+        let match_expr = fts_match_expression(query);
+        if match_expr.is_empty() || limit == 0 {
+            return Ok(Vec::new());
+        }
+        let mut stmt = self.conn.prepare(
+            "SELECT chunk_id FROM chunks_fts WHERE chunks_fts MATCH ? ORDER BY rank LIMIT ?",
+        )?;
+        let rows = stmt.query_map(params![match_expr, limit as i64], |row| {
+            row.get::<_, String>(0)
+        })?;
+        let mut chunk_ids = Vec::new();
+        for row in rows {
+            chunk_ids.push(row?);
+        }
+        Ok(chunk_ids)
+    }
+
     fn init_schema(&self) -> Result<()> {
+        // WAL lets searches read while a build is writing.
+        self.conn.pragma_update(None, "journal_mode", "WAL")?;
+        self.conn.pragma_update(None, "synchronous", "NORMAL")?;
         self.conn.execute_batch(
             "CREATE TABLE IF NOT EXISTS meta (
                 id INTEGER PRIMARY KEY CHECK (id = 1),
@@ -244,10 +617,51 @@ impl VectorStore {
                 start_line INTEGER NOT NULL,
                 end_line INTEGER NOT NULL,
                 text_hash TEXT NOT NULL,
+                text TEXT,
                 embedding BLOB NOT NULL,
                 updated_at TEXT NOT NULL
             );
-            CREATE INDEX IF NOT EXISTS chunks_by_file ON chunks(file_path);",
+            CREATE INDEX IF NOT EXISTS chunks_by_file ON chunks(file_path);
+            CREATE INDEX IF NOT EXISTS chunks_by_text_hash ON chunks(text_hash);",
+        )?;
+        self.ensure_column("chunks", "text", "TEXT")?;
+        self.ensure_column("meta", "metric", "TEXT NOT NULL DEFAULT 'cosine'")?;
+        self.ensure_column("meta", "git_commit", "TEXT")?;
+        self.ensure_column("meta", "quantization", "TEXT NOT NULL DEFAULT 'none'")?;
+        self.conn.execute_batch(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS chunks_fts USING fts5(chunk_id UNINDEXED, text);
+            CREATE TRIGGER IF NOT EXISTS chunks_fts_insert AFTER INSERT ON chunks BEGIN
+                DELETE FROM chunks_fts WHERE chunk_id = new.chunk_id;
+                INSERT INTO chunks_fts (chunk_id, text) VALUES (new.chunk_id, COALESCE(new.text, ''));
+            END;
+            CREATE TRIGGER IF NOT EXISTS chunks_fts_delete AFTER DELETE ON chunks BEGIN
+                DELETE FROM chunks_fts WHERE chunk_id = old.chunk_id;
+            END;",
+        )?;
+        // Indexes built before full-text search existed have chunks the
+        // triggers never saw; index them so hybrid search can find them.
+        self.conn.execute(
+            "INSERT INTO chunks_fts (chunk_id, text)
+             SELECT chunk_id, COALESCE(text, '') FROM chunks
+             WHERE chunk_id NOT IN (SELECT chunk_id FROM chunks_fts)",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Adds `column` to `table` when an index created by an older build lacks it.
+    fn ensure_column(&self, table: &str, column: &str, definition: &str) -> Result<()> {
+        let mut stmt = self.conn.prepare(&format!("PRAGMA table_info({table})"))?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let name: String = row.get(1)?;
+            if name == column {
+                return Ok(());
+            }
+        }
+        self.conn.execute(
+            &format!("ALTER TABLE {table} ADD COLUMN {column} {definition}"),
+            [],
         )?;
         Ok(())
     }
@@ -257,7 +671,82 @@ impl VectorStore {
     }
 }
 
-fn encode_embedding(embedding: &[f32]) -> Vec<u8> {
+fn remove_db_files(db_path: &Path) -> Result<()> {
+    let mut paths = vec![db_path.to_path_buf()];
+    for suffix in DB_SIDECAR_SUFFIXES {
+        let mut sidecar = db_path.as_os_str().to_os_string();
+        sidecar.push(suffix);
+        paths.push(PathBuf::from(sidecar));
+    }
+    for path in paths {
+        if path.exists() {
+            fs::remove_file(&path)
+                .with_context(|| format!("failed to remove semantic index {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+fn delete_file_rows(conn: &Connection, path: &str) -> Result<()> {
+    let path = normalize_index_path(path);
+    conn.execute("DELETE FROM chunks WHERE file_path = ?", params![path])?;
+    conn.execute("DELETE FROM files WHERE path = ?", params![path])?;
+    Ok(())
+}
+
+fn insert_file(conn: &Connection, file: &FileEntry) -> Result<()> {
+    conn.prepare_cached(INSERT_FILE_SQL)?.execute(params![
+        normalize_index_path(&file.path),
+        file.content_hash,
+        file.mtime,
+        file.size as i64
+    ])?;
+    Ok(())
+}
+
+fn insert_chunk(
+    conn: &Connection,
+    chunk: &ChunkEntry,
+    quantization: EmbeddingQuantization,
+) -> Result<()> {
+    let updated_at = chunk.updated_at.to_rfc3339();
+    let embedding = quantization.encode(&chunk.embedding);
+    conn.prepare_cached(INSERT_CHUNK_SQL)?.execute(params![
+        normalize_index_path(&chunk.file_path),
+        chunk.chunk_id,
+        chunk.start_line as i64,
+        chunk.end_line as i64,
+        chunk.text_hash,
+        chunk.text,
+        embedding,
+        updated_at
+    ])?;
+    Ok(())
+}
+
+/// Converts a workspace-relative path to the `/`-separated form stored in the
+/// index, so an index built on Windows resolves the same files elsewhere.
+pub fn normalize_index_path(path: &str) -> String {
+    path.replace('\\', "/")
+}
+
+/// Converts a stored index path back to a workspace-relative path using this
+/// platform's separator.
+pub fn index_path_to_native(path: &str) -> PathBuf {
+    normalize_index_path(path).split('/').collect()
+}
+
+fn fts_match_expression(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" OR ")
+}
+
+/// Serializes an embedding as little-endian `f32` bytes for the `embedding`
+/// BLOB column.
+pub fn encode_embedding(embedding: &[f32]) -> Vec<u8> {
     let mut buf = Vec::with_capacity(std::mem::size_of_val(embedding));
     for value in embedding {
         buf.extend_from_slice(&value.to_le_bytes());
@@ -267,12 +756,39 @@ fn encode_embedding(embedding: &[f32]) -> Vec<u8> {
 
 #[derive(Debug, Error)]
 #[error("embedding blob length {len} is not a multiple of {element_size}")]
-struct EmbeddingDecodeError {
+pub struct EmbeddingDecodeError {
     len: usize,
     element_size: usize,
 }
 
-fn decode_embedding(bytes: &[u8]) -> std::result::Result<Vec<f32>, EmbeddingDecodeError> {
+/// Maps the leading `file_path, chunk_id, start_line, end_line, text,
+/// mtime, embedding` columns of an embeddings query.
+fn embedding_record_from_row(
+    row: &rusqlite::Row<'_>,
+    quantization: EmbeddingQuantization,
+) -> rusqlite::Result<EmbeddingRecord> {
+    let embedding: Vec<u8> = row.get(6)?;
+    let embedding = quantization.decode(&embedding).map_err(|err| {
+        rusqlite::Error::FromSqlConversionFailure(
+            embedding.len(),
+            rusqlite::types::Type::Blob,
+            Box::new(err),
+        )
+    })?;
+    Ok(EmbeddingRecord {
+        file_path: row.get(0)?,
+        chunk_id: row.get(1)?,
+        start_line: row.get::<_, i64>(2)? as usize,
+        end_line: row.get::<_, i64>(3)? as usize,
+        chunk_text: row.get(4)?,
+        mtime: row.get(5)?,
+        embedding,
+    })
+}
+
+/// Inverse of [`encode_embedding`]. Rejects blobs whose length is not a
+/// multiple of four bytes instead of truncating them.
+pub fn decode_embedding(bytes: &[u8]) -> std::result::Result<Vec<f32>, EmbeddingDecodeError> {
     let size = std::mem::size_of::<f32>();
     if !bytes.len().is_multiple_of(size) {
         return Err(EmbeddingDecodeError {
@@ -304,9 +820,64 @@ mod tests {
     }
 
     #[test]
-    fn stats_empty_when_missing_meta() {
+    fn f16_quantization_round_trips_within_tolerance() {
+        let values: Vec<f32> = (0..256).map(|i| (i as f32 * 0.37).sin()).collect();
+        let quantization = EmbeddingQuantization::F16;
+
+        let encoded = quantization.encode(&values);
+        assert_eq!(encoded.len(), values.len() * 2);
+        let decoded = quantization.decode(&encoded).expect("decode");
+
+        assert_eq!(decoded.len(), values.len());
+        for (original, restored) in values.iter().zip(&decoded) {
+            assert!(
+                (original - restored).abs() <= 1e-3,
+                "{original} decoded as {restored}"
+            );
+        }
+    }
+
+    #[test]
+    fn quantization_is_reloaded_from_meta_on_open() {
         let dir = tempdir().expect("tempdir");
-        let store = VectorStore::open(dir.path(), StoreMode::CreateOrOpen).expect("open");
+        let embedding = vec![0.1_f32, -0.5, 0.75];
+        {
+            let store = VectorStore::open(dir.path(), StoreMode::Reset).expect("open store");
+            store.set_quantization(EmbeddingQuantization::F16);
+            store
+                .store_chunk(&ChunkEntry {
+                    embedding: embedding.clone(),
+                    ..sample_chunk("alpha", "fn alpha() {}")
+                })
+                .expect("store chunk");
+            store
+                .store_meta(&IndexMeta {
+                    schema_version: 1,
+                    embedding_model: "model".to_string(),
+                    dim: embedding.len(),
+                    chunk_size: 40,
+                    metric: DistanceMetric::Cosine,
+                    created_at: Utc::now(),
+                    workspace_fingerprint: "fingerprint".to_string(),
+                    git_commit: None,
+                    quantization: EmbeddingQuantization::F16,
+                })
+                .expect("store meta");
+        }
+
+        let store = VectorStore::open(dir.path(), StoreMode::OpenExisting).expect("reopen");
+        assert_eq!(store.quantization(), EmbeddingQuantization::F16);
+        let records = store.list_embeddings().expect("list embeddings");
+        assert_eq!(records.len(), 1);
+        for (original, restored) in embedding.iter().zip(&records[0].embedding) {
+            assert!((original - restored).abs() <= 1e-3);
+        }
+        assert_eq!(store.stats().expect("stats").total_embedding_bytes, Some(6));
+    }
+
+    #[test]
+    fn stats_empty_when_missing_meta() {
+        let store = VectorStore::in_memory().expect("open store");
         let stats = store.stats().expect("stats");
         let expected = IndexStats {
             file_count: 0,
@@ -314,7 +885,458 @@ mod tests {
             embedding_model: None,
             embedding_dim: None,
             created_at: None,
+            git_commit: None,
+            total_embedding_bytes: None,
+            avg_chunk_lines: None,
+            stale: false,
+            reused_embeddings: 0,
+            tokens_used: None,
         };
         assert_eq!(stats, expected);
     }
+
+    #[test]
+    fn stats_report_embedding_bytes_and_average_chunk_lines() {
+        let store = VectorStore::in_memory().expect("open store");
+        store
+            .store_meta(&IndexMeta {
+                schema_version: 1,
+                embedding_model: "model".to_string(),
+                dim: 1,
+                chunk_size: 10,
+                metric: DistanceMetric::Cosine,
+                created_at: Utc::now(),
+                workspace_fingerprint: "fingerprint".to_string(),
+                git_commit: Some("abc1234".to_string()),
+                quantization: EmbeddingQuantization::None,
+            })
+            .expect("store meta");
+        store
+            .store_chunk(&sample_chunk("alpha", "one line"))
+            .expect("store alpha");
+        store
+            .store_chunk(&ChunkEntry {
+                start_line: 2,
+                end_line: 5,
+                ..sample_chunk("bravo", "four lines")
+            })
+            .expect("store bravo");
+
+        let stats = store.stats().expect("stats");
+
+        assert_eq!(stats.total_embedding_bytes, Some(8));
+        assert_eq!(stats.avg_chunk_lines, Some(2.5));
+        assert_eq!(stats.git_commit.as_deref(), Some("abc1234"));
+    }
+
+    fn sample_chunk(chunk_id: &str, text: &str) -> ChunkEntry {
+        ChunkEntry {
+            file_path: "src/lib.rs".to_string(),
+            chunk_id: chunk_id.to_string(),
+            start_line: 1,
+            end_line: 1,
+            text_hash: format!("{chunk_id}-hash"),
+            text: text.to_string(),
+            embedding: vec![1.0_f32],
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn fts_search_matches_identifiers() {
+        let store = VectorStore::in_memory().expect("open store");
+        store
+            .store_chunk(&sample_chunk("alpha", "fn parse_config() {}"))
+            .expect("store alpha");
+        store
+            .store_chunk(&sample_chunk("bravo", "fn render_widget() {}"))
+            .expect("store bravo");
+
+        let matches = store.fts_search("parse_config", 10).expect("fts search");
+
+        assert_eq!(matches, vec!["alpha".to_string()]);
+    }
+
+    #[test]
+    fn fts_search_tracks_replaced_chunks() {
+        let store = VectorStore::in_memory().expect("open store");
+        store
+            .store_chunk(&sample_chunk("alpha", "old_name"))
+            .expect("store alpha");
+        store
+            .store_chunk(&sample_chunk("alpha", "new_name"))
+            .expect("replace alpha");
+
+        assert_eq!(
+            store.fts_search("old_name", 10).expect("old search"),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            store.fts_search("new_name", 10).expect("new search"),
+            vec!["alpha".to_string()]
+        );
+    }
+
+    #[test]
+    fn store_chunks_and_files_persist_batches() {
+        let store = VectorStore::in_memory().expect("open store");
+        let files = (0..5)
+            .map(|index| FileEntry {
+                path: format!("src/file_{index}.rs"),
+                content_hash: format!("hash_{index}"),
+                mtime: 0,
+                size: 1,
+            })
+            .collect::<Vec<_>>();
+        let chunks = (0..500)
+            .map(|index| ChunkEntry {
+                file_path: format!("src/file_{}.rs", index % 5),
+                ..sample_chunk(&format!("chunk_{index}"), &format!("token_{index}"))
+            })
+            .collect::<Vec<_>>();
+
+        store.store_files(&files).expect("store files");
+        store.store_chunks(&chunks).expect("store chunks");
+
+        let stats = store.stats().expect("stats");
+        assert_eq!(stats.file_count, 5);
+        assert_eq!(stats.chunk_count, 500);
+        assert_eq!(
+            store.fts_search("token_499", 10).expect("fts search"),
+            vec!["chunk_499".to_string()]
+        );
+    }
+
+    #[test]
+    fn wal_mode_allows_reads_during_write_transaction() {
+        let temp = tempdir().expect("tempdir");
+        let store = VectorStore::open(temp.path(), StoreMode::CreateOrOpen).expect("open store");
+        store
+            .store_chunk(&sample_chunk("alpha", "committed"))
+            .expect("store alpha");
+        let journal_mode: String = store
+            .conn
+            .pragma_query_value(None, "journal_mode", |row| row.get(0))
+            .expect("journal mode");
+        assert_eq!(journal_mode, "wal");
+
+        let tx = Transaction::new_unchecked(&store.conn, TransactionBehavior::Exclusive)
+            .expect("begin write");
+        insert_chunk(
+            &tx,
+            &sample_chunk("bravo", "pending"),
+            EmbeddingQuantization::None,
+        )
+        .expect("insert bravo");
+
+        let reader = Connection::open(store.db_path()).expect("open reader");
+        let visible: i64 = reader
+            .query_row("SELECT COUNT(*) FROM chunks", [], |row| row.get(0))
+            .expect("read during write");
+        assert_eq!(visible, 1);
+
+        tx.commit().expect("commit");
+        let visible: i64 = reader
+            .query_row("SELECT COUNT(*) FROM chunks", [], |row| row.get(0))
+            .expect("read after commit");
+        assert_eq!(visible, 2);
+    }
+
+    #[test]
+    fn set_pragma_rejects_names_outside_allowlist() {
+        let store = VectorStore::in_memory().expect("open store");
+
+        store.set_pragma("cache_size", "-4000").expect("cache_size");
+        let cache_size: i64 = store
+            .conn
+            .pragma_query_value(None, "cache_size", |row| row.get(0))
+            .expect("cache size");
+        assert_eq!(cache_size, -4000);
+        assert!(store.set_pragma("writable_schema", "ON").is_err());
+    }
+
+    #[test]
+    fn clear_removes_wal_sidecar_files() {
+        let temp = tempdir().expect("tempdir");
+        let store = VectorStore::open(temp.path(), StoreMode::CreateOrOpen).expect("open store");
+        store
+            .store_chunk(&sample_chunk("alpha", "text"))
+            .expect("store alpha");
+        let db_path = store.db_path().to_path_buf();
+        drop(store);
+        std::fs::write(temp.path().join("index.sqlite-wal"), b"").expect("write wal");
+
+        VectorStore::clear(temp.path()).expect("clear");
+
+        assert!(!db_path.exists());
+        assert!(!temp.path().join("index.sqlite-wal").exists());
+    }
+
+    #[test]
+    fn validate_dimensions_rejects_mismatched_embeddings() {
+        let store = VectorStore::in_memory().expect("open store");
+        store.validate_dimensions().expect("empty index is valid");
+        store
+            .store_meta(&IndexMeta {
+                schema_version: 1,
+                embedding_model: "model".to_string(),
+                dim: 1,
+                chunk_size: 10,
+                metric: DistanceMetric::Cosine,
+                created_at: Utc::now(),
+                workspace_fingerprint: "fingerprint".to_string(),
+                git_commit: None,
+                quantization: EmbeddingQuantization::None,
+            })
+            .expect("store meta");
+        store
+            .store_chunk(&sample_chunk("alpha", "text"))
+            .expect("store alpha");
+        store.validate_dimensions().expect("matching dimensions");
+
+        store
+            .store_chunk(&ChunkEntry {
+                embedding: vec![1.0, 2.0, 3.0],
+                ..sample_chunk("alpha", "text")
+            })
+            .expect("replace alpha");
+
+        let err = store.validate_dimensions().expect_err("mismatch");
+        assert!(err.to_string().contains("3 dimensions"), "{err}");
+    }
+
+    #[test]
+    fn replace_and_delete_file_update_rows() {
+        let store = VectorStore::in_memory().expect("open store");
+        let file = FileEntry {
+            path: "src/lib.rs".to_string(),
+            content_hash: "old".to_string(),
+            mtime: 0,
+            size: 1,
+        };
+        store
+            .replace_file(&file, &[sample_chunk("alpha", "old_text")])
+            .expect("insert");
+        let updated = FileEntry {
+            content_hash: "new".to_string(),
+            ..file
+        };
+        store
+            .replace_file(&updated, &[sample_chunk("bravo", "new_text")])
+            .expect("replace");
+
+        assert_eq!(store.get_file("src/lib.rs").expect("get"), Some(updated));
+        assert_eq!(
+            store.fts_search("old_text", 10).expect("old search"),
+            Vec::<String>::new()
+        );
+        assert_eq!(store.stats().expect("stats").chunk_count, 1);
+
+        store.delete_file("src/lib.rs").expect("delete");
+        assert_eq!(store.get_file("src/lib.rs").expect("get"), None);
+        assert_eq!(store.stats().expect("stats").chunk_count, 0);
+    }
+
+    #[test]
+    fn list_embeddings_returns_chunk_text() {
+        let store = VectorStore::in_memory().expect("open store");
+        store
+            .store_chunk(&ChunkEntry {
+                file_path: "src/lib.rs".to_string(),
+                chunk_id: "chunk-1".to_string(),
+                start_line: 1,
+                end_line: 2,
+                text_hash: "hash".to_string(),
+                text: "fn main() {\n}".to_string(),
+                embedding: vec![1.0_f32, 0.0_f32],
+                updated_at: Utc::now(),
+            })
+            .expect("store chunk");
+
+        let records = store.list_embeddings().expect("list embeddings");
+
+        assert_eq!(
+            records,
+            vec![EmbeddingRecord {
+                file_path: "src/lib.rs".to_string(),
+                chunk_id: "chunk-1".to_string(),
+                start_line: 1,
+                end_line: 2,
+                chunk_text: Some("fn main() {\n}".to_string()),
+                mtime: None,
+                embedding: vec![1.0_f32, 0.0_f32],
+            }]
+        );
+    }
+
+    #[test]
+    fn opening_index_without_text_column_adds_it() {
+        let dir = tempdir().expect("tempdir");
+        let legacy = Connection::open(dir.path().join(DB_FILE_NAME)).expect("open legacy db");
+        legacy
+            .execute_batch(
+                "CREATE TABLE chunks (
+                    file_path TEXT NOT NULL,
+                    chunk_id TEXT PRIMARY KEY,
+                    start_line INTEGER NOT NULL,
+                    end_line INTEGER NOT NULL,
+                    text_hash TEXT NOT NULL,
+                    embedding BLOB NOT NULL,
+                    updated_at TEXT NOT NULL
+                );",
+            )
+            .expect("create legacy chunks");
+        legacy
+            .execute(
+                "INSERT INTO chunks (file_path, chunk_id, start_line, end_line, text_hash, embedding, updated_at)
+                 VALUES ('src/old.rs', 'old', 1, 1, 'old-hash', ?, ?)",
+                params![encode_embedding(&[1.0_f32]), Utc::now().to_rfc3339()],
+            )
+            .expect("insert legacy chunk");
+        drop(legacy);
+
+        let store = VectorStore::open(dir.path(), StoreMode::OpenExisting).expect("open store");
+        store
+            .store_chunk(&sample_chunk("new", "fn new() {}"))
+            .expect("store chunk");
+
+        let mut texts: Vec<(String, Option<String>)> = store
+            .list_embeddings()
+            .expect("list embeddings")
+            .into_iter()
+            .map(|record| (record.chunk_id, record.chunk_text))
+            .collect();
+        texts.sort();
+        assert_eq!(
+            texts,
+            vec![
+                ("new".to_string(), Some("fn new() {}".to_string())),
+                ("old".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn opening_index_without_fts_backfills_existing_chunks() {
+        let dir = tempdir().expect("tempdir");
+        let legacy = Connection::open(dir.path().join(DB_FILE_NAME)).expect("open legacy db");
+        legacy
+            .execute_batch(
+                "CREATE TABLE chunks (
+                    file_path TEXT NOT NULL,
+                    chunk_id TEXT PRIMARY KEY,
+                    start_line INTEGER NOT NULL,
+                    end_line INTEGER NOT NULL,
+                    text_hash TEXT NOT NULL,
+                    text TEXT,
+                    embedding BLOB NOT NULL,
+                    updated_at TEXT NOT NULL
+                );",
+            )
+            .expect("create legacy chunks");
+        legacy
+            .execute(
+                "INSERT INTO chunks (file_path, chunk_id, start_line, end_line, text_hash, text, embedding, updated_at)
+                 VALUES ('src/old.rs', 'old', 1, 1, 'old-hash', 'fn parse_config() {}', ?, ?)",
+                params![encode_embedding(&[1.0_f32]), Utc::now().to_rfc3339()],
+            )
+            .expect("insert legacy chunk");
+        drop(legacy);
+
+        let store = VectorStore::open(dir.path(), StoreMode::OpenExisting).expect("open store");
+        assert_eq!(
+            store.fts_search("parse_config", 10).expect("fts search"),
+            vec!["old".to_string()]
+        );
+        drop(store);
+
+        let reopened =
+            VectorStore::open(dir.path(), StoreMode::OpenExisting).expect("reopen store");
+        let fts_rows: i64 = reopened
+            .conn
+            .query_row("SELECT COUNT(*) FROM chunks_fts", [], |row| row.get(0))
+            .expect("count fts rows");
+        assert_eq!(fts_rows, 1);
+    }
+
+    #[test]
+    fn windows_style_paths_are_stored_with_forward_slashes() {
+        let store = VectorStore::in_memory().expect("open store");
+        let file = FileEntry {
+            path: "src\\nested\\lib.rs".to_string(),
+            content_hash: "hash".to_string(),
+            mtime: 0,
+            size: 1,
+        };
+        let chunk = ChunkEntry {
+            file_path: file.path.clone(),
+            ..sample_chunk("alpha", "text")
+        };
+        store.replace_file(&file, &[chunk]).expect("insert");
+
+        let records = store.list_embeddings().expect("list embeddings");
+        assert_eq!(records[0].file_path, "src/nested/lib.rs");
+        assert_eq!(
+            store.get_file("src\\nested\\lib.rs").expect("get"),
+            Some(FileEntry {
+                path: "src/nested/lib.rs".to_string(),
+                ..file
+            })
+        );
+        assert_eq!(
+            index_path_to_native(&records[0].file_path),
+            Path::new("src").join("nested").join("lib.rs")
+        );
+
+        store.delete_file("src\\nested\\lib.rs").expect("delete");
+        assert_eq!(store.stats().expect("stats").chunk_count, 0);
+    }
+
+    #[test]
+    fn list_embeddings_page_walks_all_chunks() {
+        let store = VectorStore::in_memory().expect("open store");
+        for i in 0..3 {
+            store
+                .store_chunk(&ChunkEntry {
+                    file_path: "src/lib.rs".to_string(),
+                    chunk_id: format!("chunk-{i}"),
+                    start_line: i + 1,
+                    end_line: i + 1,
+                    text_hash: "hash".to_string(),
+                    text: "text".to_string(),
+                    embedding: vec![1.0_f32, 0.0_f32],
+                    updated_at: Utc::now(),
+                })
+                .expect("store chunk");
+        }
+
+        let first = store.list_embeddings_page(0, 2).expect("first page");
+        let second = store
+            .list_embeddings_page(first[1].0, 2)
+            .expect("second page");
+        let third = store
+            .list_embeddings_page(second[0].0, 2)
+            .expect("third page");
+
+        let ids: Vec<&str> = first
+            .iter()
+            .chain(&second)
+            .map(|(_, record)| record.chunk_id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["chunk-0", "chunk-1", "chunk-2"]);
+        assert!(third.is_empty());
+        assert_eq!(store.chunk_mtime_range().expect("range"), None);
+    }
+
+    #[test]
+    fn in_memory_store_reports_memory_path() {
+        let store = VectorStore::in_memory().expect("open store");
+        store
+            .store_chunk(&sample_chunk("alpha", "text"))
+            .expect("store alpha");
+
+        assert_eq!(store.db_path(), Path::new(IN_MEMORY_DB_PATH));
+        assert_eq!(store.stats().expect("stats").chunk_count, 1);
+        assert!(!Path::new(IN_MEMORY_DB_PATH).exists());
+    }
 }
@@ -1,5 +1,11 @@
+pub mod prometheus;
+
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
+use std::time::Duration;
 use tracing::trace;
 
 use crate::cache::LOG_TARGET;
@@ -19,6 +25,8 @@ pub struct CacheTelemetrySnapshot {
     pub stores: u64,
     pub evictions: u64,
     pub hit_rate: Option<f64>,
+    pub hit_latency: Option<CacheLatencySnapshot>,
+    pub store_latency: Option<CacheLatencySnapshot>,
     pub by_tool: Vec<CacheToolTelemetrySnapshot>,
 }
 
@@ -30,6 +38,36 @@ pub struct CacheToolTelemetrySnapshot {
     pub stores: u64,
     pub evictions: u64,
     pub hit_rate: Option<f64>,
+    pub hit_latency: Option<CacheLatencySnapshot>,
+    pub store_latency: Option<CacheLatencySnapshot>,
+}
+
+/// Summary of the recorded durations for one kind of cache operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheLatencySnapshot {
+    pub count: u64,
+    pub min: Duration,
+    pub max: Duration,
+    pub avg: Duration,
+}
+
+/// Counter values saved between runs. Latency summaries are not persisted.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PersistedCacheTelemetry {
+    #[serde(default)]
+    pub overall: PersistedCacheCounters,
+    /// Keyed by [`CacheableTool::config_key`].
+    #[serde(default)]
+    pub by_tool: BTreeMap<String, PersistedCacheCounters>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PersistedCacheCounters {
+    pub hits: u64,
+    pub misses: u64,
+    pub stores: u64,
+    pub evictions: u64,
 }
 
 #[derive(Debug, Default)]
@@ -38,6 +76,17 @@ struct CacheCounters {
     misses: AtomicU64,
     stores: AtomicU64,
     evictions: AtomicU64,
+    hit_latency: LatencyStats,
+    store_latency: LatencyStats,
+}
+
+/// Running min/max/total of durations, stored as nanoseconds.
+#[derive(Debug)]
+struct LatencyStats {
+    count: AtomicU64,
+    total_nanos: AtomicU64,
+    min_nanos: AtomicU64,
+    max_nanos: AtomicU64,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -46,34 +95,111 @@ struct CacheCountersSnapshot {
     misses: u64,
     stores: u64,
     evictions: u64,
+    hit_latency: Option<CacheLatencySnapshot>,
+    store_latency: Option<CacheLatencySnapshot>,
 }
 
 impl CacheTelemetry {
     pub fn record_hit(&self, tool: CacheableTool) {
         self.overall.record_hit();
         self.by_tool[tool_index(tool)].record_hit();
+        trace!(target: LOG_TARGET, event = "cache_hit", tool = %tool);
     }
 
     pub fn record_miss(&self, tool: CacheableTool) {
         self.overall.record_miss();
         self.by_tool[tool_index(tool)].record_miss();
+        trace!(target: LOG_TARGET, event = "cache_miss", tool = %tool);
     }
 
     pub fn record_store(&self, tool: CacheableTool) {
         self.overall.record_store();
         self.by_tool[tool_index(tool)].record_store();
+        trace!(target: LOG_TARGET, event = "cache_store", tool = %tool);
     }
 
     pub fn record_eviction(&self, tool: CacheableTool) {
         self.overall.record_eviction();
         self.by_tool[tool_index(tool)].record_eviction();
+        trace!(target: LOG_TARGET, event = "cache_eviction", tool = %tool);
+    }
+
+    /// Records how long a cache lookup that produced a hit took.
+    pub fn record_hit_latency(&self, tool: CacheableTool, latency: Duration) {
+        self.overall.hit_latency.record(latency);
+        self.by_tool[tool_index(tool)].hit_latency.record(latency);
+    }
+
+    /// Records how long writing an entry to the cache took.
+    pub fn record_store_latency(&self, tool: CacheableTool, latency: Duration) {
+        self.overall.store_latency.record(latency);
+        self.by_tool[tool_index(tool)].store_latency.record(latency);
+    }
+
+    /// Restores counters saved by [`CacheTelemetry::to_persisted`].
+    pub fn from_persisted(persisted: &PersistedCacheTelemetry) -> Self {
+        let telemetry = Self::default();
+        telemetry.overall.restore(&persisted.overall);
+        for tool in CacheableTool::all() {
+            if let Some(counters) = persisted.by_tool.get(tool.config_key()) {
+                telemetry.by_tool[tool_index(*tool)].restore(counters);
+            }
+        }
+        telemetry
+    }
+
+    pub fn to_persisted(&self) -> PersistedCacheTelemetry {
+        PersistedCacheTelemetry {
+            overall: self.overall.snapshot().into(),
+            by_tool: CacheableTool::all()
+                .iter()
+                .map(|tool| {
+                    let counters = self.by_tool[tool_index(*tool)].snapshot();
+                    (tool.config_key().to_string(), counters.into())
+                })
+                .collect(),
+        }
+    }
+
+    /// Zeroes every counter and latency summary, overall and per tool.
+    ///
+    /// Counters are reset one at a time, so a recording that races with the
+    /// reset may survive it; this is fine for single-threaded CLI use.
+    pub fn reset(&self) {
+        self.overall.reset();
+        for counters in &self.by_tool {
+            counters.reset();
+        }
     }
 
     pub fn snapshot(&self) -> CacheTelemetrySnapshot {
-        let overall = self.overall.snapshot();
+        self.collect_snapshot(CacheCounters::snapshot)
+    }
+
+    /// Renders the current counters in the Prometheus text exposition
+    /// format without resetting them, for scraping a long-lived process.
+    pub fn prometheus_text(&self) -> String {
+        self.snapshot().to_prometheus()
+    }
+
+    /// Returns the current snapshot and zeroes the counters, starting a new
+    /// reporting window.
+    ///
+    /// Each counter is read and zeroed in a single atomic swap, so recordings
+    /// that race with this call land in either this window or the next one,
+    /// never neither.
+    pub fn snapshot_and_reset(&self) -> CacheTelemetrySnapshot {
+        self.collect_snapshot(CacheCounters::take)
+    }
+
+    fn collect_snapshot(
+        &self,
+        read: impl Fn(&CacheCounters) -> CacheCountersSnapshot,
+    ) -> CacheTelemetrySnapshot {
+        let overall = read(&self.overall);
         let mut by_tool = Vec::with_capacity(CacheableTool::all().len());
         for tool in CacheableTool::all() {
-            let snapshot = self.by_tool[tool_index(*tool)].snapshot();
+            let snapshot = read(&self.by_tool[tool_index(*tool)]);
             by_tool.push(CacheToolTelemetrySnapshot {
                 tool: *tool,
                 hits: snapshot.hits,
@@ -81,6 +207,8 @@ impl CacheTelemetry {
                 stores: snapshot.stores,
                 evictions: snapshot.evictions,
                 hit_rate: hit_rate(snapshot.hits, snapshot.misses),
+                hit_latency: snapshot.hit_latency,
+                store_latency: snapshot.store_latency,
             });
         }
 
@@ -90,30 +218,106 @@ impl CacheTelemetry {
             stores: overall.stores,
             evictions: overall.evictions,
             hit_rate: hit_rate(overall.hits, overall.misses),
+            hit_latency: overall.hit_latency,
+            store_latency: overall.store_latency,
             by_tool,
         }
     }
 }
 
+impl CacheToolTelemetrySnapshot {
+    fn diff(&self, earlier: &CacheToolTelemetrySnapshot) -> CacheToolTelemetrySnapshot {
+        let hits = self.hits.saturating_sub(earlier.hits);
+        let misses = self.misses.saturating_sub(earlier.misses);
+        CacheToolTelemetrySnapshot {
+            tool: self.tool,
+            hits,
+            misses,
+            stores: self.stores.saturating_sub(earlier.stores),
+            evictions: self.evictions.saturating_sub(earlier.evictions),
+            hit_rate: hit_rate(hits, misses),
+            hit_latency: self.hit_latency,
+            store_latency: self.store_latency,
+        }
+    }
+}
+
+impl CacheTelemetrySnapshot {
+    /// Returns the counter deltas since `earlier`, saturating at zero (e.g.
+    /// across a reset). Hit rates are recomputed from the deltas; latency
+    /// summaries cannot be subtracted and are carried over from `self`.
+    pub fn diff(&self, earlier: &CacheTelemetrySnapshot) -> CacheTelemetrySnapshot {
+        let hits = self.hits.saturating_sub(earlier.hits);
+        let misses = self.misses.saturating_sub(earlier.misses);
+        CacheTelemetrySnapshot {
+            hits,
+            misses,
+            stores: self.stores.saturating_sub(earlier.stores),
+            evictions: self.evictions.saturating_sub(earlier.evictions),
+            hit_rate: hit_rate(hits, misses),
+            hit_latency: self.hit_latency,
+            store_latency: self.store_latency,
+            by_tool: self
+                .by_tool
+                .iter()
+                .map(
+                    |later| match earlier.by_tool.iter().find(|tool| tool.tool == later.tool) {
+                        Some(earlier) => later.diff(earlier),
+                        None => later.clone(),
+                    },
+                )
+                .collect(),
+        }
+    }
+
+    /// Renders the snapshot in the Prometheus text exposition format; see
+    /// [`prometheus::format`].
+    pub fn to_prometheus(&self) -> String {
+        prometheus::format(self)
+    }
+}
+
 impl CacheCounters {
     fn record_hit(&self) {
         self.hits.fetch_add(1, Ordering::Relaxed);
-        trace!(target: LOG_TARGET, "cache hit recorded");
     }
 
     fn record_miss(&self) {
         self.misses.fetch_add(1, Ordering::Relaxed);
-        trace!(target: LOG_TARGET, "cache miss recorded");
     }
 
     fn record_store(&self) {
         self.stores.fetch_add(1, Ordering::Relaxed);
-        trace!(target: LOG_TARGET, "cache store recorded");
     }
 
     fn record_eviction(&self) {
         self.evictions.fetch_add(1, Ordering::Relaxed);
-        trace!(target: LOG_TARGET, "cache eviction recorded");
+    }
+
+    fn restore(&self, persisted: &PersistedCacheCounters) {
+        self.hits.store(persisted.hits, Ordering::Relaxed);
+        self.misses.store(persisted.misses, Ordering::Relaxed);
+        self.stores.store(persisted.stores, Ordering::Relaxed);
+        self.evictions.store(persisted.evictions, Ordering::Relaxed);
+    }
+
+    fn reset(&self) {
+        for counter in [&self.hits, &self.misses, &self.stores, &self.evictions] {
+            counter.store(0, Ordering::Relaxed);
+        }
+        self.hit_latency.reset();
+        self.store_latency.reset();
+    }
+
+    fn take(&self) -> CacheCountersSnapshot {
+        CacheCountersSnapshot {
+            hits: self.hits.swap(0, Ordering::SeqCst),
+            misses: self.misses.swap(0, Ordering::SeqCst),
+            stores: self.stores.swap(0, Ordering::SeqCst),
+            evictions: self.evictions.swap(0, Ordering::SeqCst),
+            hit_latency: self.hit_latency.take(),
+            store_latency: self.store_latency.take(),
+        }
     }
 
     fn snapshot(&self) -> CacheCountersSnapshot {
@@ -122,7 +326,77 @@ impl CacheCounters {
             misses: self.misses.load(Ordering::Relaxed),
             stores: self.stores.load(Ordering::Relaxed),
             evictions: self.evictions.load(Ordering::Relaxed),
+            hit_latency: self.hit_latency.snapshot(),
+            store_latency: self.store_latency.snapshot(),
+        }
+    }
+}
+
+impl From<CacheCountersSnapshot> for PersistedCacheCounters {
+    fn from(snapshot: CacheCountersSnapshot) -> Self {
+        Self {
+            hits: snapshot.hits,
+            misses: snapshot.misses,
+            stores: snapshot.stores,
+            evictions: snapshot.evictions,
+        }
+    }
+}
+
+impl Default for LatencyStats {
+    fn default() -> Self {
+        Self {
+            count: AtomicU64::new(0),
+            total_nanos: AtomicU64::new(0),
+            min_nanos: AtomicU64::new(u64::MAX),
+            max_nanos: AtomicU64::new(0),
+        }
+    }
+}
+
+impl LatencyStats {
+    fn record(&self, latency: Duration) {
+        let nanos = u64::try_from(latency.as_nanos()).unwrap_or(u64::MAX);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.total_nanos.fetch_add(nanos, Ordering::Relaxed);
+        self.min_nanos.fetch_min(nanos, Ordering::Relaxed);
+        self.max_nanos.fetch_max(nanos, Ordering::Relaxed);
+    }
+
+    fn reset(&self) {
+        self.count.store(0, Ordering::Relaxed);
+        self.total_nanos.store(0, Ordering::Relaxed);
+        self.min_nanos.store(u64::MAX, Ordering::Relaxed);
+        self.max_nanos.store(0, Ordering::Relaxed);
+    }
+
+    fn take(&self) -> Option<CacheLatencySnapshot> {
+        let count = self.count.swap(0, Ordering::SeqCst);
+        let total_nanos = self.total_nanos.swap(0, Ordering::SeqCst);
+        let min_nanos = self.min_nanos.swap(u64::MAX, Ordering::SeqCst);
+        let max_nanos = self.max_nanos.swap(0, Ordering::SeqCst);
+        if count == 0 {
+            return None;
         }
+        Some(CacheLatencySnapshot {
+            count,
+            min: Duration::from_nanos(min_nanos),
+            max: Duration::from_nanos(max_nanos),
+            avg: Duration::from_nanos(total_nanos / count),
+        })
+    }
+
+    fn snapshot(&self) -> Option<CacheLatencySnapshot> {
+        let count = self.count.load(Ordering::Relaxed);
+        if count == 0 {
+            return None;
+        }
+        Some(CacheLatencySnapshot {
+            count,
+            min: Duration::from_nanos(self.min_nanos.load(Ordering::Relaxed)),
+            max: Duration::from_nanos(self.max_nanos.load(Ordering::Relaxed)),
+            avg: Duration::from_nanos(self.total_nanos.load(Ordering::Relaxed) / count),
+        })
     }
 }
 
@@ -147,6 +421,7 @@ fn tool_index(tool: CacheableTool) -> usize {
 mod tests {
     use super::*;
     use pretty_assertions::assert_eq;
+    use tracing_test::traced_test;
 
     #[test]
     fn captures_hit_rate_and_counts() {
@@ -174,7 +449,9 @@ mod tests {
                 misses: 0,
                 stores: 1,
                 evictions: 0,
-                hit_rate: Some(1.0)
+                hit_rate: Some(1.0),
+                hit_latency: None,
+                store_latency: None,
             }
         );
         assert_eq!(
@@ -185,7 +462,9 @@ mod tests {
                 misses: 1,
                 stores: 0,
                 evictions: 1,
-                hit_rate: Some(0.0)
+                hit_rate: Some(0.0),
+                hit_latency: None,
+                store_latency: None,
             }
         );
         assert_eq!(
@@ -196,11 +475,200 @@ mod tests {
                 misses: 0,
                 stores: 0,
                 evictions: 0,
-                hit_rate: Some(1.0)
+                hit_rate: Some(1.0),
+                hit_latency: None,
+                store_latency: None,
             }
         );
     }
 
+    #[test]
+    fn aggregates_latency_min_max_avg() {
+        let telemetry = CacheTelemetry::default();
+        for millis in [4, 1, 7] {
+            telemetry.record_hit_latency(CacheableTool::ReadFile, Duration::from_millis(millis));
+        }
+        telemetry.record_hit_latency(CacheableTool::GrepFiles, Duration::from_millis(12));
+        telemetry.record_store_latency(CacheableTool::ReadFile, Duration::from_millis(3));
+
+        let snapshot = telemetry.snapshot();
+
+        assert_eq!(
+            snapshot.hit_latency,
+            Some(CacheLatencySnapshot {
+                count: 4,
+                min: Duration::from_millis(1),
+                max: Duration::from_millis(12),
+                avg: Duration::from_millis(6),
+            })
+        );
+        assert_eq!(
+            snapshot.by_tool[0].hit_latency,
+            Some(CacheLatencySnapshot {
+                count: 3,
+                min: Duration::from_millis(1),
+                max: Duration::from_millis(7),
+                avg: Duration::from_millis(4),
+            })
+        );
+        assert_eq!(
+            snapshot.store_latency,
+            Some(CacheLatencySnapshot {
+                count: 1,
+                min: Duration::from_millis(3),
+                max: Duration::from_millis(3),
+                avg: Duration::from_millis(3),
+            })
+        );
+        assert_eq!(snapshot.by_tool[1].hit_latency, None);
+        assert_eq!(snapshot.by_tool[1].store_latency, None);
+    }
+
+    #[test]
+    fn renders_prometheus_metrics_with_tool_labels() {
+        let telemetry = CacheTelemetry::default();
+        telemetry.record_hit(CacheableTool::ReadFile);
+        telemetry.record_miss(CacheableTool::GrepFiles);
+        telemetry.record_store(CacheableTool::GrepFiles);
+        telemetry.record_eviction(CacheableTool::ListDir);
+
+        let rendered = telemetry.snapshot().to_prometheus();
+
+        for expected in [
+            "# TYPE codex_cache_hits_total counter",
+            "codex_cache_hits_total{tool=\"overall\"} 1",
+            "codex_cache_hits_total{tool=\"read_file\"} 1",
+            "codex_cache_hits_total{tool=\"grep_files\"} 0",
+            "codex_cache_misses_total{tool=\"grep_files\"} 1",
+            "codex_cache_stores_total{tool=\"grep_files\"} 1",
+            "codex_cache_evictions_total{tool=\"list_dir\"} 1",
+            "# TYPE codex_cache_hit_ratio gauge",
+            "codex_cache_hit_ratio{tool=\"overall\"} 0.5",
+            "codex_cache_hit_ratio{tool=\"read_file\"} 1",
+            "codex_cache_hit_ratio{tool=\"grep_files\"} 0",
+        ] {
+            assert!(
+                rendered.lines().any(|line| line == expected),
+                "missing `{expected}` in:\n{rendered}"
+            );
+        }
+        assert!(!rendered.contains("codex_cache_hit_ratio{tool=\"list_dir\"}"));
+    }
+
+    #[test]
+    #[traced_test]
+    fn recording_emits_structured_events_with_tool() {
+        let telemetry = CacheTelemetry::default();
+
+        telemetry.record_hit(CacheableTool::ReadFile);
+        telemetry.record_eviction(CacheableTool::GrepFiles);
+
+        assert!(logs_contain("event=\"cache_hit\" tool=read_file"));
+        assert!(logs_contain("event=\"cache_eviction\" tool=grep_files"));
+        assert!(!logs_contain("event=\"cache_miss\""));
+    }
+
+    #[test]
+    fn reset_zeroes_all_counters() {
+        let telemetry = CacheTelemetry::default();
+        telemetry.record_hit(CacheableTool::ReadFile);
+        telemetry.record_miss(CacheableTool::ListDir);
+        telemetry.record_store(CacheableTool::GrepFiles);
+        telemetry.record_eviction(CacheableTool::GrepFiles);
+        telemetry.record_hit_latency(CacheableTool::ReadFile, Duration::from_millis(2));
+        telemetry.record_store_latency(CacheableTool::GrepFiles, Duration::from_millis(5));
+
+        telemetry.reset();
+
+        assert_eq!(telemetry.snapshot(), CacheTelemetry::default().snapshot());
+    }
+
+    #[test]
+    fn snapshot_and_reset_starts_a_new_window() {
+        let telemetry = CacheTelemetry::default();
+        telemetry.record_hit(CacheableTool::ReadFile);
+        telemetry.record_hit(CacheableTool::ReadFile);
+        telemetry.record_miss(CacheableTool::GrepFiles);
+        telemetry.record_hit_latency(CacheableTool::ReadFile, Duration::from_millis(4));
+
+        let window = telemetry.snapshot_and_reset();
+
+        assert_eq!(window.hits, 2);
+        assert_eq!(window.misses, 1);
+        assert_eq!(window.hit_rate, Some(2.0 / 3.0));
+        assert_eq!(
+            window.hit_latency,
+            Some(CacheLatencySnapshot {
+                count: 1,
+                min: Duration::from_millis(4),
+                max: Duration::from_millis(4),
+                avg: Duration::from_millis(4),
+            })
+        );
+        assert_eq!(window.by_tool[0].hits, 2);
+        assert_eq!(window.by_tool[2].misses, 1);
+
+        let next = telemetry.snapshot();
+        assert_eq!(next, CacheTelemetry::default().snapshot());
+        assert_eq!(next.hits, 0);
+        assert_eq!(next.misses, 0);
+        assert_eq!(next.hit_rate, None);
+    }
+
+    #[test]
+    fn diff_reports_counter_deltas() {
+        let telemetry = CacheTelemetry::default();
+        telemetry.record_hit(CacheableTool::ReadFile);
+        telemetry.record_miss(CacheableTool::GrepFiles);
+        let earlier = telemetry.snapshot();
+        telemetry.record_hit(CacheableTool::ReadFile);
+        telemetry.record_hit(CacheableTool::GrepFiles);
+        telemetry.record_miss(CacheableTool::GrepFiles);
+        telemetry.record_store(CacheableTool::ListDir);
+        telemetry.record_eviction(CacheableTool::ListDir);
+
+        let diff = telemetry.snapshot().diff(&earlier);
+
+        assert_eq!(diff.hits, 2);
+        assert_eq!(diff.misses, 1);
+        assert_eq!(diff.stores, 1);
+        assert_eq!(diff.evictions, 1);
+        assert_eq!(diff.hit_rate, Some(2.0 / 3.0));
+        let deltas = diff
+            .by_tool
+            .iter()
+            .map(|tool| {
+                (
+                    tool.tool,
+                    tool.hits,
+                    tool.misses,
+                    tool.stores,
+                    tool.hit_rate,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            deltas,
+            vec![
+                (CacheableTool::ReadFile, 1, 0, 0, Some(1.0)),
+                (CacheableTool::ListDir, 0, 0, 1, None),
+                (CacheableTool::GrepFiles, 1, 1, 0, Some(0.5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_saturates_after_reset() {
+        let telemetry = CacheTelemetry::default();
+        telemetry.record_hit(CacheableTool::ReadFile);
+        let earlier = telemetry.snapshot();
+        telemetry.reset();
+
+        let diff = telemetry.snapshot().diff(&earlier);
+
+        assert_eq!(diff, CacheTelemetry::default().snapshot());
+    }
+
     #[test]
     fn hit_rate_is_none_without_samples() {
         let telemetry = CacheTelemetry::default();
@@ -215,6 +683,8 @@ mod tests {
                 stores: 0,
                 evictions: 0,
                 hit_rate: None,
+                hit_latency: None,
+                store_latency: None,
                 by_tool: vec![
                     CacheToolTelemetrySnapshot {
                         tool: CacheableTool::ReadFile,
@@ -222,7 +692,9 @@ mod tests {
                         misses: 0,
                         stores: 0,
                         evictions: 0,
-                        hit_rate: None
+                        hit_rate: None,
+                        hit_latency: None,
+                        store_latency: None,
                     },
                     CacheToolTelemetrySnapshot {
                         tool: CacheableTool::ListDir,
@@ -230,7 +702,9 @@ mod tests {
                         misses: 0,
                         stores: 0,
                         evictions: 0,
-                        hit_rate: None
+                        hit_rate: None,
+                        hit_latency: None,
+                        store_latency: None,
                     },
                     CacheToolTelemetrySnapshot {
                         tool: CacheableTool::GrepFiles,
@@ -238,7 +712,9 @@ mod tests {
                         misses: 0,
                         stores: 0,
                         evictions: 0,
-                        hit_rate: None
+                        hit_rate: None,
+                        hit_latency: None,
+                        store_latency: None,
                     },
                 ],
             }
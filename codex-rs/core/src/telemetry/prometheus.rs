@@ -0,0 +1,206 @@
+//! Prometheus text exposition format for cache and index telemetry.
+
+use crate::semantic::vector_store::IndexStats;
+use crate::telemetry::CacheTelemetrySnapshot;
+use crate::telemetry::CacheToolTelemetrySnapshot;
+
+/// Label value used for the aggregate series.
+const AGGREGATE_TOOL: &str = "overall";
+
+/// Metric name, help text, per-tool value, and aggregate value of a counter.
+type CounterSeries<'a> = (
+    &'a str,
+    &'a str,
+    fn(&CacheToolTelemetrySnapshot) -> u64,
+    u64,
+);
+
+/// Renders a cache telemetry snapshot.
+///
+/// Every metric carries a `tool` label: one series per cacheable tool plus
+/// an aggregate series labelled `tool="overall"`. The hit-ratio gauge is
+/// omitted for series without any lookups.
+pub fn format(snapshot: &CacheTelemetrySnapshot) -> String {
+    let mut out = String::new();
+    let counters: [CounterSeries; 4] = [
+        (
+            "codex_cache_hits_total",
+            "Cache lookups served from the cache.",
+            |tool| tool.hits,
+            snapshot.hits,
+        ),
+        (
+            "codex_cache_misses_total",
+            "Cache lookups that found no usable entry.",
+            |tool| tool.misses,
+            snapshot.misses,
+        ),
+        (
+            "codex_cache_stores_total",
+            "Entries written to the cache.",
+            |tool| tool.stores,
+            snapshot.stores,
+        ),
+        (
+            "codex_cache_evictions_total",
+            "Entries evicted to stay under the size limit.",
+            |tool| tool.evictions,
+            snapshot.evictions,
+        ),
+    ];
+    for (name, help, value_for_tool, aggregate) in counters {
+        out.push_str(&format!("# HELP {name} {help}\n# TYPE {name} counter\n"));
+        out.push_str(&format!(
+            "{name}{{tool=\"{AGGREGATE_TOOL}\"}} {aggregate}\n"
+        ));
+        for tool in &snapshot.by_tool {
+            let tool_name = tool.tool.config_key();
+            let value = value_for_tool(tool);
+            out.push_str(&format!("{name}{{tool=\"{tool_name}\"}} {value}\n"));
+        }
+    }
+
+    let name = "codex_cache_hit_ratio";
+    out.push_str(&format!(
+        "# HELP {name} Fraction of cache lookups that were hits.\n# TYPE {name} gauge\n"
+    ));
+    if let Some(rate) = snapshot.hit_rate {
+        out.push_str(&format!("{name}{{tool=\"{AGGREGATE_TOOL}\"}} {rate}\n"));
+    }
+    for tool in &snapshot.by_tool {
+        if let Some(rate) = tool.hit_rate {
+            let tool_name = tool.tool.config_key();
+            out.push_str(&format!("{name}{{tool=\"{tool_name}\"}} {rate}\n"));
+        }
+    }
+    out
+}
+
+/// Renders file and chunk counts of a semantic index.
+pub fn format_index(stats: &IndexStats) -> String {
+    let mut out = String::new();
+    let gauges = [
+        (
+            "codex_index_files_total",
+            "Files in the semantic index.",
+            stats.file_count,
+        ),
+        (
+            "codex_index_chunks_total",
+            "Chunks in the semantic index.",
+            stats.chunk_count,
+        ),
+    ];
+    for (name, help, value) in gauges {
+        out.push_str(&format!(
+            "# HELP {name} {help}\n# TYPE {name} gauge\n{name} {value}\n"
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::config::CacheableTool;
+    use crate::telemetry::CacheTelemetry;
+    use pretty_assertions::assert_eq;
+    use prometheus_parse::Scrape;
+    use prometheus_parse::Value;
+    use regex::Regex;
+
+    /// Fails on any line that is neither a `# HELP`/`# TYPE` comment nor a
+    /// `metric_name{labels} value` sample.
+    fn assert_valid_exposition(text: &str) {
+        let comment = Regex::new(r"^# (HELP|TYPE) [a-zA-Z_:][a-zA-Z0-9_:]* .+$").expect("regex");
+        let sample = Regex::new(
+            r#"^[a-zA-Z_:][a-zA-Z0-9_:]*(\{[a-zA-Z_][a-zA-Z0-9_]*="[^"]*"(,[a-zA-Z_][a-zA-Z0-9_]*="[^"]*")*\})? -?[0-9]+(\.[0-9]+)?([eE][-+]?[0-9]+)?$"#,
+        )
+        .expect("regex");
+        assert!(text.ends_with('\n'), "{text}");
+        for line in text.lines() {
+            assert!(
+                comment.is_match(line) || sample.is_match(line),
+                "invalid exposition line: {line}"
+            );
+        }
+    }
+
+    #[test]
+    fn cache_metrics_are_valid_exposition_format() {
+        let telemetry = CacheTelemetry::default();
+        telemetry.record_hit(CacheableTool::ReadFile);
+        telemetry.record_miss(CacheableTool::GrepFiles);
+        telemetry.record_miss(CacheableTool::GrepFiles);
+
+        let text = telemetry.prometheus_text();
+        assert_eq!(text, format(&telemetry.snapshot()));
+
+        assert_valid_exposition(&text);
+        assert!(text.contains("codex_cache_hits_total{tool=\"read_file\"} 1\n"));
+        assert!(text.contains("codex_cache_misses_total{tool=\"overall\"} 2\n"));
+    }
+
+    #[test]
+    fn cache_metrics_parse_with_prometheus_parse() {
+        let telemetry = CacheTelemetry::default();
+        telemetry.record_hit(CacheableTool::ReadFile);
+        telemetry.record_hit(CacheableTool::ReadFile);
+        telemetry.record_miss(CacheableTool::GrepFiles);
+
+        let text = telemetry.prometheus_text();
+        let scrape = Scrape::parse(text.lines().map(|line| Ok(line.to_string()))).expect("parse");
+        let sample = |metric: &str, tool: &str| {
+            scrape
+                .samples
+                .iter()
+                .find(|sample| sample.metric == metric && sample.labels.get("tool") == Some(tool))
+                .map(|sample| &sample.value)
+        };
+
+        assert_eq!(
+            sample("codex_cache_hits_total", "read_file"),
+            Some(&Value::Counter(2.0))
+        );
+        assert_eq!(
+            sample("codex_cache_misses_total", "overall"),
+            Some(&Value::Counter(1.0))
+        );
+        assert_eq!(
+            sample("codex_cache_hit_ratio", "overall"),
+            Some(&Value::Gauge(2.0 / 3.0))
+        );
+        assert_eq!(
+            sample("codex_cache_hit_ratio", "grep_files"),
+            Some(&Value::Gauge(0.0))
+        );
+        assert_eq!(sample("codex_cache_hit_ratio", "list_dir"), None);
+    }
+
+    #[test]
+    fn index_metrics_report_file_and_chunk_counts() {
+        let stats = IndexStats {
+            file_count: 12,
+            chunk_count: 40,
+            embedding_model: None,
+            embedding_dim: None,
+            created_at: None,
+            git_commit: None,
+            total_embedding_bytes: None,
+            avg_chunk_lines: None,
+            stale: false,
+            reused_embeddings: 0,
+            tokens_used: None,
+        };
+
+        let text = format_index(&stats);
+
+        assert_valid_exposition(&text);
+        assert_eq!(
+            text.lines()
+                .filter(|line| !line.starts_with('#'))
+                .collect::<Vec<_>>(),
+            vec!["codex_index_files_total 12", "codex_index_chunks_total 40"]
+        );
+    }
+}
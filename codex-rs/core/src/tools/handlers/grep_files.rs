@@ -1,12 +1,16 @@
 use std::path::Path;
 use std::path::PathBuf;
 use std::time::Duration;
+use std::time::SystemTime;
 use std::time::UNIX_EPOCH;
 
 use async_trait::async_trait;
+use ignore::WalkBuilder;
+use ignore::overrides::OverrideBuilder;
+use regex::bytes::Regex;
+use regex::bytes::RegexBuilder;
 use serde::Deserialize;
 use serde::Serialize;
-use serde_json::Value as JsonValue;
 use sha2::Digest;
 use sha2::Sha256;
 use tokio::fs;
@@ -15,9 +19,14 @@ use tokio::time::timeout;
 use tracing::warn;
 
 use crate::cache::LOG_TARGET;
+use crate::cache::canonical::canonical_json;
 use crate::cache::config::CacheableTool;
 use crate::cache::config::DEFAULT_CACHE_GREP_FILES_TTL_SECS;
+use crate::cache::tool_cache::read_git_head;
+use crate::cache::tool_cache::resolve_git_dir;
+use crate::config::types::GrepBackend;
 use crate::function_tool::FunctionCallError;
+use crate::ripgrep;
 use crate::tools::context::ToolInvocation;
 use crate::tools::context::ToolOutput;
 use crate::tools::context::ToolPayload;
@@ -29,6 +38,11 @@ pub struct GrepFilesHandler;
 const DEFAULT_LIMIT: usize = 100;
 const MAX_LIMIT: usize = 2000;
 const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_CONTEXT_LINES: usize = 20;
+/// Appended when the joined results exceed `max_output_chars`.
+const TRUNCATION_MARKER: &str = "(truncated)";
+/// Longest ripgrep warning text passed back to the model.
+const MAX_WARNING_CHARS: usize = 1_000;
 
 fn default_limit() -> usize {
     DEFAULT_LIMIT
@@ -40,9 +54,158 @@ struct GrepFilesArgs {
     #[serde(default)]
     include: Option<String>,
     #[serde(default)]
-    path: Option<String>,
+    path: Option<SearchPaths>,
     #[serde(default = "default_limit")]
     limit: usize,
+    #[serde(default)]
+    case: CaseMode,
+    /// Treat the pattern as a literal string rather than a regex.
+    #[serde(default)]
+    fixed_strings: bool,
+    /// Only match the pattern as a whole word.
+    #[serde(default)]
+    word: bool,
+    /// Allow matches to span lines.
+    #[serde(default)]
+    multiline: bool,
+    /// With `multiline`, let `.` match newlines too.
+    #[serde(default)]
+    multiline_dotall: bool,
+    /// Search hidden files and directories.
+    #[serde(default)]
+    hidden: bool,
+    /// Search files excluded by `.gitignore` and other ignore files.
+    #[serde(default)]
+    no_ignore: bool,
+    #[serde(default)]
+    sort: GrepSort,
+    /// Character budget for the joined results, marker included.
+    #[serde(default)]
+    max_output_chars: Option<usize>,
+    #[serde(default)]
+    mode: GrepMode,
+    /// Lines of context around each match in `content` mode.
+    #[serde(default)]
+    context: usize,
+}
+
+/// One path or a list of paths to search.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+enum SearchPaths {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl SearchPaths {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            SearchPaths::One(path) => vec![path],
+            SearchPaths::Many(paths) => paths,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum GrepMode {
+    /// List the paths of matching files.
+    #[default]
+    Files,
+    /// List matching lines with their line numbers.
+    Content,
+}
+
+/// Order in which matching files are reported.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum GrepSort {
+    /// Most recently modified file first.
+    #[default]
+    Modified,
+    /// Alphabetical by path.
+    Path,
+    /// Most matching lines first; only supported in `files` mode.
+    Count,
+}
+
+impl GrepSort {
+    fn as_str(self) -> &'static str {
+        match self {
+            GrepSort::Modified => "modified",
+            GrepSort::Path => "path",
+            GrepSort::Count => "count",
+        }
+    }
+}
+
+/// What ripgrep reports for a search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SearchOutput {
+    Files,
+    Content { context: usize },
+}
+
+impl SearchOutput {
+    fn from_args(mode: GrepMode, context: usize) -> Self {
+        match mode {
+            GrepMode::Files => SearchOutput::Files,
+            GrepMode::Content => SearchOutput::Content {
+                context: context.min(MAX_CONTEXT_LINES),
+            },
+        }
+    }
+}
+
+/// How the pattern's letter case is matched.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum CaseMode {
+    Sensitive,
+    Insensitive,
+    /// Case-insensitive unless the pattern contains an uppercase letter.
+    #[default]
+    Smart,
+}
+
+impl CaseMode {
+    fn as_str(self) -> &'static str {
+        match self {
+            CaseMode::Sensitive => "sensitive",
+            CaseMode::Insensitive => "insensitive",
+            CaseMode::Smart => "smart",
+        }
+    }
+
+    fn rg_flag(self) -> &'static str {
+        match self {
+            CaseMode::Sensitive => "--case-sensitive",
+            CaseMode::Insensitive => "--ignore-case",
+            CaseMode::Smart => "--smart-case",
+        }
+    }
+}
+
+/// How ripgrep interprets the pattern text, which files it searches, and
+/// how the matches are ordered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct PatternOptions {
+    case: CaseMode,
+    fixed_strings: bool,
+    word: bool,
+    multiline: bool,
+    multiline_dotall: bool,
+    hidden: bool,
+    no_ignore: bool,
+    sort: GrepSort,
+}
+
+/// Lines produced by a search, plus any warnings ripgrep printed while still
+/// completing it (e.g. unreadable files or malformed ignore globs).
+#[derive(Debug, Default, PartialEq, Eq)]
+struct SearchResults {
+    matches: Vec<String>,
+    warning: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -53,10 +216,13 @@ struct RepoState {
 
 struct GrepCacheKeyInputs<'a> {
     workspace_root: &'a Path,
-    search_path: &'a Path,
+    search_paths: &'a [PathBuf],
     pattern: &'a str,
     include: Option<&'a str>,
     limit: usize,
+    pattern_options: PatternOptions,
+    output: SearchOutput,
+    max_output_chars: Option<usize>,
     repo_state: Option<&'a RepoState>,
 }
 
@@ -69,19 +235,42 @@ struct CachedGrepOutput {
 fn build_grep_cache_key(inputs: &GrepCacheKeyInputs<'_>) -> std::io::Result<String> {
     let GrepCacheKeyInputs {
         workspace_root,
-        search_path,
+        search_paths,
         pattern,
         include,
         limit,
+        pattern_options,
+        output,
+        max_output_chars,
         repo_state,
     } = inputs;
+    let mut normalized_paths = search_paths
+        .iter()
+        .map(|path| normalize_path(path))
+        .collect::<Vec<_>>();
+    normalized_paths.sort();
+    let (mode, context) = match output {
+        SearchOutput::Files => ("files", None),
+        SearchOutput::Content { context } => ("content", Some(context)),
+    };
     let fingerprint = serde_json::json!({
         "tool": "grep_files",
         "workspace": normalize_path(workspace_root),
-        "path": normalize_path(search_path),
+        "paths": normalized_paths,
         "pattern": pattern,
         "include": include,
         "limit": limit,
+        "case": pattern_options.case.as_str(),
+        "fixed_strings": pattern_options.fixed_strings,
+        "word": pattern_options.word,
+        "multiline": pattern_options.multiline,
+        "multiline_dotall": pattern_options.multiline_dotall,
+        "hidden": pattern_options.hidden,
+        "no_ignore": pattern_options.no_ignore,
+        "sort": pattern_options.sort.as_str(),
+        "mode": mode,
+        "context": context,
+        "max_output_chars": max_output_chars,
         "git": repo_state.map(|state| serde_json::json!({
             "head": state.head_ref,
             "index_mtime": state.index_mtime_nanos,
@@ -110,12 +299,8 @@ fn cache_ttl_for_repo_state(configured: Duration, repo_state: Option<&RepoState>
 }
 
 async fn detect_repo_state(workspace_root: &Path) -> Option<RepoState> {
-    let git_dir = resolve_git_dir(workspace_root).await?;
-    let head_ref = fs::read_to_string(git_dir.join("HEAD"))
-        .await
-        .ok()
-        .map(|value| value.trim().to_string())
-        .filter(|value| !value.is_empty());
+    let git_dir = resolve_git_dir(workspace_root)?;
+    let head_ref = read_git_head(&git_dir);
     let index_mtime_nanos = fs::metadata(git_dir.join("index"))
         .await
         .ok()
@@ -133,69 +318,6 @@ async fn detect_repo_state(workspace_root: &Path) -> Option<RepoState> {
     })
 }
 
-async fn resolve_git_dir(workspace_root: &Path) -> Option<PathBuf> {
-    let mut cursor = workspace_root.to_path_buf();
-    loop {
-        let candidate = cursor.join(".git");
-        match fs::metadata(&candidate).await {
-            Ok(metadata) => {
-                if metadata.is_dir() {
-                    return Some(candidate);
-                }
-                if metadata.is_file() {
-                    return parse_gitdir_file(&candidate, &cursor).await;
-                }
-            }
-            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
-            Err(_) => return None,
-        }
-
-        if let Some(parent) = cursor.parent() {
-            cursor = parent.to_path_buf();
-        } else {
-            return None;
-        }
-    }
-}
-
-async fn parse_gitdir_file(path: &Path, repo_root: &Path) -> Option<PathBuf> {
-    let contents = fs::read_to_string(path).await.ok()?;
-    for line in contents.lines() {
-        let trimmed = line.trim();
-        if let Some(rest) = trimmed.strip_prefix("gitdir:") {
-            let gitdir = rest.trim();
-            if gitdir.is_empty() {
-                return None;
-            }
-            let candidate = PathBuf::from(gitdir);
-            return if candidate.is_absolute() {
-                Some(candidate)
-            } else {
-                Some(repo_root.join(candidate))
-            };
-        }
-    }
-    None
-}
-
-fn canonical_json(value: &JsonValue) -> JsonValue {
-    match value {
-        JsonValue::Object(map) => {
-            let mut sorted = serde_json::Map::new();
-            let mut keys = map.keys().cloned().collect::<Vec<_>>();
-            keys.sort();
-            for key in keys {
-                if let Some(val) = map.get(&key) {
-                    sorted.insert(key, canonical_json(val));
-                }
-            }
-            JsonValue::Object(sorted)
-        }
-        JsonValue::Array(items) => JsonValue::Array(items.iter().map(canonical_json).collect()),
-        other => other.clone(),
-    }
-}
-
 fn normalize_path(path: &Path) -> String {
     path.to_string_lossy().replace('\\', "/")
 }
@@ -255,10 +377,35 @@ impl ToolHandler for GrepFilesHandler {
             ));
         }
 
+        if args
+            .max_output_chars
+            .is_some_and(|max| max < TRUNCATION_MARKER.len())
+        {
+            return Err(FunctionCallError::RespondToModel(format!(
+                "max_output_chars must be at least {}",
+                TRUNCATION_MARKER.len()
+            )));
+        }
+        if args.sort == GrepSort::Count && args.mode == GrepMode::Content {
+            return Err(FunctionCallError::RespondToModel(
+                "sort `count` is only supported in `files` mode".to_string(),
+            ));
+        }
+
         let limit = args.limit.min(MAX_LIMIT);
-        let search_path = turn.resolve_path(args.path.clone());
+        let output = SearchOutput::from_args(args.mode, args.context);
+        let requested_paths = args.path.clone().map(SearchPaths::into_vec);
+        let search_paths = match requested_paths {
+            Some(paths) if !paths.is_empty() => paths
+                .into_iter()
+                .map(|path| turn.resolve_path(Some(path)))
+                .collect::<Vec<_>>(),
+            _ => vec![turn.resolve_path(None)],
+        };
 
-        verify_path_exists(&search_path).await?;
+        for search_path in &search_paths {
+            verify_path_exists(search_path).await?;
+        }
 
         let include = args.include.as_deref().map(str::trim).and_then(|val| {
             if val.is_empty() {
@@ -268,6 +415,17 @@ impl ToolHandler for GrepFilesHandler {
             }
         });
 
+        let pattern_options = PatternOptions {
+            case: args.case,
+            fixed_strings: args.fixed_strings,
+            word: args.word,
+            multiline: args.multiline,
+            multiline_dotall: args.multiline && args.multiline_dotall,
+            hidden: args.hidden,
+            no_ignore: args.no_ignore,
+            sort: args.sort,
+        };
+
         let cache_manager = session.cache_manager();
         let repo_state = if cache_manager.enabled() {
             detect_repo_state(&turn.cwd).await
@@ -277,10 +435,13 @@ impl ToolHandler for GrepFilesHandler {
         let cache_key = if cache_manager.enabled() {
             let inputs = GrepCacheKeyInputs {
                 workspace_root: &turn.cwd,
-                search_path: &search_path,
+                search_paths: &search_paths,
                 pattern,
                 include: include.as_deref(),
                 limit,
+                pattern_options,
+                output,
+                max_output_chars: args.max_output_chars,
                 repo_state: repo_state.as_ref(),
             };
             match build_grep_cache_key(&inputs) {
@@ -317,14 +478,44 @@ impl ToolHandler for GrepFilesHandler {
             );
         }
 
-        let search_results =
-            run_rg_search(pattern, include.as_deref(), &search_path, limit, &turn.cwd).await?;
+        let use_builtin =
+            ripgrep::uses_builtin(turn.client.config().grep_backend, ripgrep::installed());
+        let search_results = if use_builtin {
+            run_builtin_search(
+                pattern,
+                include.as_deref(),
+                &search_paths,
+                limit,
+                pattern_options,
+                output,
+            )
+            .await?
+        } else {
+            run_rg_search(
+                pattern,
+                include.as_deref(),
+                &search_paths,
+                limit,
+                pattern_options,
+                output,
+                &turn.cwd,
+            )
+            .await?
+        };
 
-        let (content, success) = if search_results.is_empty() {
+        let (mut content, success) = if search_results.matches.is_empty() {
             ("No matches found.".to_string(), Some(false))
         } else {
-            (search_results.join("\n"), Some(true))
+            let joined = search_results.matches.join("\n");
+            let content = match args.max_output_chars {
+                Some(max_chars) => truncate_output(joined, max_chars),
+                None => joined,
+            };
+            (content, Some(true))
         };
+        if let Some(warning) = search_results.warning {
+            content.push_str(&format!("\n\nrg warnings:\n{warning}"));
+        }
 
         if let Some(cache_key) = cache_key {
             let cached = CachedGrepOutput {
@@ -349,7 +540,7 @@ impl ToolHandler for GrepFilesHandler {
     }
 }
 
-async fn verify_path_exists(path: &Path) -> Result<(), FunctionCallError> {
+pub(super) async fn verify_path_exists(path: &Path) -> Result<(), FunctionCallError> {
     tokio::fs::metadata(path).await.map_err(|err| {
         FunctionCallError::RespondToModel(format!("unable to access `{}`: {err}", path.display()))
     })?;
@@ -359,24 +550,68 @@ async fn verify_path_exists(path: &Path) -> Result<(), FunctionCallError> {
 async fn run_rg_search(
     pattern: &str,
     include: Option<&str>,
-    search_path: &Path,
+    search_paths: &[PathBuf],
     limit: usize,
+    pattern_options: PatternOptions,
+    output: SearchOutput,
     cwd: &Path,
-) -> Result<Vec<String>, FunctionCallError> {
+) -> Result<SearchResults, FunctionCallError> {
     let mut command = Command::new("rg");
-    command
-        .current_dir(cwd)
-        .arg("--files-with-matches")
-        .arg("--sortr=modified")
-        .arg("--regexp")
-        .arg(pattern)
-        .arg("--no-messages");
+    command.current_dir(cwd);
+    let count_matches = pattern_options.sort == GrepSort::Count;
+    match output {
+        SearchOutput::Files if count_matches => {
+            command.arg("--count").arg("--with-filename");
+        }
+        SearchOutput::Files => {
+            command.arg("--files-with-matches");
+        }
+        SearchOutput::Content { context } => {
+            command
+                .arg("--line-number")
+                .arg("--with-filename")
+                .arg("--no-heading")
+                .arg("--color=never")
+                .arg(format!("--context={context}"));
+        }
+    }
+    match pattern_options.sort {
+        GrepSort::Modified => {
+            command.arg("--sortr=modified");
+        }
+        GrepSort::Path => {
+            command.arg("--sort=path");
+        }
+        // Ranked by `rank_by_count` once all counts are in.
+        GrepSort::Count => {}
+    }
+    command.arg(pattern_options.case.rg_flag());
+    if pattern_options.fixed_strings {
+        command.arg("--fixed-strings");
+    }
+    if pattern_options.word {
+        command.arg("--word-regexp");
+    }
+    // `--files-with-matches` still reports whole files in multiline mode.
+    if pattern_options.multiline {
+        command.arg("--multiline");
+    }
+    if pattern_options.multiline_dotall {
+        command.arg("--multiline-dotall");
+    }
+    if pattern_options.hidden {
+        command.arg("--hidden");
+    }
+    if pattern_options.no_ignore {
+        command.arg("--no-ignore");
+    }
+    command.arg("--regexp").arg(pattern);
 
     if let Some(glob) = include {
         command.arg("--glob").arg(glob);
     }
 
-    command.arg("--").arg(search_path);
+    command.arg("--").args(search_paths);
 
     let output = timeout(COMMAND_TIMEOUT, command.output())
         .await
@@ -385,26 +620,378 @@ async fn run_rg_search(
         })?
         .map_err(|err| {
             FunctionCallError::RespondToModel(format!(
-                "failed to launch rg: {err}. Ensure ripgrep is installed and on PATH."
+                "failed to launch rg: {err}. Ensure ripgrep is installed and on PATH, or set `grep_backend = \"builtin\"`."
             ))
         })?;
 
-    match output.status.code() {
-        Some(0) => Ok(parse_results(&output.stdout, limit)),
-        Some(1) => Ok(Vec::new()),
+    let matches = match output.status.code() {
+        Some(0) if count_matches => rank_by_count(&output.stdout, limit),
+        Some(0) => parse_results(&output.stdout, limit),
+        Some(1) => Vec::new(),
         _ => {
             let stderr = String::from_utf8_lossy(&output.stderr);
-            Err(FunctionCallError::RespondToModel(format!(
+            return Err(FunctionCallError::RespondToModel(format!(
                 "rg failed: {stderr}"
-            )))
+            )));
+        }
+    };
+    Ok(SearchResults {
+        matches,
+        warning: rg_warning(&output.stderr),
+    })
+}
+
+/// Turns the stderr of a successful ripgrep run into a warning, capped at
+/// [`MAX_WARNING_CHARS`].
+fn rg_warning(stderr: &[u8]) -> Option<String> {
+    let stderr = String::from_utf8_lossy(stderr);
+    let stderr = stderr.trim();
+    if stderr.is_empty() {
+        return None;
+    }
+    Some(truncate_output(stderr.to_string(), MAX_WARNING_CHARS))
+}
+
+/// Searches without ripgrep, producing the same output as [`run_rg_search`]:
+/// matching paths (or `path:line:text` lines in content mode) in the requested
+/// [`GrepSort`] order. Like ripgrep, it skips binary files and, unless
+/// asked otherwise, honors ignore files and skips hidden files.
+async fn run_builtin_search(
+    pattern: &str,
+    include: Option<&str>,
+    search_paths: &[PathBuf],
+    limit: usize,
+    pattern_options: PatternOptions,
+    output: SearchOutput,
+) -> Result<SearchResults, FunctionCallError> {
+    let regex = build_builtin_regex(pattern, pattern_options)?;
+    let include = include.map(str::to_string);
+    let search_paths = search_paths.to_vec();
+    let search = tokio::task::spawn_blocking(move || {
+        builtin_search(
+            &regex,
+            include.as_deref(),
+            &search_paths,
+            limit,
+            pattern_options,
+            output,
+        )
+    });
+    timeout(COMMAND_TIMEOUT, search)
+        .await
+        .map_err(|_| {
+            FunctionCallError::RespondToModel("search timed out after 30 seconds".to_string())
+        })?
+        .map_err(|err| FunctionCallError::RespondToModel(format!("search failed: {err}")))?
+        .map(|matches| SearchResults {
+            matches,
+            warning: None,
+        })
+}
+
+/// A line found by [`find_literal_lines`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LineMatch {
+    /// Path of the file, relative to the searched root.
+    pub(crate) path: PathBuf,
+    pub(crate) line_number: usize,
+}
+
+/// Finds up to `limit` lines under `root` containing any of `terms` as
+/// literal text (smart case), searching the way `grep_files` does with the
+/// given `grep_backend`.
+pub(crate) async fn find_literal_lines(
+    root: &Path,
+    terms: &[&str],
+    limit: usize,
+    backend: GrepBackend,
+) -> Result<Vec<LineMatch>, FunctionCallError> {
+    let pattern = terms
+        .iter()
+        .map(|term| regex::escape(term))
+        .collect::<Vec<_>>()
+        .join("|");
+    if pattern.is_empty() {
+        return Ok(Vec::new());
+    }
+    let search_paths = [root.to_path_buf()];
+    let pattern_options = PatternOptions::default();
+    let output = SearchOutput::Content { context: 0 };
+    let results = if !ripgrep::uses_builtin(backend, ripgrep::installed()) {
+        run_rg_search(
+            &pattern,
+            None,
+            &search_paths,
+            limit,
+            pattern_options,
+            output,
+            root,
+        )
+        .await?
+    } else {
+        run_builtin_search(
+            &pattern,
+            None,
+            &search_paths,
+            limit,
+            pattern_options,
+            output,
+        )
+        .await?
+    };
+    Ok(results
+        .matches
+        .iter()
+        .filter_map(|line| parse_line_match(line, root))
+        .collect())
+}
+
+/// Parses a `path:line:text` match line whose path starts with `root`.
+fn parse_line_match(line: &str, root: &Path) -> Option<LineMatch> {
+    let root = root.display().to_string();
+    let rest = line
+        .strip_prefix(root.as_str())?
+        .trim_start_matches(['/', '\\']);
+    // Paths may contain `:`, so the path ends at the first `:<digits>:`.
+    let mut search_from = 0;
+    while let Some(offset) = rest[search_from..].find(':') {
+        let colon = search_from + offset;
+        let after = &rest[colon + 1..];
+        let digits = after
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(after.len());
+        if digits > 0 && after[digits..].starts_with(':') {
+            return Some(LineMatch {
+                path: PathBuf::from(&rest[..colon]),
+                line_number: after[..digits].parse().ok()?,
+            });
         }
+        search_from = colon + 1;
     }
+    None
 }
 
+fn build_builtin_regex(
+    pattern: &str,
+    pattern_options: PatternOptions,
+) -> Result<Regex, FunctionCallError> {
+    let mut source = if pattern_options.fixed_strings {
+        regex::escape(pattern)
+    } else {
+        pattern.to_string()
+    };
+    if pattern_options.word {
+        source = format!(r"\b(?:{source})\b");
+    }
+    let case_insensitive = match pattern_options.case {
+        CaseMode::Sensitive => false,
+        CaseMode::Insensitive => true,
+        CaseMode::Smart => !pattern.chars().any(char::is_uppercase),
+    };
+    RegexBuilder::new(&source)
+        .case_insensitive(case_insensitive)
+        .multi_line(true)
+        .dot_matches_new_line(pattern_options.multiline_dotall)
+        .build()
+        .map_err(|err| FunctionCallError::RespondToModel(format!("invalid pattern: {err}")))
+}
+
+fn builtin_search(
+    regex: &Regex,
+    include: Option<&str>,
+    search_paths: &[PathBuf],
+    limit: usize,
+    pattern_options: PatternOptions,
+    output: SearchOutput,
+) -> Result<Vec<String>, FunctionCallError> {
+    let multiline = pattern_options.multiline;
+    let Some((first_path, other_paths)) = search_paths.split_first() else {
+        return Ok(Vec::new());
+    };
+    let mut walker = WalkBuilder::new(first_path);
+    for path in other_paths {
+        walker.add(path);
+    }
+    walker
+        .hidden(!pattern_options.hidden)
+        .ignore(!pattern_options.no_ignore)
+        .git_ignore(!pattern_options.no_ignore)
+        .git_global(!pattern_options.no_ignore)
+        .git_exclude(!pattern_options.no_ignore)
+        .parents(!pattern_options.no_ignore);
+    if let Some(glob) = include {
+        // Globs without a `/` match file names under every search path.
+        let overrides = OverrideBuilder::new(first_path)
+            .add(glob)
+            .and_then(|builder| builder.build())
+            .map_err(|err| {
+                FunctionCallError::RespondToModel(format!("invalid include glob: {err}"))
+            })?;
+        walker.overrides(overrides);
+    }
+
+    let mut matches: Vec<(SystemTime, PathBuf, Vec<u8>, usize)> = Vec::new();
+    for entry in walker.build().flatten() {
+        if !entry
+            .file_type()
+            .is_some_and(|file_type| file_type.is_file())
+        {
+            continue;
+        }
+        let Ok(contents) = std::fs::read(entry.path()) else {
+            continue;
+        };
+        if contents.contains(&0) {
+            continue;
+        }
+        let match_count = matching_lines(&contents, regex, multiline)
+            .into_iter()
+            .filter(|is_match| *is_match)
+            .count();
+        if match_count == 0 {
+            continue;
+        }
+        let modified = entry
+            .metadata()
+            .ok()
+            .and_then(|metadata| metadata.modified().ok())
+            .unwrap_or(UNIX_EPOCH);
+        matches.push((modified, entry.into_path(), contents, match_count));
+    }
+    match pattern_options.sort {
+        GrepSort::Modified => matches.sort_by(|a, b| b.0.cmp(&a.0)),
+        GrepSort::Path => matches.sort_by(|a, b| a.1.cmp(&b.1)),
+        GrepSort::Count => matches.sort_by(|a, b| b.3.cmp(&a.3).then_with(|| a.1.cmp(&b.1))),
+    }
+
+    let mut results = Vec::new();
+    for (_, path, contents, _) in matches {
+        let display = path.display().to_string();
+        match output {
+            SearchOutput::Files => results.push(display),
+            SearchOutput::Content { context } => {
+                let is_match = matching_lines(&contents, regex, multiline);
+                results.extend(content_lines(&display, &contents, &is_match, context));
+            }
+        }
+        if results.len() >= limit {
+            results.truncate(limit);
+            break;
+        }
+    }
+    Ok(results)
+}
+
+/// Splits `contents` into lines without their terminators, dropping the empty
+/// line after a trailing newline.
+fn split_lines(contents: &[u8]) -> Vec<&[u8]> {
+    let mut lines = contents.split(|byte| *byte == b'\n').collect::<Vec<_>>();
+    if lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    lines
+        .into_iter()
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .collect()
+}
+
+/// Flags each line of `contents` that a match touches. Without `multiline`,
+/// lines are matched independently, as ripgrep does by default.
+fn matching_lines(contents: &[u8], regex: &Regex, multiline: bool) -> Vec<bool> {
+    if !multiline {
+        return split_lines(contents)
+            .into_iter()
+            .map(|line| regex.is_match(line))
+            .collect();
+    }
+    let line_count = split_lines(contents).len();
+    let mut line_starts = vec![0];
+    line_starts.extend(
+        contents
+            .iter()
+            .enumerate()
+            .filter(|(_, byte)| **byte == b'\n')
+            .map(|(offset, _)| offset + 1),
+    );
+    let line_of = |offset: usize| line_starts.partition_point(|start| *start <= offset) - 1;
+    let mut is_match = vec![false; line_count];
+    for found in regex.find_iter(contents) {
+        let first = line_of(found.start());
+        let last = line_of(found.end().saturating_sub(1).max(found.start()));
+        for flag in is_match.iter_mut().take(last + 1).skip(first) {
+            *flag = true;
+        }
+    }
+    is_match
+}
+
+/// Formats matching lines as `path:line:text` and surrounding context lines
+/// as `path-line-text`, mirroring ripgrep's `--no-heading` output.
+fn content_lines(display: &str, contents: &[u8], is_match: &[bool], context: usize) -> Vec<String> {
+    let mut out = Vec::new();
+    for (index, line) in split_lines(contents).into_iter().enumerate() {
+        let window_start = index.saturating_sub(context);
+        let window_end = (index + context).min(is_match.len() - 1);
+        if !is_match[window_start..=window_end].contains(&true) {
+            continue;
+        }
+        let separator = if is_match[index] { ':' } else { '-' };
+        let text = String::from_utf8_lossy(line);
+        out.push(format!(
+            "{display}{separator}{}{separator}{text}",
+            index + 1
+        ));
+    }
+    out
+}
+
+/// Orders `path:count` lines from `rg --count` by count descending (ties by
+/// path) and returns the top `limit` paths.
+fn rank_by_count(stdout: &[u8], limit: usize) -> Vec<String> {
+    let mut counts = parse_results(stdout, usize::MAX)
+        .into_iter()
+        .filter_map(|line| {
+            let (path, count) = line.rsplit_once(':')?;
+            Some((count.parse::<u64>().ok()?, path.to_string()))
+        })
+        .collect::<Vec<_>>();
+    counts.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    counts
+        .into_iter()
+        .take(limit)
+        .map(|(_, path)| path)
+        .collect()
+}
+
+/// Cuts `content` to at most `max_chars` characters, ending with
+/// [`TRUNCATION_MARKER`] when anything was dropped. Whole lines are kept where
+/// possible.
+fn truncate_output(content: String, max_chars: usize) -> String {
+    if content.chars().count() <= max_chars {
+        return content;
+    }
+    let budget = max_chars.saturating_sub(TRUNCATION_MARKER.len() + 1);
+    let cut = content
+        .char_indices()
+        .nth(budget)
+        .map_or(content.len(), |(index, _)| index);
+    let kept = &content[..cut];
+    let kept = match kept.rfind('\n') {
+        Some(newline) => &kept[..newline],
+        None => kept,
+    };
+    if kept.is_empty() {
+        return TRUNCATION_MARKER.to_string();
+    }
+    format!("{kept}\n{TRUNCATION_MARKER}")
+}
+
+/// Splits ripgrep output into lines, dropping the `--` separators it prints
+/// between non-adjacent context groups.
 fn parse_results(stdout: &[u8], limit: usize) -> Vec<String> {
     let mut results = Vec::new();
     for line in stdout.split(|byte| *byte == b'\n') {
-        if line.is_empty() {
+        if line.is_empty() || line == b"--" {
             continue;
         }
         if let Ok(text) = std::str::from_utf8(line) {
@@ -423,7 +1010,10 @@ fn parse_results(stdout: &[u8], limit: usize) -> Vec<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cache::config::CacheConfig;
     use crate::cache::config::DEFAULT_CACHE_GREP_FILES_TTL_SECS;
+    use crate::cache::manager::CacheManager;
+    use crate::cache::store::InMemoryCacheStore;
     use pretty_assertions::assert_eq;
     use std::process::Command as StdCommand;
     use std::time::Duration;
@@ -460,7 +1050,17 @@ mod tests {
         std::fs::write(dir.join("match_two.txt"), "alpha delta").unwrap();
         std::fs::write(dir.join("other.txt"), "omega").unwrap();
 
-        let results = run_rg_search("alpha", None, dir, 10, dir).await?;
+        let results = run_rg_search(
+            "alpha",
+            None,
+            &[dir.to_path_buf()],
+            10,
+            PatternOptions::default(),
+            SearchOutput::Files,
+            dir,
+        )
+        .await?
+        .matches;
         assert_eq!(results.len(), 2);
         assert!(results.iter().any(|path| path.ends_with("match_one.txt")));
         assert!(results.iter().any(|path| path.ends_with("match_two.txt")));
@@ -477,7 +1077,17 @@ mod tests {
         std::fs::write(dir.join("match_one.rs"), "alpha beta gamma").unwrap();
         std::fs::write(dir.join("match_two.txt"), "alpha delta").unwrap();
 
-        let results = run_rg_search("alpha", Some("*.rs"), dir, 10, dir).await?;
+        let results = run_rg_search(
+            "alpha",
+            Some("*.rs"),
+            &[dir.to_path_buf()],
+            10,
+            PatternOptions::default(),
+            SearchOutput::Files,
+            dir,
+        )
+        .await?
+        .matches;
         assert_eq!(results.len(), 1);
         assert!(results.iter().all(|path| path.ends_with("match_one.rs")));
         Ok(())
@@ -494,11 +1104,762 @@ mod tests {
         std::fs::write(dir.join("two.txt"), "alpha two").unwrap();
         std::fs::write(dir.join("three.txt"), "alpha three").unwrap();
 
-        let results = run_rg_search("alpha", None, dir, 2, dir).await?;
+        let results = run_rg_search(
+            "alpha",
+            None,
+            &[dir.to_path_buf()],
+            2,
+            PatternOptions::default(),
+            SearchOutput::Files,
+            dir,
+        )
+        .await?
+        .matches;
         assert_eq!(results.len(), 2);
         Ok(())
     }
 
+    #[tokio::test]
+    async fn run_search_ignores_case_when_requested() -> anyhow::Result<()> {
+        if !rg_available() {
+            return Ok(());
+        }
+        let temp = tempdir().expect("create temp dir");
+        let dir = temp.path();
+        std::fs::write(dir.join("mixed.txt"), "let AlphaBeta = 1;").unwrap();
+
+        let sensitive = run_rg_search(
+            "alphabeta",
+            None,
+            &[dir.to_path_buf()],
+            10,
+            PatternOptions {
+                case: CaseMode::Sensitive,
+                ..PatternOptions::default()
+            },
+            SearchOutput::Files,
+            dir,
+        )
+        .await?
+        .matches;
+        let insensitive = run_rg_search(
+            "alphabeta",
+            None,
+            &[dir.to_path_buf()],
+            10,
+            PatternOptions {
+                case: CaseMode::Insensitive,
+                ..PatternOptions::default()
+            },
+            SearchOutput::Files,
+            dir,
+        )
+        .await?
+        .matches;
+        let smart_upper = run_rg_search(
+            "Alphabeta",
+            None,
+            &[dir.to_path_buf()],
+            10,
+            PatternOptions::default(),
+            SearchOutput::Files,
+            dir,
+        )
+        .await?
+        .matches;
+
+        assert!(sensitive.is_empty());
+        assert_eq!(insensitive.len(), 1);
+        assert!(insensitive[0].ends_with("mixed.txt"));
+        assert!(smart_upper.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn run_search_content_mode_reports_lines_with_context() -> anyhow::Result<()> {
+        if !rg_available() {
+            return Ok(());
+        }
+        let temp = tempdir().expect("create temp dir");
+        let dir = temp.path();
+        std::fs::write(
+            dir.join("code.rs"),
+            "fn one() {}\nlet alpha = 1;\nfn two() {}\n",
+        )
+        .unwrap();
+
+        let bare = run_rg_search(
+            "alpha",
+            None,
+            &[dir.to_path_buf()],
+            10,
+            PatternOptions::default(),
+            SearchOutput::Content { context: 0 },
+            dir,
+        )
+        .await?
+        .matches;
+        let with_context = run_rg_search(
+            "alpha",
+            None,
+            &[dir.to_path_buf()],
+            10,
+            PatternOptions::default(),
+            SearchOutput::Content { context: 1 },
+            dir,
+        )
+        .await?
+        .matches;
+
+        assert_eq!(bare.len(), 1);
+        assert!(bare[0].ends_with("code.rs:2:let alpha = 1;"), "{bare:?}");
+        assert_eq!(with_context.len(), 3);
+        assert!(
+            with_context[0].ends_with("code.rs-1-fn one() {}"),
+            "{with_context:?}"
+        );
+        assert!(with_context[1].ends_with("code.rs:2:let alpha = 1;"));
+        assert!(with_context[2].ends_with("code.rs-3-fn two() {}"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn run_search_fixed_strings_matches_literally() -> anyhow::Result<()> {
+        if !rg_available() {
+            return Ok(());
+        }
+        let temp = tempdir().expect("create temp dir");
+        let dir = temp.path();
+        std::fs::write(dir.join("literal.rs"), "let x = foo(bar);").unwrap();
+        std::fs::write(dir.join("regex.rs"), "let y = foobar;").unwrap();
+        let fixed = PatternOptions {
+            fixed_strings: true,
+            ..PatternOptions::default()
+        };
+
+        let literal = run_rg_search(
+            "foo(bar)",
+            None,
+            &[dir.to_path_buf()],
+            10,
+            fixed,
+            SearchOutput::Files,
+            dir,
+        )
+        .await?
+        .matches;
+        let unbalanced = run_rg_search(
+            "foo(bar",
+            None,
+            &[dir.to_path_buf()],
+            10,
+            fixed,
+            SearchOutput::Files,
+            dir,
+        )
+        .await?
+        .matches;
+        let regex = run_rg_search(
+            "foo(bar)",
+            None,
+            &[dir.to_path_buf()],
+            10,
+            PatternOptions::default(),
+            SearchOutput::Files,
+            dir,
+        )
+        .await?
+        .matches;
+
+        assert_eq!(literal.len(), 1);
+        assert!(literal[0].ends_with("literal.rs"));
+        assert_eq!(unbalanced, literal);
+        assert_eq!(regex.len(), 1);
+        assert!(regex[0].ends_with("regex.rs"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn multiline_pattern_only_matches_when_enabled() -> anyhow::Result<()> {
+        let temp = tempdir().expect("create temp dir");
+        let dir = temp.path();
+        std::fs::write(dir.join("lib.rs"), "fn alpha() {\n    body();\n}\n").unwrap();
+        let pattern = r"fn\s+\w+\(\)\s*\{[\s\S]*?\}";
+        let multiline = PatternOptions {
+            multiline: true,
+            ..PatternOptions::default()
+        };
+
+        let single_line = run_builtin_search(
+            pattern,
+            None,
+            &[dir.to_path_buf()],
+            10,
+            PatternOptions::default(),
+            SearchOutput::Files,
+        )
+        .await?
+        .matches;
+        let spanning = run_builtin_search(
+            pattern,
+            None,
+            &[dir.to_path_buf()],
+            10,
+            multiline,
+            SearchOutput::Content { context: 0 },
+        )
+        .await?
+        .matches;
+        assert!(single_line.is_empty());
+        assert_eq!(spanning.len(), 3);
+        assert!(spanning[2].ends_with("lib.rs:3:}"), "{spanning:?}");
+
+        if rg_available() {
+            let rg_single_line = run_rg_search(
+                pattern,
+                None,
+                &[dir.to_path_buf()],
+                10,
+                PatternOptions::default(),
+                SearchOutput::Files,
+                dir,
+            )
+            .await?
+            .matches;
+            let rg_spanning = run_rg_search(
+                pattern,
+                None,
+                &[dir.to_path_buf()],
+                10,
+                multiline,
+                SearchOutput::Files,
+                dir,
+            )
+            .await?
+            .matches;
+            assert!(rg_single_line.is_empty());
+            assert_eq!(rg_spanning.len(), 1);
+            assert!(rg_spanning[0].ends_with("lib.rs"));
+        }
+        Ok(())
+    }
+
+    /// Cache key inputs for a files-mode search of `workspace` with every
+    /// option at its default. Tests override the fields they care about;
+    /// `search_paths` is empty unless a test sets it.
+    fn base_key_inputs(workspace: &Path) -> GrepCacheKeyInputs<'_> {
+        GrepCacheKeyInputs {
+            workspace_root: workspace,
+            search_paths: &[],
+            pattern: "alpha",
+            include: None,
+            limit: 10,
+            pattern_options: PatternOptions::default(),
+            output: SearchOutput::Files,
+            max_output_chars: None,
+            repo_state: None,
+        }
+    }
+
+    #[test]
+    fn cache_key_changes_with_every_search_input() {
+        let workspace = tempdir().expect("tempdir");
+        let base = base_key_inputs(workspace.path());
+        let options = |pattern_options: PatternOptions| GrepCacheKeyInputs {
+            pattern_options,
+            ..base
+        };
+        let defaults = PatternOptions::default();
+        let repo_state = RepoState {
+            head_ref: Some("ref: refs/heads/main".to_string()),
+            index_mtime_nanos: Some(1),
+        };
+        let mutations = [
+            (
+                "pattern",
+                GrepCacheKeyInputs {
+                    pattern: "beta",
+                    ..base
+                },
+            ),
+            (
+                "include",
+                GrepCacheKeyInputs {
+                    include: Some("*.rs"),
+                    ..base
+                },
+            ),
+            ("limit", GrepCacheKeyInputs { limit: 20, ..base }),
+            (
+                "case sensitive",
+                options(PatternOptions {
+                    case: CaseMode::Sensitive,
+                    ..defaults
+                }),
+            ),
+            (
+                "case insensitive",
+                options(PatternOptions {
+                    case: CaseMode::Insensitive,
+                    ..defaults
+                }),
+            ),
+            (
+                "fixed_strings",
+                options(PatternOptions {
+                    fixed_strings: true,
+                    ..defaults
+                }),
+            ),
+            (
+                "word",
+                options(PatternOptions {
+                    word: true,
+                    ..defaults
+                }),
+            ),
+            (
+                "multiline",
+                options(PatternOptions {
+                    multiline: true,
+                    ..defaults
+                }),
+            ),
+            (
+                "multiline_dotall",
+                options(PatternOptions {
+                    multiline: true,
+                    multiline_dotall: true,
+                    ..defaults
+                }),
+            ),
+            (
+                "hidden",
+                options(PatternOptions {
+                    hidden: true,
+                    ..defaults
+                }),
+            ),
+            (
+                "no_ignore",
+                options(PatternOptions {
+                    no_ignore: true,
+                    ..defaults
+                }),
+            ),
+            (
+                "sort path",
+                options(PatternOptions {
+                    sort: GrepSort::Path,
+                    ..defaults
+                }),
+            ),
+            (
+                "sort count",
+                options(PatternOptions {
+                    sort: GrepSort::Count,
+                    ..defaults
+                }),
+            ),
+            (
+                "content mode",
+                GrepCacheKeyInputs {
+                    output: SearchOutput::Content { context: 0 },
+                    ..base
+                },
+            ),
+            (
+                "context",
+                GrepCacheKeyInputs {
+                    output: SearchOutput::Content { context: 2 },
+                    ..base
+                },
+            ),
+            (
+                "max_output_chars",
+                GrepCacheKeyInputs {
+                    max_output_chars: Some(100),
+                    ..base
+                },
+            ),
+            (
+                "repo_state",
+                GrepCacheKeyInputs {
+                    repo_state: Some(&repo_state),
+                    ..base
+                },
+            ),
+        ];
+
+        let base_key = build_grep_cache_key(&base).expect("base key");
+        let mut keys = std::collections::HashSet::from([base_key.clone()]);
+        for (name, inputs) in &mutations {
+            let key = build_grep_cache_key(inputs).expect("key");
+            assert_ne!(key, base_key, "{name} did not change the key");
+            assert!(keys.insert(key), "{name} collided with another mutation");
+        }
+    }
+
+    #[tokio::test]
+    async fn word_option_skips_substring_matches() -> anyhow::Result<()> {
+        let temp = tempdir().expect("create temp dir");
+        let dir = temp.path();
+        std::fs::write(dir.join("binding.rs"), "let id = 1;").unwrap();
+        std::fs::write(dir.join("media.rs"), "let video = 2;").unwrap();
+        let word = PatternOptions {
+            word: true,
+            ..PatternOptions::default()
+        };
+
+        let builtin = run_builtin_search(
+            "id",
+            None,
+            &[dir.to_path_buf()],
+            10,
+            word,
+            SearchOutput::Files,
+        )
+        .await?
+        .matches;
+        assert_eq!(builtin.len(), 1);
+        assert!(builtin[0].ends_with("binding.rs"));
+
+        if rg_available() {
+            let rg = run_rg_search(
+                "id",
+                None,
+                &[dir.to_path_buf()],
+                10,
+                word,
+                SearchOutput::Files,
+                dir,
+            )
+            .await?
+            .matches;
+            assert_eq!(rg.len(), 1);
+            assert!(rg[0].ends_with("binding.rs"));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn hidden_files_are_searched_only_when_requested() -> anyhow::Result<()> {
+        let temp = tempdir().expect("create temp dir");
+        let dir = temp.path();
+        std::fs::create_dir(dir.join(".github")).unwrap();
+        std::fs::write(dir.join(".github").join("ci.yml"), "deploy: true").unwrap();
+        let hidden = PatternOptions {
+            hidden: true,
+            ..PatternOptions::default()
+        };
+
+        let default = run_builtin_search(
+            "deploy",
+            None,
+            &[dir.to_path_buf()],
+            10,
+            PatternOptions::default(),
+            SearchOutput::Files,
+        )
+        .await?
+        .matches;
+        let with_hidden = run_builtin_search(
+            "deploy",
+            None,
+            &[dir.to_path_buf()],
+            10,
+            hidden,
+            SearchOutput::Files,
+        )
+        .await?
+        .matches;
+        assert!(default.is_empty());
+        assert_eq!(with_hidden.len(), 1);
+        assert!(with_hidden[0].ends_with("ci.yml"));
+
+        if rg_available() {
+            let rg_default = run_rg_search(
+                "deploy",
+                None,
+                &[dir.to_path_buf()],
+                10,
+                PatternOptions::default(),
+                SearchOutput::Files,
+                dir,
+            )
+            .await?
+            .matches;
+            let rg_hidden = run_rg_search(
+                "deploy",
+                None,
+                &[dir.to_path_buf()],
+                10,
+                hidden,
+                SearchOutput::Files,
+                dir,
+            )
+            .await?
+            .matches;
+            assert!(rg_default.is_empty());
+            assert_eq!(rg_hidden.len(), 1);
+            assert!(rg_hidden[0].ends_with("ci.yml"));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rg_warnings_are_returned_alongside_matches() -> anyhow::Result<()> {
+        if !rg_available() {
+            return Ok(());
+        }
+        let temp = tempdir().expect("create temp dir");
+        let dir = temp.path();
+        std::fs::write(dir.join(".ignore"), "[\n").unwrap();
+        std::fs::write(dir.join("match.rs"), "alpha").unwrap();
+
+        let results = run_rg_search(
+            "alpha",
+            None,
+            &[dir.to_path_buf()],
+            10,
+            PatternOptions::default(),
+            SearchOutput::Files,
+            dir,
+        )
+        .await?;
+
+        assert_eq!(results.matches.len(), 1);
+        assert!(results.matches[0].ends_with("match.rs"));
+        let warning = results.warning.expect("warning");
+        assert!(warning.contains("error parsing glob"), "{warning}");
+        Ok(())
+    }
+
+    #[test]
+    fn rg_warning_ignores_blank_stderr_and_caps_length() {
+        assert_eq!(rg_warning(b"  \n"), None);
+
+        let long = "rg: too many open files\n".repeat(100);
+        let warning = rg_warning(long.as_bytes()).expect("warning");
+
+        assert!(warning.chars().count() <= MAX_WARNING_CHARS);
+        assert!(warning.ends_with(TRUNCATION_MARKER));
+    }
+
+    #[test]
+    fn path_accepts_a_string_or_an_array() {
+        let single: GrepFilesArgs =
+            serde_json::from_value(serde_json::json!({ "pattern": "a", "path": "src" }))
+                .expect("single path");
+        let many: GrepFilesArgs =
+            serde_json::from_value(serde_json::json!({ "pattern": "a", "path": ["src", "tests"] }))
+                .expect("path list");
+
+        assert_eq!(single.path, Some(SearchPaths::One("src".to_string())));
+        assert_eq!(
+            many.path.map(SearchPaths::into_vec),
+            Some(vec!["src".to_string(), "tests".to_string()])
+        );
+    }
+
+    #[tokio::test]
+    async fn multiple_paths_return_union_of_matches() -> anyhow::Result<()> {
+        let temp = tempdir().expect("create temp dir");
+        let dir = temp.path();
+        for name in ["src", "tests", "docs"] {
+            std::fs::create_dir(dir.join(name)).unwrap();
+            std::fs::write(dir.join(name).join("file.txt"), "alpha").unwrap();
+        }
+        let search_paths = [dir.join("src"), dir.join("tests")];
+        let expected = vec![
+            dir.join("src").join("file.txt").display().to_string(),
+            dir.join("tests").join("file.txt").display().to_string(),
+        ];
+        let by_path = PatternOptions {
+            sort: GrepSort::Path,
+            ..PatternOptions::default()
+        };
+
+        let single = run_builtin_search(
+            "alpha",
+            None,
+            &search_paths[..1],
+            10,
+            by_path,
+            SearchOutput::Files,
+        )
+        .await?
+        .matches;
+        let union = run_builtin_search(
+            "alpha",
+            None,
+            &search_paths,
+            10,
+            by_path,
+            SearchOutput::Files,
+        )
+        .await?
+        .matches;
+        assert_eq!(single, expected[..1].to_vec());
+        assert_eq!(union, expected);
+
+        if rg_available() {
+            let rg_union = run_rg_search(
+                "alpha",
+                None,
+                &search_paths,
+                10,
+                by_path,
+                SearchOutput::Files,
+                dir,
+            )
+            .await?
+            .matches;
+            assert_eq!(rg_union, expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn cache_key_uses_sorted_path_list() {
+        let workspace = tempdir().expect("tempdir");
+        let src = workspace.path().join("src");
+        let tests = workspace.path().join("tests");
+        let key_for = |search_paths: &[PathBuf]| {
+            build_grep_cache_key(&GrepCacheKeyInputs {
+                search_paths,
+                ..base_key_inputs(workspace.path())
+            })
+            .expect("key")
+        };
+
+        let forward = key_for(&[src.clone(), tests.clone()]);
+
+        assert_eq!(forward, key_for(&[tests, src.clone()]));
+        assert_ne!(forward, key_for(&[src]));
+    }
+
+    #[tokio::test]
+    async fn count_sort_ranks_files_by_match_count() -> anyhow::Result<()> {
+        let temp = tempdir().expect("create temp dir");
+        let dir = temp.path();
+        std::fs::write(dir.join("many.rs"), "alpha\nalpha\nalpha\n").unwrap();
+        std::fs::write(dir.join("few.rs"), "alpha\n").unwrap();
+        std::fs::write(dir.join("some.rs"), "alpha\nalpha\n").unwrap();
+        let many = std::fs::File::options()
+            .write(true)
+            .open(dir.join("many.rs"))?;
+        many.set_modified(SystemTime::now() - Duration::from_secs(60))?;
+        let by_count = PatternOptions {
+            sort: GrepSort::Count,
+            ..PatternOptions::default()
+        };
+        let expected = vec![
+            dir.join("many.rs").display().to_string(),
+            dir.join("some.rs").display().to_string(),
+        ];
+
+        let builtin = run_builtin_search(
+            "alpha",
+            None,
+            &[dir.to_path_buf()],
+            2,
+            by_count,
+            SearchOutput::Files,
+        )
+        .await?
+        .matches;
+        assert_eq!(builtin, expected);
+
+        if rg_available() {
+            let rg = run_rg_search(
+                "alpha",
+                None,
+                &[dir.to_path_buf()],
+                2,
+                by_count,
+                SearchOutput::Files,
+                dir,
+            )
+            .await?
+            .matches;
+            assert_eq!(rg, expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn rank_by_count_orders_by_count_then_path() {
+        let stdout = b"b.rs:2\na.rs:2\nc:d.rs:7\nz.rs:1\n";
+
+        assert_eq!(
+            rank_by_count(stdout, 3),
+            vec!["c:d.rs".to_string(), "a.rs".to_string(), "b.rs".to_string()]
+        );
+    }
+
+    #[test]
+    fn truncate_output_respects_budget_and_marks_truncation() {
+        let content = (1..=50)
+            .map(|line| format!("src/lib.rs:{line}:let value = {line};"))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        for max_chars in [TRUNCATION_MARKER.len(), 20, 64, 500] {
+            let truncated = truncate_output(content.clone(), max_chars);
+            assert!(
+                truncated.chars().count() <= max_chars,
+                "{max_chars}: {truncated:?}"
+            );
+            assert!(truncated.ends_with(TRUNCATION_MARKER), "{truncated:?}");
+        }
+        assert_eq!(
+            truncate_output(content.clone(), 70),
+            "src/lib.rs:1:let value = 1;\nsrc/lib.rs:2:let value = 2;\n(truncated)"
+        );
+        assert_eq!(truncate_output(content.clone(), content.len()), content);
+    }
+
+    #[tokio::test]
+    async fn builtin_search_honors_no_ignore() -> anyhow::Result<()> {
+        let temp = tempdir().expect("create temp dir");
+        let dir = temp.path();
+        std::fs::write(dir.join(".ignore"), "generated.rs\n").unwrap();
+        std::fs::write(dir.join("generated.rs"), "alpha").unwrap();
+        let no_ignore = PatternOptions {
+            no_ignore: true,
+            ..PatternOptions::default()
+        };
+
+        let default = run_builtin_search(
+            "alpha",
+            None,
+            &[dir.to_path_buf()],
+            10,
+            PatternOptions::default(),
+            SearchOutput::Files,
+        )
+        .await?
+        .matches;
+        let unignored = run_builtin_search(
+            "alpha",
+            None,
+            &[dir.to_path_buf()],
+            10,
+            no_ignore,
+            SearchOutput::Files,
+        )
+        .await?
+        .matches;
+
+        assert!(default.is_empty());
+        assert_eq!(
+            unignored,
+            vec![dir.join("generated.rs").display().to_string()]
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn run_search_handles_no_matches() -> anyhow::Result<()> {
         if !rg_available() {
@@ -508,7 +1869,17 @@ mod tests {
         let dir = temp.path();
         std::fs::write(dir.join("one.txt"), "omega").unwrap();
 
-        let results = run_rg_search("alpha", None, dir, 5, dir).await?;
+        let results = run_rg_search(
+            "alpha",
+            None,
+            &[dir.to_path_buf()],
+            5,
+            PatternOptions::default(),
+            SearchOutput::Files,
+            dir,
+        )
+        .await?
+        .matches;
         assert!(results.is_empty());
         Ok(())
     }
@@ -529,6 +1900,35 @@ mod tests {
         assert_eq!(decoded.success, payload.success);
     }
 
+    #[test]
+    fn cached_output_is_served_from_injected_store() {
+        let codex_home = tempdir().expect("tempdir");
+        let config = CacheConfig::new(codex_home.path(), None).expect("cache config");
+        let cache_manager =
+            CacheManager::with_store(config, std::sync::Arc::new(InMemoryCacheStore::new()));
+        let key = build_grep_cache_key(&base_key_inputs(codex_home.path())).expect("cache key");
+        assert!(cache_manager.get(&key, CacheableTool::GrepFiles).is_none());
+
+        let payload = CachedGrepOutput {
+            content: "/tmp/alpha.rs".to_string(),
+            success: Some(true),
+        };
+        cache_manager.put(
+            key.clone(),
+            serde_json::to_vec(&payload).expect("encode cache output"),
+            cache_ttl_for_repo_state(cache_manager.ttl_for(CacheableTool::GrepFiles), None),
+            CacheableTool::GrepFiles,
+        );
+
+        let cached = cache_manager
+            .get(&key, CacheableTool::GrepFiles)
+            .and_then(|bytes| decode_cached_output(&bytes))
+            .expect("cached output");
+        assert_eq!(cached.content, payload.content);
+        assert_eq!(cached.success, payload.success);
+        assert!(!codex_home.path().join("cache").exists());
+    }
+
     #[tokio::test]
     async fn detects_repo_state_from_git_dir() {
         let workspace = tempdir().expect("tempdir");
@@ -579,12 +1979,9 @@ mod tests {
             index_mtime_nanos: Some(1),
         };
         let inputs = GrepCacheKeyInputs {
-            workspace_root: workspace.path(),
-            search_path: &search_path,
-            pattern: "alpha",
-            include: None,
-            limit: 10,
+            search_paths: std::slice::from_ref(&search_path),
             repo_state: Some(&first),
+            ..base_key_inputs(workspace.path())
         };
         let first_key = build_grep_cache_key(&inputs).expect("first key");
         let second_inputs = GrepCacheKeyInputs {
@@ -604,6 +2001,182 @@ mod tests {
         assert_eq!(ttl, Duration::from_secs(DEFAULT_CACHE_GREP_FILES_TTL_SECS));
     }
 
+    #[tokio::test]
+    async fn builtin_search_lists_files_by_recency() -> anyhow::Result<()> {
+        let temp = tempdir().expect("create temp dir");
+        let dir = temp.path();
+        std::fs::write(dir.join("older.rs"), "alpha one").unwrap();
+        std::fs::write(dir.join("newer.rs"), "alpha two").unwrap();
+        std::fs::write(dir.join("notes.txt"), "alpha three").unwrap();
+        std::fs::write(dir.join("other.rs"), "omega").unwrap();
+        let older = std::fs::File::options()
+            .write(true)
+            .open(dir.join("older.rs"))?;
+        older.set_modified(SystemTime::now() - Duration::from_secs(60))?;
+
+        let results = run_builtin_search(
+            "alpha",
+            Some("*.rs"),
+            &[dir.to_path_buf()],
+            10,
+            PatternOptions::default(),
+            SearchOutput::Files,
+        )
+        .await?
+        .matches;
+
+        assert_eq!(
+            results,
+            vec![
+                dir.join("newer.rs").display().to_string(),
+                dir.join("older.rs").display().to_string(),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn builtin_search_applies_pattern_options_and_limit() -> anyhow::Result<()> {
+        let temp = tempdir().expect("create temp dir");
+        let dir = temp.path();
+        std::fs::write(dir.join("one.rs"), "let x = foo(bar);").unwrap();
+        std::fs::write(dir.join("two.rs"), "let y = FOOBAR;").unwrap();
+        std::fs::write(dir.join("binary.bin"), b"foobar\0").unwrap();
+
+        let literal = run_builtin_search(
+            "foo(bar)",
+            None,
+            &[dir.to_path_buf()],
+            10,
+            PatternOptions {
+                fixed_strings: true,
+                ..PatternOptions::default()
+            },
+            SearchOutput::Files,
+        )
+        .await?
+        .matches;
+        let insensitive = run_builtin_search(
+            "foobar",
+            None,
+            &[dir.to_path_buf()],
+            10,
+            PatternOptions::default(),
+            SearchOutput::Files,
+        )
+        .await?
+        .matches;
+        let limited = run_builtin_search(
+            "let",
+            None,
+            &[dir.to_path_buf()],
+            1,
+            PatternOptions::default(),
+            SearchOutput::Files,
+        )
+        .await?
+        .matches;
+
+        assert_eq!(literal, vec![dir.join("one.rs").display().to_string()]);
+        assert_eq!(insensitive, vec![dir.join("two.rs").display().to_string()]);
+        assert_eq!(limited.len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn builtin_search_content_mode_matches_ripgrep_format() -> anyhow::Result<()> {
+        let temp = tempdir().expect("create temp dir");
+        let dir = temp.path();
+        std::fs::write(
+            dir.join("code.rs"),
+            "fn one() {}\nlet alpha = 1;\nfn two() {}\nfn three() {}\n",
+        )
+        .unwrap();
+        let path = dir.join("code.rs").display().to_string();
+
+        let results = run_builtin_search(
+            "alpha",
+            None,
+            &[dir.to_path_buf()],
+            10,
+            PatternOptions::default(),
+            SearchOutput::Content { context: 1 },
+        )
+        .await?
+        .matches;
+
+        assert_eq!(
+            results,
+            vec![
+                format!("{path}-1-fn one() {{}}"),
+                format!("{path}:2:let alpha = 1;"),
+                format!("{path}-3-fn two() {{}}"),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn builtin_search_rejects_invalid_regex() {
+        let temp = tempdir().expect("create temp dir");
+
+        let result = run_builtin_search(
+            "foo(bar",
+            None,
+            &[temp.path().to_path_buf()],
+            10,
+            PatternOptions::default(),
+            SearchOutput::Files,
+        )
+        .await;
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn parse_line_match_strips_root_and_reads_line_number() {
+        let root = Path::new("/work/repo");
+
+        assert_eq!(
+            parse_line_match("/work/repo/src/a:b.rs:12:let x = 1:2;", root),
+            Some(LineMatch {
+                path: PathBuf::from("src/a:b.rs"),
+                line_number: 12,
+            })
+        );
+        assert_eq!(parse_line_match("/elsewhere/src/lib.rs:3:fn", root), None);
+        assert_eq!(parse_line_match("/work/repo/src/lib.rs-3-fn", root), None);
+    }
+
+    #[tokio::test]
+    async fn find_literal_lines_matches_any_term() -> anyhow::Result<()> {
+        let temp = tempdir()?;
+        let dir = temp.path();
+        std::fs::write(dir.join("one.txt"), "alpha\nbeta (literal)\n")?;
+        std::fs::write(dir.join("two.txt"), "gamma\n")?;
+
+        let expected = vec![
+            LineMatch {
+                path: PathBuf::from("one.txt"),
+                line_number: 2,
+            },
+            LineMatch {
+                path: PathBuf::from("two.txt"),
+                line_number: 1,
+            },
+        ];
+        let mut backends = vec![GrepBackend::Builtin];
+        if rg_available() {
+            backends.push(GrepBackend::Ripgrep);
+        }
+        for backend in backends {
+            let mut matches = find_literal_lines(dir, &["(literal)", "gamma"], 10, backend).await?;
+            matches.sort_by(|a, b| a.path.cmp(&b.path));
+            assert_eq!(matches, expected, "{backend:?}");
+        }
+        Ok(())
+    }
+
     fn rg_available() -> bool {
         StdCommand::new("rg")
             .arg("--version")
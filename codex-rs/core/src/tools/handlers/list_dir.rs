@@ -13,11 +13,12 @@ use tracing::warn;
 
 use crate::cache::LOG_TARGET;
 use crate::cache::config::CacheableTool;
-use crate::cache::tool_cache::build_tool_cache_key_for_path;
+use crate::cache::tool_cache::build_tool_cache_key_for_dir;
 use crate::function_tool::FunctionCallError;
 use crate::tools::context::ToolInvocation;
 use crate::tools::context::ToolOutput;
 use crate::tools::context::ToolPayload;
+use crate::tools::handlers::grep_files::verify_path_exists;
 use crate::tools::registry::ToolHandler;
 use crate::tools::registry::ToolKind;
 
@@ -38,15 +39,50 @@ fn default_depth() -> usize {
     2
 }
 
+fn default_include_hidden() -> bool {
+    true
+}
+
 #[derive(Deserialize, Serialize)]
 struct ListDirArgs {
+    #[serde(alias = "path")]
     dir_path: String,
     #[serde(default = "default_offset")]
     offset: usize,
     #[serde(default = "default_limit")]
     limit: usize,
-    #[serde(default = "default_depth")]
+    #[serde(default = "default_depth", alias = "max_depth")]
     depth: usize,
+    #[serde(default = "default_include_hidden")]
+    include_hidden: bool,
+    #[serde(default)]
+    entry_type: EntryTypeFilter,
+}
+
+/// Which kinds of entries a listing reports.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum EntryTypeFilter {
+    /// Everything except directories, listed by their relative path.
+    Files,
+    Dirs,
+    #[default]
+    Both,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ListFilter {
+    include_hidden: bool,
+    entry_type: EntryTypeFilter,
+}
+
+impl Default for ListFilter {
+    fn default() -> Self {
+        Self {
+            include_hidden: default_include_hidden(),
+            entry_type: EntryTypeFilter::default(),
+        }
+    }
 }
 
 #[async_trait]
@@ -89,6 +125,8 @@ impl ToolHandler for ListDirHandler {
             offset,
             limit,
             depth,
+            include_hidden,
+            entry_type,
         } = args;
 
         if offset == 0 {
@@ -109,17 +147,12 @@ impl ToolHandler for ListDirHandler {
             ));
         }
 
-        let path = PathBuf::from(&dir_path);
-        if !path.is_absolute() {
-            return Err(FunctionCallError::RespondToModel(
-                "dir_path must be an absolute path".to_string(),
-            ));
-        }
+        let path = turn.resolve_path(Some(dir_path));
+        verify_path_exists(&path).await?;
 
         let cache_manager = session.cache_manager();
         let cache_key = if cache_manager.enabled() {
-            match build_tool_cache_key_for_path(&tool_name, &arguments_value, &turn.cwd, &path)
-                .await
+            match build_tool_cache_key_for_dir(&tool_name, &arguments_value, &turn.cwd, &path).await
             {
                 Ok(key) => Some(key),
                 Err(err) => {
@@ -154,7 +187,11 @@ impl ToolHandler for ListDirHandler {
             }
         }
 
-        let entries = list_dir_slice(&path, offset, limit, depth).await?;
+        let filter = ListFilter {
+            include_hidden,
+            entry_type,
+        };
+        let entries = list_dir_slice(&path, offset, limit, depth, filter).await?;
         let mut output = Vec::with_capacity(entries.len() + 1);
         output.push(format!("Absolute path: {}", path.display()));
         output.extend(entries);
@@ -180,9 +217,21 @@ async fn list_dir_slice(
     offset: usize,
     limit: usize,
     depth: usize,
+    filter: ListFilter,
 ) -> Result<Vec<String>, FunctionCallError> {
     let mut entries = Vec::new();
-    collect_entries(path, Path::new(""), depth, &mut entries).await?;
+    collect_entries(path, Path::new(""), depth, filter, &mut entries).await?;
+    match filter.entry_type {
+        EntryTypeFilter::Files => {
+            entries.retain(|entry| entry.kind != DirEntryKind::Directory);
+            for entry in &mut entries {
+                entry.display_name = entry.name.clone();
+                entry.depth = 0;
+            }
+        }
+        EntryTypeFilter::Dirs => entries.retain(|entry| entry.kind == DirEntryKind::Directory),
+        EntryTypeFilter::Both => {}
+    }
 
     if entries.is_empty() {
         return Ok(Vec::new());
@@ -217,6 +266,7 @@ async fn collect_entries(
     dir_path: &Path,
     relative_prefix: &Path,
     depth: usize,
+    filter: ListFilter,
     entries: &mut Vec<DirEntry>,
 ) -> Result<(), FunctionCallError> {
     let mut queue = VecDeque::new();
@@ -237,6 +287,14 @@ async fn collect_entries(
             })?;
 
             let file_name = entry.file_name();
+            if !filter.include_hidden && file_name.to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let size = if file_type.is_file() {
+                entry.metadata().await.ok().map(|metadata| metadata.len())
+            } else {
+                None
+            };
             let relative_path = if prefix.as_os_str().is_empty() {
                 PathBuf::from(&file_name)
             } else {
@@ -256,6 +314,7 @@ async fn collect_entries(
                     display_name,
                     depth: display_depth,
                     kind,
+                    size,
                 },
             ));
         }
@@ -300,7 +359,10 @@ fn format_entry_line(entry: &DirEntry) -> String {
         DirEntryKind::Other => name.push('?'),
         DirEntryKind::File => {}
     }
-    format!("{indent}{name}")
+    match entry.size {
+        Some(size) => format!("{indent}{name} ({size} bytes)"),
+        None => format!("{indent}{name}"),
+    }
 }
 
 #[derive(Clone)]
@@ -309,6 +371,8 @@ struct DirEntry {
     display_name: String,
     depth: usize,
     kind: DirEntryKind,
+    /// Size in bytes for regular files.
+    size: Option<u64>,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -336,8 +400,77 @@ impl From<&FileType> for DirEntryKind {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cache::config::CacheConfig;
+    use crate::cache::config::CacheConfigToml;
+    use crate::cache::manager::CacheManager;
     use tempfile::tempdir;
 
+    async fn cached_listing(
+        cache_manager: &CacheManager,
+        dir_path: &Path,
+    ) -> (String, Option<Vec<u8>>) {
+        let args = serde_json::json!({"dir_path": dir_path});
+        let key = build_tool_cache_key_for_dir("list_dir", &args, dir_path, dir_path)
+            .await
+            .expect("cache key");
+        let cached = cache_manager.get(&key, CacheableTool::ListDir);
+        (key, cached)
+    }
+
+    #[tokio::test]
+    async fn cached_listing_hits_until_direct_entry_added() {
+        let codex_home = tempdir().expect("codex home");
+        let workspace = tempdir().expect("workspace");
+        let dir_path = workspace.path();
+        tokio::fs::write(dir_path.join("a.txt"), b"a")
+            .await
+            .expect("write a");
+        let config = CacheConfig::new(codex_home.path(), None).expect("cache config");
+        let cache_manager = CacheManager::new(config, None).expect("cache manager");
+
+        let (key, cached) = cached_listing(&cache_manager, dir_path).await;
+        assert_eq!(cached, None);
+        cache_manager.put(
+            key,
+            b"a.txt".to_vec(),
+            cache_manager.ttl_for(CacheableTool::ListDir),
+            CacheableTool::ListDir,
+        );
+
+        let (_, cached) = cached_listing(&cache_manager, dir_path).await;
+        assert_eq!(cached, Some(b"a.txt".to_vec()));
+
+        tokio::fs::write(dir_path.join("b.txt"), b"b")
+            .await
+            .expect("write b");
+        let (_, cached) = cached_listing(&cache_manager, dir_path).await;
+        assert_eq!(cached, None);
+    }
+
+    #[tokio::test]
+    async fn cached_listing_uses_default_ttl_without_override() {
+        let codex_home = tempdir().expect("codex home");
+        let workspace = tempdir().expect("workspace");
+        let dir_path = workspace.path();
+        let config = CacheConfig::new(
+            codex_home.path(),
+            Some(CacheConfigToml {
+                default_ttl_sec: Some(0),
+                ..Default::default()
+            }),
+        )
+        .expect("cache config");
+        let cache_manager = CacheManager::new(config, None).expect("cache manager");
+        let ttl = cache_manager.ttl_for(CacheableTool::ListDir);
+        assert_eq!(ttl, std::time::Duration::ZERO);
+
+        let (key, _) = cached_listing(&cache_manager, dir_path).await;
+        cache_manager.put(key, b"listing".to_vec(), ttl, CacheableTool::ListDir);
+
+        let (_, cached) = cached_listing(&cache_manager, dir_path).await;
+        assert_eq!(cached, None);
+    }
+
     #[tokio::test]
     async fn lists_directory_entries() {
         let temp = tempdir().expect("create tempdir");
@@ -370,27 +503,27 @@ mod tests {
             symlink(dir_path.join("entry.txt"), &link_path).expect("create symlink");
         }
 
-        let entries = list_dir_slice(dir_path, 1, 20, 3)
+        let entries = list_dir_slice(dir_path, 1, 20, 3, ListFilter::default())
             .await
             .expect("list directory");
 
         #[cfg(unix)]
         let expected = vec![
-            "entry.txt".to_string(),
+            "entry.txt (7 bytes)".to_string(),
             "link@".to_string(),
             "nested/".to_string(),
-            "  child.txt".to_string(),
+            "  child.txt (5 bytes)".to_string(),
             "  deeper/".to_string(),
-            "    grandchild.txt".to_string(),
+            "    grandchild.txt (10 bytes)".to_string(),
         ];
 
         #[cfg(not(unix))]
         let expected = vec![
-            "entry.txt".to_string(),
+            "entry.txt (7 bytes)".to_string(),
             "nested/".to_string(),
-            "  child.txt".to_string(),
+            "  child.txt (5 bytes)".to_string(),
             "  deeper/".to_string(),
-            "    grandchild.txt".to_string(),
+            "    grandchild.txt (10 bytes)".to_string(),
         ];
 
         assert_eq!(entries, expected);
@@ -404,7 +537,7 @@ mod tests {
             .await
             .expect("create sub dir");
 
-        let err = list_dir_slice(dir_path, 10, 1, 2)
+        let err = list_dir_slice(dir_path, 10, 1, 2, ListFilter::default())
             .await
             .expect_err("offset exceeds entries");
         assert_eq!(
@@ -431,38 +564,38 @@ mod tests {
             .await
             .expect("write deeper");
 
-        let entries_depth_one = list_dir_slice(dir_path, 1, 10, 1)
+        let entries_depth_one = list_dir_slice(dir_path, 1, 10, 1, ListFilter::default())
             .await
             .expect("list depth 1");
         assert_eq!(
             entries_depth_one,
-            vec!["nested/".to_string(), "root.txt".to_string(),]
+            vec!["nested/".to_string(), "root.txt (4 bytes)".to_string(),]
         );
 
-        let entries_depth_two = list_dir_slice(dir_path, 1, 20, 2)
+        let entries_depth_two = list_dir_slice(dir_path, 1, 20, 2, ListFilter::default())
             .await
             .expect("list depth 2");
         assert_eq!(
             entries_depth_two,
             vec![
                 "nested/".to_string(),
-                "  child.txt".to_string(),
+                "  child.txt (5 bytes)".to_string(),
                 "  deeper/".to_string(),
-                "root.txt".to_string(),
+                "root.txt (4 bytes)".to_string(),
             ]
         );
 
-        let entries_depth_three = list_dir_slice(dir_path, 1, 30, 3)
+        let entries_depth_three = list_dir_slice(dir_path, 1, 30, 3, ListFilter::default())
             .await
             .expect("list depth 3");
         assert_eq!(
             entries_depth_three,
             vec![
                 "nested/".to_string(),
-                "  child.txt".to_string(),
+                "  child.txt (5 bytes)".to_string(),
                 "  deeper/".to_string(),
-                "    grandchild.txt".to_string(),
-                "root.txt".to_string(),
+                "    grandchild.txt (4 bytes)".to_string(),
+                "root.txt (4 bytes)".to_string(),
             ]
         );
     }
@@ -481,12 +614,15 @@ mod tests {
             .await
             .expect("write gamma");
 
-        let entries = list_dir_slice(dir_path, 2, usize::MAX, 1)
+        let entries = list_dir_slice(dir_path, 2, usize::MAX, 1, ListFilter::default())
             .await
             .expect("list without overflow");
         assert_eq!(
             entries,
-            vec!["beta.txt".to_string(), "gamma.txt".to_string(),]
+            vec![
+                "beta.txt (4 bytes)".to_string(),
+                "gamma.txt (5 bytes)".to_string(),
+            ]
         );
     }
 
@@ -502,7 +638,7 @@ mod tests {
                 .expect("write file");
         }
 
-        let entries = list_dir_slice(dir_path, 1, 25, 1)
+        let entries = list_dir_slice(dir_path, 1, 25, 1, ListFilter::default())
             .await
             .expect("list directory");
         assert_eq!(entries.len(), 26);
@@ -524,17 +660,97 @@ mod tests {
         tokio::fs::write(nested.join("child.txt"), b"child").await?;
         tokio::fs::write(deeper.join("grandchild.txt"), b"deep").await?;
 
-        let entries_depth_three = list_dir_slice(dir_path, 1, 3, 3).await?;
+        let entries_depth_three = list_dir_slice(dir_path, 1, 3, 3, ListFilter::default()).await?;
         assert_eq!(
             entries_depth_three,
             vec![
                 "nested/".to_string(),
-                "  child.txt".to_string(),
-                "root.txt".to_string(),
+                "  child.txt (5 bytes)".to_string(),
+                "root.txt (4 bytes)".to_string(),
                 "More than 3 entries found".to_string()
             ]
         );
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn include_hidden_controls_dot_entries() -> anyhow::Result<()> {
+        let temp = tempdir()?;
+        let dir_path = temp.path();
+        tokio::fs::create_dir(dir_path.join(".git")).await?;
+        tokio::fs::write(dir_path.join(".git").join("HEAD"), b"ref").await?;
+        tokio::fs::write(dir_path.join(".env"), b"KEY=1").await?;
+        tokio::fs::write(dir_path.join("main.rs"), b"fn main() {}").await?;
+
+        let hidden = ListFilter {
+            include_hidden: false,
+            ..ListFilter::default()
+        };
+        let visible_only = list_dir_slice(dir_path, 1, 10, 2, hidden).await?;
+        assert_eq!(visible_only, vec!["main.rs (12 bytes)".to_string()]);
+
+        let everything = list_dir_slice(dir_path, 1, 10, 2, ListFilter::default()).await?;
+        assert_eq!(
+            everything,
+            vec![
+                ".env (5 bytes)".to_string(),
+                ".git/".to_string(),
+                "  HEAD (3 bytes)".to_string(),
+                "main.rs (12 bytes)".to_string(),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn entry_type_filters_files_and_dirs() -> anyhow::Result<()> {
+        let temp = tempdir()?;
+        let dir_path = temp.path();
+        let nested = dir_path.join("nested");
+        tokio::fs::create_dir(&nested).await?;
+        tokio::fs::create_dir(nested.join("deeper")).await?;
+        tokio::fs::write(dir_path.join("root.txt"), b"root").await?;
+        tokio::fs::write(nested.join("child.txt"), b"child").await?;
+
+        let files = ListFilter {
+            entry_type: EntryTypeFilter::Files,
+            ..ListFilter::default()
+        };
+        let dirs = ListFilter {
+            entry_type: EntryTypeFilter::Dirs,
+            ..ListFilter::default()
+        };
+
+        assert_eq!(
+            list_dir_slice(dir_path, 1, 10, 3, files).await?,
+            vec![
+                "nested/child.txt (5 bytes)".to_string(),
+                "root.txt (4 bytes)".to_string(),
+            ]
+        );
+        assert_eq!(
+            list_dir_slice(dir_path, 1, 10, 3, dirs).await?,
+            vec!["nested/".to_string(), "  deeper/".to_string()]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn args_accept_path_and_max_depth_aliases() {
+        let args: ListDirArgs = serde_json::from_value(serde_json::json!({
+            "path": "src",
+            "max_depth": 4,
+            "include_hidden": false,
+            "entry_type": "dirs",
+        }))
+        .expect("parse args");
+
+        assert_eq!(args.dir_path, "src");
+        assert_eq!(args.depth, 4);
+        assert!(!args.include_hidden);
+        assert_eq!(args.entry_type, EntryTypeFilter::Dirs);
+    }
 }
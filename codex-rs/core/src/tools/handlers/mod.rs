@@ -14,6 +14,8 @@ pub use plan::PLAN_TOOL;
 
 pub use apply_patch::ApplyPatchHandler;
 pub use grep_files::GrepFilesHandler;
+pub(crate) use grep_files::LineMatch;
+pub(crate) use grep_files::find_literal_lines;
 pub use list_dir::ListDirHandler;
 pub use mcp::McpHandler;
 pub use mcp_resource::McpResourceHandler;
@@ -500,7 +500,8 @@ fn create_grep_files_tool() -> ToolSpec {
         "path".to_string(),
         JsonSchema::String {
             description: Some(
-                "Directory or file path to search. Defaults to the session's working directory."
+                "Directory or file path to search, or an array of paths to search together. \
+                 Defaults to the session's working directory."
                     .to_string(),
             ),
         },
@@ -509,7 +510,117 @@ fn create_grep_files_tool() -> ToolSpec {
         "limit".to_string(),
         JsonSchema::Number {
             description: Some(
-                "Maximum number of file paths to return (defaults to 100).".to_string(),
+                "Maximum number of file paths, or output lines in content mode, to return \
+                 (defaults to 100)."
+                    .to_string(),
+            ),
+        },
+    );
+    properties.insert(
+        "mode".to_string(),
+        JsonSchema::String {
+            description: Some(
+                "Optional output mode: \"files\" (default) lists matching file paths; \
+                 \"content\" lists matching lines as `path:line:text`."
+                    .to_string(),
+            ),
+        },
+    );
+    properties.insert(
+        "context".to_string(),
+        JsonSchema::Number {
+            description: Some(
+                "Lines of context to include around each match in content mode (defaults to 0, \
+                 max 20). Context lines use `path-line-text`."
+                    .to_string(),
+            ),
+        },
+    );
+    properties.insert(
+        "case".to_string(),
+        JsonSchema::String {
+            description: Some(
+                "Optional case matching: \"smart\" (default; case-insensitive unless the \
+                 pattern has uppercase letters), \"insensitive\", or \"sensitive\"."
+                    .to_string(),
+            ),
+        },
+    );
+    properties.insert(
+        "multiline".to_string(),
+        JsonSchema::Boolean {
+            description: Some(
+                "When true, allow matches to span multiple lines. In files mode the whole \
+                 matching file is still reported."
+                    .to_string(),
+            ),
+        },
+    );
+    properties.insert(
+        "multiline_dotall".to_string(),
+        JsonSchema::Boolean {
+            description: Some(
+                "With multiline, also let `.` match newlines (defaults to false).".to_string(),
+            ),
+        },
+    );
+    properties.insert(
+        "fixed_strings".to_string(),
+        JsonSchema::Boolean {
+            description: Some(
+                "When true, treat the pattern as a literal string instead of a regular \
+                 expression."
+                    .to_string(),
+            ),
+        },
+    );
+    properties.insert(
+        "word".to_string(),
+        JsonSchema::Boolean {
+            description: Some(
+                "When true, only match the pattern as a whole word, so `id` does not match \
+                 `video` (defaults to false)."
+                    .to_string(),
+            ),
+        },
+    );
+    properties.insert(
+        "hidden".to_string(),
+        JsonSchema::Boolean {
+            description: Some(
+                "When true, also search hidden files and directories such as `.github/` \
+                 (defaults to false)."
+                    .to_string(),
+            ),
+        },
+    );
+    properties.insert(
+        "sort".to_string(),
+        JsonSchema::String {
+            description: Some(
+                "Result order: \"modified\" (default, newest first), \"path\", or \"count\" \
+                 (most matching lines first; files mode only)."
+                    .to_string(),
+            ),
+        },
+    );
+    properties.insert(
+        "max_output_chars".to_string(),
+        JsonSchema::Number {
+            description: Some(
+                "Optional character budget for the output; longer results are cut and end with \
+                 \"(truncated)\"."
+                    .to_string(),
+            ),
+        },
+    );
+    properties.insert(
+        "no_ignore".to_string(),
+        JsonSchema::Boolean {
+            description: Some(
+                "When true, also search files excluded by `.gitignore` and other ignore files \
+                 (defaults to false)."
+                    .to_string(),
             ),
         },
     );
@@ -517,7 +628,7 @@ fn create_grep_files_tool() -> ToolSpec {
     ToolSpec::Function(ResponsesApiTool {
         name: "grep_files".to_string(),
         description: "Finds files whose contents match the pattern and lists them by modification \
-                      time."
+                      time, path, or match count."
             .to_string(),
         strict: false,
         parameters: JsonSchema::Object {
@@ -631,7 +742,11 @@ fn create_list_dir_tool() -> ToolSpec {
     properties.insert(
         "dir_path".to_string(),
         JsonSchema::String {
-            description: Some("Absolute path to the directory to list.".to_string()),
+            description: Some(
+                "Path to the directory to list; relative paths resolve against the working \
+                 directory."
+                    .to_string(),
+            ),
         },
     );
     properties.insert(
@@ -656,11 +771,29 @@ fn create_list_dir_tool() -> ToolSpec {
             ),
         },
     );
+    properties.insert(
+        "include_hidden".to_string(),
+        JsonSchema::Boolean {
+            description: Some(
+                "Whether to list entries whose names start with `.` (default true).".to_string(),
+            ),
+        },
+    );
+    properties.insert(
+        "entry_type".to_string(),
+        JsonSchema::String {
+            description: Some(
+                "Optional entry filter: \"both\" (default), \"files\" (non-directories, listed \
+                 by relative path), or \"dirs\"."
+                    .to_string(),
+            ),
+        },
+    );
 
     ToolSpec::Function(ResponsesApiTool {
         name: "list_dir".to_string(),
         description:
-            "Lists entries in a local directory with 1-indexed entry numbers and simple type labels."
+            "Lists entries in a local directory with 1-indexed entry numbers, simple type labels, and file sizes."
                 .to_string(),
         strict: false,
         parameters: JsonSchema::Object {
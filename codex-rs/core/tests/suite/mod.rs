@@ -47,6 +47,7 @@ mod review;
 mod rmcp_client;
 mod rollout_list_find;
 mod seatbelt;
+mod semantic_index;
 mod shell_command;
 mod shell_serialization;
 mod shell_snapshot;
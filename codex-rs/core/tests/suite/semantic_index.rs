@@ -0,0 +1,124 @@
+use anyhow::Result;
+use codex_core::ModelProviderInfo;
+use codex_core::WireApi;
+use codex_core::semantic::config::SemanticIndexConfig;
+use codex_core::semantic::index::SearchMode;
+use codex_core::semantic::index::SemanticIndex;
+use core_test_support::skip_if_no_network;
+use serde_json::json;
+use sha2::Digest;
+use sha2::Sha256;
+use tempfile::TempDir;
+use wiremock::Mock;
+use wiremock::MockServer;
+use wiremock::Request;
+use wiremock::Respond;
+use wiremock::ResponseTemplate;
+use wiremock::matchers::method;
+use wiremock::matchers::path;
+
+/// Deterministic unit-norm embedding derived from the SHA-256 of `text`.
+fn hash_embedding(text: &str) -> Vec<f32> {
+    let digest = Sha256::digest(text.as_bytes());
+    let raw: Vec<f32> = digest
+        .iter()
+        .take(16)
+        .map(|byte| f32::from(*byte) / 127.5 - 1.0)
+        .collect();
+    let norm = raw.iter().map(|value| value * value).sum::<f32>().sqrt();
+    raw.into_iter().map(|value| value / norm).collect()
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+struct HashEmbeddings;
+
+impl Respond for HashEmbeddings {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let Ok(body) = serde_json::from_slice::<serde_json::Value>(&request.body) else {
+            return ResponseTemplate::new(400);
+        };
+        let data = body["input"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .map(|(index, input)| {
+                json!({
+                    "index": index,
+                    "embedding": hash_embedding(input.as_str().unwrap_or_default()),
+                })
+            })
+            .collect::<Vec<_>>();
+        ResponseTemplate::new(200).set_body_json(json!({ "data": data }))
+    }
+}
+
+fn mock_provider(server: &MockServer) -> ModelProviderInfo {
+    ModelProviderInfo {
+        name: "mock".into(),
+        base_url: Some(server.uri()),
+        env_key: None,
+        env_key_instructions: None,
+        experimental_bearer_token: None,
+        wire_api: WireApi::Responses,
+        query_params: None,
+        http_headers: None,
+        env_http_headers: None,
+        request_max_retries: Some(0),
+        stream_max_retries: Some(0),
+        stream_idle_timeout_ms: Some(5_000),
+        requires_openai_auth: false,
+    }
+}
+
+#[tokio::test]
+async fn build_then_search_returns_closest_file() -> Result<()> {
+    skip_if_no_network!(Ok(()));
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/embeddings"))
+        .respond_with(HashEmbeddings)
+        .mount(&server)
+        .await;
+
+    let workspace = TempDir::new()?;
+    let files = [
+        ("config.rs", "fn parse_config(path: &Path) -> Config"),
+        ("render.rs", "fn render_widget(frame: &mut Frame)"),
+        ("network.rs", "async fn fetch_remote(url: &str) -> Bytes"),
+    ];
+    for (name, content) in files {
+        std::fs::write(workspace.path().join(name), content)?;
+    }
+    let config = SemanticIndexConfig::new(workspace.path(), None)?;
+    let index = SemanticIndex::new(
+        workspace.path().to_path_buf(),
+        config,
+        mock_provider(&server),
+        None,
+    );
+
+    let stats = index.build().await?;
+    assert_eq!(stats.file_count, files.len());
+
+    let query = "fn render_widget(frame: &mut Frame)";
+    let query_embedding = hash_embedding(query);
+    let expected = files
+        .iter()
+        .max_by(|(_, a), (_, b)| {
+            cosine(&query_embedding, &hash_embedding(a))
+                .total_cmp(&cosine(&query_embedding, &hash_embedding(b)))
+        })
+        .map(|(name, _)| *name);
+
+    let hits = index.search(query, 3, SearchMode::Semantic).await?;
+
+    assert_eq!(hits.len(), files.len());
+    assert_eq!(hits.first().map(|hit| hit.file_path.as_str()), expected);
+    assert_eq!(expected, Some("render.rs"));
+    Ok(())
+}
@@ -0,0 +1,12 @@
+#![no_main]
+
+use codex_core::semantic::vector_store::decode_embedding;
+use codex_core::semantic::vector_store::encode_embedding;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(embedding) = decode_embedding(data) {
+        assert_eq!(embedding.len(), data.len() / 4);
+        assert_eq!(encode_embedding(&embedding).len(), data.len());
+    }
+});
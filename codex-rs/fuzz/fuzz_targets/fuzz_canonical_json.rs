@@ -0,0 +1,28 @@
+#![no_main]
+
+use codex_core::cache::canonical::canonical_json;
+use libfuzzer_sys::fuzz_target;
+use serde_json::Value;
+
+fn assert_keys_sorted(value: &Value) {
+    match value {
+        Value::Object(map) => {
+            let keys = map.keys().collect::<Vec<_>>();
+            assert!(keys.is_sorted(), "unsorted keys: {keys:?}");
+            map.values().for_each(assert_keys_sorted);
+        }
+        Value::Array(items) => items.iter().for_each(assert_keys_sorted),
+        _ => {}
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(value) = serde_json::from_slice::<Value>(data) else {
+        return;
+    };
+    let canonical = canonical_json(&value);
+    assert_keys_sorted(&canonical);
+    let once = serde_json::to_string(&canonical).expect("serialize canonical value");
+    let twice = serde_json::to_string(&canonical_json(&canonical)).expect("serialize again");
+    assert_eq!(once, twice);
+});
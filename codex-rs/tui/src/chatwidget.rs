@@ -65,7 +65,9 @@ use codex_core::protocol::WarningEvent;
 use codex_core::protocol::WebSearchBeginEvent;
 use codex_core::protocol::WebSearchEndEvent;
 use codex_core::semantic::index::SearchHit;
+use codex_core::semantic::index::SearchMode;
 use codex_core::semantic::index::SemanticIndex;
+use codex_core::semantic::vector_store::index_path_to_native;
 use codex_core::skills::model::SkillMetadata;
 use codex_protocol::ConversationId;
 use codex_protocol::account::PlanType;
@@ -2276,15 +2278,16 @@ impl ChatWidget {
             );
             let top_k = config.semantic_index.retrieve.top_k;
             let max_chars = config.semantic_index.retrieve.max_chars;
-            let cell: Box<dyn HistoryCell> = match index.search(&query, top_k).await {
-                Ok(hits) => {
-                    let results = build_search_results(config.cwd.as_path(), hits, max_chars);
-                    Box::new(history_cell::new_search_results_output(query, results))
-                }
-                Err(err) => Box::new(history_cell::new_error_event(format!(
-                    "Search failed: {err}"
-                ))),
-            };
+            let cell: Box<dyn HistoryCell> =
+                match index.search(&query, top_k, SearchMode::Semantic).await {
+                    Ok(hits) => {
+                        let results = build_search_results(config.cwd.as_path(), hits, max_chars);
+                        Box::new(history_cell::new_search_results_output(query, results))
+                    }
+                    Err(err) => Box::new(history_cell::new_error_event(format!(
+                        "Search failed: {err}"
+                    ))),
+                };
             app_event_tx.send(AppEvent::InsertHistoryCell(cell));
         });
     }
@@ -3697,9 +3700,19 @@ fn build_search_results(
     hits.into_iter()
         .map(|hit| {
             let file_path = hit.file_path.clone();
-            let full_path = workspace_root.join(&file_path);
-            let snippet_result =
-                read_search_snippet_lines(&full_path, hit.start_line, hit.end_line, max_chars);
+            let snippet_result = match hit.chunk_text.as_deref() {
+                Some(text) => Ok(search_snippet_lines_from_text(
+                    text,
+                    hit.start_line,
+                    hit.start_line,
+                    hit.end_line,
+                    max_chars,
+                )),
+                None => {
+                    let full_path = workspace_root.join(index_path_to_native(&file_path));
+                    read_search_snippet_lines(&full_path, hit.start_line, hit.end_line, max_chars)
+                }
+            };
             let (snippet, snippet_error) = match snippet_result {
                 Ok(lines) => (lines, None),
                 Err(err) => (Vec::new(), Some(err)),
@@ -3730,6 +3743,18 @@ fn read_search_snippet_lines(
         return Ok(Vec::new());
     }
     let contents = String::from_utf8_lossy(&bytes);
+    Ok(search_snippet_lines_from_text(
+        &contents, 1, start_line, end_line, max_chars,
+    ))
+}
+
+fn search_snippet_lines_from_text(
+    contents: &str,
+    first_line_number: usize,
+    start_line: usize,
+    end_line: usize,
+    max_chars: usize,
+) -> Vec<history_cell::SnippetLine> {
     let mut out = Vec::new();
     let start = start_line.max(1);
     let end = end_line.max(start);
@@ -3740,7 +3765,7 @@ fn read_search_snippet_lines(
     };
 
     for (idx, line) in contents.lines().enumerate() {
-        let line_number = idx + 1;
+        let line_number = idx + first_line_number;
         if line_number < start {
             continue;
         }
@@ -3764,7 +3789,7 @@ fn read_search_snippet_lines(
         }
     }
 
-    Ok(out)
+    out
 }
 
 async fn fetch_rate_limits(base_url: String, auth: CodexAuth) -> Option<RateLimitSnapshot> {